@@ -7,6 +7,7 @@ use std::iter::repeat;
 use criterion::criterion_group;
 use criterion::criterion_main;
 use criterion::Criterion;
+use fsrs::synthetic::{generate_sample_items, SampleDatasetConfig};
 use fsrs::FSRSReview;
 use fsrs::NextStates;
 use fsrs::SimulatorConfig;
@@ -18,9 +19,17 @@ pub(crate) fn calc_mem(inf: &FSRS, past_reviews: usize) -> MemoryState {
     let review = FSRSReview {
         rating: 3,
         delta_t: 21,
+        elapsed_seconds: None,
     };
     let reviews = repeat(review).take(past_reviews + 1).collect_vec();
-    inf.memory_state(FSRSItem { reviews }, None).unwrap()
+    inf.memory_state(
+        FSRSItem {
+            reviews,
+            ..Default::default()
+        },
+        None,
+    )
+    .unwrap()
 }
 
 pub(crate) fn next_states(inf: &FSRS) -> NextStates {
@@ -75,5 +84,29 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, criterion_benchmark);
+pub fn throughput_benchmark(c: &mut Criterion) {
+    let items = generate_sample_items(&SampleDatasetConfig::default()).unwrap();
+    let fsrs = FSRS::new(Some(&[])).unwrap();
+    let config = SimulatorConfig {
+        deck_size: 3650,
+        learn_span: 365,
+        max_cost_perday: f32::INFINITY,
+        learn_limit: 10,
+        ..Default::default()
+    };
+    let mut group = c.benchmark_group("throughput");
+    group.sample_size(10);
+    group.bench_function("compute_parameters", |b| {
+        b.iter(|| black_box(fsrs.compute_parameters(items.clone(), None, false).unwrap()))
+    });
+    group.bench_function("evaluate", |b| {
+        b.iter(|| black_box(fsrs.evaluate(items.clone(), |_| true).unwrap()))
+    });
+    group.bench_function("simulate", |b| {
+        b.iter(|| black_box(fsrs::simulate(&config, &[], 0.9, Some(42), None).unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark, throughput_benchmark);
 criterion_main!(benches);
@@ -0,0 +1,11 @@
+use fsrs::benchmark::{run_benchmarks, BenchmarkConfig};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Measure training, evaluation and simulation on the bundled synthetic fixtures
+    let report = run_benchmarks(&BenchmarkConfig::default())?;
+
+    // Print the report as JSON, so it can be saved and compared with other builds
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    Ok(())
+}
@@ -9,21 +9,28 @@ fn migrate_with_full_history() -> Result<(), Box<dyn std::error::Error>> {
         FSRSReview {
             rating: 3,
             delta_t: 0,
+            elapsed_seconds: None,
         },
         FSRSReview {
             rating: 3,
             delta_t: 1,
+            elapsed_seconds: None,
         },
         FSRSReview {
             rating: 4,
             delta_t: 3,
+            elapsed_seconds: None,
         },
         FSRSReview {
             rating: 3,
             delta_t: 7,
+            elapsed_seconds: None,
         },
     ];
-    let item = FSRSItem { reviews };
+    let item = FSRSItem {
+        reviews,
+        ..Default::default()
+    };
 
     // Calculate the current memory state
     let memory_state = fsrs.memory_state(item, None)?;
@@ -54,17 +61,23 @@ fn migrate_with_partial_history() -> Result<(), Box<dyn std::error::Error>> {
         FSRSReview {
             rating: 3,
             delta_t: 5,
+            elapsed_seconds: None,
         },
         FSRSReview {
             rating: 4,
             delta_t: 10,
+            elapsed_seconds: None,
         },
         FSRSReview {
             rating: 3,
             delta_t: 20,
+            elapsed_seconds: None,
         },
     ];
-    let item = FSRSItem { reviews };
+    let item = FSRSItem {
+        reviews,
+        ..Default::default()
+    };
 
     // Calculate the current memory state, passing the initial state
     let memory_state = fsrs.memory_state(item, Some(initial_state))?;
@@ -179,9 +179,14 @@ fn convert_to_fsrs_item(history: Vec<(NaiveDate, u32)>) -> Vec<FSRSItem> {
 
     for (date, rating) in history {
         let delta_t = (date - last_date).num_days() as u32;
-        reviews.push(FSRSReview { rating, delta_t });
+        reviews.push(FSRSReview {
+            rating,
+            delta_t,
+            elapsed_seconds: None,
+        });
         items.push(FSRSItem {
             reviews: reviews.clone(),
+            ..Default::default()
         });
         last_date = date;
     }
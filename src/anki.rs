@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{Connection, OpenFlags, Row};
+use snafu::{OptionExt, Snafu};
+use zip::ZipArchive;
+
+use crate::convertor::{revlogs_to_fsrs_items, ConvertorConfig};
+use crate::dataset::FSRSItem;
+use crate::optimal_retention::{RevlogEntry, RevlogReviewKind};
+
+/// Controls which reviews are read from an Anki collection.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnkiCollectionOptions {
+    /// Used to convert the reviews into items. Anki stores the hour a new day starts at
+    /// in the collection config, but not the timezone, so both must be provided. Reviews
+    /// outside its `start_time`..`end_time` are not read.
+    pub convertor: ConvertorConfig,
+    /// Skip the reviews of suspended cards.
+    pub ignore_suspended_cards: bool,
+    /// Skip the reviews of cards with any of these flags.
+    pub ignore_flags: Vec<u32>,
+}
+
+/// An error reading an Anki collection or package.
+#[derive(Debug, Snafu)]
+pub enum AnkiError {
+    #[snafu(context(false), display("{source}"))]
+    Sqlite { source: rusqlite::Error },
+    #[snafu(context(false), display("{source}"))]
+    Io { source: io::Error },
+    #[snafu(context(false), display("{source}"))]
+    Zip { source: zip::result::ZipError },
+    /// The package contains none of the collection files Anki writes.
+    #[snafu(display("no collection found in the package"))]
+    MissingCollection,
+}
+
+pub type Result<T, E = AnkiError> = std::result::Result<T, E>;
+
+/// The collection files a .colpkg or .apkg package can contain, newest format first. When
+/// a newer one is present, collection.anki2 only holds a notice to upgrade Anki.
+const PACKAGE_COLLECTIONS: [&str; 3] = [
+    "collection.anki21b",
+    "collection.anki21",
+    "collection.anki2",
+];
+
+impl rusqlite::types::FromSql for RevlogReviewKind {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        let rusqlite::types::ValueRef::Integer(i) = value else {
+            return Err(rusqlite::types::FromSqlError::InvalidType);
+        };
+        match i {
+            0 => Ok(RevlogReviewKind::Learning),
+            1 => Ok(RevlogReviewKind::Review),
+            2 => Ok(RevlogReviewKind::Relearning),
+            3 => Ok(RevlogReviewKind::Filtered),
+            4 => Ok(RevlogReviewKind::Manual),
+            _ => Err(rusqlite::types::FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl TryFrom<&Row<'_>> for RevlogEntry {
+    type Error = rusqlite::Error;
+    fn try_from(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(RevlogEntry {
+            id: row.get(0)?,
+            cid: row.get(1)?,
+            usn: row.get(2)?,
+            button_chosen: row.get(3)?,
+            interval: row.get(4)?,
+            last_interval: row.get(5)?,
+            ease_factor: row.get(6)?,
+            taken_millis: row.get(7)?,
+            review_kind: row.get(8)?,
+        })
+    }
+}
+
+fn is_zip(path: &Path) -> io::Result<bool> {
+    let mut magic = [0; 4];
+    match File::open(path)?.read_exact(&mut magic) {
+        Ok(()) => Ok(magic == *b"PK\x03\x04"),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Extract the collection of a package into a temporary file, decompressing it if needed.
+fn extract_collection(package: &Path) -> Result<PathBuf> {
+    static EXTRACTED: AtomicUsize = AtomicUsize::new(0);
+    let mut archive = ZipArchive::new(File::open(package)?)?;
+    let names: HashSet<_> = archive.file_names().collect();
+    let name = *PACKAGE_COLLECTIONS
+        .iter()
+        .find(|name| names.contains(*name))
+        .context(MissingCollectionSnafu)?;
+    let mut file = archive.by_name(name)?;
+    let path = std::env::temp_dir().join(format!(
+        "fsrs-{}-{}.anki21",
+        std::process::id(),
+        EXTRACTED.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut out = File::create(&path)?;
+    let copied = if name.ends_with('b') {
+        zstd::stream::copy_decode(&mut file, &mut out)
+    } else {
+        io::copy(&mut file, &mut out).map(|_| ())
+    };
+    if let Err(err) = copied {
+        let _ = std::fs::remove_file(&path);
+        return Err(err.into());
+    }
+    Ok(path)
+}
+
+/// Open the collection at `path` read-only, extracting it first if it is a package, and
+/// pass it to `f`.
+fn with_collection<T>(path: &Path, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    if !is_zip(path)? {
+        return f(&Connection::open_with_flags(path, flags)?);
+    }
+    let extracted = extract_collection(path)?;
+    let result = Connection::open_with_flags(&extracted, flags)
+        .map_err(AnkiError::from)
+        .and_then(|db| f(&db));
+    let _ = std::fs::remove_file(extracted);
+    result
+}
+
+/// Read the review logs of an Anki collection, sorted by card id.
+///
+/// `path` can point to the SQLite database (collection.anki2 or collection.anki21), or to
+/// a .colpkg or .apkg package, whose collection is extracted into a temporary file. The
+/// collection is opened read-only, so a wrong path is an error rather than an empty
+/// collection.
+pub fn anki_revlogs_from_collection(
+    path: impl AsRef<Path>,
+    options: &AnkiCollectionOptions,
+) -> Result<Vec<RevlogEntry>> {
+    with_collection(path.as_ref(), |db| read_revlogs(db, options))
+}
+
+fn read_revlogs(db: &Connection, options: &AnkiCollectionOptions) -> Result<Vec<RevlogEntry>> {
+    let flags_str = if !options.ignore_flags.is_empty() {
+        format!(
+            "AND flags NOT IN ({})",
+            options
+                .ignore_flags
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    } else {
+        "".to_string()
+    };
+
+    let suspended_cards_str = if options.ignore_suspended_cards {
+        "AND queue != -1"
+    } else {
+        ""
+    };
+
+    let current_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(i64::MAX);
+    let revlogs = db
+        .prepare_cached(&format!(
+            "SELECT *
+        FROM revlog
+        WHERE id < ?1
+        AND id >= ?2
+        AND id < ?3
+        AND cid < ?1
+        AND cid IN (
+            SELECT id
+            FROM cards
+            WHERE queue != 0
+            {suspended_cards_str}
+            {flags_str}
+        )
+        AND ease BETWEEN 1 AND 4
+        AND (
+            type != 3
+            OR factor != 0
+        )
+        order by cid"
+        ))?
+        .query_and_then(
+            (
+                current_timestamp,
+                options.convertor.start_time.unwrap_or(i64::MIN),
+                options.convertor.end_time.unwrap_or(i64::MAX),
+            ),
+            |x| x.try_into(),
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(revlogs)
+}
+
+/// Read the review history of an Anki collection and convert it into items that can be
+/// passed to [FSRS::compute_parameters](crate::FSRS::compute_parameters). See
+/// [anki_revlogs_from_collection] for the supported files.
+pub fn items_from_anki_collection(
+    path: impl AsRef<Path>,
+    options: &AnkiCollectionOptions,
+) -> Result<Vec<FSRSItem>> {
+    let revlogs = anki_revlogs_from_collection(path, options)?;
+    Ok(revlogs_to_fsrs_items(revlogs, &options.convertor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_MS: i64 = 86400 * 1000;
+
+    fn create_collection(path: &Path) -> Result<()> {
+        let _ = std::fs::remove_file(path);
+        let db = Connection::open(path)?;
+        db.execute_batch(
+            "CREATE TABLE cards (id integer primary key, queue integer, flags integer);
+            CREATE TABLE revlog (
+                id integer primary key, cid integer, usn integer, ease integer, ivl integer,
+                lastIvl integer, factor integer, time integer, type integer
+            );",
+        )?;
+        // card 1 is a normal card, 2 is suspended, 3 is flagged red, 4 is new
+        for (id, queue, flags) in [(1, 2, 0), (2, -1, 0), (3, 2, 1), (4, 0, 0)] {
+            db.execute("INSERT INTO cards VALUES (?1, ?2, ?3)", (id, queue, flags))?;
+        }
+        let start = 1_600_000_000_000;
+        for cid in 1..=4 {
+            for (day, ease, kind) in [(0, 3, 0), (2, 3, 1), (7, 1, 1)] {
+                db.execute(
+                    "INSERT INTO revlog VALUES (?1, ?2, 0, ?3, 0, 0, 2500, 5000, ?4)",
+                    (start + day * DAY_MS + cid, cid, ease, kind),
+                )?;
+            }
+        }
+        // a cram review that should be ignored
+        db.execute(
+            "INSERT INTO revlog VALUES (?1, 1, 0, 3, 0, 0, 0, 5000, 3)",
+            (start + DAY_MS,),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn read_anki_collection() -> Result<()> {
+        let path = std::env::temp_dir().join("fsrs_read_anki_collection.anki21");
+        create_collection(&path)?;
+
+        let revlogs = anki_revlogs_from_collection(&path, &AnkiCollectionOptions::default())?;
+        assert_eq!(revlogs.len(), 9);
+        assert!(revlogs.windows(2).all(|w| w[0].cid <= w[1].cid));
+        let items = items_from_anki_collection(&path, &AnkiCollectionOptions::default())?;
+        assert_eq!(items.len(), 6);
+
+        let options = AnkiCollectionOptions {
+            ignore_suspended_cards: true,
+            ignore_flags: vec![1],
+            ..Default::default()
+        };
+        let items = items_from_anki_collection(&path, &options)?;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].reviews.len(), 3);
+
+        let options = AnkiCollectionOptions {
+            convertor: ConvertorConfig {
+                start_time: Some(1_600_000_000_000 + DAY_MS),
+                end_time: Some(1_600_000_000_000 + 7 * DAY_MS),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let revlogs = anki_revlogs_from_collection(&path, &options)?;
+        assert_eq!(revlogs.len(), 3);
+
+        std::fs::remove_file(path).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn missing_collection() {
+        let path = std::env::temp_dir().join("fsrs_missing_collection.anki21");
+        let _ = std::fs::remove_file(&path);
+        let err = anki_revlogs_from_collection(&path, &AnkiCollectionOptions::default());
+        assert!(matches!(err, Err(AnkiError::Io { .. })));
+        assert!(!path.exists());
+    }
+
+    fn create_package(path: &Path, files: &[(&str, &[u8])]) -> Result<()> {
+        let mut zip = zip::ZipWriter::new(File::create(path)?);
+        for (name, contents) in files {
+            zip.start_file(*name, zip::write::FileOptions::default())?;
+            io::copy(&mut &contents[..], &mut zip)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn read_anki_package() -> Result<()> {
+        let dir = std::env::temp_dir();
+        let collection_path = dir.join("fsrs_read_anki_package.anki21");
+        create_collection(&collection_path)?;
+        let collection = std::fs::read(&collection_path)?;
+        std::fs::remove_file(collection_path)?;
+        let compressed = zstd::encode_all(&collection[..], 0)?;
+
+        // the current format, with a placeholder for older versions of Anki
+        let colpkg = dir.join("fsrs_read_anki_package.colpkg");
+        create_package(
+            &colpkg,
+            &[
+                ("collection.anki2", b"placeholder"),
+                ("collection.anki21b", &compressed),
+                ("media", b""),
+            ],
+        )?;
+        // the legacy format
+        let apkg = dir.join("fsrs_read_anki_package.apkg");
+        create_package(&apkg, &[("collection.anki21", &collection)])?;
+        for path in [&colpkg, &apkg] {
+            let items = items_from_anki_collection(path, &AnkiCollectionOptions::default())?;
+            assert_eq!(items.len(), 6);
+        }
+
+        create_package(&apkg, &[("media", b"")])?;
+        let err = anki_revlogs_from_collection(&apkg, &AnkiCollectionOptions::default());
+        assert!(matches!(err, Err(AnkiError::MissingCollection)));
+
+        std::fs::remove_file(colpkg)?;
+        std::fs::remove_file(apkg)?;
+        Ok(())
+    }
+}
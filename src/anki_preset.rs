@@ -0,0 +1,167 @@
+use std::io::{Error, ErrorKind, Result};
+
+use serde_json::{json, Map, Value};
+
+use crate::inference::ModelEvaluation;
+use crate::model::check_and_fill_parameters;
+
+const PARAMS_KEY: &str = "fsrsParams5";
+/// Used by Anki versions before FSRS-5, for 17 parameters.
+const LEGACY_PARAMS_KEY: &str = "fsrsWeights";
+const DESIRED_RETENTION_KEY: &str = "desiredRetention";
+// not read by Anki, which keeps unknown keys of a preset when saving it
+const OPTIMAL_RETENTION_KEY: &str = "fsrsOptimalRetention";
+const EVALUATION_KEY: &str = "fsrsEvaluation";
+
+/// The FSRS settings of an Anki deck preset, in the JSON layout Anki uses for deck configs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnkiPreset {
+    pub parameters: Vec<f32>,
+    pub desired_retention: f32,
+    /// The result of [FSRS::optimal_retention](crate::FSRS::optimal_retention), if known.
+    pub optimal_retention: Option<f32>,
+    /// How well the parameters fit the collection, if known.
+    pub evaluation: Option<ModelEvaluation>,
+}
+
+impl AnkiPreset {
+    pub fn new(parameters: Vec<f32>, desired_retention: f32) -> Self {
+        Self {
+            parameters,
+            desired_retention,
+            optimal_retention: None,
+            evaluation: None,
+        }
+    }
+
+    /// The settings as a JSON object, with only the keys of the FSRS settings.
+    pub fn to_json(&self) -> Value {
+        let mut preset = Value::Object(Map::new());
+        self.apply_to(&mut preset);
+        preset
+    }
+
+    /// Write the settings into an existing preset (eg the `dconf` entry of a collection, or a
+    /// preset exported by an add-on), leaving its other keys alone. A `preset` that is not a
+    /// JSON object is replaced with one.
+    pub fn apply_to(&self, preset: &mut Value) {
+        if !preset.is_object() {
+            *preset = Value::Object(Map::new());
+        }
+        let object = preset.as_object_mut().unwrap();
+        object.insert(PARAMS_KEY.into(), json!(self.parameters));
+        // so Anki versions that only read the legacy key don't keep using stale parameters
+        object.remove(LEGACY_PARAMS_KEY);
+        object.insert(DESIRED_RETENTION_KEY.into(), json!(self.desired_retention));
+        match self.optimal_retention {
+            Some(retention) => object.insert(OPTIMAL_RETENTION_KEY.into(), json!(retention)),
+            None => object.remove(OPTIMAL_RETENTION_KEY),
+        };
+        match &self.evaluation {
+            Some(evaluation) => object.insert(EVALUATION_KEY.into(), json!(evaluation)),
+            None => object.remove(EVALUATION_KEY),
+        };
+    }
+
+    /// Read the FSRS settings of a preset. Parameters saved by older versions of Anki are
+    /// converted to the current model. Returns an [ErrorKind::InvalidData] error if the
+    /// preset has no parameters, or they are invalid.
+    pub fn from_json(preset: &Value) -> Result<Self> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+        let parameters = |key| -> Result<Vec<f32>> {
+            match preset.get(key) {
+                Some(value) => serde_json::from_value(value.clone()).map_err(Error::from),
+                None => Ok(vec![]),
+            }
+        };
+        let mut parameters_found = parameters(PARAMS_KEY)?;
+        if parameters_found.is_empty() {
+            parameters_found = parameters(LEGACY_PARAMS_KEY)?;
+        }
+        if parameters_found.is_empty() {
+            return Err(invalid("preset has no FSRS parameters"));
+        }
+        let parameters = check_and_fill_parameters(&parameters_found, true)
+            .map_err(|_| invalid("invalid FSRS parameters"))?;
+        let desired_retention = match preset.get(DESIRED_RETENTION_KEY) {
+            Some(value) => value
+                .as_f64()
+                .ok_or_else(|| invalid("desired retention is not a number"))?
+                as f32,
+            // Anki's default
+            None => 0.9,
+        };
+        let optimal_retention = preset
+            .get(OPTIMAL_RETENTION_KEY)
+            .and_then(Value::as_f64)
+            .map(|retention| retention as f32);
+        let evaluation = preset
+            .get(EVALUATION_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok());
+        Ok(Self {
+            parameters,
+            desired_retention,
+            optimal_retention,
+            evaluation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_PARAMETERS;
+
+    #[test]
+    fn anki_preset() {
+        let preset = AnkiPreset {
+            optimal_retention: Some(0.85),
+            evaluation: Some(ModelEvaluation {
+                log_loss: 0.25,
+                rmse_bins: 0.5,
+                auc: None,
+            }),
+            ..AnkiPreset::new(DEFAULT_PARAMETERS.to_vec(), 0.9)
+        };
+        let json = preset.to_json();
+        assert_eq!(json["desiredRetention"].as_f64(), Some(0.9f32 as f64));
+        assert_eq!(json["fsrsParams5"].as_array().unwrap().len(), 19);
+        assert_eq!(AnkiPreset::from_json(&json).unwrap(), preset);
+
+        // other settings of the preset are kept
+        let mut existing = json!({
+            "id": 1,
+            "name": "Default",
+            "fsrsWeights": vec![0.4; 17],
+            "desiredRetention": 0.8,
+        });
+        let without_extras = AnkiPreset::new(DEFAULT_PARAMETERS.to_vec(), 0.95);
+        without_extras.apply_to(&mut existing);
+        assert_eq!(existing["name"], "Default");
+        assert_eq!(existing.get("fsrsWeights"), None);
+        assert_eq!(existing.get("fsrsOptimalRetention"), None);
+        assert_eq!(AnkiPreset::from_json(&existing).unwrap(), without_extras);
+    }
+
+    #[test]
+    fn presets_from_older_anki_versions() {
+        let preset = json!({ "fsrsWeights": vec![0.4; 17], "fsrsParams5": [] });
+        let read = AnkiPreset::from_json(&preset).unwrap();
+        assert_eq!(read.parameters.len(), 19);
+        assert_eq!(read.desired_retention, 0.9);
+        assert_eq!(read.evaluation, None);
+
+        for invalid in [
+            json!({}),
+            json!({ "fsrsParams5": [1.0, 2.0] }),
+            json!({ "fsrsParams5": "none" }),
+            json!({ "fsrsParams5": DEFAULT_PARAMETERS, "desiredRetention": "high" }),
+        ] {
+            assert_eq!(
+                AnkiPreset::from_json(&invalid).unwrap_err().kind(),
+                ErrorKind::InvalidData,
+                "{invalid}"
+            );
+        }
+    }
+}
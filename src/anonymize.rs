@@ -0,0 +1,172 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::dataset::{FSRSItem, ItemMetadata};
+use crate::item_cache::save_items;
+use crate::optimal_retention::RevlogEntry;
+
+const DAY_MS: i64 = 86_400_000;
+/// Reviews of each card are moved back by up to this many whole days.
+const MAX_SHIFT_DAYS: i64 = 365;
+
+/// Maps each distinct id to a number in `1..=n`, in a random order.
+fn shuffled_ids<T: Copy + Eq + Hash>(
+    ids: impl Iterator<Item = T>,
+    rng: &mut StdRng,
+) -> HashMap<T, i64> {
+    let mut distinct: Vec<T> = vec![];
+    let mut seen = HashSet::new();
+    for id in ids {
+        if seen.insert(id) {
+            distinct.push(id);
+        }
+    }
+    let mut new_ids: Vec<i64> = (1..=distinct.len() as i64).collect();
+    new_ids.shuffle(rng);
+    distinct.into_iter().zip(new_ids).collect()
+}
+
+/// Strip identifying information from a review log so it can be shared in a bug report.
+/// Card ids are replaced with shuffled sequential ids, and the reviews of each card are
+/// moved back by a random number of whole days, so the day each review falls on relative
+/// to the other reviews of the same card (and hence the `delta_t`s and ratings produced
+/// by the convertor) is preserved. The update sequence number is cleared.
+pub fn anonymize_revlogs(revlogs: &[RevlogEntry], seed: u64) -> Vec<RevlogEntry> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let cids = shuffled_ids(revlogs.iter().map(|r| r.cid), &mut rng);
+    let mut shifts: HashMap<i64, i64> = HashMap::new();
+    let mut result: Vec<RevlogEntry> = revlogs
+        .iter()
+        .map(|r| {
+            let shift = *shifts
+                .entry(r.cid)
+                .or_insert_with(|| rng.gen_range(0..=MAX_SHIFT_DAYS) * DAY_MS);
+            RevlogEntry {
+                id: r.id - shift,
+                cid: cids[&r.cid],
+                usn: 0,
+                ..*r
+            }
+        })
+        .collect();
+    result.sort_by_key(|r| (r.cid, r.id));
+    result
+}
+
+/// Strip identifying information from items so they can be shared in a bug report.
+/// Card and deck ids are replaced with shuffled sequential ids; the reviews and the
+/// order of the items are unchanged.
+pub fn anonymize_items(items: &[FSRSItem], seed: u64) -> Vec<FSRSItem> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let metadata = || items.iter().filter_map(|item| item.metadata);
+    let cards = shuffled_ids(metadata().filter_map(|m| m.card_id), &mut rng);
+    let decks = shuffled_ids(metadata().filter_map(|m| m.deck_id), &mut rng);
+    items
+        .iter()
+        .map(|item| FSRSItem {
+            reviews: item.reviews.clone(),
+            metadata: item.metadata.map(|m| ItemMetadata {
+                card_id: m.card_id.map(|id| cards[&id]),
+                deck_id: m.deck_id.map(|id| decks[&id]),
+            }),
+        })
+        .collect()
+}
+
+/// Anonymize items with [anonymize_items] and save them with [save_items], producing a
+/// file that can be attached to a bug report and loaded with [crate::item_cache::load_items].
+pub fn export_anonymized_items(
+    items: &[FSRSItem],
+    seed: u64,
+    path: impl AsRef<Path>,
+) -> std::io::Result<()> {
+    save_items(&anonymize_items(items, seed), path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convertor::{revlogs_to_fsrs_items, ConvertorConfig};
+    use crate::dataset::FSRSReview;
+    use crate::item_cache::load_items;
+    use crate::optimal_retention::RevlogReviewKind;
+    use crate::test_helpers::item_from;
+
+    #[test]
+    fn anonymized_revlogs_preserve_items() {
+        let start = 1_700_000_000_000;
+        let entry = |cid, id, button_chosen, review_kind| RevlogEntry {
+            id,
+            cid,
+            usn: 42,
+            button_chosen,
+            review_kind,
+            ..Default::default()
+        };
+        let revlogs = vec![
+            entry(1001, start, 1, RevlogReviewKind::Learning),
+            entry(1001, start + 60_000, 3, RevlogReviewKind::Learning),
+            entry(1001, start + 3 * DAY_MS, 3, RevlogReviewKind::Review),
+            entry(2002, start + DAY_MS, 3, RevlogReviewKind::Learning),
+            entry(2002, start + 5 * DAY_MS, 2, RevlogReviewKind::Review),
+            entry(2002, start + 9 * DAY_MS, 4, RevlogReviewKind::Review),
+        ];
+        let anonymized = anonymize_revlogs(&revlogs, 7);
+        assert_eq!(anonymized.len(), revlogs.len());
+        assert!(anonymized
+            .iter()
+            .all(|r| (1..=2).contains(&r.cid) && r.usn == 0));
+
+        let reviews = |revlogs: Vec<RevlogEntry>| {
+            let mut reviews: Vec<Vec<FSRSReview>> =
+                revlogs_to_fsrs_items(revlogs, &ConvertorConfig::default())
+                    .into_iter()
+                    .map(|item| item.reviews)
+                    .collect();
+            reviews.sort_by_key(|r| r.iter().map(|r| (r.rating, r.delta_t)).collect::<Vec<_>>());
+            reviews
+        };
+        assert_eq!(reviews(anonymized), reviews(revlogs));
+    }
+
+    #[test]
+    fn anonymized_items() {
+        let item = |card_id, deck_id| FSRSItem {
+            metadata: Some(ItemMetadata {
+                card_id: Some(card_id),
+                deck_id,
+            }),
+            ..item_from(&[(3, 0)])
+        };
+        let items = vec![
+            item(1_600_000_000_000, Some(1_500_000_000_000)),
+            item(1_600_000_000_001, Some(1_500_000_000_000)),
+            item(1_600_000_000_000, None),
+        ];
+        let anonymized = anonymize_items(&items, 1);
+        let ids: Vec<_> = anonymized
+            .iter()
+            .map(|item| {
+                let m = item.metadata.unwrap();
+                (m.card_id.unwrap(), m.deck_id)
+            })
+            .collect();
+        assert!(ids.iter().all(|(card, _)| (1..=2).contains(card)));
+        assert_ne!(ids[0].0, ids[1].0);
+        assert_eq!(ids[0].0, ids[2].0);
+        assert_eq!(ids[0].1, Some(1));
+        assert_eq!(ids[1].1, Some(1));
+        assert_eq!(ids[2].1, None);
+        assert_eq!(anonymize_items(&items, 1), anonymized);
+
+        let path = std::env::temp_dir().join("fsrs_anonymized_items.bin");
+        export_anonymized_items(&items, 1, &path).unwrap();
+        assert_eq!(load_items(&path).unwrap(), anonymized);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+use crate::error::Result;
+use crate::inference::Parameters;
+use crate::FSRS;
+
+#[cfg(feature = "backend-wgpu")]
+type SelectedBackend = burn::backend::Wgpu;
+
+#[cfg(all(feature = "backend-tch", not(feature = "backend-wgpu")))]
+type SelectedBackend = burn::backend::LibTorch;
+
+#[cfg(not(any(feature = "backend-wgpu", feature = "backend-tch")))]
+type SelectedBackend = burn::backend::NdArray;
+
+/// The burn backend chosen with the `backend-*` cargo features, used by [FSRS::new] and
+/// [FSRS::default_for_platform], and the default backend of [FSRS], [FSRSBuilder](crate::FSRSBuilder)
+/// and [Scheduler](crate::Scheduler).
+///
+/// `backend-ndarray` (the default) runs on the CPU. `backend-wgpu` adds GPU support through WebGPU (Vulkan, Metal, DirectX), and
+/// `backend-tch` through LibTorch (CUDA, MPS), which is downloaded when building. When
+/// several are enabled, this is the first of wgpu, LibTorch and NdArray, which is also used
+/// when none are, eg with `default-features = false`.
+pub type DefaultBackend = SelectedBackend;
+
+/// The device of [DefaultBackend] expected to be fastest on this machine.
+pub fn default_device() -> <DefaultBackend as burn::tensor::backend::Backend>::Device {
+    #[cfg(feature = "backend-wgpu")]
+    {
+        // the first discrete GPU, falling back to integrated ones and then the CPU
+        burn::backend::wgpu::WgpuDevice::BestAvailable
+    }
+    #[cfg(all(feature = "backend-tch", not(feature = "backend-wgpu")))]
+    {
+        use burn::backend::libtorch::LibTorchDevice;
+        if tch::Cuda::is_available() {
+            LibTorchDevice::Cuda(0)
+        } else if tch::utils::has_mps() {
+            LibTorchDevice::Mps
+        } else {
+            LibTorchDevice::Cpu
+        }
+    }
+    #[cfg(not(any(feature = "backend-wgpu", feature = "backend-tch")))]
+    {
+        burn::backend::ndarray::NdArrayDevice::Cpu
+    }
+}
+
+impl FSRS<DefaultBackend> {
+    /// The same as [FSRS::new], which runs on the backend selected with the cargo features,
+    /// and the device of that backend expected to be fastest on this machine.
+    pub fn default_for_platform(parameters: Option<&Parameters>) -> Result<Self> {
+        Self::new_with_backend(parameters, default_device())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
+    use crate::{ModelConfig, TrainingConfig, DEFAULT_PARAMETERS};
+
+    #[test]
+    fn default_for_platform() -> Result<()> {
+        let fsrs = FSRS::default_for_platform(Some(&[]))?;
+        let next = fsrs.next_states(None, 0.9, 0)?;
+        let reference = FSRS::new(Some(&DEFAULT_PARAMETERS))?.next_states(None, 0.9, 0)?;
+        assert!((next.good.memory.stability - reference.good.memory.stability).abs() < 1e-4);
+        assert!((next.good.memory.difficulty - reference.good.memory.difficulty).abs() < 1e-4);
+        Ok(())
+    }
+
+    /// With `--features backend-wgpu` or `backend-tch`, checks that training and evaluation
+    /// on the GPU match the CPU.
+    #[test]
+    fn training_matches_ndarray() -> Result<()> {
+        let items = generate_sample_items(&SampleDatasetConfig {
+            reviews_per_card: 5,
+            ..Default::default()
+        })?;
+        let config = TrainingConfig::new(ModelConfig::default()).with_num_epochs(1);
+        let fsrs = FSRS::default_for_platform(None)?;
+        let parameters = fsrs.compute_parameters_with_config(items.clone(), None, &config)?;
+        let reference =
+            FSRS::new(None)?.compute_parameters_with_config(items.clone(), None, &config)?;
+        assert!(parameters
+            .iter()
+            .zip(&reference)
+            .all(|(a, b)| (a - b).abs() < 1e-2));
+
+        let evaluation =
+            FSRS::default_for_platform(Some(&parameters))?.evaluate(items.clone(), |_| true)?;
+        let reference = FSRS::new(Some(&parameters))?.evaluate(items, |_| true)?;
+        assert!((evaluation.log_loss - reference.log_loss).abs() < 1e-4);
+        assert!((evaluation.rmse_bins - reference.rmse_bins).abs() < 1e-4);
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+use burn::tensor::backend::Backend;
+
+use crate::dataset::FSRSItem;
+use crate::error::Result;
+use crate::inference::{ItemProgress, ModelEvaluation};
+use crate::optimal_retention::SimulatorConfig;
+use crate::training::CombinedProgressState;
+use crate::FSRS;
+
+struct TaskState<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Blocking work running on a dedicated thread. Await it from async code, or call
+/// [BackgroundTask::join] to block until it is done. The work can be stopped with the
+/// [CancellationToken](crate::CancellationToken) of the [FSRS] instance that started it.
+///
+/// The work keeps running if the task is dropped. If it panics, the panic is resumed when
+/// the result is awaited or joined.
+pub struct BackgroundTask<T> {
+    state: Arc<Mutex<TaskState<T>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> BackgroundTask<T> {
+    /// Run `work` on a new thread.
+    pub fn spawn(work: impl FnOnce() -> T + Send + 'static) -> Self {
+        let state = Arc::new(Mutex::new(TaskState {
+            result: None,
+            waker: None,
+        }));
+        let thread_state = state.clone();
+        let thread = thread::spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(work));
+            let mut state = thread_state.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        Self {
+            state,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl<T> BackgroundTask<T> {
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().result.is_some()
+    }
+
+    /// Block the current thread until the work is done, and return its result.
+    pub fn join(mut self) -> T {
+        if let Some(thread) = self.thread.take() {
+            // panics are caught on the thread, and resumed below
+            let _ = thread.join();
+        }
+        let result = self.state.lock().unwrap().result.take();
+        unwrap_result(result.expect("the result is set before the thread exits"))
+    }
+}
+
+fn unwrap_result<T>(result: thread::Result<T>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+impl<T> Future for BackgroundTask<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(unwrap_result(result)),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Async versions of the long-running methods, for GUI and server hosts that must not block
+/// their event loop. Each runs on its own thread with a clone of this instance, so later
+/// calls to [FSRS::set_parameters] don't affect it.
+impl<B: Backend> FSRS<B> {
+    /// [FSRS::compute_parameters] on a background thread.
+    pub fn compute_parameters_async(
+        &self,
+        train_set: Vec<FSRSItem>,
+        progress: Option<Arc<Mutex<CombinedProgressState>>>,
+        enable_short_term: bool,
+    ) -> BackgroundTask<Result<Vec<f32>>> {
+        let fsrs = self.clone();
+        BackgroundTask::spawn(move || {
+            fsrs.compute_parameters(train_set, progress, enable_short_term)
+        })
+    }
+
+    /// [FSRS::evaluate] on a background thread.
+    pub fn evaluate_async<F>(
+        &self,
+        items: Vec<FSRSItem>,
+        progress: F,
+    ) -> BackgroundTask<Result<ModelEvaluation>>
+    where
+        F: FnMut(ItemProgress) -> bool + Send + 'static,
+    {
+        let fsrs = self.clone();
+        BackgroundTask::spawn(move || fsrs.evaluate(items, progress))
+    }
+
+    /// [FSRS::optimal_retention] on a background thread.
+    pub fn optimal_retention_async<F>(
+        &self,
+        config: SimulatorConfig,
+        parameters: Vec<f32>,
+        progress: F,
+    ) -> BackgroundTask<Result<f32>>
+    where
+        F: FnMut(ItemProgress) -> bool + Send + 'static,
+    {
+        let fsrs = self.clone();
+        BackgroundTask::spawn(move || fsrs.optimal_retention(&config, &parameters, progress))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::item_from;
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    use crate::error::FSRSError;
+    use crate::progress::{CancellationToken, ProgressStage};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// A minimal executor, so the tests don't need an async runtime.
+    fn block_on<F: Future + Unpin>(mut future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match Pin::new(&mut future).poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    fn items() -> Vec<FSRSItem> {
+        vec![item_from(&[(3, 0), (3, 2)]); 1000]
+    }
+
+    #[test]
+    fn background_tasks() -> Result<()> {
+        let fsrs = FSRS::new(Some(&[]))?;
+        let expected = fsrs.evaluate(items(), |_| true)?;
+        let task = fsrs.evaluate_async(items(), |_| true);
+        assert_eq!(block_on(task)?, expected);
+        assert_eq!(fsrs.evaluate_async(items(), |_| true).join()?, expected);
+
+        let task = BackgroundTask::spawn(|| 2 + 2);
+        assert_eq!(task.join(), 4);
+        let task = BackgroundTask::spawn(|| -> i32 { panic!("failed") });
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| task.join())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cancellation_token() -> Result<()> {
+        let token = CancellationToken::new();
+        let fsrs = FSRS::new(Some(&[]))?.with_cancellation_token(token.clone());
+        assert!(fsrs.evaluate(items(), |_| true).is_ok());
+
+        token.cancel();
+        assert_eq!(
+            fsrs.evaluate_async(items(), |_| true).join().unwrap_err(),
+            FSRSError::Interrupted {
+                stage: ProgressStage::Evaluation
+            }
+        );
+        assert_eq!(
+            fsrs.optimal_retention(&SimulatorConfig::default(), &[], |_| true)
+                .unwrap_err(),
+            FSRSError::Interrupted {
+                stage: ProgressStage::OptimalRetention
+            }
+        );
+        let train_set = items()
+            .into_iter()
+            .chain(vec![item_from(&[(1, 0), (3, 1), (3, 3)]); 100])
+            .collect();
+        assert_eq!(
+            fsrs.compute_parameters_async(train_set, None, false)
+                .join()
+                .unwrap_err(),
+            FSRSError::Interrupted {
+                stage: ProgressStage::Training
+            }
+        );
+        Ok(())
+    }
+}
@@ -14,9 +14,18 @@ pub(crate) struct BatchTensorDataset<B: Backend> {
 }
 
 impl<B: Backend> BatchTensorDataset<B> {
-    /// Creates a new shuffled dataset.
-    pub fn new(dataset: FSRSDataset, batch_size: usize, device: B::Device) -> Self {
-        let batcher = FSRSBatcher::<B>::new(device);
+    /// Creates a new shuffled dataset. With `max_seq_len`, the histories are truncated to
+    /// their most recent reviews.
+    pub fn new(
+        dataset: FSRSDataset,
+        batch_size: usize,
+        max_seq_len: Option<usize>,
+        device: B::Device,
+    ) -> Self {
+        let mut batcher = FSRSBatcher::<B>::new(device);
+        if let Some(max_seq_len) = max_seq_len {
+            batcher = batcher.with_max_seq_len(max_seq_len);
+        }
         let dataset = dataset
             .items
             .chunks(batch_size)
@@ -36,7 +45,7 @@ impl<B: Backend> BatchTensorDataset<B> {
     }
 }
 
-pub struct ShuffleDataLoader<B: Backend> {
+pub(crate) struct ShuffleDataLoader<B: Backend> {
     dataset: BatchTensorDataset<B>,
     rng: Mutex<rand::rngs::StdRng>,
 }
@@ -92,6 +101,14 @@ impl<B: Backend> DataLoaderIterator<FSRSBatch<B>> for ShuffleDataLoaderIterator<
 }
 
 impl<B: Backend> ShuffleDataLoader<B> {
+    /// Advance the RNG as if [ShuffleDataLoader::iter] had been called `epochs` times.
+    pub(crate) fn skip_epochs(&self, epochs: usize) {
+        let mut indices: Vec<_> = (0..self.dataset.len()).collect();
+        for _ in 0..epochs {
+            indices.shuffle(&mut *self.rng.lock().unwrap());
+        }
+    }
+
     pub(crate) fn iter(&self) -> ShuffleDataLoaderIterator<B> {
         let mut indices: Vec<_> = (0..self.dataset.len()).collect();
         indices.shuffle(&mut *self.rng.lock().unwrap());
@@ -110,7 +127,7 @@ mod tests {
     use super::*;
     use crate::{
         convertor_tests::anki21_sample_file_converted_to_fsrs,
-        dataset::{constant_weighted_fsrs_items, prepare_training_data},
+        dataset::{constant_weighted_fsrs_items, prepare_training_data, OutlierFilterConfig},
     };
 
     #[test]
@@ -119,14 +136,15 @@ mod tests {
             .into_iter()
             .sorted_by_cached_key(|item| item.reviews.len())
             .collect();
-        let (_pre_train_set, train_set) = prepare_training_data(train_set);
+        let (_pre_train_set, train_set) =
+            prepare_training_data(train_set, Some(&OutlierFilterConfig::new()));
         let dataset = FSRSDataset::from(constant_weighted_fsrs_items(train_set));
         let batch_size = 512;
         let seed = 114514;
         let device = NdArrayDevice::Cpu;
         type Backend = NdArray<f32>;
 
-        let dataset = BatchTensorDataset::<Backend>::new(dataset, batch_size, device);
+        let dataset = BatchTensorDataset::<Backend>::new(dataset, batch_size, None, device);
         let dataloader = ShuffleDataLoader::new(dataset, seed);
         let mut iterator = dataloader.iter();
         // dbg!(&iterator.indices);
@@ -0,0 +1,151 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::optimal_retention::{simulate, SimulatorConfig};
+use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
+use crate::FSRS;
+
+/// Controls the workloads measured by [run_benchmarks].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkConfig {
+    /// The synthetic items trained and evaluated on.
+    pub dataset: SampleDatasetConfig,
+    /// The collection simulated.
+    pub simulator: SimulatorConfig,
+    /// The number of times each workload is run. The reported time is the fastest run.
+    pub runs: usize,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            dataset: SampleDatasetConfig {
+                card_count: 500,
+                ..Default::default()
+            },
+            simulator: SimulatorConfig {
+                deck_size: 3650,
+                learn_span: 365,
+                max_cost_perday: f32::INFINITY,
+                learn_limit: 10,
+                ..Default::default()
+            },
+            runs: 3,
+        }
+    }
+}
+
+/// The time taken by one workload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    /// `training`, `evaluation` or `simulation`.
+    pub name: String,
+    /// The amount of work done in one run, in `unit`s.
+    pub count: usize,
+    /// `items` for training and evaluation, `days` for the simulation.
+    pub unit: String,
+    /// The fastest run, in seconds.
+    pub seconds: f64,
+    pub per_second: f64,
+}
+
+/// The output of [run_benchmarks]. Serialize it (eg with serde_json) to compare runs across
+/// commits and machines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    /// The version of this crate that was measured.
+    pub fsrs_version: String,
+    pub results: Vec<BenchmarkResult>,
+}
+
+fn measure(
+    name: &str,
+    count: usize,
+    unit: &str,
+    runs: usize,
+    mut run: impl FnMut() -> Result<()>,
+) -> Result<BenchmarkResult> {
+    let mut seconds = f64::INFINITY;
+    for _ in 0..runs.max(1) {
+        let start = Instant::now();
+        run()?;
+        seconds = seconds.min(start.elapsed().as_secs_f64());
+    }
+    Ok(BenchmarkResult {
+        name: name.into(),
+        count,
+        unit: unit.into(),
+        seconds,
+        per_second: count as f64 / seconds,
+    })
+}
+
+/// Measure training throughput, evaluation throughput and simulation speed on synthetic
+/// fixtures generated from `config`. The fixtures are deterministic, so reports from
+/// different builds measure the same work.
+pub fn run_benchmarks(config: &BenchmarkConfig) -> Result<BenchmarkReport> {
+    let items = generate_sample_items(&config.dataset)?;
+    let fsrs = FSRS::new(Some(&config.dataset.parameters))?;
+    let training = measure("training", items.len(), "items", config.runs, || {
+        fsrs.compute_parameters(items.clone(), None, false)?;
+        Ok(())
+    })?;
+    let evaluation = measure("evaluation", items.len(), "items", config.runs, || {
+        fsrs.evaluate(items.clone(), |_| true)?;
+        Ok(())
+    })?;
+    let simulation = measure(
+        "simulation",
+        config.simulator.learn_span,
+        "days",
+        config.runs,
+        || {
+            simulate(
+                &config.simulator,
+                &config.dataset.parameters,
+                0.9,
+                Some(42),
+                None,
+            )?;
+            Ok(())
+        },
+    )?;
+    Ok(BenchmarkReport {
+        fsrs_version: env!("CARGO_PKG_VERSION").to_string(),
+        results: vec![training, evaluation, simulation],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benchmark_report() -> Result<()> {
+        let config = BenchmarkConfig {
+            dataset: SampleDatasetConfig {
+                card_count: 10,
+                ..Default::default()
+            },
+            simulator: SimulatorConfig {
+                deck_size: 100,
+                learn_span: 30,
+                ..Default::default()
+            },
+            runs: 1,
+        };
+        let report = run_benchmarks(&config)?;
+        let names: Vec<_> = report.results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["training", "evaluation", "simulation"]);
+        assert_eq!(report.results[0].count, 100);
+        assert_eq!(report.results[2].count, 30);
+        assert!(report.results.iter().all(|r| r.per_second > 0.0));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["results"][1]["unit"], "items");
+        assert_eq!(json["results"][2]["count"], 30);
+        Ok(())
+    }
+}
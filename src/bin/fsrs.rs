@@ -0,0 +1,217 @@
+//! Command line interface to the optimizer.
+//!
+//! Review logs are read from a CSV file with the columns
+//! `card_id,review_time,review_rating,review_state`, where `review_time` is in milliseconds
+//! since the epoch and `review_state` is 0 (new), 1 (learning), 2 (review), 3 (relearning),
+//! 4 (filtered) or 5 (manual). Parameters are read and written as a [ParameterFile], and
+//! simulator configs as a JSON object with the fields of [SimulatorConfig]; missing fields
+//! take their default values.
+
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono_tz::Tz;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use fsrs::convertor::revlogs_to_fsrs_items;
+use fsrs::{
+    simulate, ConvertorConfig, FSRSItem, ModelConfig, ParameterFile, RevlogEntry, RevlogReviewKind,
+    SimulatorConfig, TrainingConfig, DEFAULT_PARAMETERS, FSRS,
+};
+use serde::Deserialize;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Debug, Deserialize)]
+struct RevlogCsv {
+    card_id: i64,
+    review_time: i64,
+    review_rating: u8,
+    review_state: u8,
+}
+
+fn command() -> Command {
+    let input = Arg::new("input")
+        .long("input")
+        .required(true)
+        .help("Review log CSV file");
+    let params = Arg::new("params")
+        .long("params")
+        .help("JSON file with the parameters to use [default: the default parameters]");
+    let timezone = Arg::new("timezone")
+        .long("timezone")
+        .default_value("UTC")
+        .help("Timezone the reviews were done in, eg Europe/Berlin");
+    let next_day_starts_at = Arg::new("next-day-starts-at")
+        .long("next-day-starts-at")
+        .value_parser(value_parser!(i64))
+        .default_value("4")
+        .help("Hour at which a new day starts");
+    let config = Arg::new("config")
+        .long("config")
+        .help("JSON file with the simulator config [default: the default config]");
+    let seed = Arg::new("seed")
+        .long("seed")
+        .value_parser(value_parser!(u64))
+        .help("Seed for the simulation");
+    Command::new("fsrs")
+        .about("Train, evaluate and simulate FSRS")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("train")
+                .about("Compute parameters from a review log")
+                .arg(input.clone())
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .required(true)
+                        .help("File to write the parameters to"),
+                )
+                .arg(
+                    Arg::new("short-term")
+                        .long("short-term")
+                        .action(ArgAction::SetTrue)
+                        .help("Also fit the parameters for same-day reviews"),
+                )
+                .arg(timezone.clone())
+                .arg(next_day_starts_at.clone()),
+        )
+        .subcommand(
+            Command::new("evaluate")
+                .about("Print the log loss and RMSE of parameters on a review log")
+                .arg(input)
+                .arg(params.clone())
+                .arg(timezone)
+                .arg(next_day_starts_at),
+        )
+        .subcommand(
+            Command::new("simulate")
+                .about("Simulate reviews with the given parameters and print a summary")
+                .arg(params.clone())
+                .arg(config.clone())
+                .arg(
+                    Arg::new("desired-retention")
+                        .long("desired-retention")
+                        .value_parser(value_parser!(f32))
+                        .default_value("0.9"),
+                )
+                .arg(seed),
+        )
+        .subcommand(
+            Command::new("optimal-retention")
+                .about("Find the desired retention that minimizes the workload")
+                .arg(params)
+                .arg(config),
+        )
+}
+
+fn review_kind(state: u8) -> Result<RevlogReviewKind> {
+    Ok(match state {
+        0 | 1 => RevlogReviewKind::Learning,
+        2 => RevlogReviewKind::Review,
+        3 => RevlogReviewKind::Relearning,
+        4 => RevlogReviewKind::Filtered,
+        5 => RevlogReviewKind::Manual,
+        _ => return Err(format!("invalid review state {state}").into()),
+    })
+}
+
+fn read_items(args: &ArgMatches) -> Result<Vec<FSRSItem>> {
+    let path = args.get_one::<String>("input").unwrap();
+    let revlogs = csv::Reader::from_path(path)?
+        .deserialize()
+        .map(|row| {
+            let row: RevlogCsv = row?;
+            Ok(RevlogEntry {
+                id: row.review_time,
+                cid: row.card_id,
+                button_chosen: row.review_rating,
+                review_kind: review_kind(row.review_state)?,
+                ..Default::default()
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let config = ConvertorConfig {
+        timezone: args.get_one::<String>("timezone").unwrap().parse::<Tz>()?,
+        next_day_starts_at: *args.get_one("next-day-starts-at").unwrap(),
+        ..Default::default()
+    };
+    Ok(revlogs_to_fsrs_items(revlogs, &config))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: impl AsRef<Path>) -> Result<T> {
+    Ok(serde_json::from_reader(File::open(path)?)?)
+}
+
+fn read_params(args: &ArgMatches) -> Result<Vec<f32>> {
+    match args.get_one::<String>("params") {
+        Some(path) => Ok(ParameterFile::load(path)?.parameters),
+        None => Ok(DEFAULT_PARAMETERS.to_vec()),
+    }
+}
+
+fn read_simulator_config(args: &ArgMatches) -> Result<SimulatorConfig> {
+    match args.get_one::<String>("config") {
+        Some(path) => read_json(path),
+        None => Ok(SimulatorConfig::default()),
+    }
+}
+
+fn main() -> Result<()> {
+    match command().get_matches().subcommand() {
+        Some(("train", args)) => {
+            let items = read_items(args)?;
+            let item_count = items.len();
+            println!("training on {item_count} items");
+            let fsrs = FSRS::new(None)?;
+            let parameters =
+                fsrs.compute_parameters(items.clone(), None, args.get_flag("short-term"))?;
+            let evaluation = FSRS::new(Some(&parameters))?.evaluate(items, |_| true)?;
+            let file = ParameterFile {
+                trained_at: Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64),
+                item_count: Some(item_count),
+                seed: Some(TrainingConfig::new(ModelConfig::default()).seed),
+                evaluation: Some(evaluation),
+                ..ParameterFile::new(parameters)
+            };
+            file.save(args.get_one::<String>("out").unwrap())?;
+            println!("parameters: {:?}", file.parameters);
+        }
+        Some(("evaluate", args)) => {
+            let items = read_items(args)?;
+            let fsrs = FSRS::new(Some(&read_params(args)?))?;
+            let evaluation = fsrs.evaluate(items, |_| true)?;
+            println!("log loss: {:.4}", evaluation.log_loss);
+            println!("RMSE (bins): {:.4}", evaluation.rmse_bins);
+            if let Some(auc) = evaluation.auc {
+                println!("AUC: {auc:.4}");
+            }
+        }
+        Some(("simulate", args)) => {
+            let config = read_simulator_config(args)?;
+            let desired_retention = *args.get_one("desired-retention").unwrap();
+            let seed = args.get_one::<u64>("seed").copied();
+            let result = simulate(&config, &read_params(args)?, desired_retention, seed, None)?;
+            println!(
+                "memorized at the end: {:.0}",
+                result
+                    .memorized_cnt_per_day
+                    .last()
+                    .copied()
+                    .unwrap_or_default()
+            );
+            println!("reviews: {}", result.review_cnt_per_day.sum());
+            println!("new cards learnt: {}", result.learn_cnt_per_day.sum());
+            println!("total time (s): {:.0}", result.cost_per_day.sum());
+        }
+        Some(("optimal-retention", args)) => {
+            let config = read_simulator_config(args)?;
+            let fsrs = FSRS::new(None)?;
+            let retention = fsrs.optimal_retention(&config, &read_params(args)?, |_| true)?;
+            println!("optimal retention: {retention:.2}");
+        }
+        _ => unreachable!("a subcommand is required"),
+    }
+    Ok(())
+}
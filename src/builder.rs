@@ -0,0 +1,318 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use burn::tensor::backend::Backend;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::backend::{default_device, DefaultBackend};
+use crate::error::{FSRSError, Result};
+use crate::events::{EventHook, FSRSEvent};
+use crate::forgetting_curve::CurveKind;
+use crate::model::{check_and_fill_parameters, parameters_to_model, Model};
+use crate::progress::{CancellationToken, ProgressHandle};
+use crate::training::TrainingConfig;
+use crate::FSRS;
+
+/// Configures an [FSRS] instance in one call, eg
+///
+/// ```
+/// # use fsrs::{FSRSBuilder, Result};
+/// # fn main() -> Result<()> {
+/// let fsrs = FSRSBuilder::new().parameters(&[]).max_threads(2).build()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Options that are not set keep the defaults of [FSRS::new]. New options are added here,
+/// rather than as new constructors. The `FSRS::with_*` methods are shorthands for changing
+/// one option of an existing instance through this builder.
+pub struct FSRSBuilder<B: Backend = DefaultBackend> {
+    parameters: Option<Vec<f32>>,
+    /// The model of an existing instance, which is kept unless new parameters are set.
+    model: Option<Model<B>>,
+    device: B::Device,
+    thread_pool: Option<Arc<ThreadPool>>,
+    max_threads: Option<usize>,
+    cancellation_token: Option<CancellationToken>,
+    progress_handle: Option<ProgressHandle>,
+    event_hook: Option<EventHook>,
+    training_config: Option<TrainingConfig>,
+    forgetting_curve: CurveKind,
+    linear_damping: bool,
+    exponential_initial_difficulty: bool,
+}
+
+impl FSRSBuilder<DefaultBackend> {
+    /// An instance on the [default_device] of the [DefaultBackend], without parameters.
+    pub fn new() -> Self {
+        Self::on_device(default_device())
+    }
+}
+
+impl FSRS<DefaultBackend> {
+    /// See [FSRSBuilder].
+    pub fn builder() -> FSRSBuilder<DefaultBackend> {
+        FSRSBuilder::new()
+    }
+}
+
+impl Default for FSRSBuilder<DefaultBackend> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: Backend> FSRSBuilder<B> {
+    pub(crate) fn on_device(device: B::Device) -> Self {
+        Self {
+            parameters: None,
+            model: None,
+            device,
+            thread_pool: None,
+            max_threads: None,
+            cancellation_token: None,
+            progress_handle: None,
+            event_hook: None,
+            training_config: None,
+            forgetting_curve: CurveKind::default(),
+            linear_damping: true,
+            exponential_initial_difficulty: true,
+        }
+    }
+
+    /// Run on `device` of another backend, as with [FSRS::new_with_backend].
+    pub fn device<B2: Backend>(self, device: B2::Device) -> FSRSBuilder<B2> {
+        // a model can't be moved to another backend, but its parameters can
+        let parameters = match self.model {
+            Some(model) => Some(model.w.val().to_data().convert().value),
+            None => self.parameters,
+        };
+        FSRSBuilder {
+            parameters,
+            model: None,
+            device,
+            thread_pool: self.thread_pool,
+            max_threads: self.max_threads,
+            cancellation_token: self.cancellation_token,
+            progress_handle: self.progress_handle,
+            event_hook: self.event_hook,
+            training_config: self.training_config,
+            forgetting_curve: self.forgetting_curve,
+            linear_damping: self.linear_damping,
+            exponential_initial_difficulty: self.exponential_initial_difficulty,
+        }
+    }
+
+    /// The parameters used by commands that need them. May be empty to use the defaults.
+    pub fn parameters(mut self, parameters: &[f32]) -> Self {
+        self.parameters = Some(parameters.to_vec());
+        self.model = None;
+        self
+    }
+
+    /// Run parallel work, such as the simulations done by
+    /// [FSRS::optimal_retention](crate::FSRS::optimal_retention), on `thread_pool` instead
+    /// of rayon's global pool. Replaces [FSRSBuilder::max_threads].
+    pub fn thread_pool(mut self, thread_pool: Arc<ThreadPool>) -> Self {
+        self.thread_pool = Some(thread_pool);
+        self.max_threads = None;
+        self
+    }
+
+    /// Run parallel work on a new pool of at most `max_threads` threads, instead of rayon's
+    /// global pool. Replaces [FSRSBuilder::thread_pool].
+    pub fn max_threads(mut self, max_threads: usize) -> Self {
+        self.max_threads = Some(max_threads);
+        self.thread_pool = None;
+        self
+    }
+
+    /// Stop training, evaluation and optimal retention searches run by the instance once
+    /// `token` is cancelled.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Report the progress of every long-running command of the instance (training,
+    /// evaluation, simulation and the optimal retention searches) to `handle`, and stop
+    /// them when it is cancelled. The progress callbacks passed to each command are still
+    /// called.
+    pub fn progress_handle(mut self, handle: &ProgressHandle) -> Self {
+        self.progress_handle = Some(handle.clone());
+        self.cancellation_token(handle.cancellation_token())
+    }
+
+    /// Call `hook` with the [FSRSEvent]s of the instance, eg to log or report them.
+    pub fn event_hook(mut self, hook: impl Fn(&FSRSEvent) + Send + Sync + 'static) -> Self {
+        self.event_hook = Some(EventHook::new(hook));
+        self
+    }
+
+    /// Train with `config` in [FSRS::compute_parameters], instead of the defaults. Whether
+    /// the initial and short-term stabilities are frozen is still decided by the
+    /// `enable_short_term` argument.
+    pub fn training_config(mut self, config: TrainingConfig) -> Self {
+        self.training_config = Some(config);
+        self
+    }
+
+    /// Use `curve` for scheduling and evaluation, and when training with the default
+    /// [TrainingConfig]. Parameters should be trained on the curve they are used with.
+    pub fn forgetting_curve(mut self, curve: CurveKind) -> Self {
+        self.forgetting_curve = curve;
+        self
+    }
+
+    /// Whether the change in difficulty shrinks as the difficulty grows, as set by
+    /// [ModelConfig::linear_damping](crate::ModelConfig::linear_damping). On by default;
+    /// parameters trained without it should be used without it.
+    pub fn linear_damping(mut self, linear_damping: bool) -> Self {
+        self.linear_damping = linear_damping;
+        self
+    }
+
+    /// Which formula gives the initial difficulty, as set by
+    /// [ModelConfig::exponential_initial_difficulty](crate::ModelConfig::exponential_initial_difficulty).
+    /// On by default. With it off, FSRS-4.5 parameters are used as they are, rather than
+    /// converted.
+    pub fn exponential_initial_difficulty(mut self, exponential: bool) -> Self {
+        self.exponential_initial_difficulty = exponential;
+        self
+    }
+
+    /// Returns an error if the parameters are invalid, or the thread pool can't be created.
+    pub fn build(self) -> Result<FSRS<B>> {
+        let mut model = match (self.model, self.parameters) {
+            (Some(model), _) => Some(model),
+            (None, Some(parameters)) => Some(parameters_to_model::<B>(
+                &check_and_fill_parameters(&parameters, self.exponential_initial_difficulty)?,
+                self.exponential_initial_difficulty,
+            )),
+            (None, None) => None,
+        };
+        if let Some(model) = &mut model {
+            model.config.forgetting_curve = self.forgetting_curve;
+            model.config.linear_damping = self.linear_damping;
+            model.config.exponential_initial_difficulty = self.exponential_initial_difficulty;
+        }
+        let thread_pool = match self.max_threads {
+            Some(max_threads) => Some(Arc::new(
+                ThreadPoolBuilder::new()
+                    .num_threads(max_threads)
+                    .build()
+                    .map_err(|_| FSRSError::InvalidInput)?,
+            )),
+            None => self.thread_pool,
+        };
+        Ok(FSRS {
+            model: Mutex::new(model),
+            device: self.device,
+            thread_pool,
+            cancellation_token: self.cancellation_token,
+            progress_handle: self.progress_handle,
+            event_hook: self.event_hook,
+            training_config: self.training_config,
+            forgetting_curve: self.forgetting_curve,
+            linear_damping: self.linear_damping,
+            exponential_initial_difficulty: self.exponential_initial_difficulty,
+        })
+    }
+}
+
+impl<B: Backend> From<FSRS<B>> for FSRSBuilder<B> {
+    /// A builder with the options of `fsrs`, to change some of them.
+    fn from(fsrs: FSRS<B>) -> Self {
+        Self {
+            parameters: None,
+            model: fsrs
+                .model
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner),
+            device: fsrs.device,
+            thread_pool: fsrs.thread_pool,
+            max_threads: None,
+            cancellation_token: fsrs.cancellation_token,
+            progress_handle: fsrs.progress_handle,
+            event_hook: fsrs.event_hook,
+            training_config: fsrs.training_config,
+            forgetting_curve: fsrs.forgetting_curve,
+            linear_damping: fsrs.linear_damping,
+            exponential_initial_difficulty: fsrs.exponential_initial_difficulty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FSRSError;
+    use crate::model::{InvalidParametersReason, ModelConfig};
+    use crate::progress::ProgressStage;
+    use crate::test_helpers::item_from;
+    use burn::backend::{ndarray::NdArrayDevice, NdArray};
+    use std::sync::Mutex;
+
+    #[test]
+    fn builder() -> Result<()> {
+        let handle = ProgressHandle::new();
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        let fsrs = FSRSBuilder::new()
+            .parameters(&[])
+            .max_threads(1)
+            .progress_handle(&handle)
+            .event_hook(move |event| recorded.lock().unwrap().push(event.clone()))
+            .training_config(TrainingConfig::new(ModelConfig::default()).with_num_epochs(1))
+            .build()?;
+        assert_eq!(fsrs.training_config().unwrap().num_epochs, 1);
+        assert_eq!(fsrs.install(rayon::current_num_threads), 1);
+
+        let items = vec![item_from(&[(3, 0), (3, 2)]); 10];
+        fsrs.evaluate(items.clone(), |_| true)?;
+        assert_eq!(events.lock().unwrap().len(), 1);
+        handle.cancel();
+        assert_eq!(
+            fsrs.evaluate(items, |_| true).unwrap_err(),
+            FSRSError::Interrupted {
+                stage: ProgressStage::Evaluation
+            }
+        );
+
+        let fsrs = FSRS::builder()
+            .device::<NdArray>(NdArrayDevice::Cpu)
+            .build()?;
+        assert!(fsrs.training_config().is_none());
+
+        // the with_* methods keep the parameters and the other options
+        let mut parameters = crate::DEFAULT_PARAMETERS;
+        parameters[0] = 1.0;
+        let fsrs = FSRSBuilder::new()
+            .parameters(&parameters)
+            .forgetting_curve(CurveKind::Exponential)
+            .linear_damping(false)
+            .build()?
+            .with_max_threads(2)?
+            .with_cancellation_token(CancellationToken::new());
+        assert_eq!(fsrs.model().w.val().to_data().value, parameters);
+        assert_eq!(fsrs.model().config.forgetting_curve, CurveKind::Exponential);
+        assert_eq!(fsrs.install(rayon::current_num_threads), 2);
+        let fsrs = fsrs.reconfigure(|builder| builder.max_threads(3).parameters(&[]))?;
+        assert_eq!(fsrs.install(rayon::current_num_threads), 3);
+        assert_eq!(
+            fsrs.model().w.val().to_data().value[0],
+            crate::DEFAULT_PARAMETERS[0]
+        );
+        assert!(!fsrs.model().config.linear_damping);
+        fsrs.set_parameters(&parameters)?;
+        assert!(!fsrs.model().config.linear_damping);
+        assert!(FSRS::new(Some(&[]))?.model().config.linear_damping);
+        assert!(fsrs.cancellation_token().is_some());
+        assert_eq!(
+            FSRSBuilder::new().parameters(&[1.0]).build().unwrap_err(),
+            FSRSError::InvalidParameters {
+                reason: InvalidParametersReason::WrongCount { count: 1 }
+            }
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+
+use crate::inference::PredictionComparison;
+use crate::optimal_retention::SimulationResult;
+
+/// A labelled line or bar series, ready to be passed to a chart library (eg serialized as
+/// JSON for a web view). `x` and `y` have the same length.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartSeries {
+    pub caption: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub x: Vec<f32>,
+    pub y: Vec<f32>,
+}
+
+impl ChartSeries {
+    fn new(caption: &str, x_label: &str, y_label: &str, x: Vec<f32>, y: Vec<f32>) -> Self {
+        Self {
+            caption: caption.into(),
+            x_label: x_label.into(),
+            y_label: y_label.into(),
+            x,
+            y,
+        }
+    }
+}
+
+/// Average consecutive days into at most `max_points` points, each placed at the mean day
+/// of the days it covers.
+fn downsample_days(values: &[f32], max_points: usize) -> (Vec<f32>, Vec<f32>) {
+    let chunk_size = values.len().div_ceil(max_points.max(1)).max(1);
+    values
+        .chunks(chunk_size)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let first_day = (index * chunk_size) as f32;
+            let count = chunk.len() as f32;
+            (
+                first_day + (count - 1.0) / 2.0,
+                chunk.iter().sum::<f32>() / count,
+            )
+        })
+        .unzip()
+}
+
+/// The daily series of a simulation: reviews, new cards, time spent (in minutes) and cards
+/// memorized, each averaged down to at most `max_points` points.
+pub fn simulation_charts(result: &SimulationResult, max_points: usize) -> Vec<ChartSeries> {
+    let daily = |caption, y_label, values: Vec<f32>| {
+        let (x, y) = downsample_days(&values, max_points);
+        ChartSeries::new(caption, "Day", y_label, x, y)
+    };
+    vec![
+        daily(
+            "Reviews per day",
+            "Reviews",
+            result
+                .review_cnt_per_day
+                .iter()
+                .map(|&n| n as f32)
+                .collect(),
+        ),
+        daily(
+            "New cards per day",
+            "Cards",
+            result.learn_cnt_per_day.iter().map(|&n| n as f32).collect(),
+        ),
+        daily(
+            "Time per day",
+            "Minutes",
+            result
+                .cost_per_day
+                .iter()
+                .map(|&cost| cost / 60.0)
+                .collect(),
+        ),
+        daily(
+            "Cards memorized",
+            "Cards",
+            result.memorized_cnt_per_day.to_vec(),
+        ),
+    ]
+}
+
+/// How the predicted retention of the items is distributed, for the parameters of the
+/// instance and the ones compared with, as the share of items in each of `bins` equal-width
+/// ranges between 0 and 1.
+pub fn prediction_comparison_charts(
+    comparison: &PredictionComparison,
+    bins: usize,
+) -> Vec<ChartSeries> {
+    let bins = bins.max(1);
+    let histogram = |caption, retention: Vec<f32>| {
+        let mut counts = vec![0.0; bins];
+        for r in &retention {
+            let bin = ((r * bins as f32) as usize).min(bins - 1);
+            counts[bin] += 1.0;
+        }
+        let total = retention.len().max(1) as f32;
+        ChartSeries::new(
+            caption,
+            "Predicted retention",
+            "Share of items",
+            (0..bins)
+                .map(|bin| (bin as f32 + 0.5) / bins as f32)
+                .collect(),
+            counts.into_iter().map(|count| count / total).collect(),
+        )
+    };
+    vec![
+        histogram(
+            "Current parameters",
+            comparison
+                .predictions
+                .iter()
+                .map(|p| p.retention_self)
+                .collect(),
+        ),
+        histogram(
+            "Other parameters",
+            comparison
+                .predictions
+                .iter()
+                .map(|p| p.retention_other)
+                .collect(),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::PredictionDiff;
+    use crate::optimal_retention::{simulate, SimulatorConfig};
+    use crate::DEFAULT_PARAMETERS;
+
+    #[test]
+    fn downsampling() {
+        let (x, y) = downsample_days(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0], 4);
+        assert_eq!(x, [1.0, 4.0, 7.0, 9.0]);
+        assert_eq!(y, [1.0, 4.0, 7.0, 9.0]);
+        let (x, y) = downsample_days(&[5.0, 7.0], 10);
+        assert_eq!(x, [0.0, 1.0]);
+        assert_eq!(y, [5.0, 7.0]);
+        let (x, y) = downsample_days(&[], 10);
+        assert!(x.is_empty() && y.is_empty());
+    }
+
+    #[test]
+    fn simulation() {
+        let config = SimulatorConfig {
+            deck_size: 1000,
+            learn_span: 365,
+            learn_limit: 10,
+            ..Default::default()
+        };
+        let result = simulate(&config, &DEFAULT_PARAMETERS, 0.9, Some(1), None).unwrap();
+        let charts = simulation_charts(&result, 100);
+        assert_eq!(charts.len(), 4);
+        for chart in &charts {
+            assert_eq!(chart.x.len(), 92);
+            assert_eq!(chart.y.len(), chart.x.len());
+        }
+        // the total number of reviews is kept, as each point averages 4 days
+        let reviews: f32 = charts[0].y[..91].iter().sum::<f32>() * 4.0 + charts[0].y[91];
+        assert!((reviews - result.review_cnt_per_day.sum() as f32).abs() < 1.0);
+        assert_eq!(charts[1].y[0], 10.0);
+
+        let json = serde_json::to_value(&charts[3]).unwrap();
+        assert_eq!(json["caption"], "Cards memorized");
+        assert_eq!(json["x"].as_array().unwrap().len(), 92);
+    }
+
+    #[test]
+    fn prediction_comparison() {
+        let diff = |retention_self, retention_other| PredictionDiff {
+            index: 0,
+            metadata: None,
+            retention_self,
+            retention_other,
+        };
+        let comparison = PredictionComparison {
+            predictions: vec![diff(0.95, 0.85), diff(1.0, 0.85), diff(0.1, 0.85)],
+            largest_divergences: vec![],
+        };
+        let charts = prediction_comparison_charts(&comparison, 10);
+        assert_eq!(charts[0].x[9], 0.95);
+        assert_eq!(charts[0].y[9], 2.0 / 3.0);
+        assert_eq!(charts[0].y[1], 1.0 / 3.0);
+        assert_eq!(charts[1].y[8], 1.0);
+        assert_eq!(charts[1].y.iter().sum::<f32>(), 1.0);
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::Mutex;
+
+use burn::record::{FullPrecisionSettings, Record};
+use burn::tensor::backend::Backend;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{FSRSError, Result};
+
+/// The state of training after the last completed epoch of each split, passed to the
+/// callback of [FSRS::compute_parameters_with_checkpoints](crate::FSRS::compute_parameters_with_checkpoints).
+///
+/// Save it with serde, eg to a file, and pass it back to resume training where it left off,
+/// eg after the app was killed by the OS. Training must be resumed with the same items and
+/// config; the parameters are then the same as if training had not been interrupted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainingCheckpoint {
+    /// The config and number of items training started with.
+    fingerprint: String,
+    splits: Vec<Option<SplitCheckpoint>>,
+}
+
+impl TrainingCheckpoint {
+    pub(crate) fn new(fingerprint: String, n_splits: usize) -> Self {
+        Self {
+            fingerprint,
+            splits: vec![None; n_splits],
+        }
+    }
+
+    /// The number of epochs every split has completed.
+    pub fn epochs_completed(&self) -> usize {
+        self.splits
+            .iter()
+            .map(|split| split.as_ref().map_or(0, |split| split.epoch))
+            .min()
+            .unwrap_or_default()
+    }
+}
+
+/// The state of training of a split after an epoch. Records are stored in their
+/// serialized form, as their types depend on the backend and optimizer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SplitCheckpoint {
+    pub epoch: usize,
+    pub model: Value,
+    pub best_model: Value,
+    /// None if no epoch had a finite loss.
+    pub best_loss: Option<f64>,
+    pub epoch_losses: Vec<f64>,
+    pub optimizer: Value,
+    pub lr_step: usize,
+}
+
+/// Collects the checkpoints of the splits being trained in parallel.
+pub(crate) struct Checkpointer<'a> {
+    resume_from: Option<TrainingCheckpoint>,
+    state: Mutex<Option<TrainingCheckpoint>>,
+    on_checkpoint: &'a (dyn Fn(&TrainingCheckpoint) + Sync),
+}
+
+impl<'a> Checkpointer<'a> {
+    pub fn new(
+        resume_from: Option<TrainingCheckpoint>,
+        on_checkpoint: &'a (dyn Fn(&TrainingCheckpoint) + Sync),
+    ) -> Self {
+        Self {
+            resume_from,
+            state: Mutex::new(None),
+            on_checkpoint,
+        }
+    }
+
+    /// Called once the items to train on are known. The checkpoint to resume from, if
+    /// any, must have been started with the same `fingerprint` and number of splits.
+    pub fn start(&self, fingerprint: String, n_splits: usize) -> Result<()> {
+        let state = match &self.resume_from {
+            Some(checkpoint) => {
+                if checkpoint.fingerprint != fingerprint || checkpoint.splits.len() != n_splits {
+                    return Err(FSRSError::InvalidInput);
+                }
+                checkpoint.clone()
+            }
+            None => TrainingCheckpoint::new(fingerprint, n_splits),
+        };
+        *self.state.lock().unwrap() = Some(state);
+        Ok(())
+    }
+
+    pub fn resume(&self, split: usize) -> Option<SplitCheckpoint> {
+        let state = self.state.lock().unwrap();
+        state.as_ref().and_then(|state| state.splits[split].clone())
+    }
+
+    /// Store the state of `split` and pass the updated checkpoint to the callback.
+    pub fn save(&self, split: usize, checkpoint: SplitCheckpoint) {
+        let mut state = self.state.lock().unwrap();
+        let state = state.as_mut().expect("training was started");
+        state.splits[split] = Some(checkpoint);
+        (self.on_checkpoint)(state);
+    }
+}
+
+pub(crate) fn record_to_value<B: Backend, R: Record<B>>(record: R) -> Value {
+    serde_json::to_value(record.into_item::<FullPrecisionSettings>())
+        .expect("records have string keys")
+}
+
+pub(crate) fn record_from_value<B: Backend, R: Record<B>>(
+    value: &Value,
+    device: &B::Device,
+) -> Result<R> {
+    let item = serde_json::from_value(value.clone()).map_err(|_| FSRSError::InvalidInput)?;
+    Ok(R::from_item::<FullPrecisionSettings>(item, device))
+}
@@ -0,0 +1,229 @@
+use std::fs::File;
+use std::path::Path;
+
+use parquet::errors::{ParquetError, Result as ParquetResult};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::Field;
+
+use crate::convertor::{revlogs_to_fsrs_items, ConvertorConfig};
+use crate::dataset::FSRSItem;
+use crate::error::{FSRSError, Result};
+use crate::optimal_retention::{RevlogEntry, RevlogReviewKind};
+
+/// A review log stored column by column, as read from a Parquet file with
+/// [revlog_columns_from_parquet]. All columns must have the same length; row `i` of each
+/// column describes the same review.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RevlogColumns {
+    pub card_id: Vec<i64>,
+    /// Milliseconds since the Unix epoch.
+    pub review_time: Vec<i64>,
+    /// 1-4 (again, hard, good, easy), or 0 for manual rescheduling.
+    pub rating: Vec<u8>,
+    /// The Anki review kind: 0 learning, 1 review, 2 relearning, 3 filtered, 4 manual.
+    pub review_kind: Vec<u8>,
+    /// Milliseconds taken to answer. May be empty if the dataset doesn't record it.
+    pub duration: Vec<u32>,
+}
+
+fn review_kind(kind: u8) -> Result<RevlogReviewKind> {
+    Ok(match kind {
+        0 => RevlogReviewKind::Learning,
+        1 => RevlogReviewKind::Review,
+        2 => RevlogReviewKind::Relearning,
+        3 => RevlogReviewKind::Filtered,
+        4 => RevlogReviewKind::Manual,
+        _ => return Err(FSRSError::InvalidInput),
+    })
+}
+
+/// Turn columnar review data into revlog entries. Returns [FSRSError::InvalidInput] if
+/// the columns have different lengths, or a rating or review kind is out of range.
+pub fn revlogs_from_columns(columns: &RevlogColumns) -> Result<Vec<RevlogEntry>> {
+    let len = columns.card_id.len();
+    if columns.review_time.len() != len
+        || columns.rating.len() != len
+        || columns.review_kind.len() != len
+        || !(columns.duration.is_empty() || columns.duration.len() == len)
+    {
+        return Err(FSRSError::InvalidInput);
+    }
+    (0..len)
+        .map(|i| {
+            let rating = columns.rating[i];
+            if rating > 4 {
+                return Err(FSRSError::InvalidInput);
+            }
+            Ok(RevlogEntry {
+                id: columns.review_time[i],
+                cid: columns.card_id[i],
+                button_chosen: rating,
+                taken_millis: columns.duration.get(i).copied().unwrap_or_default(),
+                review_kind: review_kind(columns.review_kind[i])?,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// The value of an integer column, whatever its physical and logical type.
+fn integer(field: &Field) -> Option<i64> {
+    Some(match *field {
+        Field::Byte(value) => value.into(),
+        Field::Short(value) => value.into(),
+        Field::Int(value) => value.into(),
+        Field::Long(value) => value,
+        Field::UByte(value) => value.into(),
+        Field::UShort(value) => value.into(),
+        Field::UInt(value) => value.into(),
+        Field::ULong(value) => i64::try_from(value).ok()?,
+        _ => return None,
+    })
+}
+
+/// Read a review log from a Parquet file, with one row per review and integer columns
+/// named after the fields of [RevlogColumns]. The `duration` column is optional, and null
+/// durations are read as 0; other columns are ignored. Returns an error if a required
+/// column is missing, or a value is null or out of range.
+pub fn revlog_columns_from_parquet(path: impl AsRef<Path>) -> ParquetResult<RevlogColumns> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let schema = reader.metadata().file_metadata().schema_descr();
+    let has_column = |name: &str| schema.columns().iter().any(|column| column.name() == name);
+    for name in ["card_id", "review_time", "rating", "review_kind"] {
+        if !has_column(name) {
+            return Err(ParquetError::General(format!("missing column {name}")));
+        }
+    }
+
+    let mut columns = RevlogColumns::default();
+    for row in reader.get_row_iter(None)? {
+        let row = row?;
+        for (name, field) in row.get_column_iter() {
+            let value = integer(field);
+            let invalid = || ParquetError::General(format!("invalid {name}: {field}"));
+            match name.as_str() {
+                "card_id" => columns.card_id.push(value.ok_or_else(invalid)?),
+                "review_time" => columns.review_time.push(value.ok_or_else(invalid)?),
+                "rating" => columns.rating.push(
+                    value
+                        .and_then(|value| u8::try_from(value).ok())
+                        .ok_or_else(invalid)?,
+                ),
+                "review_kind" => columns.review_kind.push(
+                    value
+                        .and_then(|value| u8::try_from(value).ok())
+                        .ok_or_else(invalid)?,
+                ),
+                "duration" => columns.duration.push(match field {
+                    Field::Null => 0,
+                    _ => value
+                        .and_then(|value| u32::try_from(value).ok())
+                        .ok_or_else(invalid)?,
+                }),
+                _ => {}
+            }
+        }
+    }
+    Ok(columns)
+}
+
+/// Convert columnar review data into [FSRSItem]s, applying the same rules as
+/// [revlogs_to_fsrs_items].
+pub fn items_from_columns(
+    columns: &RevlogColumns,
+    config: &ConvertorConfig,
+) -> Result<Vec<FSRSItem>> {
+    Ok(revlogs_to_fsrs_items(
+        revlogs_from_columns(columns)?,
+        config,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::FSRSReview;
+
+    const DAY_MS: i64 = 86400 * 1000;
+
+    #[test]
+    fn columns_to_items() {
+        let start = 1_700_000_000_000;
+        let mut columns = RevlogColumns {
+            card_id: vec![1, 1, 1, 2],
+            review_time: vec![start, start + DAY_MS, start + 4 * DAY_MS, start],
+            rating: vec![3, 1, 3, 4],
+            review_kind: vec![0, 1, 2, 0],
+            duration: vec![],
+        };
+        let items = items_from_columns(&columns, &ConvertorConfig::default()).unwrap();
+        let reviews: Vec<_> = items.into_iter().map(|item| item.reviews).collect();
+        assert_eq!(
+            reviews,
+            [
+                vec![
+                    FSRSReview {
+                        rating: 3,
+                        delta_t: 0,
+                        elapsed_seconds: None,
+                    },
+                    FSRSReview {
+                        rating: 1,
+                        delta_t: 1,
+                        elapsed_seconds: None,
+                    }
+                ],
+                vec![
+                    FSRSReview {
+                        rating: 3,
+                        delta_t: 0,
+                        elapsed_seconds: None,
+                    },
+                    FSRSReview {
+                        rating: 1,
+                        delta_t: 1,
+                        elapsed_seconds: None,
+                    },
+                    FSRSReview {
+                        rating: 3,
+                        delta_t: 3,
+                        elapsed_seconds: None,
+                    }
+                ]
+            ]
+        );
+
+        columns.review_kind[3] = 9;
+        assert_eq!(revlogs_from_columns(&columns), Err(FSRSError::InvalidInput));
+        columns.review_kind.pop();
+        assert_eq!(revlogs_from_columns(&columns), Err(FSRSError::InvalidInput));
+    }
+
+    #[test]
+    fn read_parquet() -> ParquetResult<()> {
+        let start = 1_700_000_000_000;
+        let columns = revlog_columns_from_parquet("tests/data/revlog.parquet")?;
+        assert_eq!(
+            columns,
+            RevlogColumns {
+                card_id: vec![1, 1, 1, 2, 2],
+                review_time: vec![
+                    start,
+                    start + DAY_MS + 3_600_000,
+                    start + 4 * DAY_MS,
+                    start,
+                    start + 2 * DAY_MS
+                ],
+                rating: vec![3, 1, 3, 4, 3],
+                review_kind: vec![0, 1, 2, 0, 1],
+                // the duration of the fourth review is null
+                duration: vec![5_000, 12_000, 8_000, 0, 3_000],
+            }
+        );
+        let items = items_from_columns(&columns, &ConvertorConfig::default()).unwrap();
+        assert_eq!(items.len(), 3);
+
+        assert!(revlog_columns_from_parquet("Cargo.toml").is_err());
+        Ok(())
+    }
+}
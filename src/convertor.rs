@@ -0,0 +1,764 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use itertools::Itertools;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::dataset::{FSRSItem, FSRSReview, ItemMetadata};
+use crate::optimal_retention::{RevlogEntry, RevlogReviewKind};
+
+/// Controls how review logs are turned into [FSRSItem]s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertorConfig {
+    /// The hour at which a new day starts, eg 4 means reviews done before 4am count
+    /// towards the previous day.
+    pub next_day_starts_at: i64,
+    /// The timezone the user studies in. Day boundaries are computed in this timezone.
+    pub timezone: Tz,
+    /// Drop entries logged when a card was rescheduled by hand (eg with "set due date" or
+    /// "forget"). They carry no rating, and would otherwise split the interval between the
+    /// surrounding reviews. Off by default, which keeps them as earlier versions did.
+    pub filter_out_manual: bool,
+    /// Drop reviews done in filtered decks with rescheduling disabled, as they do not
+    /// change the card's schedule. Off by default, like `filter_out_manual`.
+    pub filter_out_cram: bool,
+    /// If set, a review of the same card with the same rating as the previous one, done
+    /// within this many milliseconds of it, is treated as a duplicate (eg a sync artifact or
+    /// a double tap) and dropped. See [remove_duplicate_revlogs].
+    pub duplicate_tolerance_ms: Option<i64>,
+    /// Keep only the first review of each day, collapsing (re)learning steps into the first
+    /// review of the day, as the Python optimizer does. This changes the fitted initial
+    /// stabilities, as the first rating is then the first button pressed on a new card.
+    pub collapse_learning_steps: bool,
+    /// If set, reviews done before this time (in milliseconds since the epoch) are dropped
+    /// before the items are built. A card learnt before this time has an incomplete history
+    /// within the window, so it is skipped unless it was reset and learnt again inside it.
+    pub start_time: Option<i64>,
+    /// If set, reviews done at or after this time (in milliseconds since the epoch) are
+    /// dropped before the items are built.
+    pub end_time: Option<i64>,
+    /// If set, the button pressed in each review is replaced with `rating_map[button]`
+    /// before conversion, eg `vec![0, 1, 3, 3, 4]` treats hard as good, and a 0-5 scale can
+    /// be mapped with a 6-element map. Reviews whose button is out of range or mapped to 0
+    /// are dropped; manual entries are left alone.
+    pub rating_map: Option<Vec<u8>>,
+    /// Set [FSRSReview::elapsed_seconds] from the review timestamps, so that the model
+    /// sees the exact time between reviews instead of whole days. Parameters trained this
+    /// way should be used with the exact time as well, eg with
+    /// [FSRS::next_states_fractional](crate::FSRS::next_states_fractional).
+    pub elapsed_seconds: bool,
+}
+
+impl Default for ConvertorConfig {
+    fn default() -> Self {
+        Self {
+            next_day_starts_at: 4,
+            timezone: Tz::UTC,
+            filter_out_manual: false,
+            filter_out_cram: false,
+            duplicate_tolerance_ms: None,
+            collapse_learning_steps: false,
+            start_time: None,
+            end_time: None,
+            rating_map: None,
+            elapsed_seconds: false,
+        }
+    }
+}
+
+/// Convert review logs into FSRS items that can be used for training and evaluation.
+///
+/// Only `id` (the review timestamp in milliseconds), `cid`, `button_chosen`,
+/// `review_kind` and `ease_factor` are used; the other fields of [RevlogEntry] may be left
+/// at their defaults. Reviews outside `start_time`..`end_time` and manual and cram entries
+/// are filtered out first, if enabled.
+/// The logs do not need to be sorted. For each card, reviews before the last time it was
+/// (re)learnt from scratch are discarded, and `delta_t` is calculated as the number of days
+/// between reviews, taking `next_day_starts_at` and `timezone` into account. A card with n
+/// reviews produces up to n-1 items, each containing the history up to and including one
+/// review; reviews done on the same day as the previous one do not produce an item.
+///
+/// The returned items are sorted by review time, as expected by
+/// [FSRS::compute_parameters](crate::FSRS::compute_parameters), and have their card id
+/// set in [ItemMetadata].
+pub fn revlogs_to_fsrs_items(
+    mut revlogs: Vec<RevlogEntry>,
+    config: &ConvertorConfig,
+) -> Vec<FSRSItem> {
+    revlogs.sort_by_key(|r| (r.cid, r.id));
+    if let Some(tolerance_ms) = config.duplicate_tolerance_ms {
+        let removed = remove_duplicate_revlogs(&mut revlogs, tolerance_ms);
+        info!("removed {removed} duplicate reviews");
+    }
+    let mut items = revlogs
+        .into_iter()
+        .group_by(|r| r.cid)
+        .into_iter()
+        .flat_map(|(cid, entries)| convert_card(cid, entries.collect(), config))
+        .collect_vec();
+    items.sort_by_cached_key(|(id, _)| *id);
+    items.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Describes a converted dataset, including the conversion settings needed to interpret
+/// parameters trained on it, such as a rating remap.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatasetSummary {
+    pub item_count: usize,
+    /// The number of distinct cards, counted from the items' metadata.
+    pub card_count: usize,
+    /// How often each rating (again, hard, good, easy) occurs in the reviews the items
+    /// end with.
+    pub rating_counts: [usize; 4],
+    /// The [ConvertorConfig::rating_map] the items were converted with.
+    pub rating_map: Option<Vec<u8>>,
+}
+
+impl DatasetSummary {
+    pub fn new(items: &[FSRSItem], config: &ConvertorConfig) -> Self {
+        let mut rating_counts = [0; 4];
+        for item in items {
+            if let Some(count) = item
+                .reviews
+                .last()
+                .and_then(|r| (r.rating as usize).checked_sub(1))
+                .and_then(|i| rating_counts.get_mut(i))
+            {
+                *count += 1;
+            }
+        }
+        Self {
+            item_count: items.len(),
+            card_count: items
+                .iter()
+                .filter_map(|item| item.metadata.and_then(|m| m.card_id))
+                .unique()
+                .count(),
+            rating_counts,
+            rating_map: config.rating_map.clone(),
+        }
+    }
+}
+
+/// Remove reviews of the same card with the same rating as the previous review, done within
+/// `tolerance_ms` milliseconds of it, keeping the first one. Returns the number of reviews
+/// removed.
+pub fn remove_duplicate_revlogs(revlogs: &mut Vec<RevlogEntry>, tolerance_ms: i64) -> usize {
+    let before = revlogs.len();
+    revlogs.sort_by_key(|r| (r.cid, r.id));
+    revlogs.dedup_by(|r, previous| {
+        r.cid == previous.cid
+            && r.button_chosen == previous.button_chosen
+            && r.id - previous.id <= tolerance_ms
+    });
+    before - revlogs.len()
+}
+
+/// Merge the review logs of the same cards exported from several collections or devices,
+/// eg when a collection was split and studied separately for a while. Cards are matched by
+/// `cid`. Entries with the same card and timestamp are the same review synced to several
+/// places; the one from the earliest collection in `collections` is kept. If
+/// `tolerance_ms` is set, reviews with the same rating done within that many milliseconds
+/// of each other are also treated as one review, to allow for clock differences between
+/// devices (see [remove_duplicate_revlogs]). The result is sorted by card and time.
+pub fn merge_revlogs(
+    collections: Vec<Vec<RevlogEntry>>,
+    tolerance_ms: Option<i64>,
+) -> Vec<RevlogEntry> {
+    let mut merged = collections.into_iter().flatten().collect_vec();
+    // the sort is stable, so earlier collections win
+    merged.sort_by_key(|r| (r.cid, r.id));
+    merged.dedup_by_key(|r| (r.cid, r.id));
+    if let Some(tolerance_ms) = tolerance_ms {
+        remove_duplicate_revlogs(&mut merged, tolerance_ms);
+    }
+    merged
+}
+
+fn convert_card(
+    cid: i64,
+    entries: Vec<RevlogEntry>,
+    config: &ConvertorConfig,
+) -> impl Iterator<Item = (i64, FSRSItem)> {
+    let metadata = Some(ItemMetadata {
+        card_id: Some(cid),
+        deck_id: None,
+    });
+    convert_to_fsrs_items(entries, config)
+        .into_iter()
+        .map(move |(id, item)| (id, FSRSItem { metadata, ..item }))
+}
+
+/// Converts review logs into items like [revlogs_to_fsrs_items], but keeps the logs so
+/// that reviews logged later can be added without converting the whole history again:
+/// only the cards that have new reviews are converted.
+#[derive(Debug, Clone)]
+pub struct IncrementalConvertor {
+    config: ConvertorConfig,
+    revlogs: HashMap<i64, Vec<RevlogEntry>>,
+    /// Sorted by the id of the review each item ends with.
+    items: Vec<(i64, FSRSItem)>,
+}
+
+impl IncrementalConvertor {
+    pub fn new(config: ConvertorConfig) -> Self {
+        Self {
+            config,
+            revlogs: HashMap::new(),
+            items: vec![],
+        }
+    }
+
+    /// Add review logs, and update the items of the cards they belong to. Logs that were
+    /// already added are ignored.
+    pub fn add_revlogs(&mut self, revlogs: Vec<RevlogEntry>) {
+        let mut updated_cards = HashSet::new();
+        for revlog in revlogs {
+            updated_cards.insert(revlog.cid);
+            self.revlogs.entry(revlog.cid).or_default().push(revlog);
+        }
+        if updated_cards.is_empty() {
+            return;
+        }
+        // a new review can make earlier ones of the same card obsolete (eg when the card
+        // was reset and learnt again), so the card's items are replaced
+        self.items.retain(|(_, item)| {
+            !item
+                .metadata
+                .and_then(|m| m.card_id)
+                .is_some_and(|cid| updated_cards.contains(&cid))
+        });
+        for cid in updated_cards {
+            let entries = self.revlogs.get_mut(&cid).unwrap();
+            entries.sort_by_key(|r| r.id);
+            entries.dedup_by_key(|r| r.id);
+            if let Some(tolerance_ms) = self.config.duplicate_tolerance_ms {
+                remove_duplicate_revlogs(entries, tolerance_ms);
+            }
+            self.items
+                .extend(convert_card(cid, entries.clone(), &self.config));
+        }
+        self.items.sort_by_key(|(id, _)| *id);
+    }
+
+    /// The items converted so far, sorted by review time.
+    pub fn items(&self) -> impl Iterator<Item = &FSRSItem> {
+        self.items.iter().map(|(_, item)| item)
+    }
+
+    pub fn into_items(self) -> Vec<FSRSItem> {
+        self.items.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+pub(crate) fn filter_out_cram(entries: Vec<RevlogEntry>) -> Vec<RevlogEntry> {
+    entries
+        .into_iter()
+        .filter(|entry| entry.review_kind != RevlogReviewKind::Filtered || entry.ease_factor != 0)
+        .collect()
+}
+
+pub(crate) fn filter_out_manual(entries: Vec<RevlogEntry>) -> Vec<RevlogEntry> {
+    entries
+        .into_iter()
+        .filter(|entry| entry.review_kind != RevlogReviewKind::Manual && entry.button_chosen != 0)
+        .collect()
+}
+
+pub(crate) fn filter_by_time(
+    entries: Vec<RevlogEntry>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+) -> Vec<RevlogEntry> {
+    entries
+        .into_iter()
+        .filter(|entry| start_time.map_or(true, |start| entry.id >= start))
+        .filter(|entry| end_time.map_or(true, |end| entry.id < end))
+        .collect()
+}
+
+pub(crate) fn remap_ratings(entries: Vec<RevlogEntry>, rating_map: &[u8]) -> Vec<RevlogEntry> {
+    entries
+        .into_iter()
+        .filter_map(|entry| {
+            if entry.review_kind == RevlogReviewKind::Manual {
+                return Some(entry);
+            }
+            match rating_map.get(entry.button_chosen as usize) {
+                Some(&rating @ 1..=4) => Some(RevlogEntry {
+                    button_chosen: rating,
+                    ..entry
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn remove_revlog_before_last_first_learn(entries: Vec<RevlogEntry>) -> Vec<RevlogEntry> {
+    let mut last_first_learn_index = 0;
+    for (index, entry) in entries.iter().enumerate().rev() {
+        if entry.review_kind == RevlogReviewKind::Learning {
+            last_first_learn_index = index;
+        } else if last_first_learn_index != 0 {
+            break;
+        }
+    }
+    match entries.get(last_first_learn_index) {
+        Some(entry) if entry.review_kind == RevlogReviewKind::Learning => {
+            entries[last_first_learn_index..].to_vec()
+        }
+        _ => vec![],
+    }
+}
+
+/// The day a review counts towards: reviews done before `next_day_starts_at` o'clock local
+/// time count towards the previous day. The cutoff is applied to the local wall-clock time,
+/// so it stays at the same hour across daylight saving time changes.
+pub(crate) fn convert_to_date(timestamp: i64, next_day_starts_at: i64, timezone: Tz) -> NaiveDate {
+    let local = Utc
+        .timestamp_millis_opt(timestamp)
+        .unwrap()
+        .with_timezone(&timezone)
+        .naive_local();
+    (local - Duration::hours(next_day_starts_at)).date()
+}
+
+/// Given a list of revlog entries for a single card with length n, we create
+/// n-1 FSRS items, where each item contains the history of the preceding reviews.
+/// Each item is returned together with the id of the review it ends with.
+pub(crate) fn convert_to_fsrs_items(
+    mut entries: Vec<RevlogEntry>,
+    config: &ConvertorConfig,
+) -> Vec<(i64, FSRSItem)> {
+    // filtering by time before the first learning entry is located ensures a history
+    // cut off by the window is dropped, instead of starting with a review whose delta_t
+    // is missing
+    entries = filter_by_time(entries, config.start_time, config.end_time);
+    if let Some(rating_map) = &config.rating_map {
+        entries = remap_ratings(entries, rating_map);
+    }
+    if config.filter_out_cram {
+        entries = filter_out_cram(entries);
+    }
+    if config.filter_out_manual {
+        entries = filter_out_manual(entries);
+    }
+    entries = remove_revlog_before_last_first_learn(entries);
+
+    for i in 1..entries.len() {
+        let date_current =
+            convert_to_date(entries[i].id, config.next_day_starts_at, config.timezone);
+        let date_previous = convert_to_date(
+            entries[i - 1].id,
+            config.next_day_starts_at,
+            config.timezone,
+        );
+        entries[i].last_interval = (date_current - date_previous).num_days() as i32;
+    }
+    if config.collapse_learning_steps {
+        let first = entries.first().map(|entry| entry.id);
+        entries.retain(|entry| Some(entry.id) == first || entry.last_interval > 0);
+    }
+
+    let reviews: Vec<_> = entries
+        .iter()
+        .enumerate()
+        .map(|(idx, r)| FSRSReview {
+            rating: r.button_chosen as u32,
+            delta_t: r.last_interval.max(0) as u32,
+            elapsed_seconds: (config.elapsed_seconds && idx > 0)
+                .then(|| ((r.id - entries[idx - 1].id).max(0) / 1000) as u32),
+        })
+        .collect();
+    entries
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(idx, entry)| {
+            (
+                entry.id,
+                FSRSItem {
+                    reviews: reviews[..=idx].to_vec(),
+                    ..Default::default()
+                },
+            )
+        })
+        .filter(|(_, item)| item.current().delta_t > 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::item_from;
+
+    const DAY_MS: i64 = 86400 * 1000;
+
+    fn entry(cid: i64, id: i64, button_chosen: u8, review_kind: RevlogReviewKind) -> RevlogEntry {
+        RevlogEntry {
+            id,
+            cid,
+            button_chosen,
+            review_kind,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn revlogs_to_items() {
+        let start = 1_700_000_000_000;
+        let revlogs = vec![
+            entry(2, start + DAY_MS, 3, RevlogReviewKind::Learning),
+            entry(1, start + 3 * DAY_MS, 3, RevlogReviewKind::Review),
+            entry(1, start, 1, RevlogReviewKind::Learning),
+            entry(2, start + 2 * DAY_MS, 4, RevlogReviewKind::Review),
+            entry(1, start + 60_000, 3, RevlogReviewKind::Learning),
+        ];
+        let items = revlogs_to_fsrs_items(revlogs, &ConvertorConfig::default());
+        let item = |card_id, reviews: &[(u32, u32)]| FSRSItem {
+            metadata: Some(ItemMetadata {
+                card_id: Some(card_id),
+                deck_id: None,
+            }),
+            ..item_from(reviews)
+        };
+        assert_eq!(
+            items,
+            [
+                item(2, &[(3, 0), (4, 1)]),
+                item(1, &[(1, 0), (3, 0), (3, 3)])
+            ]
+        );
+    }
+
+    #[test]
+    fn next_day_starts_at() {
+        // 2023-11-14 22:13 UTC and 2023-11-15 03:13 UTC
+        let first = 1_700_000_000_000;
+        let second = first + 5 * 3600 * 1000;
+        let revlogs = vec![
+            entry(1, first, 3, RevlogReviewKind::Learning),
+            entry(1, second, 3, RevlogReviewKind::Review),
+        ];
+        let config = ConvertorConfig {
+            next_day_starts_at: 0,
+            timezone: Tz::UTC,
+            ..Default::default()
+        };
+        assert_eq!(revlogs_to_fsrs_items(revlogs.clone(), &config).len(), 1);
+        // with a 4am cutoff, both reviews fall on the same day
+        let config = ConvertorConfig {
+            next_day_starts_at: 4,
+            timezone: Tz::UTC,
+            ..Default::default()
+        };
+        assert!(revlogs_to_fsrs_items(revlogs.clone(), &config).is_empty());
+        // in Berlin, the second review is done at 04:13 local time, after the cutoff
+        let config = ConvertorConfig {
+            next_day_starts_at: 4,
+            timezone: Tz::Europe__Berlin,
+            ..Default::default()
+        };
+        assert_eq!(revlogs_to_fsrs_items(revlogs, &config).len(), 1);
+    }
+
+    #[test]
+    fn daylight_saving_time() {
+        // Berlin switches to summer time at 2023-03-26 02:00 local time. 04:30 local time
+        // on that day is 02:30 UTC, which is after the 4am cutoff.
+        let after_cutoff = 1_679_797_800_000;
+        assert_eq!(
+            convert_to_date(after_cutoff, 4, Tz::Europe__Berlin),
+            NaiveDate::from_ymd_opt(2023, 3, 26).unwrap()
+        );
+        // 03:30 local time is still before it
+        assert_eq!(
+            convert_to_date(after_cutoff - 3600 * 1000, 4, Tz::Europe__Berlin),
+            NaiveDate::from_ymd_opt(2023, 3, 25).unwrap()
+        );
+    }
+
+    #[test]
+    fn filter_reviews() {
+        let start = 1_700_000_000_000;
+        let cram = RevlogEntry {
+            ease_factor: 0,
+            ..entry(1, start + 2 * DAY_MS, 3, RevlogReviewKind::Filtered)
+        };
+        let revlogs = vec![
+            entry(1, start, 3, RevlogReviewKind::Learning),
+            // set due date
+            entry(1, start + DAY_MS, 0, RevlogReviewKind::Manual),
+            cram,
+            entry(1, start + 5 * DAY_MS, 3, RevlogReviewKind::Review),
+        ];
+        // kept by default
+        let items = revlogs_to_fsrs_items(revlogs.clone(), &ConvertorConfig::default());
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[2].reviews.len(), 4);
+
+        let config = ConvertorConfig {
+            filter_out_manual: true,
+            filter_out_cram: true,
+            ..Default::default()
+        };
+        let items = revlogs_to_fsrs_items(revlogs, &config);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].reviews, item_from(&[(3, 0), (3, 5)]).reviews);
+    }
+
+    #[test]
+    fn collapse_learning_steps() {
+        let start = 1_700_000_000_000;
+        let revlogs = vec![
+            entry(1, start, 1, RevlogReviewKind::Learning),
+            entry(1, start + 60_000, 3, RevlogReviewKind::Learning),
+            entry(1, start + 600_000, 3, RevlogReviewKind::Learning),
+            entry(1, start + DAY_MS, 1, RevlogReviewKind::Review),
+            entry(1, start + DAY_MS + 600_000, 3, RevlogReviewKind::Relearning),
+            entry(1, start + 3 * DAY_MS, 3, RevlogReviewKind::Review),
+        ];
+        let reviews = |items: Vec<FSRSItem>| {
+            items
+                .last()
+                .unwrap()
+                .reviews
+                .iter()
+                .map(|r| (r.rating, r.delta_t))
+                .collect_vec()
+        };
+        let items = revlogs_to_fsrs_items(revlogs.clone(), &ConvertorConfig::default());
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            reviews(items),
+            [(1, 0), (3, 0), (3, 0), (1, 1), (3, 0), (3, 2)]
+        );
+        let config = ConvertorConfig {
+            collapse_learning_steps: true,
+            ..Default::default()
+        };
+        let items = revlogs_to_fsrs_items(revlogs, &config);
+        assert_eq!(items.len(), 2);
+        assert_eq!(reviews(items), [(1, 0), (1, 1), (3, 2)]);
+    }
+
+    #[test]
+    fn elapsed_seconds() {
+        let start = 1_700_000_000_000;
+        let revlogs = vec![
+            entry(1, start, 3, RevlogReviewKind::Learning),
+            entry(1, start + 600_000, 3, RevlogReviewKind::Learning),
+            entry(1, start + DAY_MS + 3_600_000, 3, RevlogReviewKind::Review),
+        ];
+        let elapsed_seconds = |config: &ConvertorConfig| {
+            revlogs_to_fsrs_items(revlogs.clone(), config)
+                .last()
+                .unwrap()
+                .reviews
+                .iter()
+                .map(|r| r.elapsed_seconds)
+                .collect_vec()
+        };
+        assert_eq!(
+            elapsed_seconds(&ConvertorConfig::default()),
+            [None, None, None]
+        );
+        let config = ConvertorConfig {
+            elapsed_seconds: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            elapsed_seconds(&config),
+            [None, Some(600), Some(86400 + 3000)]
+        );
+        let config = ConvertorConfig {
+            collapse_learning_steps: true,
+            ..config
+        };
+        assert_eq!(elapsed_seconds(&config), [None, Some(86400 + 3600)]);
+    }
+
+    #[test]
+    fn date_range() {
+        let start = 1_700_000_000_000;
+        let revlogs = vec![
+            entry(1, start, 3, RevlogReviewKind::Learning),
+            entry(1, start + 2 * DAY_MS, 3, RevlogReviewKind::Review),
+            entry(1, start + 6 * DAY_MS, 3, RevlogReviewKind::Review),
+            entry(2, start + 3 * DAY_MS, 1, RevlogReviewKind::Learning),
+            entry(2, start + 4 * DAY_MS, 3, RevlogReviewKind::Review),
+            entry(2, start + 9 * DAY_MS, 3, RevlogReviewKind::Review),
+        ];
+        let card_ids = |items: &[FSRSItem]| {
+            items
+                .iter()
+                .map(|item| item.metadata.unwrap().card_id.unwrap())
+                .collect_vec()
+        };
+        let config = ConvertorConfig {
+            start_time: Some(start + DAY_MS),
+            ..Default::default()
+        };
+        // card 1 was learnt before the window, so its history is incomplete
+        let items = revlogs_to_fsrs_items(revlogs.clone(), &config);
+        assert_eq!(card_ids(&items), [2, 2]);
+        assert_eq!(items[1].reviews.last().unwrap().delta_t, 5);
+
+        let config = ConvertorConfig {
+            end_time: Some(start + 5 * DAY_MS),
+            ..Default::default()
+        };
+        let items = revlogs_to_fsrs_items(revlogs, &config);
+        assert_eq!(card_ids(&items), [1, 2]);
+    }
+
+    #[test]
+    fn rating_map() {
+        let start = 1_700_000_000_000;
+        let revlogs = vec![
+            entry(1, start, 0, RevlogReviewKind::Learning),
+            entry(1, start + DAY_MS, 2, RevlogReviewKind::Review),
+            entry(1, start + 2 * DAY_MS, 0, RevlogReviewKind::Manual),
+            entry(1, start + 4 * DAY_MS, 5, RevlogReviewKind::Review),
+            entry(1, start + 8 * DAY_MS, 7, RevlogReviewKind::Review),
+        ];
+        let config = ConvertorConfig {
+            rating_map: Some(vec![1, 1, 2, 3, 3, 4]),
+            filter_out_manual: true,
+            ..Default::default()
+        };
+        let items = revlogs_to_fsrs_items(revlogs, &config);
+        assert_eq!(
+            items
+                .last()
+                .unwrap()
+                .reviews
+                .iter()
+                .map(|r| (r.rating, r.delta_t))
+                .collect_vec(),
+            [(1, 0), (2, 1), (4, 3)]
+        );
+        let summary = DatasetSummary::new(&items, &config);
+        assert_eq!(summary.rating_map, config.rating_map);
+        assert_eq!(summary.item_count, 2);
+        assert_eq!(summary.rating_counts, [0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn duplicates() {
+        let start = 1_700_000_000_000;
+        let mut revlogs = vec![
+            entry(1, start, 3, RevlogReviewKind::Learning),
+            entry(2, start + 500, 3, RevlogReviewKind::Learning),
+            entry(1, start + 400, 3, RevlogReviewKind::Learning),
+            entry(1, start + 800, 3, RevlogReviewKind::Learning),
+            entry(1, start + 1200, 4, RevlogReviewKind::Learning),
+            entry(1, start + 5000, 4, RevlogReviewKind::Learning),
+            entry(1, start + DAY_MS, 3, RevlogReviewKind::Review),
+            entry(1, start + DAY_MS, 3, RevlogReviewKind::Review),
+        ];
+        let config = ConvertorConfig {
+            duplicate_tolerance_ms: Some(1000),
+            ..Default::default()
+        };
+        let items = revlogs_to_fsrs_items(revlogs.clone(), &config);
+        assert_eq!(items[0].reviews.len(), 4);
+
+        assert_eq!(remove_duplicate_revlogs(&mut revlogs, 1000), 3);
+        assert_eq!(
+            revlogs.iter().map(|r| (r.cid, r.id - start)).collect_vec(),
+            [(1, 0), (1, 1200), (1, 5000), (1, DAY_MS), (2, 500)]
+        );
+        assert_eq!(remove_duplicate_revlogs(&mut revlogs, 5000), 1);
+    }
+
+    #[test]
+    fn merge() {
+        let start = 1_700_000_000_000;
+        let desktop = vec![
+            entry(1, start, 3, RevlogReviewKind::Learning),
+            entry(1, start + 2 * DAY_MS, 3, RevlogReviewKind::Review),
+            entry(2, start, 3, RevlogReviewKind::Learning),
+        ];
+        let phone = vec![
+            // synced from the desktop, with a different rating recorded
+            entry(1, start, 4, RevlogReviewKind::Learning),
+            // reviewed offline, logged with a skewed clock
+            entry(2, start + 3 * DAY_MS, 3, RevlogReviewKind::Review),
+            entry(2, start + 3 * DAY_MS + 500, 3, RevlogReviewKind::Review),
+            entry(1, start + 5 * DAY_MS, 2, RevlogReviewKind::Review),
+        ];
+        let merged = merge_revlogs(vec![desktop.clone(), phone.clone()], Some(1000));
+        assert_eq!(
+            merged
+                .iter()
+                .map(|r| (r.cid, r.id - start, r.button_chosen))
+                .collect_vec(),
+            [
+                (1, 0, 3),
+                (1, 2 * DAY_MS, 3),
+                (1, 5 * DAY_MS, 2),
+                (2, 0, 3),
+                (2, 3 * DAY_MS, 3)
+            ]
+        );
+        assert_eq!(merge_revlogs(vec![phone, desktop], None).len(), 6);
+    }
+
+    #[test]
+    fn incremental() {
+        let start = 1_700_000_000_000;
+        let mut revlogs = vec![];
+        for cid in 1..=3 {
+            revlogs.push(entry(cid, start + cid, 3, RevlogReviewKind::Learning));
+            for day in [1, 3, 8, 20] {
+                revlogs.push(entry(
+                    cid,
+                    start + day * DAY_MS + cid,
+                    3,
+                    RevlogReviewKind::Review,
+                ));
+            }
+        }
+        // card 2 is reset and learnt again
+        revlogs.push(entry(2, start + 25 * DAY_MS, 1, RevlogReviewKind::Learning));
+        revlogs.push(entry(2, start + 27 * DAY_MS, 3, RevlogReviewKind::Review));
+        // card 4 is new
+        revlogs.push(entry(4, start + 25 * DAY_MS, 3, RevlogReviewKind::Learning));
+        revlogs.push(entry(4, start + 26 * DAY_MS, 3, RevlogReviewKind::Review));
+        let config = ConvertorConfig::default();
+        let expected = revlogs_to_fsrs_items(revlogs.clone(), &config);
+
+        let (old, new): (Vec<_>, Vec<_>) = revlogs
+            .into_iter()
+            .partition(|r| r.id < start + 10 * DAY_MS);
+        let mut convertor = IncrementalConvertor::new(config);
+        convertor.add_revlogs(old.clone());
+        assert_eq!(convertor.items().count(), 9);
+        convertor.add_revlogs(old);
+        assert_eq!(convertor.items().count(), 9);
+        convertor.add_revlogs(new);
+        assert_eq!(convertor.items().cloned().collect_vec(), expected);
+        assert_eq!(convertor.into_items(), expected);
+    }
+
+    #[test]
+    fn serde() {
+        let revlog = entry(1, 2, 3, RevlogReviewKind::Relearning);
+        let json = serde_json::to_string(&revlog).unwrap();
+        assert_eq!(serde_json::from_str::<RevlogEntry>(&json).unwrap(), revlog);
+    }
+
+    #[test]
+    fn no_learning_entries() {
+        assert!(revlogs_to_fsrs_items(vec![], &ConvertorConfig::default()).is_empty());
+        let revlogs = vec![
+            entry(1, 0, 3, RevlogReviewKind::Review),
+            entry(1, DAY_MS, 3, RevlogReviewKind::Review),
+        ];
+        assert!(revlogs_to_fsrs_items(revlogs, &ConvertorConfig::default()).is_empty());
+    }
+}
@@ -1,3 +1,8 @@
+use crate::anki::{anki_revlogs_from_collection, AnkiCollectionOptions, Result};
+use crate::convertor::{
+    convert_to_fsrs_items, filter_out_cram, filter_out_manual,
+    remove_revlog_before_last_first_learn, revlogs_to_fsrs_items, ConvertorConfig,
+};
 use crate::convertor_tests::RevlogReviewKind::*;
 use crate::dataset::{constant_weighted_fsrs_items, FSRSBatcher};
 use crate::dataset::{FSRSItem, FSRSReview};
@@ -8,137 +13,21 @@ use burn::data::dataloader::batcher::Batcher;
 use burn::data::dataloader::Dataset;
 use burn::data::dataset::InMemDataset;
 use burn::tensor::Data;
-use chrono::prelude::*;
 use chrono_tz::Tz;
 use itertools::Itertools;
-use rusqlite::Connection;
-use rusqlite::{Result, Row};
 use serde::{Deserialize, Serialize};
 
-impl rusqlite::types::FromSql for RevlogReviewKind {
-    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
-        let rusqlite::types::ValueRef::Integer(i) = value else {
-            return Err(rusqlite::types::FromSqlError::InvalidType);
-        };
-        match i {
-            0 => Ok(RevlogReviewKind::Learning),
-            1 => Ok(RevlogReviewKind::Review),
-            2 => Ok(RevlogReviewKind::Relearning),
-            3 => Ok(RevlogReviewKind::Filtered),
-            4 => Ok(RevlogReviewKind::Manual),
-            _ => Err(rusqlite::types::FromSqlError::InvalidType),
-        }
-    }
-}
-
-impl TryFrom<&Row<'_>> for RevlogEntry {
-    type Error = rusqlite::Error;
-    fn try_from(row: &Row<'_>) -> Result<Self> {
-        Ok(RevlogEntry {
-            id: row.get(0)?,
-            cid: row.get(1)?,
-            usn: row.get(2)?,
-            button_chosen: row.get(3)?,
-            interval: row.get(4)?,
-            last_interval: row.get(5)?,
-            ease_factor: row.get(6)?,
-            taken_millis: row.get(7)?,
-            review_kind: row.get(8)?,
-        })
-    }
-}
-
-fn filter_out_cram(entries: Vec<RevlogEntry>) -> Vec<RevlogEntry> {
-    entries
-        .into_iter()
-        .filter(|entry| entry.review_kind != Filtered || entry.ease_factor != 0)
-        .collect()
-}
-
-fn filter_out_manual(entries: Vec<RevlogEntry>) -> Vec<RevlogEntry> {
-    entries
-        .into_iter()
-        .filter(|entry| entry.review_kind != Manual && entry.button_chosen != 0)
-        .collect()
-}
-
-fn remove_revlog_before_last_first_learn(entries: Vec<RevlogEntry>) -> Vec<RevlogEntry> {
-    let mut last_first_learn_index = 0;
-    for (index, entry) in entries.iter().enumerate().rev() {
-        if entry.review_kind == Learning {
-            last_first_learn_index = index;
-        } else if last_first_learn_index != 0 {
-            break;
-        }
-    }
-    if entries[last_first_learn_index].review_kind == Learning {
-        entries[last_first_learn_index..].to_vec()
-    } else {
-        vec![]
-    }
-}
-
-fn convert_to_date(timestamp: i64, next_day_starts_at: i64, timezone: Tz) -> NaiveDate {
-    let timestamp_seconds = timestamp - next_day_starts_at * 3600 * 1000;
-    let datetime = Utc
-        .timestamp_millis_opt(timestamp_seconds)
-        .unwrap()
-        .with_timezone(&timezone);
-    datetime.date_naive()
+/// Convert a series of revlog entries sorted by card id into FSRS items.
+pub(crate) fn anki_to_fsrs(revlogs: Vec<RevlogEntry>) -> Vec<FSRSItem> {
+    revlogs_to_fsrs_items(revlogs, &anki_config())
 }
 
-/// Given a list of revlog entries for a single card with length n, we create
-/// n-1 FSRS items, where each item contains the history of the preceding reviews.
-
-fn convert_to_fsrs_items(
-    mut entries: Vec<RevlogEntry>,
-    next_day_starts_at: i64,
-    timezone: Tz,
-) -> Option<Vec<(i64, FSRSItem)>> {
-    // entries = filter_out_cram(entries);
-    // entries = filter_out_manual(entries);
-    entries = remove_revlog_before_last_first_learn(entries);
-
-    for i in 1..entries.len() {
-        let date_current = convert_to_date(entries[i].id, next_day_starts_at, timezone);
-        let date_previous = convert_to_date(entries[i - 1].id, next_day_starts_at, timezone);
-        entries[i].last_interval = (date_current - date_previous).num_days() as i32;
+fn anki_config() -> ConvertorConfig {
+    ConvertorConfig {
+        next_day_starts_at: 4,
+        timezone: Tz::Asia__Shanghai,
+        ..Default::default()
     }
-
-    Some(
-        entries
-            .iter()
-            .enumerate()
-            .skip(1)
-            .map(|(idx, entry)| {
-                let reviews = entries
-                    .iter()
-                    .take(idx + 1)
-                    .map(|r| FSRSReview {
-                        rating: r.button_chosen as u32,
-                        delta_t: r.last_interval.max(0) as u32,
-                    })
-                    .collect();
-                (entry.id, FSRSItem { reviews })
-            })
-            .filter(|(_, item)| item.current().delta_t > 0)
-            .collect(),
-    )
-}
-
-/// Convert a series of revlog entries sorted by card id into FSRS items.
-pub(crate) fn anki_to_fsrs(revlogs: Vec<RevlogEntry>) -> Vec<FSRSItem> {
-    let mut revlogs = revlogs
-        .into_iter()
-        .group_by(|r| r.cid)
-        .into_iter()
-        .filter_map(|(_cid, entries)| {
-            convert_to_fsrs_items(entries.collect(), 4, Tz::Asia__Shanghai)
-        })
-        .flatten()
-        .collect_vec();
-    revlogs.sort_by_cached_key(|(id, _)| *id);
-    revlogs.into_iter().map(|(_, item)| item).collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -185,53 +74,10 @@ pub(crate) fn anki21_sample_file_converted_to_fsrs() -> Vec<FSRSItem> {
 }
 
 pub(crate) fn read_collection() -> Result<Vec<RevlogEntry>> {
-    let db = Connection::open("tests/data/collection.anki21")?;
-    let filter_out_suspended_cards = false;
-    let filter_out_flags = [];
-    let flags_str = if !filter_out_flags.is_empty() {
-        format!(
-            "AND flags NOT IN ({})",
-            filter_out_flags
-                .iter()
-                .map(|x: &i32| x.to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
-        )
-    } else {
-        "".to_string()
-    };
-
-    let suspended_cards_str = if filter_out_suspended_cards {
-        "AND queue != -1"
-    } else {
-        ""
-    };
-
-    let current_timestamp = Utc::now().timestamp() * 1000;
-    // This sql query will be remove in the futrue. See https://github.com/open-spaced-repetition/fsrs-optimizer-burn/pull/14#issuecomment-1685895643
-    let revlogs = db
-        .prepare_cached(&format!(
-            "SELECT *
-        FROM revlog
-        WHERE id < ?1
-        AND cid < ?2
-        AND cid IN (
-            SELECT id
-            FROM cards
-            WHERE queue != 0
-            {suspended_cards_str}
-            {flags_str}
-        )
-        AND ease BETWEEN 1 AND 4
-        AND (
-            type != 3
-            OR factor != 0
-        )
-        order by cid"
-        ))?
-        .query_and_then((current_timestamp, current_timestamp), |x| x.try_into())?
-        .collect::<Result<Vec<_>>>()?;
-    Ok(revlogs)
+    anki_revlogs_from_collection(
+        "tests/data/collection.anki21",
+        &AnkiCollectionOptions::default(),
+    )
 }
 
 // This test currently expects the following .anki21 file to be placed in tests/data/:
@@ -258,8 +104,7 @@ fn conversion_works() {
     // convert a subset and check it matches expectations
     let fsrs_items = single_card_revlog
         .into_iter()
-        .filter_map(|entries| convert_to_fsrs_items(entries, 4, Tz::Asia__Shanghai))
-        .flatten()
+        .flat_map(|entries| convert_to_fsrs_items(entries, &anki_config()))
         .map(|(_, item)| item)
         .collect_vec();
     assert_eq!(
@@ -268,32 +113,57 @@ fn conversion_works() {
             FSRSItem {
                 reviews: [(3, 0), (4, 0), (3, 5)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
-                    .collect()
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None
+                    })
+                    .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(3, 0), (4, 0), (3, 5), (3, 10)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
-                    .collect()
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None
+                    })
+                    .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(3, 0), (4, 0), (3, 5), (3, 10), (3, 22)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
-                    .collect()
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None
+                    })
+                    .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(3, 0), (4, 0), (3, 5), (3, 10), (3, 22), (2, 56)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
-                    .collect()
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None
+                    })
+                    .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(3, 0), (4, 0), (3, 5), (3, 10), (3, 22), (2, 56), (3, 64)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
-                    .collect()
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None
+                    })
+                    .collect(),
+                ..Default::default()
             }
         ]
     );
@@ -319,19 +189,19 @@ fn conversion_works() {
 fn ordering_of_inputs_should_not_change() {
     let revlogs = anki21_sample_file_converted_to_fsrs();
     assert_eq!(
-        revlogs[0],
-        FSRSItem {
-            reviews: vec![
-                FSRSReview {
-                    rating: 4,
-                    delta_t: 0
-                },
-                FSRSReview {
-                    rating: 3,
-                    delta_t: 3
-                }
-            ]
-        }
+        revlogs[0].reviews,
+        [
+            FSRSReview {
+                rating: 4,
+                delta_t: 0,
+                elapsed_seconds: None,
+            },
+            FSRSReview {
+                rating: 3,
+                delta_t: 3,
+                elapsed_seconds: None,
+            }
+        ]
     );
 }
 
@@ -354,22 +224,30 @@ fn delta_t_is_correct() -> Result<()> {
                 revlog(RevlogReviewKind::Learning, 1),
                 revlog(RevlogReviewKind::Review, 0)
             ],
-            NEXT_DAY_AT,
-            Tz::Asia__Shanghai
+            &ConvertorConfig {
+                next_day_starts_at: NEXT_DAY_AT,
+                timezone: Tz::Asia__Shanghai,
+                ..Default::default()
+            }
         )
-        .map(|items| items.into_iter().map(|(_, item)| item).collect_vec()),
-        Some(vec![FSRSItem {
+        .into_iter()
+        .map(|(_, item)| item)
+        .collect_vec(),
+        vec![FSRSItem {
             reviews: vec![
                 FSRSReview {
                     rating: 3,
-                    delta_t: 0
+                    delta_t: 0,
+                    elapsed_seconds: None,
                 },
                 FSRSReview {
                     rating: 3,
-                    delta_t: 1
+                    delta_t: 1,
+                    elapsed_seconds: None,
                 }
-            ]
-        }])
+            ],
+            ..Default::default()
+        }]
     );
 
     assert_eq!(
@@ -380,30 +258,50 @@ fn delta_t_is_correct() -> Result<()> {
                 revlog(RevlogReviewKind::Review, 10),
                 revlog(RevlogReviewKind::Review, 5)
             ],
-            NEXT_DAY_AT,
-            Tz::Asia__Shanghai
+            &ConvertorConfig {
+                next_day_starts_at: NEXT_DAY_AT,
+                timezone: Tz::Asia__Shanghai,
+                ..Default::default()
+            }
         )
-        .map(|items| items.into_iter().map(|(_, item)| item).collect_vec()),
-        Some(vec![
+        .into_iter()
+        .map(|(_, item)| item)
+        .collect_vec(),
+        vec![
             FSRSItem {
                 reviews: [(3, 0), (3, 2)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
-                    .collect()
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None
+                    })
+                    .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(3, 0), (3, 2), (3, 3)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
-                    .collect()
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None
+                    })
+                    .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(3, 0), (3, 2), (3, 3), (3, 5)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
-                    .collect()
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None
+                    })
+                    .collect(),
+                ..Default::default()
             },
-        ])
+        ]
     );
 
     Ok(())
@@ -1,22 +1,42 @@
+use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 
 use burn::data::dataloader::batcher::Batcher;
 use burn::{
     data::dataset::Dataset,
-    tensor::{backend::Backend, Data, ElementConversion, Float, Int, Shape, Tensor},
+    tensor::{backend::Backend, Bool, Data, ElementConversion, Float, Int, Shape, Tensor},
 };
 
+use burn::config::Config;
 use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
+use crate::error::{FSRSError, Result};
+
 /// Stores a list of reviews for a card, in chronological order. Each FSRSItem corresponds
 /// to a single review, but contains the previous reviews of the card as well, after the
 /// first one.
 /// When used during review, the last item should include the correct delta_t, but
 /// the provided rating is ignored as all four ratings are returned by .next_states()
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
 pub struct FSRSItem {
     pub reviews: Vec<FSRSReview>,
+    /// Identifies where the item came from. It is not used for training, but is passed
+    /// through to per-item outputs such as [PredictionDiff](crate::inference::PredictionDiff).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ItemMetadata>,
+}
+
+/// Opaque identifiers of the card an item was built from.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
+pub struct ItemMetadata {
+    pub card_id: Option<i64>,
+    pub deck_id: Option<i64>,
 }
 
 #[derive(Debug, Clone)]
@@ -25,7 +45,14 @@ pub(crate) struct WeightedFSRSItem {
     pub item: FSRSItem,
 }
 
+impl Borrow<FSRSItem> for WeightedFSRSItem {
+    fn borrow(&self) -> &FSRSItem {
+        &self.item
+    }
+}
+
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
 pub struct FSRSReview {
     /// 1-4
     pub rating: u32,
@@ -33,9 +60,47 @@ pub struct FSRSReview {
     /// # Warning
     /// `delta_t` for item first(initial) review must be 0
     pub delta_t: u32,
+    /// The exact time since the previous review in seconds, if known, eg from the review
+    /// timestamps. When set, it is used instead of `delta_t` as the elapsed time of the
+    /// review, so that a review done in the evening is not treated as being as old as one
+    /// done in the morning of the same day. Whether a review is a same-day review is still
+    /// decided by `delta_t` being 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_seconds: Option<u32>,
+}
+
+impl FSRSReview {
+    /// A review without an exact elapsed time. Unlike a struct literal, this keeps
+    /// compiling when fields are added, as `elapsed_seconds` was.
+    pub fn new(rating: u32, delta_t: u32) -> Self {
+        Self {
+            rating,
+            delta_t,
+            elapsed_seconds: None,
+        }
+    }
+
+    /// The elapsed time used by the model, in fractional days: 0 for same-day reviews,
+    /// otherwise `elapsed_seconds` if set, or `delta_t`.
+    pub fn elapsed_days(&self) -> f32 {
+        match (self.delta_t, self.elapsed_seconds) {
+            (0, _) => 0.0,
+            (_, Some(seconds)) => seconds as f32 / 86400.0,
+            (delta_t, None) => delta_t as f32,
+        }
+    }
 }
 
 impl FSRSItem {
+    /// An item without metadata. Unlike a struct literal, this keeps compiling when fields
+    /// are added, as `metadata` was.
+    pub fn new(reviews: Vec<FSRSReview>) -> Self {
+        Self {
+            reviews,
+            metadata: None,
+        }
+    }
+
     // The previous reviews done before the current one.
     pub(crate) fn history(&self) -> impl Iterator<Item = &FSRSReview> {
         self.reviews.iter().take(self.reviews.len() - 1)
@@ -77,28 +142,137 @@ impl FSRSItem {
     }
 }
 
+/// Why an item was rejected by [validate_items].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidItemReason {
+    /// The item has fewer than two reviews, so there is no history to predict from.
+    EmptyHistory,
+    /// A review has a rating outside of 1-4.
+    InvalidRating { rating: u32 },
+    /// The first review has a non-zero `delta_t`.
+    NonZeroInitialDeltaT { delta_t: u32 },
+}
+
+impl std::fmt::Display for InvalidItemReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EmptyHistory => write!(f, "at least two reviews are required"),
+            Self::InvalidRating { rating } => write!(f, "rating {rating} is not in 1-4"),
+            Self::NonZeroInitialDeltaT { delta_t } => {
+                write!(f, "first review has delta_t {delta_t} instead of 0")
+            }
+        }
+    }
+}
+
+fn check_item(item: &FSRSItem) -> Result<(), InvalidItemReason> {
+    if item.reviews.len() < 2 {
+        return Err(InvalidItemReason::EmptyHistory);
+    }
+    if let Some(review) = item.reviews.iter().find(|r| !(1..=4).contains(&r.rating)) {
+        return Err(InvalidItemReason::InvalidRating {
+            rating: review.rating,
+        });
+    }
+    match item.reviews[0].delta_t {
+        0 => Ok(()),
+        delta_t => Err(InvalidItemReason::NonZeroInitialDeltaT { delta_t }),
+    }
+}
+
+/// Check that the items can be used for training or evaluation, returning
+/// [FSRSError::InvalidItem] for the first one that can't. As `delta_t` is the number of
+/// days since the previous review, reviews are always in chronological order; the first
+/// review must have a `delta_t` of 0, each item needs a non-empty history, and all
+/// ratings must be 1-4.
+pub fn validate_items(items: &[FSRSItem]) -> Result<()> {
+    items.iter().enumerate().try_for_each(|(index, item)| {
+        check_item(item).map_err(|reason| FSRSError::InvalidItem { index, reason })
+    })
+}
+
+/// Where padding is added when the histories in a batch have different lengths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaddingSide {
+    /// Pad after the reviews. This is what the model expects: padded steps leave the memory
+    /// state unchanged.
+    #[default]
+    Right,
+    /// Pad before the reviews, so the last review of every history is aligned. Only for
+    /// custom analysis of the batches: the model would start each history from the padding,
+    /// so training and evaluation panic when given a left-padded batch.
+    Left,
+}
+
+/// Turns items into batches of tensors.
 #[derive(Clone)]
-pub(crate) struct FSRSBatcher<B: Backend> {
+pub struct FSRSBatcher<B: Backend> {
     device: B::Device,
+    padding_side: PaddingSide,
+    max_seq_len: Option<usize>,
 }
 
 impl<B: Backend> FSRSBatcher<B> {
     pub const fn new(device: B::Device) -> Self {
-        Self { device }
+        Self {
+            device,
+            padding_side: PaddingSide::Right,
+            max_seq_len: None,
+        }
+    }
+
+    pub fn with_padding_side(mut self, padding_side: PaddingSide) -> Self {
+        self.padding_side = padding_side;
+        self
+    }
+
+    /// Keep only the most recent `max_seq_len` reviews of each item, as with
+    /// [TruncationPolicy::KeepRecent], so that a few very long histories don't widen the
+    /// whole batch.
+    pub fn with_max_seq_len(mut self, max_seq_len: usize) -> Self {
+        self.max_seq_len = Some(max_seq_len);
+        self
     }
 }
 
 #[derive(Debug, Clone)]
-pub(crate) struct FSRSBatch<B: Backend> {
+pub struct FSRSBatch<B: Backend> {
+    /// The elapsed days of each review in the histories, see [FSRSReview::elapsed_days].
+    /// [seq_len, batch_size]
     pub t_historys: Tensor<B, 2, Float>,
+    /// The rating of each review in the histories, or 0 for padding. [seq_len, batch_size]
     pub r_historys: Tensor<B, 2, Float>,
+    /// True for reviews, false for padding. [seq_len, batch_size]
+    pub mask: Tensor<B, 2, Bool>,
+    /// The elapsed days of the current review of each item. [batch_size]
     pub delta_ts: Tensor<B, 1, Float>,
+    /// 0 if the current review was a lapse, 1 otherwise. [batch_size]
     pub labels: Tensor<B, 1, Int>,
+    /// The weight of each item in the loss. [batch_size]
     pub weights: Tensor<B, 1, Float>,
+    /// Where the histories were padded.
+    pub padding_side: PaddingSide,
+}
+
+impl<B: Backend> FSRSBatch<B> {
+    /// Panics unless the batch is right-padded, as the model requires.
+    pub(crate) fn assert_right_padded(&self) {
+        assert_eq!(
+            self.padding_side,
+            PaddingSide::Right,
+            "the model can only be run on right-padded batches"
+        );
+    }
 }
 
 impl<B: Backend> Batcher<WeightedFSRSItem, FSRSBatch<B>> for FSRSBatcher<B> {
-    fn batch(&self, weighted_items: Vec<WeightedFSRSItem>) -> FSRSBatch<B> {
+    fn batch(&self, mut weighted_items: Vec<WeightedFSRSItem>) -> FSRSBatch<B> {
+        if let Some(max_seq_len) = self.max_seq_len {
+            let policy = TruncationPolicy::KeepRecent(max_seq_len);
+            for weighted_item in &mut weighted_items {
+                weighted_item.item = policy.apply(std::mem::take(&mut weighted_item.item));
+            }
+        }
         let pad_size = weighted_items
             .iter()
             .map(|x| x.item.reviews.len())
@@ -106,16 +280,28 @@ impl<B: Backend> Batcher<WeightedFSRSItem, FSRSBatch<B>> for FSRSBatcher<B> {
             .expect("FSRSItem is empty")
             - 1;
 
-        let (time_histories, rating_histories) = weighted_items
+        let (time_histories, rating_histories, masks) = weighted_items
             .iter()
             .map(|weighted_item| {
                 let (mut delta_t, mut rating): (Vec<_>, Vec<_>) = weighted_item
                     .item
                     .history()
-                    .map(|r| (r.delta_t, r.rating))
+                    .map(|r| (r.elapsed_days(), r.rating))
                     .unzip();
-                delta_t.resize(pad_size, 0);
-                rating.resize(pad_size, 0);
+                let mut mask = vec![true; delta_t.len()];
+                let padding = pad_size - delta_t.len();
+                match self.padding_side {
+                    PaddingSide::Right => {
+                        delta_t.resize(pad_size, 0.0);
+                        rating.resize(pad_size, 0);
+                        mask.resize(pad_size, false);
+                    }
+                    PaddingSide::Left => {
+                        delta_t.splice(0..0, vec![0.0; padding]);
+                        rating.splice(0..0, vec![0; padding]);
+                        mask.splice(0..0, vec![false; padding]);
+                    }
+                }
                 let delta_t = Tensor::from_data(
                     Data::new(
                         delta_t,
@@ -136,16 +322,25 @@ impl<B: Backend> Batcher<WeightedFSRSItem, FSRSBatch<B>> for FSRSBatcher<B> {
                     .convert(),
                     &self.device,
                 );
-                (delta_t, rating)
+                let mask = Tensor::from_bool(
+                    Data::new(
+                        mask,
+                        Shape {
+                            dims: [1, pad_size],
+                        },
+                    ),
+                    &self.device,
+                );
+                (delta_t, rating, mask)
             })
-            .unzip();
+            .multiunzip();
 
         let (delta_ts, labels, weights) = weighted_items
             .iter()
             .map(|weighted_item| {
                 let current = weighted_item.item.current();
                 let delta_t: Tensor<B, 1> =
-                    Tensor::from_data(Data::from([current.delta_t.elem()]), &self.device);
+                    Tensor::from_data(Data::from([current.elapsed_days().elem()]), &self.device);
                 let label = match current.rating {
                     1 => 0.0,
                     _ => 1.0,
@@ -164,6 +359,7 @@ impl<B: Backend> Batcher<WeightedFSRSItem, FSRSBatch<B>> for FSRSBatcher<B> {
         let r_historys = Tensor::cat(rating_histories, 0)
             .transpose()
             .to_device(&self.device); // [seq_len, batch_size]
+        let mask = Tensor::cat(masks, 0).transpose().to_device(&self.device); // [seq_len, batch_size]
         let delta_ts = Tensor::cat(delta_ts, 0).to_device(&self.device);
         let labels = Tensor::cat(labels, 0).to_device(&self.device);
         let weights = Tensor::cat(weights, 0).to_device(&self.device);
@@ -174,13 +370,21 @@ impl<B: Backend> Batcher<WeightedFSRSItem, FSRSBatch<B>> for FSRSBatcher<B> {
         FSRSBatch {
             t_historys,
             r_historys,
+            mask,
             delta_ts,
             labels,
             weights,
+            padding_side: self.padding_side,
         }
     }
 }
 
+impl<B: Backend> Batcher<FSRSItem, FSRSBatch<B>> for FSRSBatcher<B> {
+    fn batch(&self, items: Vec<FSRSItem>) -> FSRSBatch<B> {
+        self.batch(constant_weighted_fsrs_items(items))
+    }
+}
+
 pub(crate) struct FSRSDataset {
     pub(crate) items: Vec<WeightedFSRSItem>,
 }
@@ -204,15 +408,233 @@ impl From<Vec<WeightedFSRSItem>> for FSRSDataset {
     }
 }
 
-pub fn filter_outlier(
-    pretrainset: Vec<FSRSItem>,
-    mut trainset: Vec<FSRSItem>,
-) -> (Vec<FSRSItem>, Vec<FSRSItem>) {
-    let mut groups = HashMap::<u32, HashMap<u32, Vec<FSRSItem>>>::new();
+/// A [Dataset] that reads items from a source on demand, instead of requiring them to be
+/// collected into a `Vec<FSRSItem>` first.
+///
+/// `open` is called to start a new pass over the source, eg by running a query against a
+/// database. Only the current position of the source is kept in memory: reading the items
+/// in order is cheap, while going backwards restarts the source from the beginning. The
+/// source must yield the same items each time it is opened.
+pub struct StreamingFSRSDataset<F, I> {
+    open: F,
+    len: usize,
+    cursor: Mutex<(usize, I)>,
+}
+
+impl<F, I> StreamingFSRSDataset<F, I>
+where
+    F: Fn() -> I,
+    I: Iterator<Item = FSRSItem>,
+{
+    /// Creates a dataset over the items yielded by `open()`. The source is read once up
+    /// front to count the items.
+    pub fn new(open: F) -> Self {
+        let len = open().count();
+        let cursor = Mutex::new((0, open()));
+        Self { open, len, cursor }
+    }
+}
+
+impl<F, I> Dataset<FSRSItem> for StreamingFSRSDataset<F, I>
+where
+    F: Fn() -> I + Send + Sync,
+    I: Iterator<Item = FSRSItem> + Send,
+{
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Option<FSRSItem> {
+        if index >= self.len {
+            return None;
+        }
+        let mut cursor = self.cursor.lock().unwrap();
+        let (position, iter) = &mut *cursor;
+        if index < *position {
+            *position = 0;
+            *iter = (self.open)();
+        }
+        let item = iter.nth(index - *position);
+        *position = index + 1;
+        item
+    }
+}
+
+/// How to shorten items with very long review histories, which otherwise inflate the padded
+/// size of every batch they are in while contributing little to accuracy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TruncationPolicy {
+    /// Keep the histories unchanged.
+    #[default]
+    None,
+    /// Keep only the most recent reviews, including the current one (at least 2). The first
+    /// review that is kept becomes the start of the history, so its `delta_t` is set to 0
+    /// and its `elapsed_seconds` cleared.
+    ///
+    /// The memory built up by the dropped reviews is discarded: a truncated card is treated
+    /// as new, with its first kept review as its first ever review. There is no policy that
+    /// collapses the dropped reviews into a starting state, as that state depends on the
+    /// parameters being trained. To keep the old reviews when computing memory states, use
+    /// [chunk_item] or [FSRS::memory_state_chunked](crate::FSRS::memory_state_chunked)
+    /// instead.
+    KeepRecent(usize),
+}
+
+impl TruncationPolicy {
+    fn apply(self, mut item: FSRSItem) -> FSRSItem {
+        match self {
+            Self::None => item,
+            Self::KeepRecent(n) => {
+                let n = n.max(2);
+                if item.reviews.len() > n {
+                    item.reviews.drain(..item.reviews.len() - n);
+                    item.reviews[0].delta_t = 0;
+                    item.reviews[0].elapsed_seconds = None;
+                }
+                item
+            }
+        }
+    }
+}
+
+/// Split the reviews of an item into consecutive chunks of at most `chunk_len` reviews (at
+/// least 1), so that a very long history can be processed a chunk at a time. Only the first
+/// chunk starts with the card's first review; the first review of each later chunk keeps its
+/// `delta_t`, and must be processed starting from the memory state reached at the end of the
+/// previous chunk, eg with [FSRS::memory_state](crate::FSRS::memory_state)'s
+/// `starting_state`.
+pub fn chunk_item(item: &FSRSItem, chunk_len: usize) -> Vec<FSRSItem> {
+    item.reviews
+        .chunks(chunk_len.max(1))
+        .map(|reviews| FSRSItem {
+            reviews: reviews.to_vec(),
+            metadata: item.metadata,
+        })
+        .collect()
+}
+
+/// Shorten the history of each item according to `policy`.
+pub fn truncate_items(items: Vec<FSRSItem>, policy: TruncationPolicy) -> Vec<FSRSItem> {
+    items.into_iter().map(|item| policy.apply(item)).collect()
+}
+
+/// The number of successes and failures among the items whose current review has a
+/// `delta_t` in `min_delta_t..=max_delta_t`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassBalance {
+    pub min_delta_t: u32,
+    pub max_delta_t: u32,
+    pub successes: usize,
+    pub failures: usize,
+}
+
+impl ClassBalance {
+    pub fn failure_ratio(&self) -> f32 {
+        self.failures as f32 / (self.successes + self.failures) as f32
+    }
+}
+
+/// Count successes and failures per `delta_t` bucket. Buckets double in size (0, 1, 2-3,
+/// 4-7, ...), and only non-empty buckets are returned, ordered by `delta_t`.
+pub fn class_balance(items: &[FSRSItem]) -> Vec<ClassBalance> {
+    let mut buckets: HashMap<u32, ClassBalance> = HashMap::new();
+    for item in items {
+        let delta_t = item.current().delta_t;
+        let bucket = u32::BITS - delta_t.leading_zeros();
+        let balance = buckets.entry(bucket).or_insert(ClassBalance {
+            min_delta_t: (1 << bucket) >> 1,
+            max_delta_t: ((1u64 << bucket) - 1) as u32,
+            successes: 0,
+            failures: 0,
+        });
+        if item.current().rating == 1 {
+            balance.failures += 1;
+        } else {
+            balance.successes += 1;
+        }
+    }
+    buckets
+        .into_iter()
+        .sorted_by_key(|(bucket, _)| *bucket)
+        .map(|(_, balance)| balance)
+        .collect()
+}
+
+/// Rebalances successes and failures, for collections where nearly all reviews are
+/// successes.
+#[derive(Config, Debug, PartialEq)]
+pub struct ResampleConfig {
+    /// The fraction of successes that are kept, chosen at random.
+    #[config(default = 1.0)]
+    pub success_keep_ratio: f64,
+    /// How many times each failure is included.
+    #[config(default = 1)]
+    pub failure_copies: usize,
+}
+
+pub(crate) fn resample<T: Clone>(
+    items: Vec<T>,
+    is_failure: impl Fn(&T) -> bool,
+    config: &ResampleConfig,
+    seed: u64,
+) -> Vec<T> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let keep_ratio = config.success_keep_ratio.clamp(0.0, 1.0);
+    let mut resampled = vec![];
+    for item in items {
+        if is_failure(&item) {
+            resampled.extend(std::iter::repeat(item).take(config.failure_copies));
+        } else if rng.gen_bool(keep_ratio) {
+            resampled.push(item);
+        }
+    }
+    resampled
+}
+
+/// Undersample successes and/or oversample failures. The relative order of the items is
+/// kept, and the same seed always selects the same items.
+pub fn resample_items(items: Vec<FSRSItem>, config: &ResampleConfig, seed: u64) -> Vec<FSRSItem> {
+    resample(items, |item| item.current().rating == 1, config, seed)
+}
+
+/// Controls the removal of outliers before training, as in the Python optimizer. The items
+/// with a single long-term review are grouped by the rating of their first review and the
+/// `delta_t` of their current review; the smallest groups of each rating are removed, as
+/// are groups with absurd intervals, which are often caused by corrupted or imported
+/// revlogs. Items of the other reviews are removed if their first long-term review falls in
+/// a removed group.
+#[derive(Config, Debug, PartialEq)]
+pub struct OutlierFilterConfig {
+    /// The fraction of the items of each first rating that is removed, smallest groups
+    /// first.
+    #[config(default = 0.05)]
+    pub remove_fraction: f64,
+    /// The minimum number of items of each first rating that is removed.
+    #[config(default = 20)]
+    pub min_removed: usize,
+    /// Groups with fewer items are removed.
+    #[config(default = 6)]
+    pub min_group_size: usize,
+    /// Groups with a longer `delta_t` are removed, if the first rating is not easy.
+    #[config(default = 100)]
+    pub max_delta_t: u32,
+    /// Groups with a longer `delta_t` are removed, if the first rating is easy.
+    #[config(default = 365)]
+    pub max_delta_t_easy: u32,
+}
+
+pub(crate) fn filter_outlier<T: Borrow<FSRSItem> + Clone>(
+    pretrainset: Vec<T>,
+    mut trainset: Vec<T>,
+    config: &OutlierFilterConfig,
+) -> (Vec<T>, Vec<T>) {
+    let mut groups = HashMap::<u32, HashMap<u32, Vec<T>>>::new();
 
     // group by rating of first review and delta_t of second review
     for item in pretrainset.into_iter() {
-        let (first_review, second_review) = (item.reviews.first().unwrap(), item.current());
+        let fsrs_item: &FSRSItem = item.borrow();
+        let (first_review, second_review) =
+            (fsrs_item.reviews.first().unwrap(), fsrs_item.current());
         let rating_group = groups.entry(first_review.rating).or_default();
         let delta_t_group = rating_group.entry(second_review.delta_t).or_default();
         delta_t_group.push(item);
@@ -233,15 +655,23 @@ pub fn filter_outlier(
         });
 
         let total = sub_groups.iter().map(|(_, vec)| vec.len()).sum::<usize>();
+        let to_remove = config
+            .min_removed
+            .max((total as f64 * config.remove_fraction) as usize);
+        let max_delta_t = if rating != 4 {
+            config.max_delta_t
+        } else {
+            config.max_delta_t_easy
+        };
         let mut has_been_removed = 0;
 
         for (delta_t, sub_group) in sub_groups.iter().rev() {
-            // remove 5% items (20 at least) of each group
-            if has_been_removed + sub_group.len() >= 20.max(total / 20) {
+            // remove 5% items (20 at least) of each group by default
+            if has_been_removed + sub_group.len() >= to_remove {
                 // keep the sub_group if it includes at least six items
                 // and the delta_t is less than 100 days if rating is not 4
                 // or less than 365 days if rating is 4
-                if sub_group.len() >= 6 && *delta_t <= if rating != 4 { 100 } else { 365 } {
+                if sub_group.len() >= config.min_group_size && *delta_t <= max_delta_t {
                     filtered_items.extend_from_slice(sub_group);
                 } else {
                     removed_pairs[rating as usize].insert(*delta_t);
@@ -254,18 +684,27 @@ pub fn filter_outlier(
     }
     // keep the items in trainset if they are not removed from filtered_items
     trainset.retain(|item| {
+        let item: &FSRSItem = item.borrow();
         !removed_pairs[item.reviews[0].rating as usize]
             .contains(&item.first_long_term_review().delta_t)
     });
     (filtered_items, trainset)
 }
 
-pub fn prepare_training_data(items: Vec<FSRSItem>) -> (Vec<FSRSItem>, Vec<FSRSItem>) {
+/// Split the items into the pretraining set and the training set, which includes the
+/// pretraining set. Outliers are removed with `outlier_filter`, unless the
+/// `FSRS_NO_OUTLIER` environment variable is set.
+pub(crate) fn prepare_training_data<T: Borrow<FSRSItem> + Clone>(
+    items: Vec<T>,
+    outlier_filter: Option<&OutlierFilterConfig>,
+) -> (Vec<T>, Vec<T>) {
     let (mut pretrainset, mut trainset) = items
         .into_iter()
-        .partition(|item| item.long_term_review_cnt() == 1);
-    if std::env::var("FSRS_NO_OUTLIER").is_err() {
-        (pretrainset, trainset) = filter_outlier(pretrainset, trainset);
+        .partition(|item| item.borrow().long_term_review_cnt() == 1);
+    if let Some(outlier_filter) = outlier_filter {
+        if std::env::var("FSRS_NO_OUTLIER").is_err() {
+            (pretrainset, trainset) = filter_outlier(pretrainset, trainset, outlier_filter);
+        }
     }
     (pretrainset.clone(), [pretrainset, trainset].concat())
 }
@@ -286,21 +725,51 @@ pub(crate) fn constant_weighted_fsrs_items(items: Vec<FSRSItem>) -> Vec<Weighted
 
 /// The input items should be sorted by the review timestamp.
 pub(crate) fn recency_weighted_fsrs_items(items: Vec<FSRSItem>) -> Vec<WeightedFSRSItem> {
-    let length = items.len() as f32 - 1.0;
-    items
+    let mut weighted_items = constant_weighted_fsrs_items(items);
+    apply_recency_weights(&mut weighted_items);
+    weighted_items
+}
+
+/// Multiply the weights of the items by their recency weight, keeping any sample weights.
+/// The input items should be sorted by the review timestamp.
+pub(crate) fn apply_recency_weights(weighted_items: &mut [WeightedFSRSItem]) {
+    let len = weighted_items.len();
+    for (idx, weighted_item) in weighted_items.iter_mut().enumerate() {
+        weighted_item.weight *= recency_weight(idx, len);
+    }
+}
+
+/// The recency weight of the item at `idx` out of `len` items, from 0.25 for the oldest
+/// item to 1.0 for the most recent one.
+pub(crate) fn recency_weight(idx: usize, len: usize) -> f32 {
+    0.25 + 0.75 * (idx as f32 / (len as f32 - 1.0)).powi(3)
+}
+
+/// Pair `items` with `sample_weights`, which must be positive and finite, with one weight
+/// per item.
+pub(crate) fn sample_weighted_fsrs_items(
+    items: Vec<FSRSItem>,
+    sample_weights: &[f32],
+) -> Result<Vec<WeightedFSRSItem>> {
+    if items.len() != sample_weights.len()
+        || sample_weights
+            .iter()
+            .any(|weight| !weight.is_finite() || *weight <= 0.0)
+    {
+        return Err(FSRSError::InvalidInput);
+    }
+    Ok(items
         .into_iter()
-        .enumerate()
-        .map(|(idx, item)| WeightedFSRSItem {
-            weight: 0.25 + 0.75 * (idx as f32 / length).powi(3),
-            item,
-        })
-        .collect()
+        .zip(sample_weights)
+        .map(|(item, &weight)| WeightedFSRSItem { weight, item })
+        .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::convertor_tests::anki21_sample_file_converted_to_fsrs;
+    use crate::test_helpers::item_from;
 
     #[test]
     fn from_anki() {
@@ -310,19 +779,19 @@ mod tests {
             anki21_sample_file_converted_to_fsrs(),
         ));
         assert_eq!(
-            dataset.get(704).unwrap().item,
-            FSRSItem {
-                reviews: vec![
-                    FSRSReview {
-                        rating: 4,
-                        delta_t: 0
-                    },
-                    FSRSReview {
-                        rating: 3,
-                        delta_t: 3
-                    }
-                ],
-            }
+            dataset.get(704).unwrap().item.reviews,
+            [
+                FSRSReview {
+                    rating: 4,
+                    delta_t: 0,
+                    elapsed_seconds: None,
+                },
+                FSRSReview {
+                    rating: 3,
+                    delta_t: 3,
+                    elapsed_seconds: None,
+                }
+            ]
         );
 
         use burn::backend::ndarray::NdArrayDevice;
@@ -356,50 +825,90 @@ mod tests {
             FSRSItem {
                 reviews: [(4, 0), (3, 5)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None,
+                    })
                     .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(4, 0), (3, 5), (3, 11)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None,
+                    })
                     .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(4, 0), (3, 2)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None,
+                    })
                     .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(4, 0), (3, 2), (3, 6)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None,
+                    })
                     .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(4, 0), (3, 2), (3, 6), (3, 16)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None,
+                    })
                     .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(4, 0), (3, 2), (3, 6), (3, 16), (3, 39)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None,
+                    })
                     .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(1, 0), (1, 1)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None,
+                    })
                     .collect(),
+                ..Default::default()
             },
             FSRSItem {
                 reviews: [(1, 0), (1, 1), (3, 1)]
                     .into_iter()
-                    .map(|(rating, delta_t)| FSRSReview { rating, delta_t })
+                    .map(|(rating, delta_t)| FSRSReview {
+                        rating,
+                        delta_t,
+                        elapsed_seconds: None,
+                    })
                     .collect(),
+                ..Default::default()
             },
         ];
         let items = items
@@ -430,6 +939,288 @@ mod tests {
             Data::from([5.0, 11.0, 2.0, 6.0, 16.0, 39.0, 1.0, 1.0])
         );
         assert_eq!(batch.labels.to_data(), Data::from([1, 1, 1, 1, 1, 1, 0, 1]));
+        assert_eq!(
+            batch.mask.to_data(),
+            Data::from([
+                [true, true, true, true, true, true, true, true],
+                [false, true, false, true, true, true, false, true],
+                [false, false, false, false, true, true, false, false],
+                [false, false, false, false, false, true, false, false]
+            ])
+        );
+    }
+
+    #[test]
+    fn left_padding() {
+        use burn::backend::ndarray::NdArrayDevice;
+        use burn::backend::NdArray;
+        type Backend = NdArray<f32>;
+        let batcher =
+            FSRSBatcher::<Backend>::new(NdArrayDevice::Cpu).with_padding_side(PaddingSide::Left);
+        let items = [
+            [(4, 0), (3, 5)].as_slice(),
+            &[(1, 0), (3, 1), (3, 2), (4, 6)],
+        ]
+        .into_iter()
+        .map(|reviews| FSRSItem {
+            reviews: reviews
+                .iter()
+                .map(|&(rating, delta_t)| FSRSReview {
+                    rating,
+                    delta_t,
+                    elapsed_seconds: None,
+                })
+                .collect(),
+            ..Default::default()
+        })
+        .collect();
+        let batch: FSRSBatch<Backend> = batcher.batch(items);
+        assert_eq!(
+            batch.t_historys.to_data(),
+            Data::from([[0.0, 0.0], [0.0, 1.0], [0.0, 2.0]])
+        );
+        assert_eq!(
+            batch.r_historys.to_data(),
+            Data::from([[0.0, 1.0], [0.0, 3.0], [4.0, 3.0]])
+        );
+        assert_eq!(
+            batch.mask.to_data(),
+            Data::from([[false, true], [false, true], [true, true]])
+        );
+        assert_eq!(batch.padding_side, PaddingSide::Left);
+        assert_eq!(batch.delta_ts.to_data(), Data::from([5.0, 6.0]));
+        assert_eq!(batch.weights.to_data(), Data::from([1.0, 1.0]));
+    }
+
+    #[test]
+    fn max_seq_len() {
+        use burn::backend::ndarray::NdArrayDevice;
+        use burn::backend::NdArray;
+        type Backend = NdArray<f32>;
+        let batcher = FSRSBatcher::<Backend>::new(NdArrayDevice::Cpu).with_max_seq_len(3);
+        let items = [
+            [(4, 0), (3, 5)].as_slice(),
+            &[(1, 0), (3, 1), (3, 2), (4, 6)],
+        ]
+        .into_iter()
+        .map(|reviews| FSRSItem {
+            reviews: reviews
+                .iter()
+                .map(|&(rating, delta_t)| FSRSReview {
+                    rating,
+                    delta_t,
+                    elapsed_seconds: None,
+                })
+                .collect(),
+            ..Default::default()
+        })
+        .collect();
+        let batch: FSRSBatch<Backend> = batcher.batch(items);
+        assert_eq!(
+            batch.t_historys.to_data(),
+            Data::from([[0.0, 0.0], [0.0, 2.0]])
+        );
+        assert_eq!(
+            batch.r_historys.to_data(),
+            Data::from([[4.0, 3.0], [0.0, 3.0]])
+        );
+        assert_eq!(
+            batch.mask.to_data(),
+            Data::from([[true, true], [false, true]])
+        );
+        assert_eq!(batch.delta_ts.to_data(), Data::from([5.0, 6.0]));
+        assert_eq!(batch.weights.to_data(), Data::from([1.0, 1.0]));
+    }
+
+    #[test]
+    fn elapsed_seconds() {
+        use burn::backend::ndarray::NdArrayDevice;
+        use burn::backend::NdArray;
+        type Backend = NdArray<f32>;
+        let batcher = FSRSBatcher::<Backend>::new(NdArrayDevice::Cpu);
+        let review = |rating, delta_t, elapsed_seconds| FSRSReview {
+            rating,
+            delta_t,
+            elapsed_seconds,
+        };
+        let items = vec![
+            FSRSItem {
+                reviews: vec![
+                    review(3, 0, None),
+                    review(3, 0, Some(600)),
+                    review(3, 1, Some(43_200)),
+                    review(3, 3, Some(3 * 86_400 + 21_600)),
+                ],
+                ..Default::default()
+            },
+            FSRSItem {
+                reviews: vec![review(4, 0, None), review(3, 2, None)],
+                ..Default::default()
+            },
+        ];
+        assert_eq!(items[0].reviews[1].elapsed_days(), 0.0);
+        assert_eq!(items[0].reviews[2].elapsed_days(), 0.5);
+        let batch: FSRSBatch<Backend> = batcher.batch(items);
+        assert_eq!(
+            batch.t_historys.to_data(),
+            Data::from([[0.0, 0.0], [0.0, 0.0], [0.5, 0.0]])
+        );
+        assert_eq!(batch.delta_ts.to_data(), Data::from([3.25, 2.0]));
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(validate_items(&[]), Ok(()));
+        assert_eq!(
+            validate_items(&[
+                item_from(&[(3, 0), (3, 1)]),
+                item_from(&[(1, 0), (4, 1), (3, 5)])
+            ]),
+            Ok(())
+        );
+        let error =
+            validate_items(&[item_from(&[(3, 0), (3, 1)]), item_from(&[(3, 0)])]).unwrap_err();
+        assert_eq!(
+            error,
+            FSRSError::InvalidItem {
+                index: 1,
+                reason: InvalidItemReason::EmptyHistory
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "item 1 is invalid: at least two reviews are required"
+        );
+        assert_eq!(
+            validate_items(&[item_from(&[(3, 0), (5, 1)])]),
+            Err(FSRSError::InvalidItem {
+                index: 0,
+                reason: InvalidItemReason::InvalidRating { rating: 5 }
+            })
+        );
+        assert_eq!(
+            validate_items(&[item_from(&[(3, 2), (3, 1)])]),
+            Err(FSRSError::InvalidItem {
+                index: 0,
+                reason: InvalidItemReason::NonZeroInitialDeltaT { delta_t: 2 }
+            })
+        );
+    }
+
+    #[test]
+    fn class_balance_and_resampling() {
+        let item = |rating, delta_t| item_from(&[(3, 0), (rating, delta_t)]);
+        let mut items = vec![];
+        for delta_t in [1, 2, 3, 5, 30] {
+            items.extend((0..9).map(|_| item(3, delta_t)));
+            items.push(item(1, delta_t));
+        }
+        let balance = class_balance(&items);
+        assert_eq!(
+            balance,
+            [
+                ClassBalance {
+                    min_delta_t: 1,
+                    max_delta_t: 1,
+                    successes: 9,
+                    failures: 1
+                },
+                ClassBalance {
+                    min_delta_t: 2,
+                    max_delta_t: 3,
+                    successes: 18,
+                    failures: 2
+                },
+                ClassBalance {
+                    min_delta_t: 4,
+                    max_delta_t: 7,
+                    successes: 9,
+                    failures: 1
+                },
+                ClassBalance {
+                    min_delta_t: 16,
+                    max_delta_t: 31,
+                    successes: 9,
+                    failures: 1
+                },
+            ]
+        );
+        assert_eq!(balance[0].failure_ratio(), 0.1);
+
+        let config = ResampleConfig::new()
+            .with_success_keep_ratio(0.5)
+            .with_failure_copies(3);
+        let resampled = resample_items(items.clone(), &config, 42);
+        assert_eq!(resample_items(items.clone(), &config, 42), resampled);
+        let failures = resampled.iter().filter(|i| i.current().rating == 1).count();
+        assert_eq!(failures, 15);
+        assert!((10..35).contains(&(resampled.len() - failures)));
+        assert_eq!(
+            resample_items(items.clone(), &ResampleConfig::new(), 42),
+            items
+        );
+    }
+
+    #[test]
+    fn truncation() {
+        let items = vec![
+            item_from(&[(3, 0), (3, 1)]),
+            item_from(&[(3, 0), (1, 1), (3, 1), (4, 5), (3, 20)]),
+        ];
+        assert_eq!(truncate_items(items.clone(), TruncationPolicy::None), items);
+        assert_eq!(
+            truncate_items(items.clone(), TruncationPolicy::KeepRecent(3)),
+            [
+                item_from(&[(3, 0), (3, 1)]),
+                item_from(&[(3, 0), (4, 5), (3, 20)])
+            ]
+        );
+        assert_eq!(
+            truncate_items(items, TruncationPolicy::KeepRecent(0)),
+            [item_from(&[(3, 0), (3, 1)]), item_from(&[(4, 0), (3, 20)])]
+        );
+    }
+
+    #[test]
+    fn streaming_dataset() {
+        let item = |delta_t| item_from(&[(3, 0), (3, delta_t)]);
+        let dataset = StreamingFSRSDataset::new(|| (1..=100).map(item));
+        assert_eq!(dataset.len(), 100);
+        assert_eq!(dataset.get(0), Some(item(1)));
+        assert_eq!(dataset.get(1), Some(item(2)));
+        assert_eq!(dataset.get(50), Some(item(51)));
+        assert_eq!(dataset.get(10), Some(item(11)));
+        assert_eq!(dataset.get(99), Some(item(100)));
+        assert_eq!(dataset.get(100), None);
+        assert_eq!(
+            dataset.iter().collect_vec(),
+            (1..=100).map(item).collect_vec()
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let item = item_from(&[(3, 0), (1, 5)]);
+        let json = serde_json::to_string(&item).unwrap();
+        assert_eq!(
+            json,
+            r#"{"reviews":[{"rating":3,"delta_t":0},{"rating":1,"delta_t":5}]}"#
+        );
+        assert_eq!(serde_json::from_str::<FSRSItem>(&json).unwrap(), item);
+
+        let item = FSRSItem {
+            metadata: Some(ItemMetadata {
+                card_id: Some(1),
+                deck_id: None,
+            }),
+            ..item
+        };
+        let json = serde_json::to_string(&item).unwrap();
+        assert_eq!(
+            json,
+            r#"{"reviews":[{"rating":3,"delta_t":0},{"rating":1,"delta_t":5}],"metadata":{"card_id":1,"deck_id":null}}"#
+        );
+        assert_eq!(serde_json::from_str::<FSRSItem>(&json).unwrap(), item);
     }
 
     #[test]
@@ -440,8 +1231,35 @@ mod tests {
             .partition(|item| item.long_term_review_cnt() == 1);
         assert_eq!(pretrainset.len(), 3315);
         assert_eq!(trainset.len(), 10975);
-        (pretrainset, trainset) = filter_outlier(pretrainset, trainset);
+        (pretrainset, trainset) =
+            filter_outlier(pretrainset, trainset, &OutlierFilterConfig::new());
         assert_eq!(pretrainset.len(), 3265);
         assert_eq!(trainset.len(), 10900);
     }
+
+    #[test]
+    fn outlier_filter_config() {
+        let item = |delta_t| item_from(&[(3, 0), (3, delta_t)]);
+        let pretrainset = [(5, 30), (200, 10), (7, 3)]
+            .into_iter()
+            .flat_map(|(delta_t, count)| std::iter::repeat(item(delta_t)).take(count))
+            .collect::<Vec<_>>();
+        let delta_ts = |config: &OutlierFilterConfig| {
+            let (filtered, _) = filter_outlier(pretrainset.clone(), vec![], config);
+            filtered
+                .iter()
+                .map(|item| item.current().delta_t)
+                .dedup()
+                .collect::<Vec<_>>()
+        };
+        // the two smallest groups make up the 20 items removed at least
+        assert_eq!(delta_ts(&OutlierFilterConfig::new()), [5]);
+        // the rare and the absurd groups are removed
+        let config = OutlierFilterConfig::new()
+            .with_min_removed(0)
+            .with_remove_fraction(0.0);
+        assert_eq!(delta_ts(&config), [5]);
+        let config = config.with_min_group_size(1).with_max_delta_t(365);
+        assert_eq!(delta_ts(&config), [7, 200, 5]);
+    }
 }
@@ -1,13 +1,35 @@
 use snafu::Snafu;
 
+use crate::dataset::InvalidItemReason;
+use crate::model::InvalidParametersReason;
+use crate::progress::ProgressStage;
+
 #[derive(Snafu, Debug, PartialEq)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Error), uniffi(flat_error))]
 pub enum FSRSError {
-    NotEnoughData,
-    Interrupted,
-    InvalidParameters,
+    /// There are too few items, or too few of the kind needed, eg items with a single
+    /// long-term review for pretraining.
+    #[snafu(display("not enough data: found {found} items, at least {required} required"))]
+    NotEnoughData {
+        found: usize,
+        required: usize,
+    },
+    #[snafu(display("{stage:?} was interrupted"))]
+    Interrupted {
+        stage: ProgressStage,
+    },
+    #[snafu(display("invalid parameters: {reason}"))]
+    InvalidParameters {
+        reason: InvalidParametersReason,
+    },
     OptimalNotFound,
     InvalidInput,
     InvalidDeckSize,
+    #[snafu(display("item {index} is invalid: {reason}"))]
+    InvalidItem {
+        index: usize,
+        reason: InvalidItemReason,
+    },
 }
 
 pub type Result<T, E = FSRSError> = std::result::Result<T, E>;
@@ -0,0 +1,106 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::inference::ModelEvaluation;
+
+/// Something that happened in an [FSRS](crate::FSRS) instance, reported to the hook given
+/// to [FSRS::with_event_hook](crate::FSRS::with_event_hook).
+#[derive(Debug, Clone, PartialEq)]
+pub enum FSRSEvent {
+    /// [FSRS::compute_parameters](crate::FSRS::compute_parameters) was called.
+    TrainingStarted { item_count: usize },
+    /// Training returned parameters. Not sent when it fails or is interrupted.
+    TrainingFinished { parameters: Vec<f32> },
+    /// [FSRS::set_parameters](crate::FSRS::set_parameters) replaced the parameters.
+    ParametersUpdated { parameters: Vec<f32> },
+    /// [FSRS::evaluate](crate::FSRS::evaluate) finished.
+    EvaluationCompleted { evaluation: ModelEvaluation },
+    /// [FSRS::optimal_retention](crate::FSRS::optimal_retention) simulated a desired
+    /// retention, and found the given cost per memorized card.
+    RetentionSimulated { desired_retention: f32, cost: f32 },
+    /// [FSRS::optimal_retention](crate::FSRS::optimal_retention) found the optimal retention.
+    OptimalRetentionFound { desired_retention: f32 },
+}
+
+/// A callback receiving [FSRSEvent]s. It is called on the thread doing the work, so it
+/// should return quickly.
+#[derive(Clone)]
+pub(crate) struct EventHook(Arc<dyn Fn(&FSRSEvent) + Send + Sync>);
+
+impl EventHook {
+    pub(crate) fn new(hook: impl Fn(&FSRSEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    pub(crate) fn emit(&self, event: FSRSEvent) {
+        (self.0)(&event)
+    }
+}
+
+impl fmt::Debug for EventHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventHook")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::item_from;
+    use std::sync::Mutex;
+
+    use crate::error::Result;
+    use crate::optimal_retention::SimulatorConfig;
+    use crate::{DEFAULT_PARAMETERS, FSRS};
+
+    #[test]
+    fn event_hook() -> Result<()> {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        let fsrs = FSRS::new(None)?
+            .with_event_hook(move |event| recorded.lock().unwrap().push(event.clone()));
+        let take = || std::mem::take(&mut *events.lock().unwrap());
+
+        fsrs.set_parameters(&[])?;
+        assert_eq!(
+            take(),
+            [FSRSEvent::ParametersUpdated {
+                parameters: DEFAULT_PARAMETERS.to_vec()
+            }]
+        );
+
+        let item = item_from(&[(3, 0), (3, 2)]);
+        let evaluation = fsrs.evaluate(vec![item.clone(); 10], |_| true)?;
+        assert_eq!(take(), [FSRSEvent::EvaluationCompleted { evaluation }]);
+
+        // too few items to train on, so the defaults are returned
+        let parameters = fsrs.compute_parameters(vec![item; 5], None, false)?;
+        assert_eq!(
+            take(),
+            [
+                FSRSEvent::TrainingStarted { item_count: 5 },
+                FSRSEvent::TrainingFinished { parameters }
+            ]
+        );
+
+        let config = SimulatorConfig {
+            deck_size: 3650,
+            learn_span: 365,
+            max_cost_perday: f32::INFINITY,
+            learn_limit: 10,
+            ..Default::default()
+        };
+        let desired_retention = fsrs.optimal_retention(&config, &[], |_| true)?;
+        let events = take();
+        let (last, simulated) = events.split_last().unwrap();
+        assert_eq!(
+            last,
+            &FSRSEvent::OptimalRetentionFound { desired_retention }
+        );
+        assert!(!simulated.is_empty());
+        assert!(simulated
+            .iter()
+            .all(|event| matches!(event, FSRSEvent::RetentionSimulated { .. })));
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+//! UniFFI bindings for Kotlin and Swift, enabled with the `uniffi` feature. They expose an
+//! `FSRS` object for training, evaluation and scheduling, with [FSRSItem], [FSRSReview],
+//! [MemoryState], [NextStates] and [ModelEvaluation] as records, and [FSRSError] as an
+//! exception carrying its message. Build the library with eg
+//! `cargo rustc --release --lib --features uniffi --crate-type cdylib`, then generate the
+//! bindings from it with `uniffi-bindgen generate --library` of the same UniFFI version.
+
+use std::sync::Arc;
+
+use crate::dataset::FSRSItem;
+use crate::error::Result;
+use crate::inference::{ItemProgress, MemoryState, ModelEvaluation, NextStates};
+use crate::model;
+
+#[cfg(doc)]
+use crate::{dataset::FSRSReview, error::FSRSError};
+
+/// Implemented by the app to follow long-running operations.
+#[uniffi::export(callback_interface)]
+pub trait ProgressCallback: Send + Sync {
+    /// Called with the number of items processed so far. The operation stops with
+    /// [FSRSError::Interrupted] once it returns false.
+    fn on_progress(&self, current: u64, total: u64) -> bool;
+}
+
+fn report(progress: &Option<Box<dyn ProgressCallback>>, item_progress: ItemProgress) -> bool {
+    progress.as_ref().map_or(true, |callback| {
+        callback.on_progress(item_progress.current as u64, item_progress.total as u64)
+    })
+}
+
+/// [model::FSRS] with the default backend.
+#[derive(uniffi::Object)]
+pub struct FSRS(model::FSRS);
+
+#[uniffi::export]
+impl FSRS {
+    /// The default parameters are used if `parameters` is empty.
+    #[uniffi::constructor]
+    pub fn new(parameters: Vec<f32>) -> Result<Arc<Self>> {
+        Ok(Arc::new(Self(model::FSRS::new(Some(&parameters))?)))
+    }
+
+    /// See [model::FSRS::compute_parameters_with_progress].
+    pub fn compute_parameters(
+        &self,
+        train_set: Vec<FSRSItem>,
+        enable_short_term: bool,
+        progress: Option<Box<dyn ProgressCallback>>,
+    ) -> Result<Vec<f32>> {
+        self.0
+            .compute_parameters_with_progress(train_set, enable_short_term, |item_progress| {
+                report(&progress, item_progress)
+            })
+    }
+
+    /// See [model::FSRS::evaluate].
+    pub fn evaluate(
+        &self,
+        items: Vec<FSRSItem>,
+        progress: Option<Box<dyn ProgressCallback>>,
+    ) -> Result<ModelEvaluation> {
+        self.0
+            .evaluate(items, |item_progress| report(&progress, item_progress))
+    }
+
+    /// See [model::FSRS::memory_state].
+    pub fn memory_state(
+        &self,
+        item: FSRSItem,
+        starting_state: Option<MemoryState>,
+    ) -> Result<MemoryState> {
+        self.0.memory_state(item, starting_state)
+    }
+
+    /// See [model::FSRS::next_states].
+    pub fn next_states(
+        &self,
+        current_memory_state: Option<MemoryState>,
+        desired_retention: f32,
+        days_elapsed: u32,
+    ) -> Result<NextStates> {
+        self.0
+            .next_states(current_memory_state, desired_retention, days_elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FSRSError;
+    use crate::progress::ProgressStage;
+    use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
+    use crate::test_helpers::{assert_approx_eq, item_from};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    struct StopAfter {
+        calls: AtomicU64,
+        limit: u64,
+    }
+
+    impl ProgressCallback for StopAfter {
+        fn on_progress(&self, current: u64, total: u64) -> bool {
+            assert!(current <= total);
+            self.calls.fetch_add(1, Ordering::Relaxed) < self.limit
+        }
+    }
+
+    #[test]
+    fn scheduling() -> Result<()> {
+        let fsrs = FSRS::new(vec![])?;
+        let item = item_from(&[(3, 0), (3, 3)]);
+        let state = fsrs.memory_state(item.clone(), None)?;
+        assert_eq!(
+            state,
+            model::FSRS::new(Some(&[]))?.memory_state(item, None)?
+        );
+        assert!(fsrs.next_states(Some(state), 0.9, 3)?.good.interval > 3.0);
+        assert!(FSRS::new(vec![1.0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn progress() -> Result<()> {
+        let fsrs = FSRS::new(vec![])?;
+        let items = generate_sample_items(&SampleDatasetConfig::default())?;
+        let evaluation = fsrs.evaluate(items.clone(), None)?;
+        let expected = model::FSRS::new(Some(&[]))?.evaluate(items.clone(), |_| true)?;
+        assert_approx_eq(
+            [evaluation.log_loss, evaluation.rmse_bins],
+            [expected.log_loss, expected.rmse_bins],
+        );
+        let callback = StopAfter {
+            calls: AtomicU64::new(0),
+            limit: 1,
+        };
+        assert_eq!(
+            fsrs.evaluate(items, Some(Box::new(callback))),
+            Err(FSRSError::Interrupted {
+                stage: ProgressStage::Evaluation
+            })
+        );
+        Ok(())
+    }
+}
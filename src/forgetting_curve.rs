@@ -0,0 +1,139 @@
+use burn::constant;
+use serde::{Deserialize, Serialize};
+
+use crate::inference::{DECAY, FACTOR};
+
+/// How retrievability falls with the time since the last review. For every curve, stability
+/// is the number of days after which retrievability is 90%, so memory states mean the same
+/// thing whichever curve is used.
+pub trait ForgettingCurve {
+    /// The probability of recall after `days_elapsed`, for a memory of `stability`.
+    fn retrievability(&self, days_elapsed: f32, stability: f32) -> f32;
+
+    /// The number of days after which retrievability falls to `desired_retention`.
+    fn interval(&self, stability: f32, desired_retention: f32) -> f32;
+}
+
+/// The curve of FSRS, `(1 + factor * t / s) ^ decay`, where `factor` makes retrievability
+/// 90% at `t == s`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerCurve {
+    /// Negative; closer to 0 for a flatter tail.
+    pub decay: f32,
+}
+
+impl Default for PowerCurve {
+    fn default() -> Self {
+        Self {
+            decay: DECAY as f32,
+        }
+    }
+}
+
+impl PowerCurve {
+    pub(crate) fn factor(&self) -> f32 {
+        if self.decay == DECAY as f32 {
+            FACTOR as f32
+        } else {
+            0.9f32.powf(1.0 / self.decay) - 1.0
+        }
+    }
+}
+
+impl ForgettingCurve for PowerCurve {
+    fn retrievability(&self, days_elapsed: f32, stability: f32) -> f32 {
+        (days_elapsed / stability)
+            .mul_add(self.factor(), 1.0)
+            .powf(self.decay)
+    }
+
+    fn interval(&self, stability: f32, desired_retention: f32) -> f32 {
+        stability / self.factor() * (desired_retention.powf(1.0 / self.decay) - 1.0)
+    }
+}
+
+/// The curve of FSRS v3 and earlier, `0.9 ^ (t / s)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExponentialCurve;
+
+impl ForgettingCurve for ExponentialCurve {
+    fn retrievability(&self, days_elapsed: f32, stability: f32) -> f32 {
+        0.9f32.powf(days_elapsed / stability)
+    }
+
+    fn interval(&self, stability: f32, desired_retention: f32) -> f32 {
+        stability * desired_retention.ln() / 0.9f32.ln()
+    }
+}
+
+/// Which forgetting curve to train, evaluate, schedule and simulate with. See
+/// [ModelConfig::forgetting_curve](crate::ModelConfig::forgetting_curve),
+/// [FSRS::with_forgetting_curve](crate::FSRS::with_forgetting_curve) and
+/// [SimulatorConfig::forgetting_curve](crate::SimulatorConfig::forgetting_curve).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveKind {
+    /// [PowerCurve], with the decay of the parameters if it was trained.
+    #[default]
+    Power,
+    /// [ExponentialCurve]. A trained decay is ignored.
+    Exponential,
+}
+
+constant!(CurveKind);
+
+impl CurveKind {
+    /// The curve of this kind, for parameters with `decay`.
+    pub(crate) fn curve(self, decay: f32) -> Curve {
+        match self {
+            Self::Power => Curve::Power(PowerCurve { decay }),
+            Self::Exponential => Curve::Exponential(ExponentialCurve),
+        }
+    }
+}
+
+/// A [CurveKind] with its decay, to avoid dynamic dispatch in hot loops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Curve {
+    Power(PowerCurve),
+    Exponential(ExponentialCurve),
+}
+
+impl ForgettingCurve for Curve {
+    fn retrievability(&self, days_elapsed: f32, stability: f32) -> f32 {
+        match self {
+            Self::Power(curve) => curve.retrievability(days_elapsed, stability),
+            Self::Exponential(curve) => curve.retrievability(days_elapsed, stability),
+        }
+    }
+
+    fn interval(&self, stability: f32, desired_retention: f32) -> f32 {
+        match self {
+            Self::Power(curve) => curve.interval(stability, desired_retention),
+            Self::Exponential(curve) => curve.interval(stability, desired_retention),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves() {
+        let curves = [
+            CurveKind::Power.curve(DECAY as f32),
+            CurveKind::Power.curve(-0.2),
+            CurveKind::Exponential.curve(DECAY as f32),
+        ];
+        for curve in curves {
+            assert_eq!(curve.retrievability(0.0, 10.0), 1.0);
+            assert!((curve.retrievability(10.0, 10.0) - 0.9).abs() < 1e-6);
+            assert!((curve.interval(10.0, 0.9) - 10.0).abs() < 1e-4);
+            let interval = curve.interval(10.0, 0.7);
+            assert!((curve.retrievability(interval, 10.0) - 0.7).abs() < 1e-5);
+        }
+        // the power curve has a longer tail
+        assert!(curves[0].retrievability(100.0, 10.0) > curves[2].retrievability(100.0, 10.0));
+    }
+}
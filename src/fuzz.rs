@@ -0,0 +1,104 @@
+//! Interval fuzzing, which spreads out the due dates of cards that would otherwise stay due
+//! together, within the same ranges as Anki.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Intervals are fuzzed by the sum of `factor * (part of the interval in [start, end))`
+/// over these ranges, plus one day, matching Anki.
+const FUZZ_RANGES: [(f32, f32, f32); 3] = [
+    (2.5, 7.0, 0.15),
+    (7.0, 20.0, 0.1),
+    (20.0, f32::INFINITY, 0.05),
+];
+
+/// A number in `[0, 1)` to fuzz with, which is always the same for the same `seed`.
+pub fn fuzz_factor(seed: u64) -> f32 {
+    StdRng::seed_from_u64(seed).gen()
+}
+
+/// The range of days (inclusive) an interval may be fuzzed to. If the card was reviewed
+/// late, the interval is kept above the days elapsed.
+pub fn fuzz_range(interval: f32, elapsed_days: u32, maximum_interval: u32) -> (u32, u32) {
+    let delta = FUZZ_RANGES
+        .iter()
+        .map(|&(start, end, factor)| factor * (interval.min(end) - start).max(0.0))
+        .sum::<f32>()
+        + 1.0;
+    let mut min_interval = ((interval - delta).round().max(0.0) as u32).max(2);
+    let max_interval = ((interval + delta).round() as u32).min(maximum_interval);
+    if interval > elapsed_days as f32 {
+        min_interval = min_interval.max(elapsed_days + 1);
+    }
+    (min_interval.min(max_interval), max_interval)
+}
+
+/// Pick a day in [fuzz_range] with `fuzz_factor`, a number in `[0, 1)`.
+pub fn fuzzed_interval(
+    interval: f32,
+    elapsed_days: u32,
+    maximum_interval: u32,
+    fuzz_factor: f32,
+) -> u32 {
+    let (min_interval, max_interval) = fuzz_range(interval, elapsed_days, maximum_interval);
+    let fuzzed = (fuzz_factor * (max_interval - min_interval + 1) as f32).floor() as u32;
+    (min_interval + fuzzed).min(max_interval)
+}
+
+/// Fuzz `interval` with a factor seeded from `card_id`, so that the same card always gets
+/// the same result, and round it to whole days between 1 and `maximum_interval`. Intervals
+/// under 2.5 days are only rounded. To vary the fuzz between reviews of a card, mix the
+/// review count into the seed of [fuzz_factor], and pass it to [fuzzed_interval].
+pub fn fuzz_interval(interval: f32, elapsed_days: u32, maximum_interval: u32, card_id: i64) -> u32 {
+    fuzz_with_seed(interval, elapsed_days, maximum_interval, card_id as u64)
+}
+
+pub(crate) fn fuzz_with_seed(
+    interval: f32,
+    elapsed_days: u32,
+    maximum_interval: u32,
+    seed: u64,
+) -> u32 {
+    let maximum_interval = maximum_interval.max(1);
+    if interval >= 2.5 {
+        fuzzed_interval(interval, elapsed_days, maximum_interval, fuzz_factor(seed))
+    } else {
+        (interval.round() as u32).clamp(1, maximum_interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzz() {
+        assert_eq!(fuzz_range(3.0, 0, 36500), (2, 4));
+        assert_eq!(fuzz_range(100.0, 0, 36500), (93, 107));
+        assert_eq!(fuzz_range(100.0, 0, 105), (93, 105));
+        // reviewed 95 days after the previous review
+        assert_eq!(fuzz_range(100.0, 95, 36500), (96, 107));
+        assert_eq!(fuzzed_interval(100.0, 0, 36500, 0.0), 93);
+        assert_eq!(fuzzed_interval(100.0, 0, 36500, 0.999), 107);
+    }
+
+    #[test]
+    fn fuzz_by_card() {
+        let intervals: Vec<_> = (0..100)
+            .map(|card_id| fuzz_interval(100.0, 0, 36500, card_id))
+            .collect();
+        assert!(intervals
+            .iter()
+            .all(|interval| (93..=107).contains(interval)));
+        assert!(intervals.iter().min() < intervals.iter().max());
+        assert_eq!(
+            intervals,
+            (0..100)
+                .map(|card_id| fuzz_interval(100.0, 0, 36500, card_id))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(fuzz_interval(2.4, 0, 36500, 1), 2);
+        assert_eq!(fuzz_interval(0.2, 0, 36500, 1), 1);
+        assert_eq!(fuzz_interval(100.0, 0, 5, 1), 5);
+    }
+}
@@ -1,13 +1,19 @@
 use std::collections::HashMap;
 use std::ops::{Add, Sub};
 
+use crate::builder::FSRSBuilder;
+use crate::events::FSRSEvent;
+use crate::forgetting_curve::{Curve, ForgettingCurve, PowerCurve};
 use crate::model::{Get, MemoryStateTensors, FSRS};
+use crate::progress::ProgressStage;
+use burn::data::dataset::Dataset;
 use burn::nn::loss::Reduction;
 use burn::tensor::{Data, Shape, Tensor};
 use burn::{data::dataloader::batcher::Batcher, tensor::backend::Backend};
 
 use crate::dataset::{
-    constant_weighted_fsrs_items, recency_weighted_fsrs_items, FSRSBatch, FSRSBatcher,
+    chunk_item, constant_weighted_fsrs_items, recency_weight, recency_weighted_fsrs_items,
+    validate_items, FSRSBatch, FSRSBatcher, ItemMetadata, WeightedFSRSItem,
 };
 use crate::error::Result;
 use crate::model::Model;
@@ -22,6 +28,7 @@ pub(crate) const S_MAX: f32 = 36500.0;
 /// This is a slice for efficiency, but should always be 17 in length.
 pub type Parameters = [f32];
 use itertools::izip;
+use serde::{Deserialize, Serialize};
 
 pub static DEFAULT_PARAMETERS: [f32; 19] = [
     0.40255, 1.18385, 3.173, 15.69105, 7.1949, 0.5345, 1.4604, 0.0046, 1.54575, 0.1192, 1.01925,
@@ -32,17 +39,36 @@ fn infer<B: Backend>(
     model: &Model<B>,
     batch: FSRSBatch<B>,
 ) -> (MemoryStateTensors<B>, Tensor<B, 1>) {
+    batch.assert_right_padded();
     let state = model.forward(batch.t_historys, batch.r_historys, None);
-    let retention = model.power_forgetting_curve(batch.delta_ts, state.stability.clone());
+    let retention = model.forgetting_curve(batch.delta_ts, state.stability.clone());
     (state, retention)
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
 pub struct MemoryState {
     pub stability: f32,
     pub difficulty: f32,
 }
 
+impl MemoryState {
+    /// How well the user is likely to remember the item after `days_elapsed` since the
+    /// previous review, on the forgetting curve of `fsrs`, including a trained decay.
+    /// Fractional days can be used for reviews at a specific time of day.
+    pub fn retrievability<B: Backend>(&self, fsrs: &FSRS<B>, days_elapsed: f32) -> f32 {
+        self.retrievability_on(fsrs.curve(), days_elapsed)
+    }
+
+    pub(crate) fn retrievability_on(&self, curve: Curve, days_elapsed: f32) -> f32 {
+        if curve == Curve::Power(PowerCurve::default()) {
+            (days_elapsed as f64 / self.stability as f64 * FACTOR + 1.0).powf(DECAY) as f32
+        } else {
+            curve.retrievability(days_elapsed, self.stability)
+        }
+    }
+}
+
 impl<B: Backend> From<MemoryStateTensors<B>> for MemoryState {
     fn from(m: MemoryStateTensors<B>) -> Self {
         Self {
@@ -67,10 +93,38 @@ impl<B: Backend> From<MemoryState> for MemoryStateTensors<B> {
     }
 }
 
+/// The number of days after which a memory of `stability` falls to `desired_retention`, on
+/// the default forgetting curve. Not rounded or limited to at least a day. For parameters
+/// with a trained decay or another curve, use [FSRS::next_interval].
 pub fn next_interval(stability: f32, desired_retention: f32) -> f32 {
-    stability / FACTOR as f32 * (desired_retention.powf(1.0 / DECAY as f32) - 1.0)
+    PowerCurve::default().interval(stability, desired_retention)
 }
 
+/// The inverse of [next_interval]: the retention a memory of `stability` is expected to
+/// have after `interval` days, eg to show the retention implied by an interval the user
+/// picked.
+pub fn retention_for_interval(stability: f32, interval: f32) -> f32 {
+    MemoryState {
+        stability,
+        difficulty: 0.0,
+    }
+    .retrievability_on(Curve::Power(PowerCurve::default()), interval)
+}
+
+/// The decay of the forgetting curve of `parameters`: the 20th parameter if it was trained,
+/// or the fixed [DECAY] otherwise.
+pub(crate) fn decay_of(parameters: &Parameters) -> f32 {
+    parameters.get(19).map_or(DECAY as f32, |w| -w)
+}
+
+/// Items are evaluated in chunks of this size. Per-item outputs never depend on it.
+const EVAL_BATCH_SIZE: usize = 512;
+/// Fewer items than this can't tell trained parameters apart from the defaults; training
+/// also falls back to (partly) default parameters below this size.
+const MIN_COMPARISON_ITEMS: usize = 64;
+/// The number of equal-width prediction bins the AUC is computed from.
+const AUC_BINS: usize = 10_000;
+
 #[derive(Default)]
 struct RMatrixValue {
     predicted: f32,
@@ -90,8 +144,11 @@ impl<B: Backend> FSRS<B> {
         item: FSRSItem,
         starting_state: Option<MemoryState>,
     ) -> Result<MemoryState> {
-        let (time_history, rating_history) =
-            item.reviews.iter().map(|r| (r.delta_t, r.rating)).unzip();
+        let (time_history, rating_history) = item
+            .reviews
+            .iter()
+            .map(|r| (r.elapsed_days(), r.rating))
+            .unzip();
         let size = item.reviews.len();
         let time_history = Tensor::from_data(
             Data::new(time_history, Shape { dims: [size] }).convert(),
@@ -116,6 +173,25 @@ impl<B: Backend> FSRS<B> {
         }
     }
 
+    /// Like [FSRS::memory_state], but processes the reviews `chunk_len` at a time (see
+    /// [chunk_item]), carrying the memory state over from one chunk to the next. This bounds
+    /// the sequence length of each step for cards with very long histories, without
+    /// discarding old reviews.
+    /// Parameters must have been provided when calling FSRS::new().
+    pub fn memory_state_chunked(
+        &self,
+        item: FSRSItem,
+        chunk_len: usize,
+        starting_state: Option<MemoryState>,
+    ) -> Result<MemoryState> {
+        chunk_item(&item, chunk_len)
+            .into_iter()
+            .try_fold(starting_state, |state, chunk| {
+                self.memory_state(chunk, state).map(Some)
+            })?
+            .ok_or(FSRSError::InvalidInput)
+    }
+
     /// If a card has incomplete learning history, memory state can be approximated from
     /// current sm2 values.
     /// Parameters must have been provided when calling FSRS::new().
@@ -125,9 +201,10 @@ impl<B: Backend> FSRS<B> {
         interval: f32,
         sm2_retention: f32,
     ) -> Result<MemoryState> {
-        let stability =
-            interval.max(S_MIN) * FACTOR as f32 / (sm2_retention.powf(1.0 / DECAY as f32) - 1.0);
-        let w = &self.model().w;
+        let model = self.model();
+        // intervals are proportional to stability on every curve
+        let stability = interval.max(S_MIN) / model.curve().interval(1.0, sm2_retention);
+        let w = &model.w;
         let w8: f32 = w.get(8).into_scalar().elem();
         let w9: f32 = w.get(9).into_scalar().elem();
         let w10: f32 = w.get(10).into_scalar().elem();
@@ -162,7 +239,7 @@ impl<B: Backend> FSRS<B> {
             let model = self.model();
             model.init_stability(rating).into_scalar().elem()
         });
-        next_interval(stability, desired_retention)
+        self.curve().interval(stability, desired_retention)
     }
 
     /// The intervals and memory states for each answer button.
@@ -173,12 +250,34 @@ impl<B: Backend> FSRS<B> {
         desired_retention: f32,
         days_elapsed: u32,
     ) -> Result<NextStates> {
+        self.next_states_fractional(current_memory_state, desired_retention, days_elapsed as f32)
+    }
+
+    /// Like [FSRS::next_states], with the time since the previous review in fractional days
+    /// (eg hours / 24), for reviews done at a specific time of day, as with
+    /// [FSRSReview::elapsed_seconds](crate::FSRSReview::elapsed_seconds). A review less than a day after the previous one is a
+    /// same-day review.
+    pub fn next_states_fractional(
+        &self,
+        current_memory_state: Option<MemoryState>,
+        desired_retention: f32,
+        days_elapsed: f32,
+    ) -> Result<NextStates> {
+        if !days_elapsed.is_finite() || days_elapsed < 0.0 {
+            return Err(FSRSError::InvalidInput);
+        }
+        let days_elapsed = if days_elapsed < 1.0 {
+            0.0
+        } else {
+            days_elapsed
+        };
         let delta_t = Tensor::from_data(
             Data::new(vec![days_elapsed.elem()], Shape { dims: [1] }),
             &self.device(),
         );
         let current_memory_state_tensors = current_memory_state.map(MemoryStateTensors::from);
         let model = self.model();
+        let curve = model.curve();
         let mut next_memory_states = (1..=4).map(|rating| {
             Ok({
                 let state = MemoryState::from(model.step(
@@ -198,7 +297,7 @@ impl<B: Backend> FSRS<B> {
 
         let mut get_next_state = || {
             let memory = next_memory_states.next().unwrap()?;
-            let interval = next_interval(memory.stability, desired_retention);
+            let interval = curve.interval(memory.stability, desired_retention);
             Ok(ItemState { memory, interval })
         };
 
@@ -212,34 +311,104 @@ impl<B: Backend> FSRS<B> {
 
     /// Determine how well the model and parameters predict performance.
     /// Parameters must have been provided when calling FSRS::new().
-    pub fn evaluate<F>(&self, items: Vec<FSRSItem>, mut progress: F) -> Result<ModelEvaluation>
+    pub fn evaluate<F>(&self, items: Vec<FSRSItem>, progress: F) -> Result<ModelEvaluation>
     where
         F: FnMut(ItemProgress) -> bool,
     {
         if items.is_empty() {
-            return Err(FSRSError::NotEnoughData);
+            return Err(FSRSError::NotEnoughData {
+                found: 0,
+                required: 1,
+            });
         }
+        validate_items(&items)?;
+        let total = items.len();
         let weighted_items = recency_weighted_fsrs_items(items);
+        let chunks = weighted_items
+            .chunks(EVAL_BATCH_SIZE)
+            .map(|chunk| Ok(chunk.to_vec()));
+        self.evaluate_chunks(chunks, total, None, progress)
+    }
+
+    /// Like [FSRS::evaluate], reading the items from `dataset` a batch at a time, so that
+    /// collections too large to fit in memory can be evaluated, eg with a
+    /// [StreamingFSRSDataset](crate::dataset::StreamingFSRSDataset). The items are read in order,
+    /// and should be sorted by the review timestamp.
+    pub fn evaluate_dataset<D, F>(&self, dataset: &D, progress: F) -> Result<ModelEvaluation>
+    where
+        D: Dataset<FSRSItem>,
+        F: FnMut(ItemProgress) -> bool,
+    {
+        let total = dataset.len();
+        if total == 0 {
+            return Err(FSRSError::NotEnoughData {
+                found: 0,
+                required: 1,
+            });
+        }
+        let chunks = (0..total).step_by(EVAL_BATCH_SIZE).map(|start| {
+            let end = (start + EVAL_BATCH_SIZE).min(total);
+            let items = (start..end)
+                .map(|index| dataset.get(index).ok_or(FSRSError::InvalidInput))
+                .collect::<Result<Vec<_>>>()?;
+            validate_items(&items).map_err(|err| match err {
+                FSRSError::InvalidItem { index, reason } => FSRSError::InvalidItem {
+                    index: start + index,
+                    reason,
+                },
+                err => err,
+            })?;
+            Ok(izip!(start.., items)
+                .map(|(index, item)| WeightedFSRSItem {
+                    weight: recency_weight(index, total),
+                    item,
+                })
+                .collect())
+        });
+        self.evaluate_chunks(chunks, total, None, progress)
+    }
+
+    /// Evaluate the model on `total` weighted items, yielded a batch at a time. Memory use
+    /// doesn't grow with the number of items, unless `scores` is provided, in which case the
+    /// prediction and label of each item are pushed to it in input order.
+    fn evaluate_chunks<I, F>(
+        &self,
+        chunks: I,
+        total: usize,
+        mut scores: Option<&mut Vec<(f32, bool)>>,
+        mut progress: F,
+    ) -> Result<ModelEvaluation>
+    where
+        I: Iterator<Item = Result<Vec<WeightedFSRSItem>>>,
+        F: FnMut(ItemProgress) -> bool,
+    {
         let batcher = FSRSBatcher::new(self.device());
-        let mut all_retention = vec![];
-        let mut all_labels = vec![];
-        let mut all_weights = vec![];
-        let mut progress_info = ItemProgress {
-            current: 0,
-            total: weighted_items.len(),
-        };
+        let mut loss_sum = 0.0;
+        let mut weight_sum = 0.0;
+        let mut progress_info = ItemProgress { current: 0, total };
         let model = self.model();
         let mut r_matrix: HashMap<(u32, u32, u32), RMatrixValue> = HashMap::new();
+        let mut auc = AucHistogram::new();
 
-        for chunk in weighted_items.chunks(512) {
-            let batch = batcher.batch(chunk.to_vec());
-            let (_state, retention) = infer::<B>(model, batch.clone());
+        for chunk in chunks {
+            let chunk = chunk?;
+            let batch = batcher.batch(chunk.clone());
+            let (_state, retention) = infer::<B>(&model, batch.clone());
             let pred = retention.clone().to_data().convert::<f32>().value;
             let true_val = batch.labels.clone().to_data().convert::<f32>().value;
-            all_retention.push(retention);
-            all_labels.push(batch.labels);
-            all_weights.push(batch.weights);
-            izip!(chunk, pred, true_val).for_each(|(weighted_item, p, y)| {
+            let loss = BCELoss::new().forward(
+                retention,
+                batch.labels.float(),
+                batch.weights.clone(),
+                Reduction::Sum,
+            );
+            loss_sum += loss.into_scalar().elem::<f64>();
+            weight_sum += batch.weights.sum().into_scalar().elem::<f64>();
+            izip!(&chunk, pred, true_val).for_each(|(weighted_item, p, y)| {
+                auc.add(p, y > 0.5);
+                if let Some(scores) = scores.as_deref_mut() {
+                    scores.push((p, y > 0.5));
+                }
                 let bin = weighted_item.item.r_matrix_index();
                 let value = r_matrix.entry(bin).or_default();
                 value.predicted += p;
@@ -248,9 +417,7 @@ impl<B: Backend> FSRS<B> {
                 value.weight += weighted_item.weight;
             });
             progress_info.current += chunk.len();
-            if !progress(progress_info) {
-                return Err(FSRSError::Interrupted);
-            }
+            self.report_progress(ProgressStage::Evaluation, progress_info, &mut progress)?;
         }
         let rmse = (r_matrix
             .values()
@@ -262,20 +429,25 @@ impl<B: Backend> FSRS<B> {
             .sum::<f32>()
             / r_matrix.values().map(|v| v.weight).sum::<f32>())
         .sqrt();
-        let all_retention = Tensor::cat(all_retention, 0);
-        let all_labels = Tensor::cat(all_labels, 0).float();
-        let all_weights = Tensor::cat(all_weights, 0);
-        let loss = BCELoss::new().forward(all_retention, all_labels, all_weights, Reduction::Auto);
-        Ok(ModelEvaluation {
-            log_loss: loss.to_data().value[0].elem(),
+        let evaluation = ModelEvaluation {
+            log_loss: (loss_sum / weight_sum) as f32,
             rmse_bins: rmse,
-        })
+            auc: auc.auc(),
+        };
+        self.emit(FSRSEvent::EvaluationCompleted { evaluation });
+        Ok(evaluation)
     }
 
     /// How well the user is likely to remember the item after `days_elapsed` since the previous
     /// review.
     pub fn current_retrievability(&self, state: MemoryState, days_elapsed: u32) -> f32 {
-        (days_elapsed as f64 / state.stability as f64 * FACTOR + 1.0).powf(DECAY) as f32
+        state.retrievability_on(self.curve(), days_elapsed as f32)
+    }
+
+    /// Like [FSRS::current_retrievability], with the time since the previous review in
+    /// seconds.
+    pub fn current_retrievability_seconds(&self, state: MemoryState, seconds_elapsed: u32) -> f32 {
+        state.retrievability_on(self.curve(), seconds_elapsed as f32 / 86400.0)
     }
 
     /// Returns the universal metrics for the existing and provided parameters. If the first value
@@ -290,8 +462,12 @@ impl<B: Backend> FSRS<B> {
         F: FnMut(ItemProgress) -> bool,
     {
         if items.is_empty() {
-            return Err(FSRSError::NotEnoughData);
+            return Err(FSRSError::NotEnoughData {
+                found: 0,
+                required: 1,
+            });
         }
+        validate_items(&items)?;
         let weighted_items = constant_weighted_fsrs_items(items);
         let batcher = FSRSBatcher::new(self.device());
         let mut all_predictions_self = vec![];
@@ -302,25 +478,28 @@ impl<B: Backend> FSRS<B> {
             total: weighted_items.len(),
         };
         let model_self = self.model();
-        let fsrs_other = Self::new_with_backend(Some(parameters), self.device())?;
+        let fsrs_other = FSRSBuilder::<B>::on_device(self.device())
+            .parameters(parameters)
+            .forgetting_curve(self.forgetting_curve())
+            .linear_damping(self.linear_damping())
+            .exponential_initial_difficulty(self.exponential_initial_difficulty())
+            .build()?;
         let model_other = fsrs_other.model();
-        for chunk in weighted_items.chunks(512) {
+        for chunk in weighted_items.chunks(EVAL_BATCH_SIZE) {
             let batch = batcher.batch(chunk.to_vec());
 
-            let (_state, retention) = infer::<B>(model_self, batch.clone());
+            let (_state, retention) = infer::<B>(&model_self, batch.clone());
             let pred = retention.clone().to_data().convert::<f32>().value;
             all_predictions_self.extend(pred);
 
-            let (_state, retention) = infer::<B>(model_other, batch.clone());
+            let (_state, retention) = infer::<B>(&model_other, batch.clone());
             let pred = retention.clone().to_data().convert::<f32>().value;
             all_predictions_other.extend(pred);
 
             let true_val = batch.labels.clone().to_data().convert::<f32>().value;
             all_true_val.extend(true_val);
             progress_info.current += chunk.len();
-            if !progress(progress_info) {
-                return Err(FSRSError::Interrupted);
-            }
+            self.report_progress(ProgressStage::Evaluation, progress_info, &mut progress)?;
         }
         let self_by_other =
             measure_a_by_b(&all_predictions_self, &all_predictions_other, &all_true_val);
@@ -328,15 +507,295 @@ impl<B: Backend> FSRS<B> {
             measure_a_by_b(&all_predictions_other, &all_predictions_self, &all_true_val);
         Ok((self_by_other, other_by_self))
     }
+
+    /// Decide whether the parameters of this instance should be used instead of
+    /// [DEFAULT_PARAMETERS], by evaluating both on `items`. The items should be held out from
+    /// training (eg the most recent reviews), as parameters always fit their own training
+    /// data better than the defaults do.
+    pub fn benchmark_against_default<F>(
+        &self,
+        items: Vec<FSRSItem>,
+        mut progress: F,
+    ) -> Result<DefaultComparison>
+    where
+        F: FnMut(ItemProgress) -> bool,
+    {
+        if items.is_empty() {
+            return Err(FSRSError::NotEnoughData {
+                found: 0,
+                required: 1,
+            });
+        }
+        validate_items(&items)?;
+        let count = items.len();
+        let total = count * 2;
+        let weighted_items = recency_weighted_fsrs_items(items);
+        let chunks = || {
+            weighted_items
+                .chunks(EVAL_BATCH_SIZE)
+                .map(|chunk| Ok(chunk.to_vec()))
+        };
+        let mut scores = Vec::with_capacity(count);
+        let evaluation = self.evaluate_chunks(chunks(), count, Some(&mut scores), |p| {
+            progress(ItemProgress {
+                current: p.current,
+                total,
+            })
+        })?;
+        let default_fsrs = FSRSBuilder::<B>::on_device(self.device())
+            .parameters(&DEFAULT_PARAMETERS)
+            .forgetting_curve(self.forgetting_curve())
+            .linear_damping(self.linear_damping())
+            .exponential_initial_difficulty(self.exponential_initial_difficulty())
+            .build()?;
+        let default_fsrs = match self.cancellation_token() {
+            Some(token) => default_fsrs.with_cancellation_token(token.clone()),
+            None => default_fsrs,
+        };
+        let mut default_scores = Vec::with_capacity(count);
+        let default_evaluation =
+            default_fsrs.evaluate_chunks(chunks(), count, Some(&mut default_scores), |p| {
+                progress(ItemProgress {
+                    current: count + p.current,
+                    total,
+                })
+            })?;
+        let recommendation = if count < MIN_COMPARISON_ITEMS {
+            ParametersRecommendation::NeedMoreData
+        } else if evaluation.log_loss < default_evaluation.log_loss {
+            ParametersRecommendation::UseParameters
+        } else {
+            ParametersRecommendation::KeepDefaults
+        };
+        let loss_reductions: Vec<f64> = izip!(&scores, &default_scores)
+            .map(|(&(p, recalled), &(default_p, _))| {
+                item_log_loss(default_p, recalled) - item_log_loss(p, recalled)
+            })
+            .collect();
+        Ok(DefaultComparison {
+            recommendation,
+            evaluation,
+            default_evaluation,
+            p_value: paired_p_value(&loss_reductions),
+        })
+    }
+
+    /// Compare the retention predicted for each item by the existing parameters with the
+    /// retention predicted by the provided parameters. Predictions are returned in the same
+    /// order as the input items regardless of how they are batched internally, and the `top_n`
+    /// items whose predictions differ the most are listed separately, largest divergence first
+    /// (ties keep their input order).
+    pub fn compare_predictions<F>(
+        &self,
+        items: Vec<FSRSItem>,
+        parameters: &Parameters,
+        top_n: usize,
+        mut progress: F,
+    ) -> Result<PredictionComparison>
+    where
+        F: FnMut(ItemProgress) -> bool,
+    {
+        if items.is_empty() {
+            return Err(FSRSError::NotEnoughData {
+                found: 0,
+                required: 1,
+            });
+        }
+        validate_items(&items)?;
+        let weighted_items = constant_weighted_fsrs_items(items);
+        let batcher = FSRSBatcher::new(self.device());
+        let mut predictions = Vec::with_capacity(weighted_items.len());
+        let mut progress_info = ItemProgress {
+            current: 0,
+            total: weighted_items.len(),
+        };
+        let model_self = self.model();
+        let fsrs_other = FSRSBuilder::<B>::on_device(self.device())
+            .parameters(parameters)
+            .forgetting_curve(self.forgetting_curve())
+            .linear_damping(self.linear_damping())
+            .exponential_initial_difficulty(self.exponential_initial_difficulty())
+            .build()?;
+        let model_other = fsrs_other.model();
+        for chunk in weighted_items.chunks(EVAL_BATCH_SIZE) {
+            let batch = batcher.batch(chunk.to_vec());
+
+            let (_state, retention) = infer::<B>(&model_self, batch.clone());
+            let pred_self = retention.to_data().convert::<f32>().value;
+
+            let (_state, retention) = infer::<B>(&model_other, batch);
+            let pred_other = retention.to_data().convert::<f32>().value;
+
+            predictions.extend(izip!(pred_self, pred_other).enumerate().map(
+                |(offset, (retention_self, retention_other))| PredictionDiff {
+                    index: progress_info.current + offset,
+                    metadata: chunk[offset].item.metadata,
+                    retention_self,
+                    retention_other,
+                },
+            ));
+            progress_info.current += chunk.len();
+            self.report_progress(ProgressStage::Evaluation, progress_info, &mut progress)?;
+        }
+        let mut largest_divergences = predictions.clone();
+        largest_divergences.sort_by(|a, b| b.divergence().total_cmp(&a.divergence()));
+        largest_divergences.truncate(top_n);
+        Ok(PredictionComparison {
+            predictions,
+            largest_divergences,
+        })
+    }
+
+    /// Predict each item with the parameters of this instance, for callers that want more
+    /// than the aggregate metrics of [FSRS::evaluate], eg to plot calibration, find the
+    /// worst-predicted cards or export the predictions. Predictions are returned in the same
+    /// order as the input items.
+    pub fn predictions<F>(
+        &self,
+        items: Vec<FSRSItem>,
+        mut progress: F,
+    ) -> Result<Vec<ItemPrediction>>
+    where
+        F: FnMut(ItemProgress) -> bool,
+    {
+        validate_items(&items)?;
+        let weighted_items = constant_weighted_fsrs_items(items);
+        let batcher = FSRSBatcher::new(self.device());
+        let mut predictions = Vec::with_capacity(weighted_items.len());
+        let mut progress_info = ItemProgress {
+            current: 0,
+            total: weighted_items.len(),
+        };
+        let model = self.model();
+        for chunk in weighted_items.chunks(EVAL_BATCH_SIZE) {
+            let batch: FSRSBatch<B> = batcher.batch(chunk.to_vec());
+            let labels = batch.labels.clone().to_data().convert::<f32>().value;
+            let (state, retention) = infer::<B>(&model, batch);
+            let retrievability = retention.to_data().convert::<f32>().value;
+            let stability = state.stability.to_data().convert::<f32>().value;
+            let difficulty = state.difficulty.to_data().convert::<f32>().value;
+            predictions.extend(
+                izip!(chunk, retrievability, stability, difficulty, labels).map(
+                    |(weighted_item, retrievability, stability, difficulty, label)| {
+                        ItemPrediction {
+                            metadata: weighted_item.item.metadata,
+                            retrievability,
+                            stability,
+                            difficulty,
+                            label,
+                        }
+                    },
+                ),
+            );
+            progress_info.current += chunk.len();
+            self.report_progress(ProgressStage::Evaluation, progress_info, &mut progress)?;
+        }
+        Ok(predictions)
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
+/// How well parameters predict a set of items, returned by [FSRS::evaluate].
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
 pub struct ModelEvaluation {
+    /// The recency-weighted log loss of the predicted retrievabilities.
     pub log_loss: f32,
+    /// RMSE(bins) of the Python optimizer: the items are grouped by the bins of their
+    /// `delta_t`, number of long-term reviews and number of lapses, and the difference
+    /// between the mean predicted retrievability and the actual recall of each group is
+    /// weighted by the recency weights of its items.
     pub rmse_bins: f32,
+    /// The area under the ROC curve of the predicted retrievabilities: the probability that
+    /// a recalled item is predicted a higher retrievability than a forgotten one, 0.5 being
+    /// no better than chance. Unlike the log loss, it does not depend on how often the items
+    /// are recalled. None if all items were recalled, or all were forgotten.
+    pub auc: Option<f32>,
+}
+
+/// What [FSRS::benchmark_against_default] suggests doing with the parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParametersRecommendation {
+    /// The parameters predict the items better than the defaults.
+    UseParameters,
+    /// The defaults predict the items at least as well.
+    KeepDefaults,
+    /// There are too few items to compare the two reliably.
+    NeedMoreData,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DefaultComparison {
+    pub recommendation: ParametersRecommendation,
+    /// How well the parameters of the FSRS instance predict the items.
+    pub evaluation: ModelEvaluation,
+    /// How well [DEFAULT_PARAMETERS] predict the items.
+    pub default_evaluation: ModelEvaluation,
+    /// The one-sided p-value of a paired test of the log loss of each item: the probability
+    /// of the parameters doing at least this much better than the defaults if both predicted
+    /// the items equally well. A small value, eg below 0.05, means the improvement is
+    /// unlikely to be chance; a value near 1 means the defaults are better. None if there
+    /// are too few items, or the predictions are identical.
+    pub p_value: Option<f32>,
+}
+
+impl DefaultComparison {
+    /// The reduction in log loss relative to the defaults, eg 0.05 when the parameters have
+    /// a 5% lower log loss. Negative when the defaults are better.
+    pub fn log_loss_improvement(&self) -> f32 {
+        1.0 - self.evaluation.log_loss / self.default_evaluation.log_loss
+    }
+
+    /// The reduction in RMSE relative to the defaults, like
+    /// [DefaultComparison::log_loss_improvement].
+    pub fn rmse_improvement(&self) -> f32 {
+        1.0 - self.evaluation.rmse_bins / self.default_evaluation.rmse_bins
+    }
+}
+
+/// Retention predicted for a single item by two parameter sets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PredictionDiff {
+    /// Position of the item in the input.
+    pub index: usize,
+    /// Copied from the item.
+    pub metadata: Option<ItemMetadata>,
+    /// Predicted by the parameters the FSRS instance was created with.
+    pub retention_self: f32,
+    /// Predicted by the parameters that were passed in.
+    pub retention_other: f32,
+}
+
+impl PredictionDiff {
+    /// Absolute difference between the two predictions.
+    pub fn divergence(&self) -> f32 {
+        (self.retention_other - self.retention_self).abs()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+pub struct PredictionComparison {
+    /// One entry per input item, in input order.
+    pub predictions: Vec<PredictionDiff>,
+    /// The items with the largest divergence, largest first.
+    pub largest_divergences: Vec<PredictionDiff>,
+}
+
+/// The prediction for the current review of an item, returned by [FSRS::predictions].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ItemPrediction {
+    /// Copied from the item.
+    pub metadata: Option<ItemMetadata>,
+    /// The predicted probability of recalling the item at the current review.
+    pub retrievability: f32,
+    /// The memory state after the history, before the current review.
+    pub stability: f32,
+    pub difficulty: f32,
+    /// 1.0 if the item was recalled at the current review, 0.0 if it was forgotten.
+    pub label: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
 pub struct NextStates {
     pub again: ItemState,
     pub hard: ItemState,
@@ -345,6 +804,7 @@ pub struct NextStates {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
 pub struct ItemState {
     pub memory: MemoryState,
     pub interval: f32,
@@ -356,6 +816,151 @@ pub struct ItemProgress {
     pub total: usize,
 }
 
+/// The log loss of a single prediction.
+fn item_log_loss(prediction: f32, recalled: bool) -> f64 {
+    let p = prediction as f64;
+    -if recalled { p.ln() } else { (1.0 - p).ln() }
+}
+
+/// The one-sided p-value of the mean of `differences` being greater than 0, with the
+/// normal approximation of the paired t-test, which is accurate for the number of items
+/// needed to compare parameters.
+fn paired_p_value(differences: &[f64]) -> Option<f32> {
+    let n = differences.len() as f64;
+    if n < 2.0 {
+        return None;
+    }
+    let mean = differences.iter().sum::<f64>() / n;
+    let variance = differences.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    if variance <= 0.0 || !variance.is_finite() {
+        return None;
+    }
+    let t = mean / (variance / n).sqrt();
+    Some((1.0 - normal_cdf(t)) as f32)
+}
+
+/// The cumulative distribution function of the standard normal distribution, using the
+/// approximation of erf by Abramowitz and Stegun (7.1.26), which has an error below 1.5e-7.
+fn normal_cdf(x: f64) -> f64 {
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / 0.327_591_1f64.mul_add(z, 1.0);
+    let poly = [
+        1.061_405_429,
+        -1.453_152_027,
+        1.421_413_741,
+        -0.284_496_736,
+        0.254_829_592,
+    ]
+    .iter()
+    .fold(0.0, |acc, c| acc * t + c)
+        * t;
+    let erf = 1.0 - poly * (-z * z).exp();
+    if x >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+/// Counts of recalled and forgotten items by prediction, from which the area under the ROC
+/// curve is computed without keeping every prediction. Predictions in the same bin are
+/// treated as tied, which changes the result by at most 1 / [AUC_BINS].
+struct AucHistogram {
+    positives: Vec<u64>,
+    negatives: Vec<u64>,
+}
+
+impl AucHistogram {
+    fn new() -> Self {
+        Self {
+            positives: vec![0; AUC_BINS],
+            negatives: vec![0; AUC_BINS],
+        }
+    }
+
+    fn add(&mut self, prediction: f32, recalled: bool) {
+        let bin = ((prediction.clamp(0.0, 1.0) * AUC_BINS as f32) as usize).min(AUC_BINS - 1);
+        if recalled {
+            self.positives[bin] += 1;
+        } else {
+            self.negatives[bin] += 1;
+        }
+    }
+
+    /// The Mann-Whitney U statistic of the binned predictions, with tied predictions given
+    /// their average rank. None unless there are both recalled and forgotten items.
+    fn auc(&self) -> Option<f32> {
+        let positives = self.positives.iter().sum::<u64>() as f64;
+        let negatives = self.negatives.iter().sum::<u64>() as f64;
+        if positives == 0.0 || negatives == 0.0 {
+            return None;
+        }
+        let mut positive_rank_sum = 0.0;
+        let mut ranked = 0.0;
+        for (&tied_positives, &tied_negatives) in izip!(&self.positives, &self.negatives) {
+            let tied = (tied_positives + tied_negatives) as f64;
+            // ranks start at 1
+            let average_rank = ranked + (tied + 1.0) / 2.0;
+            positive_rank_sum += average_rank * tied_positives as f64;
+            ranked += tied;
+        }
+        let u = positive_rank_sum - positives * (positives + 1.0) / 2.0;
+        Some((u / (positives * negatives)) as f32)
+    }
+}
+
+/// The scheduling and evaluation commands of [FSRS], so that code using them can be generic
+/// over the model, and tested with
+/// [MockFSRS](crate::test_support::MockFSRS) (with the `test-support` feature) instead of
+/// trained parameters. Each method is the inherent one of the same name.
+pub trait MemoryModel {
+    /// See [FSRS::memory_state].
+    fn memory_state(
+        &self,
+        item: FSRSItem,
+        starting_state: Option<MemoryState>,
+    ) -> Result<MemoryState>;
+
+    /// See [FSRS::next_states].
+    fn next_states(
+        &self,
+        current_memory_state: Option<MemoryState>,
+        desired_retention: f32,
+        days_elapsed: u32,
+    ) -> Result<NextStates>;
+
+    /// See [FSRS::evaluate].
+    fn evaluate<F>(&self, items: Vec<FSRSItem>, progress: F) -> Result<ModelEvaluation>
+    where
+        F: FnMut(ItemProgress) -> bool;
+}
+
+impl<B: Backend> MemoryModel for FSRS<B> {
+    fn memory_state(
+        &self,
+        item: FSRSItem,
+        starting_state: Option<MemoryState>,
+    ) -> Result<MemoryState> {
+        FSRS::memory_state(self, item, starting_state)
+    }
+
+    fn next_states(
+        &self,
+        current_memory_state: Option<MemoryState>,
+        desired_retention: f32,
+        days_elapsed: u32,
+    ) -> Result<NextStates> {
+        FSRS::next_states(self, current_memory_state, desired_retention, days_elapsed)
+    }
+
+    fn evaluate<F>(&self, items: Vec<FSRSItem>, progress: F) -> Result<ModelEvaluation>
+    where
+        F: FnMut(ItemProgress) -> bool,
+    {
+        FSRS::evaluate(self, items, progress)
+    }
+}
+
 fn get_bin(x: f32, bins: i32) -> i32 {
     let log_base = (bins.add(1) as f32).ln();
     let binned_x = (x * log_base).exp().floor().sub(1.0);
@@ -386,9 +991,13 @@ fn measure_a_by_b(pred_a: &[f32], pred_b: &[f32], true_val: &[f32]) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_helpers::item_from;
     use crate::{
-        convertor_tests::anki21_sample_file_converted_to_fsrs, dataset::filter_outlier,
-        test_helpers::assert_approx_eq, FSRSReview,
+        convertor_tests::anki21_sample_file_converted_to_fsrs,
+        dataset::{filter_outlier, OutlierFilterConfig},
+        forgetting_curve::CurveKind,
+        test_helpers::assert_approx_eq,
+        FSRSReview,
     };
 
     static PARAMETERS: &[f32] = &[
@@ -428,31 +1037,48 @@ mod tests {
     }
 
     #[test]
-    fn test_memo_state() -> Result<()> {
+    fn test_memory_state_chunked() -> Result<()> {
+        let fsrs = FSRS::new(Some(PARAMETERS))?;
+        let reviews = (0..200)
+            .map(|i| FSRSReview {
+                rating: [3, 3, 1, 4, 2][i % 5],
+                delta_t: if i == 0 { 0 } else { (i % 7) as u32 + 1 },
+                elapsed_seconds: None,
+            })
+            .collect();
         let item = FSRSItem {
-            reviews: vec![
-                FSRSReview {
-                    rating: 1,
-                    delta_t: 0,
-                },
-                FSRSReview {
-                    rating: 3,
-                    delta_t: 1,
-                },
-                FSRSReview {
-                    rating: 3,
-                    delta_t: 3,
-                },
-                FSRSReview {
-                    rating: 3,
-                    delta_t: 8,
-                },
-                FSRSReview {
-                    rating: 3,
-                    delta_t: 21,
-                },
-            ],
+            reviews,
+            ..Default::default()
         };
+        let chunks = crate::dataset::chunk_item(&item, 64);
+        assert_eq!(
+            chunks.iter().map(|c| c.reviews.len()).collect::<Vec<_>>(),
+            [64, 64, 64, 8]
+        );
+        let expected = fsrs.memory_state(item.clone(), None)?;
+        for chunk_len in [1, 7, 64, 200, 500] {
+            let state = fsrs.memory_state_chunked(item.clone(), chunk_len, None)?;
+            assert_approx_eq(
+                [state.stability, state.difficulty],
+                [expected.stability, expected.difficulty],
+            );
+        }
+        let starting_state = Some(MemoryState {
+            stability: 20.0,
+            difficulty: 7.0,
+        });
+        let expected = fsrs.memory_state(item.clone(), starting_state)?;
+        let state = fsrs.memory_state_chunked(item, 30, starting_state)?;
+        assert_approx_eq(
+            [state.stability, state.difficulty],
+            [expected.stability, expected.difficulty],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_memo_state() -> Result<()> {
+        let item = item_from(&[(1, 0), (3, 1), (3, 3), (3, 8), (3, 21)]);
         let fsrs = FSRS::new(Some(PARAMETERS))?;
         assert_eq!(
             fsrs.memory_state(item, None).unwrap(),
@@ -492,13 +1118,24 @@ mod tests {
         assert_eq!(intervals, [422, 102, 43, 22, 13, 8, 4, 2, 1, 1]);
     }
 
+    #[test]
+    fn test_retention_for_interval() {
+        assert_eq!(retention_for_interval(10.0, 0.0), 1.0);
+        assert_eq!(retention_for_interval(10.0, 10.0), 0.9);
+        for retention in [0.5, 0.7, 0.85, 0.95, 0.99] {
+            let interval = next_interval(7.0, retention);
+            assert!((retention_for_interval(7.0, interval) - retention).abs() < 1e-6);
+        }
+    }
+
     #[test]
     fn test_evaluate() -> Result<()> {
         let items = anki21_sample_file_converted_to_fsrs();
         let (mut pretrainset, mut trainset): (Vec<FSRSItem>, Vec<FSRSItem>) = items
             .into_iter()
             .partition(|item| item.long_term_review_cnt() == 1);
-        (pretrainset, trainset) = filter_outlier(pretrainset, trainset);
+        (pretrainset, trainset) =
+            filter_outlier(pretrainset, trainset, &OutlierFilterConfig::new());
         let items = [pretrainset, trainset].concat();
 
         let fsrs = FSRS::new(Some(&[
@@ -530,27 +1167,236 @@ mod tests {
     }
 
     #[test]
-    fn next_states() -> Result<()> {
-        let item = FSRSItem {
-            reviews: vec![
-                FSRSReview {
-                    rating: 1,
-                    delta_t: 0,
-                },
-                FSRSReview {
-                    rating: 3,
-                    delta_t: 1,
-                },
-                FSRSReview {
-                    rating: 3,
-                    delta_t: 3,
-                },
-                FSRSReview {
-                    rating: 3,
-                    delta_t: 8,
-                },
-            ],
+    fn evaluate_dataset() -> Result<()> {
+        use crate::dataset::StreamingFSRSDataset;
+        use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
+
+        let items = generate_sample_items(&SampleDatasetConfig::default())?;
+        assert!(items.len() > EVAL_BATCH_SIZE);
+        let fsrs = FSRS::new(Some(PARAMETERS))?;
+        let expected = fsrs.evaluate(items.clone(), |_| true)?;
+        let dataset = StreamingFSRSDataset::new(|| items.clone().into_iter());
+        let mut last_progress = ItemProgress {
+            current: 0,
+            total: 0,
         };
+        let metrics = fsrs.evaluate_dataset(&dataset, |p| {
+            last_progress = p;
+            true
+        })?;
+        assert_approx_eq(
+            [metrics.log_loss, metrics.rmse_bins],
+            [expected.log_loss, expected.rmse_bins],
+        );
+        assert_eq!(last_progress.current, items.len());
+        assert_eq!(last_progress.total, items.len());
+        let auc = metrics.auc.unwrap();
+        assert!(auc > 0.5 && auc <= 1.0);
+        assert_approx_eq([auc], [expected.auc.unwrap()]);
+
+        let mut invalid = items.clone();
+        invalid[EVAL_BATCH_SIZE + 1].reviews[0].rating = 5;
+        let dataset = StreamingFSRSDataset::new(|| invalid.clone().into_iter());
+        assert!(matches!(
+            fsrs.evaluate_dataset(&dataset, |_| true),
+            Err(FSRSError::InvalidItem { index, .. }) if index == EVAL_BATCH_SIZE + 1
+        ));
+        let dataset = StreamingFSRSDataset::new(std::iter::empty);
+        assert!(matches!(
+            fsrs.evaluate_dataset(&dataset, |_| true),
+            Err(FSRSError::NotEnoughData {
+                found: 0,
+                required: 1
+            })
+        ));
+        Ok(())
+    }
+
+    fn roc_auc(scores: Vec<(f32, bool)>) -> Option<f32> {
+        let mut histogram = AucHistogram::new();
+        for (prediction, recalled) in scores {
+            histogram.add(prediction, recalled);
+        }
+        histogram.auc()
+    }
+
+    #[test]
+    fn auc() {
+        assert_eq!(
+            roc_auc(vec![(0.1, false), (0.4, false), (0.35, true), (0.8, true)]),
+            Some(0.75)
+        );
+        assert_eq!(roc_auc(vec![(0.5, true), (0.5, false)]), Some(0.5));
+        assert_eq!(
+            roc_auc(vec![(0.9, false), (0.5, true), (0.5, false), (0.2, true)]),
+            Some(0.125)
+        );
+        assert_eq!(roc_auc(vec![(0.5, true), (0.7, true)]), None);
+        assert_eq!(roc_auc(vec![]), None);
+        // predictions closer than the bin width are tied
+        assert_eq!(roc_auc(vec![(0.50001, true), (0.5, false)]), Some(0.5));
+
+        // evaluations saved before the AUC was added
+        let evaluation: ModelEvaluation =
+            serde_json::from_str(r#"{"log_loss":0.3,"rmse_bins":0.05}"#).unwrap();
+        assert_eq!(evaluation.auc, None);
+    }
+
+    #[test]
+    fn paired_test() {
+        assert_approx_eq(
+            [normal_cdf(0.0), normal_cdf(1.96), normal_cdf(-1.96)].map(|p| p as f32),
+            [0.5, 0.975, 0.025],
+        );
+        let p_value = |differences: &[f64]| paired_p_value(differences).unwrap();
+        // t = 2 / (sqrt(2.5) / sqrt(5)) = 2.83
+        assert_approx_eq([p_value(&[0.0, 1.0, 2.0, 3.0, 4.0])], [0.002339]);
+        assert_approx_eq([p_value(&[0.0, -1.0, -2.0, -3.0, -4.0])], [0.997661]);
+        assert_eq!(paired_p_value(&[1.0]), None);
+        assert_eq!(paired_p_value(&[0.5; 10]), None);
+    }
+
+    #[test]
+    fn compare_predictions() -> Result<()> {
+        let items = vec![
+            item_from(&[(3, 0), (3, 3)]),
+            item_from(&[(1, 0), (3, 1), (3, 10)]),
+        ];
+        let fsrs = FSRS::new(Some(PARAMETERS))?;
+        let comparison =
+            fsrs.compare_predictions(items.clone(), &DEFAULT_PARAMETERS, 1, |_| true)?;
+        assert_eq!(comparison.predictions.len(), 2);
+        assert_eq!(comparison.largest_divergences.len(), 1);
+
+        let default = FSRS::new(Some(&DEFAULT_PARAMETERS))?;
+        for (prediction, item) in comparison.predictions.iter().zip(items) {
+            let current = item.current().delta_t;
+            let history = FSRSItem {
+                reviews: item.history().copied().collect(),
+                ..Default::default()
+            };
+            let expected_self =
+                fsrs.current_retrievability(fsrs.memory_state(history.clone(), None)?, current);
+            let expected_other =
+                default.current_retrievability(default.memory_state(history, None)?, current);
+            assert_approx_eq(
+                [prediction.retention_self, prediction.retention_other],
+                [expected_self, expected_other],
+            );
+        }
+        let largest = comparison
+            .predictions
+            .iter()
+            .max_by(|a, b| a.divergence().total_cmp(&b.divergence()))
+            .unwrap();
+        assert_eq!(&comparison.largest_divergences[0], largest);
+        Ok(())
+    }
+
+    #[test]
+    fn other_parameters_use_the_same_curve() -> Result<()> {
+        use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
+
+        let mut items = generate_sample_items(&SampleDatasetConfig::default())?;
+        items.truncate(100);
+        let fsrs = FSRS::new(Some(PARAMETERS))?.with_forgetting_curve(CurveKind::Exponential);
+        // the same parameters on the same curve predict the same retention
+        let comparison = fsrs.compare_predictions(items.clone(), PARAMETERS, 1, |_| true)?;
+        assert!(comparison.largest_divergences[0].divergence() < 1e-6);
+        let (self_by_other, other_by_self) =
+            fsrs.universal_metrics(items.clone(), PARAMETERS, |_| true)?;
+        assert_approx_eq([self_by_other], [other_by_self]);
+        let default =
+            FSRS::new(Some(&DEFAULT_PARAMETERS))?.with_forgetting_curve(CurveKind::Exponential);
+        let comparison = default.benchmark_against_default(items, |_| true)?;
+        let (evaluation, default) = (comparison.evaluation, comparison.default_evaluation);
+        assert_approx_eq(
+            [evaluation.log_loss, evaluation.rmse_bins],
+            [default.log_loss, default.rmse_bins],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn predictions() -> Result<()> {
+        let items = vec![
+            item_from(&[(3, 0), (3, 3)]),
+            item_from(&[(1, 0), (3, 1), (1, 10)]),
+            item_from(&[(4, 0), (3, 8), (3, 20), (2, 40)]),
+        ];
+        let fsrs = FSRS::new(Some(PARAMETERS))?;
+        let predictions = fsrs.predictions(items.clone(), |_| true)?;
+        assert_eq!(predictions.len(), items.len());
+        for (prediction, item) in predictions.iter().zip(items) {
+            let history = FSRSItem {
+                reviews: item.history().copied().collect(),
+                ..Default::default()
+            };
+            let state = fsrs.memory_state(history, None)?;
+            assert_approx_eq(
+                [prediction.stability, prediction.difficulty],
+                [state.stability, state.difficulty],
+            );
+            assert_approx_eq(
+                [prediction.retrievability],
+                [fsrs.current_retrievability(state, item.current().delta_t)],
+            );
+            let recalled = item.current().rating > 1;
+            assert_eq!(prediction.label, if recalled { 1.0 } else { 0.0 });
+        }
+        assert!(fsrs.predictions(vec![], |_| true)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn compare_predictions_keeps_input_order() -> Result<()> {
+        // Cross several chunk boundaries with items of varying length, so that the
+        // predictions would be shuffled if batching reordered them.
+        let items = (0..EVAL_BATCH_SIZE * 2 + 7)
+            .map(|i| FSRSItem {
+                reviews: (0..(i % 5 + 2))
+                    .map(|j| FSRSReview {
+                        rating: (i + j) as u32 % 4 + 1,
+                        delta_t: if j == 0 { 0 } else { (i * j % 30 + 1) as u32 },
+                        elapsed_seconds: None,
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let fsrs = FSRS::new(Some(PARAMETERS))?;
+        let forward = fsrs.compare_predictions(items.clone(), &DEFAULT_PARAMETERS, 0, |_| true)?;
+        let reversed = fsrs.compare_predictions(
+            items.iter().rev().cloned().collect(),
+            &DEFAULT_PARAMETERS,
+            0,
+            |_| true,
+        )?;
+        assert_eq!(forward.predictions.len(), items.len());
+        for (i, prediction) in forward.predictions.iter().enumerate() {
+            assert_eq!(prediction.index, i);
+            let mirrored = &reversed.predictions[items.len() - 1 - i];
+            assert_eq!(prediction.retention_self, mirrored.retention_self);
+            assert_eq!(prediction.retention_other, mirrored.retention_other);
+        }
+        for i in [0, EVAL_BATCH_SIZE - 1, EVAL_BATCH_SIZE, items.len() - 1] {
+            let item = &items[i];
+            let history = FSRSItem {
+                reviews: item.history().copied().collect(),
+                ..Default::default()
+            };
+            let state = fsrs.memory_state(history, None)?;
+            assert_approx_eq(
+                [forward.predictions[i].retention_self],
+                [fsrs.current_retrievability(state, item.current().delta_t)],
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn next_states() -> Result<()> {
+        let item = item_from(&[(1, 0), (3, 1), (3, 3), (3, 8)]);
         let fsrs = FSRS::new(Some(PARAMETERS))?;
         let state = fsrs.memory_state(item, None).unwrap();
         assert_eq!(
@@ -601,6 +1447,172 @@ mod tests {
         assert_eq!(fsrs.current_retrievability(state, 1), 0.9);
         assert_eq!(fsrs.current_retrievability(state, 2), 0.82502866);
         assert_eq!(fsrs.current_retrievability(state, 3), 0.76613088);
+        assert_eq!(state.retrievability(&fsrs, 1.0), 0.9);
+        assert!((0.9..1.0).contains(&state.retrievability(&fsrs, 0.5)));
+        let stable = MemoryState {
+            stability: 100.0,
+            ..state
+        };
+        assert_eq!(stable.retrievability(&fsrs, 100.0), 0.9);
+        let exponential = fsrs.with_forgetting_curve(CurveKind::Exponential);
+        assert_eq!(
+            stable.retrievability(&exponential, 200.0),
+            exponential.current_retrievability(stable, 200)
+        );
+        assert!(stable.retrievability(&exponential, 200.0) < 0.82);
+    }
+
+    #[test]
+    fn fractional_days() -> Result<()> {
+        let fsrs = FSRS::new(Some(&[]))?;
+        let state = MemoryState {
+            stability: 10.0,
+            difficulty: 5.0,
+        };
+        assert_eq!(
+            fsrs.next_states_fractional(Some(state), 0.9, 3.0)?,
+            fsrs.next_states(Some(state), 0.9, 3)?
+        );
+        assert_eq!(
+            fsrs.next_states_fractional(Some(state), 0.9, 0.75)?,
+            fsrs.next_states(Some(state), 0.9, 0)?
+        );
+        // reviewing later, when less is remembered, strengthens the memory more
+        let early = fsrs.next_states(Some(state), 0.9, 3)?.good.memory.stability;
+        let between = fsrs.next_states_fractional(Some(state), 0.9, 3.5)?;
+        let late = fsrs.next_states(Some(state), 0.9, 4)?.good.memory.stability;
+        assert!(early < between.good.memory.stability && between.good.memory.stability < late);
+        assert_eq!(
+            fsrs.next_states_fractional(Some(state), 0.9, -1.0),
+            Err(FSRSError::InvalidInput)
+        );
+
+        assert_eq!(
+            fsrs.current_retrievability_seconds(state, 86400 * 10),
+            fsrs.current_retrievability(state, 10)
+        );
+        let half_day = fsrs.current_retrievability_seconds(state, 43200);
+        assert!(half_day < 1.0 && half_day > fsrs.current_retrievability(state, 1));
+
+        // the exact time between reviews of an item is used in the same way
+        let item = FSRSItem {
+            reviews: vec![
+                FSRSReview {
+                    rating: 3,
+                    delta_t: 0,
+                    elapsed_seconds: None,
+                },
+                FSRSReview {
+                    rating: 3,
+                    delta_t: 4,
+                    elapsed_seconds: Some(3 * 86400 + 43200),
+                },
+            ],
+            ..Default::default()
+        };
+        let first = fsrs.next_states(None, 0.9, 0)?.good.memory;
+        let expected = fsrs
+            .next_states_fractional(Some(first), 0.9, 3.5)?
+            .good
+            .memory;
+        let state = fsrs.memory_state(item, None)?;
+        assert_approx_eq(
+            [state.stability, state.difficulty],
+            [expected.stability, expected.difficulty],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn exponential_curve() -> Result<()> {
+        let state = MemoryState {
+            stability: 10.0,
+            difficulty: 5.0,
+        };
+        let power = FSRS::new(Some(&[]))?;
+        let fsrs = FSRS::builder()
+            .parameters(&[])
+            .forgetting_curve(CurveKind::Exponential)
+            .build()?;
+        assert!((fsrs.current_retrievability(state, 20) - 0.81).abs() < 1e-6);
+        assert!((fsrs.next_interval(Some(10.0), 0.81, 1) - 20.0).abs() < 1e-4);
+        assert!(fsrs.next_interval(Some(10.0), 0.7, 1) < power.next_interval(Some(10.0), 0.7, 1));
+
+        let items = crate::test_support::fixture_items();
+        let log_loss = fsrs.evaluate(items.clone(), |_| true)?.log_loss;
+        assert_ne!(log_loss, power.evaluate(items.clone(), |_| true)?.log_loss);
+        // new parameters keep the curve
+        fsrs.set_parameters(&DEFAULT_PARAMETERS)?;
+        assert_eq!(fsrs.evaluate(items, |_| true)?.log_loss, log_loss);
+        Ok(())
+    }
+
+    #[test]
+    fn trained_decay() -> Result<()> {
+        let state = MemoryState {
+            stability: 10.0,
+            difficulty: 5.0,
+        };
+        let default = FSRS::new(Some(&[]))?;
+        let parameters = [&DEFAULT_PARAMETERS[..], &[0.2]].concat();
+        let fsrs = FSRS::new(Some(&parameters))?;
+        // both curves are at 90% after `stability` days
+        assert!((fsrs.current_retrievability(state, 10) - 0.9).abs() < 1e-6);
+        assert!((fsrs.next_interval(Some(10.0), 0.9, 1) - 10.0).abs() < 1e-4);
+        // but a smaller decay forgets less afterwards, so intervals at lower retention are longer
+        assert!(fsrs.current_retrievability(state, 30) > default.current_retrievability(state, 30));
+        assert!(fsrs.next_interval(Some(10.0), 0.8, 1) > default.next_interval(Some(10.0), 0.8, 1));
+        let next = fsrs.next_states(Some(state), 0.8, 10)?;
+        assert!(next.good.interval > default.next_states(Some(state), 0.8, 10)?.good.interval);
+        Ok(())
+    }
+
+    #[test]
+    fn benchmark_against_default() -> Result<()> {
+        use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
+
+        // items generated from other parameters are predicted better by those parameters
+        let parameters = [
+            1.0, 2.0, 6.0, 15.0, 6.0, 0.5, 1.5, 0.01, 1.8, 0.1, 1.2, 1.5, 0.05, 0.4, 1.5, 0.3, 2.5,
+            0.5, 0.5,
+        ];
+        let items = generate_sample_items(&SampleDatasetConfig {
+            parameters: parameters.to_vec(),
+            ..Default::default()
+        })?;
+        let fsrs = FSRS::new(Some(&parameters))?;
+        let mut last_progress = ItemProgress {
+            current: 0,
+            total: 0,
+        };
+        let comparison = fsrs.benchmark_against_default(items.clone(), |p| {
+            last_progress = p;
+            true
+        })?;
+        assert_eq!(
+            comparison.recommendation,
+            ParametersRecommendation::UseParameters
+        );
+        assert!(comparison.log_loss_improvement() > 0.0);
+        assert!(comparison.p_value.unwrap() < 0.05);
+        assert_eq!(last_progress.current, last_progress.total);
+        assert_eq!(last_progress.total, items.len() * 2);
+
+        let default = FSRS::new(Some(&[]))?;
+        let comparison = default.benchmark_against_default(items.clone(), |_| true)?;
+        assert_eq!(
+            comparison.recommendation,
+            ParametersRecommendation::KeepDefaults
+        );
+        assert_eq!(comparison.log_loss_improvement(), 0.0);
+        assert_eq!(comparison.p_value, None);
+
+        let comparison = fsrs.benchmark_against_default(items[..10].to_vec(), |_| true)?;
+        assert_eq!(
+            comparison.recommendation,
+            ParametersRecommendation::NeedMoreData
+        );
+        Ok(())
     }
 
     #[test]
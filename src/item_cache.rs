@@ -0,0 +1,204 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+
+use crate::dataset::{FSRSItem, FSRSReview, ItemMetadata};
+
+const MAGIC: &[u8; 8] = b"FSRSITEM";
+/// Bumped whenever the layout below changes. Files with a different version are rejected.
+const VERSION: u32 = 2;
+
+const HAS_CARD_ID: u8 = 1;
+const HAS_DECK_ID: u8 = 2;
+
+// Layout, after the magic and the version (u32, little endian):
+// - item count
+// - for each item: review count, then (rating: u8, delta_t, elapsed_seconds + 1 or 0 if
+//   unknown) for each review, then a flags byte followed by the card id and deck id if
+//   present
+// Counts, delta_t and elapsed_seconds are LEB128 varints, ids are i64 little endian.
+
+pub(crate) fn write_varint(writer: &mut impl Write, mut value: u64) -> Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+pub(crate) fn read_varint(reader: &mut impl Read) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(reader)?;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(Error::new(ErrorKind::InvalidData, "varint is too long"))
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8> {
+    let mut buf = [0; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+pub(crate) fn read_i64(reader: &mut impl Read) -> Result<i64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(i64::from_le_bytes(buf))
+}
+
+/// Write items in a compact binary format, so that they can be loaded again with
+/// [read_items] without converting and filtering the review logs again.
+pub fn write_items(items: &[FSRSItem], mut writer: impl Write) -> Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    write_varint(&mut writer, items.len() as u64)?;
+    for item in items {
+        write_varint(&mut writer, item.reviews.len() as u64)?;
+        for review in &item.reviews {
+            let rating = u8::try_from(review.rating)
+                .map_err(|_| Error::new(ErrorKind::InvalidInput, "rating is out of range"))?;
+            writer.write_all(&[rating])?;
+            write_varint(&mut writer, review.delta_t as u64)?;
+            write_varint(
+                &mut writer,
+                review
+                    .elapsed_seconds
+                    .map_or(0, |seconds| seconds as u64 + 1),
+            )?;
+        }
+        let metadata = item.metadata.unwrap_or_default();
+        let mut flags = 0;
+        if metadata.card_id.is_some() {
+            flags |= HAS_CARD_ID;
+        }
+        if metadata.deck_id.is_some() {
+            flags |= HAS_DECK_ID;
+        }
+        writer.write_all(&[flags])?;
+        for id in [metadata.card_id, metadata.deck_id].into_iter().flatten() {
+            writer.write_all(&id.to_le_bytes())?;
+        }
+    }
+    writer.flush()
+}
+
+/// Read items written by [write_items]. Returns an [ErrorKind::InvalidData] error if the
+/// data is not in the expected format, or was written by an incompatible version.
+pub fn read_items(mut reader: impl Read) -> Result<Vec<FSRSItem>> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "not an item cache"));
+    }
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("unsupported item cache version {version}"),
+        ));
+    }
+    let count = read_varint(&mut reader)?;
+    let mut items = Vec::with_capacity(count.min(1 << 20) as usize);
+    for _ in 0..count {
+        let review_count = read_varint(&mut reader)?;
+        let reviews = (0..review_count)
+            .map(|_| {
+                let rating = read_u8(&mut reader)? as u32;
+                let delta_t = u32::try_from(read_varint(&mut reader)?)
+                    .map_err(|_| Error::new(ErrorKind::InvalidData, "delta_t is out of range"))?;
+                let elapsed_seconds = match read_varint(&mut reader)? {
+                    0 => None,
+                    seconds => Some(u32::try_from(seconds - 1).map_err(|_| {
+                        Error::new(ErrorKind::InvalidData, "elapsed_seconds is out of range")
+                    })?),
+                };
+                Ok(FSRSReview {
+                    rating,
+                    delta_t,
+                    elapsed_seconds,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let flags = read_u8(&mut reader)?;
+        let metadata = if flags == 0 {
+            None
+        } else {
+            let card_id = (flags & HAS_CARD_ID != 0)
+                .then(|| read_i64(&mut reader))
+                .transpose()?;
+            let deck_id = (flags & HAS_DECK_ID != 0)
+                .then(|| read_i64(&mut reader))
+                .transpose()?;
+            Some(ItemMetadata { card_id, deck_id })
+        };
+        items.push(FSRSItem { reviews, metadata });
+    }
+    Ok(items)
+}
+
+/// Save items to a file with [write_items].
+pub fn save_items(items: &[FSRSItem], path: impl AsRef<Path>) -> Result<()> {
+    write_items(items, BufWriter::new(File::create(path)?))
+}
+
+/// Load items from a file written by [save_items].
+pub fn load_items(path: impl AsRef<Path>) -> Result<Vec<FSRSItem>> {
+    read_items(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let mut items = generate_sample_items(&SampleDatasetConfig::default()).unwrap();
+        items[0].metadata = None;
+        items[1].metadata = Some(ItemMetadata {
+            card_id: Some(-5),
+            deck_id: Some(i64::MAX),
+        });
+        items[2].reviews[1].delta_t = u32::MAX;
+        items[3].reviews[1].elapsed_seconds = Some(0);
+        items[4].reviews[1].elapsed_seconds = Some(u32::MAX);
+        let mut buf = vec![];
+        write_items(&items, &mut buf)?;
+        assert!(buf.len() < serde_json::to_vec(&items).unwrap().len() / 5);
+        assert_eq!(read_items(buf.as_slice())?, items);
+
+        let path = std::env::temp_dir().join("fsrs_item_cache_round_trip.bin");
+        save_items(&items, &path)?;
+        assert_eq!(load_items(&path)?, items);
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_data() {
+        let mut buf = vec![];
+        write_items(&[], &mut buf).unwrap();
+        assert_eq!(read_items(buf.as_slice()).unwrap(), []);
+
+        buf[8] = 1;
+        let error = read_items(buf.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "unsupported item cache version 1");
+
+        buf[0] = b'X';
+        let error = read_items(buf.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let error = read_items(&MAGIC[..4]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
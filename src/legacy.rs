@@ -0,0 +1,205 @@
+use std::fmt;
+
+use crate::error::{FSRSError, Result};
+use crate::model::{check_and_fill_parameters, InvalidParametersReason};
+use crate::parameter_clipper::clip_parameters;
+
+/// A part of an older model that has no exact equivalent in the current one, so the
+/// migrated parameters only approximate the old behaviour there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyApproximation {
+    /// FSRS v3 used an exponential forgetting curve, and FSRS v4 a power curve with a
+    /// different exponent. Stability keeps its meaning (the interval at which retention
+    /// drops to 90%), but retention is predicted differently at other intervals.
+    ForgettingCurve,
+    /// FSRS v3 computed the stability after a lapse from `S^w`, the current model from
+    /// `(S + 1)^w - 1`. The two agree for long intervals, but differ for short ones.
+    PostLapseStability,
+    /// FSRS v3 treated a hard rating like good. The migrated hard penalty is neutral.
+    HardPenalty,
+    /// FSRS v3 treated an easy rating like good. The migrated easy bonus is neutral.
+    EasyBonus,
+    /// FSRS v3 and v4 did not model same-day reviews. The migrated short-term parameters
+    /// are neutral.
+    ShortTermStability,
+}
+
+impl LegacyApproximation {
+    /// The indices of the migrated parameters that are affected. Empty when the
+    /// approximation is in the model itself rather than in particular parameters.
+    pub fn affected_parameters(&self) -> &'static [usize] {
+        match self {
+            Self::ForgettingCurve => &[],
+            Self::PostLapseStability => &[11, 12, 13, 14],
+            Self::HardPenalty => &[15],
+            Self::EasyBonus => &[16],
+            Self::ShortTermStability => &[17, 18],
+        }
+    }
+}
+
+impl fmt::Display for LegacyApproximation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Self::ForgettingCurve => "the forgetting curve has a different shape",
+            Self::PostLapseStability => "the stability after a lapse is approximated",
+            Self::HardPenalty => "the hard penalty was not trained",
+            Self::EasyBonus => "the easy bonus was not trained",
+            Self::ShortTermStability => "same-day reviews were not trained",
+        };
+        f.write_str(description)
+    }
+}
+
+/// Parameters converted from an older version of FSRS, with the ways in which they differ
+/// from the originals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacyMigration {
+    /// The parameters in the current format, clipped to the range the model accepts.
+    pub parameters: Vec<f32>,
+    pub approximations: Vec<LegacyApproximation>,
+}
+
+fn check_legacy_weights(weights: &[f32], expected: usize) -> Result<()> {
+    if weights.len() != expected {
+        return Err(FSRSError::InvalidParameters {
+            reason: InvalidParametersReason::WrongCount {
+                count: weights.len(),
+            },
+        });
+    }
+    if let Some(index) = weights.iter().position(|w| !w.is_finite()) {
+        return Err(FSRSError::InvalidParameters {
+            reason: InvalidParametersReason::NotFinite { index },
+        });
+    }
+    Ok(())
+}
+
+/// Convert the 13 weights of FSRS v3 to the current parameters. Retraining on the review
+/// history gives better parameters; this is for users who only have the old weights.
+pub fn migrate_fsrs_v3_weights(weights: &[f32]) -> Result<LegacyMigration> {
+    check_legacy_weights(weights, 13)?;
+    let w = weights;
+    let v4 = [
+        // the initial stability was linear in the rating
+        w[0],
+        w[0] + w[1],
+        w[0] + 2.0 * w[1],
+        w[0] + 3.0 * w[1],
+        // the initial difficulty and its change were added rather than subtracted
+        w[2],
+        (-w[3]).max(0.0),
+        -w[4],
+        w[5],
+        // the stability after success had the same shape, with the sign of the exponent
+        // flipped
+        w[6],
+        -w[7],
+        w[8],
+        // the stability after a lapse
+        w[9],
+        -w[10],
+        w[11],
+        w[12],
+        // no hard penalty or easy bonus
+        1.0,
+        1.0,
+    ];
+    let parameters = check_and_fill_parameters(&v4, true)?;
+    Ok(LegacyMigration {
+        parameters: clip_parameters(&parameters, true),
+        approximations: vec![
+            LegacyApproximation::ForgettingCurve,
+            LegacyApproximation::PostLapseStability,
+            LegacyApproximation::HardPenalty,
+            LegacyApproximation::EasyBonus,
+            LegacyApproximation::ShortTermStability,
+        ],
+    })
+}
+
+/// Convert the 17 weights of FSRS v4 to the current parameters. The weights of FSRS 4.5,
+/// which share the layout but not the forgetting curve, are also accepted by
+/// [FSRS::new](crate::FSRS::new) directly.
+pub fn migrate_fsrs_v4_weights(weights: &[f32]) -> Result<LegacyMigration> {
+    check_legacy_weights(weights, 17)?;
+    let parameters = check_and_fill_parameters(weights, true)?;
+    Ok(LegacyMigration {
+        parameters: clip_parameters(&parameters, true),
+        approximations: vec![
+            LegacyApproximation::ForgettingCurve,
+            LegacyApproximation::ShortTermStability,
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::MemoryState;
+    use crate::FSRS;
+
+    // the defaults of FSRS v3
+    static V3_WEIGHTS: [f32; 13] = [
+        1.0, 1.0, 5.0, -0.5, -0.5, 0.2, 1.4, -0.12, 0.8, 2.0, -0.2, 0.2, 1.0,
+    ];
+
+    #[test]
+    fn migrate_v3() -> Result<()> {
+        let migration = migrate_fsrs_v3_weights(&V3_WEIGHTS)?;
+        assert_eq!(migration.parameters.len(), 19);
+        assert_eq!(&migration.parameters[..4], &[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(migration.parameters[15..], [1.0, 1.0, 0.0, 0.0]);
+        assert_eq!(migration.approximations.len(), 5);
+
+        // in FSRS v3, a card first rated good had a stability of w0 + 2 * w1 days
+        let fsrs = FSRS::new(Some(&migration.parameters))?;
+        let next = fsrs.next_states(None, 0.9, 0)?;
+        assert_eq!(next.good.memory.stability, 3.0);
+        // and successful reviews increased the stability
+        let state = MemoryState {
+            stability: 3.0,
+            difficulty: 5.0,
+        };
+        assert!(fsrs.next_states(Some(state), 0.9, 3)?.good.memory.stability > 3.0);
+
+        assert_eq!(
+            migrate_fsrs_v3_weights(&V3_WEIGHTS[..12]),
+            Err(FSRSError::InvalidParameters {
+                reason: InvalidParametersReason::WrongCount { count: 12 }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_v4() -> Result<()> {
+        let weights = [
+            0.4, 0.6, 2.4, 5.8, 4.93, 0.94, 0.86, 0.01, 1.49, 0.14, 0.94, 2.18, 0.05, 0.34, 1.26,
+            0.29, 2.61,
+        ];
+        let migration = migrate_fsrs_v4_weights(&weights)?;
+        assert_eq!(
+            migration.parameters,
+            clip_parameters(&check_and_fill_parameters(&weights, true)?, true)
+        );
+        assert_eq!(
+            migration.approximations[0].affected_parameters(),
+            &[] as &[usize]
+        );
+        assert_eq!(
+            migration.approximations[1].to_string(),
+            "same-day reviews were not trained"
+        );
+        let mut invalid = weights;
+        invalid[3] = f32::NAN;
+        assert_eq!(
+            migrate_fsrs_v4_weights(&invalid),
+            Err(FSRSError::InvalidParameters {
+                reason: InvalidParametersReason::NotFinite { index: 3 }
+            })
+        );
+        Ok(())
+    }
+}
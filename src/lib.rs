@@ -1,27 +1,82 @@
 #![allow(clippy::single_range_in_vec_init)]
 
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+#[cfg(any(test, feature = "anki-convertor"))]
+pub mod anki;
+pub mod anki_preset;
+pub mod anonymize;
+pub mod backend;
+pub mod background;
 mod batch_shuffle;
+#[cfg(any(test, feature = "benchmark"))]
+pub mod benchmark;
+mod builder;
+#[cfg(any(test, feature = "charts"))]
+pub mod charts;
+pub mod checkpoint;
+#[cfg(any(test, feature = "parquet"))]
+pub mod columnar;
+pub mod convertor;
 #[cfg(test)]
 mod convertor_tests;
 mod cosine_annealing;
-mod dataset;
+pub mod dataset;
 mod error;
-mod inference;
-mod model;
-mod optimal_retention;
+pub mod events;
+#[cfg(feature = "uniffi")]
+pub mod ffi;
+pub mod forgetting_curve;
+pub mod fuzz;
+pub mod inference;
+pub mod item_cache;
+pub mod legacy;
+pub mod lr_schedule;
+pub mod manifest;
+pub mod memory_file;
+#[cfg(any(test, feature = "mmap-dataset"))]
+pub mod mmap_dataset;
+#[cfg(any(test, feature = "mnemosyne-convertor"))]
+pub mod mnemosyne;
+pub mod model;
+pub mod optimal_retention;
 mod parameter_clipper;
-mod pre_training;
+pub mod parameter_file;
+pub mod pre_training;
+pub mod prelude;
+pub mod presets;
+pub mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod scheduler;
+#[cfg(any(test, feature = "proptest"))]
+pub mod strategies;
+#[cfg(any(test, feature = "supermemo-convertor"))]
+pub mod supermemo;
+pub mod synthetic;
 #[cfg(test)]
 mod test_helpers;
-mod training;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+pub mod training;
 
+// The crate root only exports the prelude, and the functions it exported before the
+// prelude existed. Everything else is used through its module, eg `fsrs::dataset::FSRSBatcher`.
+pub use builder::FSRSBuilder;
+pub use convertor::ConvertorConfig;
 pub use dataset::{FSRSItem, FSRSReview};
 pub use error::{FSRSError, Result};
 pub use inference::{
-    ItemProgress, ItemState, MemoryState, ModelEvaluation, NextStates, DEFAULT_PARAMETERS,
+    ItemProgress, ItemState, MemoryModel, MemoryState, ModelEvaluation, NextStates, Parameters,
+    DEFAULT_PARAMETERS,
 };
-pub use model::FSRS;
+pub use model::{ModelConfig, FSRS};
 pub use optimal_retention::{
-    extract_simulator_config, simulate, Card, RevlogEntry, RevlogReviewKind, SimulatorConfig,
+    extract_simulator_config, simulate, Card, RevlogEntry, RevlogReviewKind, SimulationResult,
+    SimulatorConfig,
 };
-pub use training::CombinedProgressState;
+pub use parameter_file::ParameterFile;
+pub use progress::{CancellationToken, ProgressHandle, ProgressStage};
+pub use scheduler::{Scheduler, SchedulerConfig};
+pub use training::{CombinedProgressState, TrainingConfig};
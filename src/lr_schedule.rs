@@ -0,0 +1,224 @@
+use burn::{lr_scheduler::LrScheduler, tensor::backend::Backend, LearningRate};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+use crate::cosine_annealing::CosineAnnealingLR;
+use crate::error::{FSRSError, Result};
+
+/// How the learning rate of [TrainingConfig](crate::TrainingConfig) changes over the
+/// training steps, one step per batch.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LrSchedule {
+    /// The learning rate is used for every step.
+    Constant,
+    /// Cosine annealing from the learning rate to 0 over `t_max` steps, then back up over
+    /// the next `t_max`, and so on. By default, `t_max` is the number of steps of the whole
+    /// training, as in the Python optimizer.
+    #[default]
+    Cosine,
+    /// Cosine annealing with `t_max` set explicitly.
+    CosineWithPeriod { t_max: usize },
+    /// Cosine annealing to `eta_min` over `t_0` steps, after which the learning rate jumps
+    /// back up, with each period `t_mult` times longer than the previous one (SGDR).
+    CosineWithWarmRestarts {
+        t_0: usize,
+        t_mult: usize,
+        eta_min: f64,
+    },
+    /// Increase the learning rate linearly over `warmup_steps`, then decay it to `eta_min`
+    /// with cosine annealing over the remaining steps.
+    WarmupCosine { warmup_steps: usize, eta_min: f64 },
+}
+
+impl LrSchedule {
+    fn validate(&self) -> Result<()> {
+        match *self {
+            Self::CosineWithPeriod { t_max: 0 }
+            | Self::CosineWithWarmRestarts { t_0: 0, .. }
+            | Self::CosineWithWarmRestarts { t_mult: 0, .. } => Err(FSRSError::InvalidInput),
+            Self::CosineWithWarmRestarts { eta_min, .. } | Self::WarmupCosine { eta_min, .. }
+                if !eta_min.is_finite() || eta_min < 0.0 =>
+            {
+                Err(FSRSError::InvalidInput)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The learning rate of `step`, counting from 0, for schedules without state.
+    fn learning_rate(&self, init_lr: LearningRate, step: usize, total_steps: usize) -> f64 {
+        let cosine = |eta_min: f64, progress: f64| {
+            (init_lr - eta_min).mul_add((1.0 + (PI * progress).cos()) / 2.0, eta_min)
+        };
+        match *self {
+            Self::CosineWithWarmRestarts {
+                t_0,
+                t_mult,
+                eta_min,
+            } => {
+                let (mut step, mut period) = (step, t_0);
+                while step >= period {
+                    step -= period;
+                    period = period.saturating_mul(t_mult);
+                }
+                cosine(eta_min, step as f64 / period as f64)
+            }
+            Self::WarmupCosine {
+                warmup_steps,
+                eta_min,
+            } => {
+                if step < warmup_steps {
+                    init_lr * (step + 1) as f64 / warmup_steps as f64
+                } else {
+                    let decay_steps = total_steps.saturating_sub(warmup_steps).max(1);
+                    cosine(eta_min, (step - warmup_steps) as f64 / decay_steps as f64)
+                }
+            }
+            _ => init_lr,
+        }
+    }
+}
+
+/// Steps through an [LrSchedule] during training.
+#[derive(Clone, Debug)]
+pub(crate) struct ScheduledLr {
+    schedule: LrSchedule,
+    init_lr: LearningRate,
+    total_steps: usize,
+    step_count: usize,
+    cosine: Option<CosineAnnealingLR>,
+}
+
+impl ScheduledLr {
+    pub fn init(schedule: LrSchedule, init_lr: LearningRate, total_steps: usize) -> Result<Self> {
+        schedule.validate()?;
+        Ok(Self {
+            schedule,
+            init_lr,
+            total_steps,
+            step_count: 0,
+            cosine: Self::cosine(schedule, init_lr, total_steps),
+        })
+    }
+
+    /// Cosine annealing is computed incrementally, as in PyTorch, so that parameters are
+    /// the same as those of the Python optimizer.
+    fn cosine(
+        schedule: LrSchedule,
+        init_lr: LearningRate,
+        total_steps: usize,
+    ) -> Option<CosineAnnealingLR> {
+        match schedule {
+            LrSchedule::Cosine => Some(CosineAnnealingLR::init(total_steps as f64, init_lr)),
+            LrSchedule::CosineWithPeriod { t_max } => {
+                Some(CosineAnnealingLR::init(t_max as f64, init_lr))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<B: Backend> LrScheduler<B> for ScheduledLr {
+    type Record = usize;
+
+    fn step(&mut self) -> LearningRate {
+        let lr = match &mut self.cosine {
+            Some(cosine) => LrScheduler::<B>::step(cosine),
+            None => self
+                .schedule
+                .learning_rate(self.init_lr, self.step_count, self.total_steps),
+        };
+        self.step_count += 1;
+        lr
+    }
+
+    fn to_record(&self) -> Self::Record {
+        self.step_count
+    }
+
+    fn load_record(mut self, record: Self::Record) -> Self {
+        self.step_count = record;
+        self.cosine =
+            Self::cosine(self.schedule, self.init_lr, self.total_steps).map(|mut cosine| {
+                for _ in 0..record {
+                    LrScheduler::<B>::step(&mut cosine);
+                }
+                cosine
+            });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+
+    fn learning_rates(schedule: LrSchedule, total_steps: usize) -> Vec<f64> {
+        let mut scheduler = ScheduledLr::init(schedule, 1.0, total_steps).unwrap();
+        (0..total_steps)
+            .map(|_| LrScheduler::<NdArray>::step(&mut scheduler))
+            .collect()
+    }
+
+    fn round(lrs: Vec<f64>) -> Vec<f64> {
+        lrs.into_iter()
+            .map(|lr| (lr * 1000.0).round() / 1000.0)
+            .collect()
+    }
+
+    #[test]
+    fn schedules() {
+        assert_eq!(learning_rates(LrSchedule::Constant, 3), [1.0; 3]);
+        assert_eq!(
+            round(learning_rates(LrSchedule::Cosine, 5)),
+            [1.0, 0.905, 0.655, 0.345, 0.095]
+        );
+        assert_eq!(
+            round(learning_rates(LrSchedule::CosineWithPeriod { t_max: 2 }, 5)),
+            [1.0, 0.5, 0.0, 0.5, 1.0]
+        );
+        assert_eq!(
+            round(learning_rates(
+                LrSchedule::CosineWithWarmRestarts {
+                    t_0: 2,
+                    t_mult: 2,
+                    eta_min: 0.0
+                },
+                7
+            )),
+            [1.0, 0.5, 1.0, 0.854, 0.5, 0.146, 1.0]
+        );
+        let mut scheduler = ScheduledLr::init(LrSchedule::Cosine, 1.0, 5).unwrap();
+        scheduler = LrScheduler::<NdArray>::load_record(scheduler, 3);
+        assert_eq!(
+            (LrScheduler::<NdArray>::step(&mut scheduler) * 1000.0).round(),
+            345.0
+        );
+        assert_eq!(
+            round(learning_rates(
+                LrSchedule::WarmupCosine {
+                    warmup_steps: 2,
+                    eta_min: 0.1
+                },
+                5
+            )),
+            [0.5, 1.0, 1.0, 0.775, 0.325]
+        );
+        for invalid in [
+            LrSchedule::CosineWithPeriod { t_max: 0 },
+            LrSchedule::CosineWithWarmRestarts {
+                t_0: 1,
+                t_mult: 0,
+                eta_min: 0.0,
+            },
+            LrSchedule::WarmupCosine {
+                warmup_steps: 1,
+                eta_min: -1.0,
+            },
+        ] {
+            assert!(ScheduledLr::init(invalid, 1.0, 10).is_err());
+        }
+    }
+}
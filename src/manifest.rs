@@ -0,0 +1,334 @@
+use burn::tensor::backend::Backend;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::dataset::FSRSItem;
+use crate::error::{FSRSError, Result};
+use crate::inference::ModelEvaluation;
+use crate::optimal_retention::SimulatorConfig;
+use crate::training::TrainingConfig;
+use crate::FSRS;
+
+/// The version of the FSRS formulas implemented by this crate, as opposed to the version of
+/// the crate itself.
+pub const FSRS_MODEL_VERSION: &str = "FSRS-5";
+
+/// What was run, with everything needed to run it again apart from the items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RunOperation {
+    /// Training, with the [TrainingConfig] serialized as JSON.
+    Training { config: Value },
+    /// [FSRS::evaluate] with [RunManifest::parameters].
+    Evaluation,
+    /// [FSRS::optimal_retention] with [RunManifest::parameters].
+    OptimalRetention { config: Box<SimulatorConfig> },
+}
+
+/// The result of a run, as returned by [FSRS::rerun].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RunOutput {
+    Parameters(Vec<f32>),
+    Evaluation(ModelEvaluation),
+    OptimalRetention(f32),
+}
+
+/// Where and how a result was produced, to tell why two runs that should match don't. Save
+/// it alongside the result (it serializes to JSON), and pass it to [FSRS::rerun] with the
+/// same items to run again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// The version of this crate.
+    pub fsrs_version: String,
+    /// See [FSRS_MODEL_VERSION].
+    pub model_version: String,
+    pub operation: RunOperation,
+    /// The parameters the operation was run with. None for training, which starts from the
+    /// defaults.
+    pub parameters: Option<Vec<f32>>,
+    /// The random seed, for operations that use one.
+    pub seed: Option<u64>,
+    /// A hash of [RunManifest::operation], to compare configs at a glance.
+    pub config_hash: String,
+    /// A hash of the reviews of the items, in order. Metadata is not included.
+    pub dataset_fingerprint: String,
+    pub item_count: usize,
+    /// The burn backend and device.
+    pub backend: String,
+    pub device: String,
+    pub os: String,
+    pub arch: String,
+    /// The number of threads of the thread pool the operation ran on.
+    pub threads: usize,
+}
+
+/// 64-bit FNV-1a, which unlike the std hashers is guaranteed to stay the same across Rust
+/// versions and platforms.
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> String {
+    let hash = bytes
+        .into_iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{hash:016x}")
+}
+
+fn dataset_fingerprint(items: &[FSRSItem]) -> String {
+    fnv1a(items.iter().flat_map(|item| {
+        (item.reviews.len() as u32)
+            .to_le_bytes()
+            .into_iter()
+            .chain(item.reviews.iter().flat_map(|review| {
+                review
+                    .rating
+                    .to_le_bytes()
+                    .into_iter()
+                    .chain(review.delta_t.to_le_bytes())
+                    // only hashed when set, so that fingerprints of items without it are
+                    // unchanged
+                    .chain(
+                        review
+                            .elapsed_seconds
+                            .map(u32::to_le_bytes)
+                            .into_iter()
+                            .flatten(),
+                    )
+            }))
+    }))
+}
+
+impl RunManifest {
+    /// The names of the fields that differ from `other`, eg `["backend", "threads"]`.
+    pub fn differences(&self, other: &Self) -> Vec<&'static str> {
+        let mut differences = vec![];
+        let mut check = |name, differs| {
+            if differs {
+                differences.push(name);
+            }
+        };
+        check("fsrs_version", self.fsrs_version != other.fsrs_version);
+        check("model_version", self.model_version != other.model_version);
+        check("operation", self.operation != other.operation);
+        check("parameters", self.parameters != other.parameters);
+        check("seed", self.seed != other.seed);
+        check(
+            "dataset_fingerprint",
+            self.dataset_fingerprint != other.dataset_fingerprint,
+        );
+        check("item_count", self.item_count != other.item_count);
+        check("backend", self.backend != other.backend);
+        check("device", self.device != other.device);
+        check("os", self.os != other.os);
+        check("arch", self.arch != other.arch);
+        check("threads", self.threads != other.threads);
+        differences
+    }
+}
+
+impl<B: Backend> FSRS<B> {
+    fn manifest(
+        &self,
+        operation: RunOperation,
+        parameters: Option<Vec<f32>>,
+        seed: Option<u64>,
+        items: &[FSRSItem],
+    ) -> RunManifest {
+        let config_hash = fnv1a(serde_json::to_vec(&operation).unwrap());
+        RunManifest {
+            fsrs_version: env!("CARGO_PKG_VERSION").to_string(),
+            model_version: FSRS_MODEL_VERSION.to_string(),
+            operation,
+            parameters,
+            seed,
+            config_hash,
+            dataset_fingerprint: dataset_fingerprint(items),
+            item_count: items.len(),
+            backend: std::any::type_name::<B>().to_string(),
+            device: format!("{:?}", self.device()),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            threads: self.install(rayon::current_num_threads),
+        }
+    }
+
+    fn current_parameters(&self) -> Vec<f32> {
+        self.model().w.val().to_data().convert().value
+    }
+
+    /// Like [FSRS::compute_parameters], also returning a manifest of the run.
+    pub fn compute_parameters_with_manifest(
+        &self,
+        train_set: Vec<FSRSItem>,
+        enable_short_term: bool,
+    ) -> Result<(Vec<f32>, RunManifest)> {
+        let config = self.default_training_config(enable_short_term);
+        self.train_with_manifest(train_set, &config)
+    }
+
+    fn train_with_manifest(
+        &self,
+        train_set: Vec<FSRSItem>,
+        config: &TrainingConfig,
+    ) -> Result<(Vec<f32>, RunManifest)> {
+        let operation = RunOperation::Training {
+            config: serde_json::to_value(config).unwrap(),
+        };
+        let manifest = self.manifest(operation, None, Some(config.seed), &train_set);
+        let parameters = self.compute_parameters_with_config(train_set, None, config)?;
+        Ok((parameters, manifest))
+    }
+
+    /// Like [FSRS::evaluate], also returning a manifest of the run.
+    /// Parameters must have been provided when calling FSRS::new().
+    pub fn evaluate_with_manifest(
+        &self,
+        items: Vec<FSRSItem>,
+    ) -> Result<(ModelEvaluation, RunManifest)> {
+        let manifest = self.manifest(
+            RunOperation::Evaluation,
+            Some(self.current_parameters()),
+            None,
+            &items,
+        );
+        let evaluation = self.evaluate(items, |_| true)?;
+        Ok((evaluation, manifest))
+    }
+
+    /// Like [FSRS::optimal_retention], also returning a manifest of the run.
+    pub fn optimal_retention_with_manifest(
+        &self,
+        config: &SimulatorConfig,
+        parameters: &[f32],
+    ) -> Result<(f32, RunManifest)> {
+        let manifest = self.manifest(
+            RunOperation::OptimalRetention {
+                config: Box::new(config.clone()),
+            },
+            Some(parameters.to_vec()),
+            None,
+            &[],
+        );
+        let retention = self.optimal_retention(config, parameters, |_| true)?;
+        Ok((retention, manifest))
+    }
+
+    /// Run the operation of `manifest` again, on this instance's backend, device and thread
+    /// pool. Returns the result, and a manifest of the new run to compare with
+    /// [RunManifest::differences]. Returns [FSRSError::InvalidInput] if `items` are not the
+    /// ones the manifest was created with (optimal retention uses no items).
+    pub fn rerun(
+        &self,
+        manifest: &RunManifest,
+        items: Vec<FSRSItem>,
+    ) -> Result<(RunOutput, RunManifest)> {
+        if dataset_fingerprint(&items) != manifest.dataset_fingerprint {
+            return Err(FSRSError::InvalidInput);
+        }
+        match &manifest.operation {
+            RunOperation::Training { config } => {
+                let config: TrainingConfig =
+                    serde_json::from_value(config.clone()).map_err(|_| FSRSError::InvalidInput)?;
+                let (parameters, manifest) = self.train_with_manifest(items, &config)?;
+                Ok((RunOutput::Parameters(parameters), manifest))
+            }
+            RunOperation::Evaluation => {
+                let parameters = manifest
+                    .parameters
+                    .as_ref()
+                    .ok_or(FSRSError::InvalidInput)?;
+                // a clone keeps its own parameters, so this instance is unaffected
+                let fsrs = self.clone();
+                fsrs.set_parameters(parameters)?;
+                let (evaluation, manifest) = fsrs.evaluate_with_manifest(items)?;
+                Ok((RunOutput::Evaluation(evaluation), manifest))
+            }
+            RunOperation::OptimalRetention { config } => {
+                let parameters = manifest
+                    .parameters
+                    .as_ref()
+                    .ok_or(FSRSError::InvalidInput)?;
+                let (retention, manifest) =
+                    self.optimal_retention_with_manifest(config, parameters)?;
+                Ok((RunOutput::OptimalRetention(retention), manifest))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::item_from;
+    use crate::DEFAULT_PARAMETERS;
+
+    fn items() -> Vec<FSRSItem> {
+        vec![item_from(&[(3, 0), (3, 2)]); 10]
+    }
+
+    #[test]
+    fn fingerprint() {
+        assert_eq!(fnv1a(*b""), "cbf29ce484222325");
+        assert_eq!(fnv1a(*b"a"), "af63dc4c8601ec8c");
+        let items = items();
+        let mut changed = items.clone();
+        changed[9].reviews[1].delta_t = 3;
+        assert_ne!(dataset_fingerprint(&items), dataset_fingerprint(&changed));
+        assert_ne!(
+            dataset_fingerprint(&items),
+            dataset_fingerprint(&items[1..])
+        );
+    }
+
+    #[test]
+    fn evaluation_manifest() -> Result<()> {
+        let fsrs = FSRS::new(Some(&[]))?;
+        let (evaluation, manifest) = fsrs.evaluate_with_manifest(items())?;
+        assert_eq!(manifest.model_version, "FSRS-5");
+        assert_eq!(manifest.item_count, 10);
+        assert_eq!(
+            manifest.parameters.as_deref(),
+            Some(&DEFAULT_PARAMETERS[..])
+        );
+        assert!(manifest.backend.contains("NdArray"));
+
+        // the manifest survives a round trip through JSON
+        let json = serde_json::to_string(&manifest).unwrap();
+        let manifest: RunManifest = serde_json::from_str(&json).unwrap();
+
+        // rerunning on an instance with other parameters uses the ones of the manifest
+        let other = FSRS::new(Some(&[1.0; 19]))?.with_max_threads(1)?;
+        let other_parameters = other.current_parameters();
+        let (output, rerun) = other.rerun(&manifest, items())?;
+        assert_eq!(output, RunOutput::Evaluation(evaluation));
+        assert_eq!(rerun.threads, 1);
+        assert!(rerun
+            .differences(&manifest)
+            .iter()
+            .all(|field| *field == "threads"));
+        assert_eq!(other.current_parameters(), other_parameters);
+
+        assert_eq!(
+            other.rerun(&manifest, items()[1..].to_vec()),
+            Err(FSRSError::InvalidInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn training_manifest() -> Result<()> {
+        let fsrs = FSRS::new(None)?;
+        // too few items to train on, so this returns quickly
+        let (parameters, manifest) = fsrs.compute_parameters_with_manifest(items(), false)?;
+        assert_eq!(manifest.seed, Some(2023));
+        assert_eq!(manifest.parameters, None);
+        let RunOperation::Training { config } = &manifest.operation else {
+            panic!("{:?}", manifest.operation);
+        };
+        assert_eq!(config["num_epochs"], 5);
+        let (output, rerun) = fsrs.rerun(&manifest, items())?;
+        assert_eq!(output, RunOutput::Parameters(parameters));
+        assert!(rerun.differences(&manifest).is_empty());
+        assert_eq!(rerun.config_hash, manifest.config_hash);
+        Ok(())
+    }
+}
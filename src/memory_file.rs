@@ -0,0 +1,203 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+
+use crate::inference::{next_interval, MemoryState};
+use crate::item_cache::{read_i64, read_varint, write_varint};
+use crate::optimal_retention::Card;
+
+const MAGIC: &[u8; 8] = b"FSRSMEMO";
+/// Bumped whenever the layout below changes. Files with a different version are rejected.
+const VERSION: u32 = 1;
+
+// Layout, after the magic and the version (u32, little endian):
+// - state count, as a LEB128 varint
+// - for each state: card id (i64), stability and difficulty (f32), all little endian
+
+/// Write the memory state of each card in a compact binary format, so that it can be backed
+/// up independently of the application's database, and restored with [read_memory_states].
+pub fn write_memory_states(states: &[(i64, MemoryState)], mut writer: impl Write) -> Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    write_varint(&mut writer, states.len() as u64)?;
+    for (card_id, state) in states {
+        writer.write_all(&card_id.to_le_bytes())?;
+        writer.write_all(&state.stability.to_le_bytes())?;
+        writer.write_all(&state.difficulty.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+fn read_f32(reader: &mut impl Read) -> Result<f32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+/// Read states written by [write_memory_states], in the order they were written. Returns an
+/// [ErrorKind::InvalidData] error if the data is not in the expected format, was written by
+/// an incompatible version, or contains a state that is not finite.
+pub fn read_memory_states(mut reader: impl Read) -> Result<Vec<(i64, MemoryState)>> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "not a memory state file",
+        ));
+    }
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("unsupported memory state file version {version}"),
+        ));
+    }
+    let count = read_varint(&mut reader)?;
+    let mut states = Vec::with_capacity(count.min(1 << 20) as usize);
+    for _ in 0..count {
+        let card_id = read_i64(&mut reader)?;
+        let stability = read_f32(&mut reader)?;
+        let difficulty = read_f32(&mut reader)?;
+        if !stability.is_finite() || !difficulty.is_finite() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("memory state of card {card_id} is not finite"),
+            ));
+        }
+        states.push((
+            card_id,
+            MemoryState {
+                stability,
+                difficulty,
+            },
+        ));
+    }
+    Ok(states)
+}
+
+/// Save states to a file with [write_memory_states].
+pub fn save_memory_states(states: &[(i64, MemoryState)], path: impl AsRef<Path>) -> Result<()> {
+    write_memory_states(states, BufWriter::new(File::create(path)?))
+}
+
+/// Load states from a file written by [save_memory_states].
+pub fn load_memory_states(path: impl AsRef<Path>) -> Result<Vec<(i64, MemoryState)>> {
+    read_memory_states(BufReader::new(File::open(path)?))
+}
+
+/// Existing cards to seed [simulate](crate::simulate) with, from their memory states.
+///
+/// The states don't record when each card was last reviewed, so the cards are assumed to
+/// be spread evenly over their current interval at `desired_retention`: some are due
+/// today, others were only just reviewed.
+pub fn cards_from_memory_states(
+    states: &[(i64, MemoryState)],
+    desired_retention: f32,
+) -> Vec<Card> {
+    states
+        .iter()
+        .enumerate()
+        .map(|(index, (_, state))| {
+            let interval = next_interval(state.stability, desired_retention).max(1.0);
+            // a low-discrepancy sequence, so that cards with similar states are not all
+            // due on the same day
+            let elapsed = (interval * (index as f32 * 0.618_034).fract()).round();
+            Card {
+                difficulty: state.difficulty,
+                stability: state.stability,
+                last_date: -elapsed,
+                due: (interval - elapsed).round(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimal_retention::{simulate, SimulatorConfig};
+    use crate::DEFAULT_PARAMETERS;
+
+    fn states() -> Vec<(i64, MemoryState)> {
+        (0..100)
+            .map(|i| {
+                (
+                    1_700_000_000_000 + i,
+                    MemoryState {
+                        stability: 1.0 + i as f32 * 3.5,
+                        difficulty: 1.0 + (i % 10) as f32,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let states = states();
+        let mut buf = vec![];
+        write_memory_states(&states, &mut buf)?;
+        assert_eq!(buf.len(), 8 + 4 + 1 + states.len() * 16);
+        assert_eq!(read_memory_states(buf.as_slice())?, states);
+
+        let path = std::env::temp_dir().join("fsrs_memory_states_round_trip.bin");
+        save_memory_states(&states, &path)?;
+        assert_eq!(load_memory_states(&path)?, states);
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_data() {
+        let mut buf = vec![];
+        write_memory_states(
+            &[(
+                1,
+                MemoryState {
+                    stability: f32::NAN,
+                    difficulty: 5.0,
+                },
+            )],
+            &mut buf,
+        )
+        .unwrap();
+        let error = read_memory_states(buf.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "memory state of card 1 is not finite");
+
+        buf[8] = 2;
+        let error = read_memory_states(buf.as_slice()).unwrap_err();
+        assert_eq!(error.to_string(), "unsupported memory state file version 2");
+
+        let mut items = vec![];
+        crate::item_cache::write_items(&[], &mut items).unwrap();
+        let error = read_memory_states(items.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let error = read_memory_states(&MAGIC[..4]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn seed_simulation() {
+        let states = states();
+        let cards = cards_from_memory_states(&states, 0.9);
+        assert_eq!(cards.len(), states.len());
+        assert!(cards
+            .iter()
+            .all(|card| card.last_date <= 0.0 && card.due >= 0.0));
+        // spread over the first interval, rather than all due at once
+        assert!(cards.iter().filter(|card| card.due == 0.0).count() < 10);
+
+        let config = SimulatorConfig {
+            deck_size: 200,
+            learn_span: 30,
+            ..Default::default()
+        };
+        let result = simulate(&config, &DEFAULT_PARAMETERS, 0.9, Some(1), Some(cards)).unwrap();
+        assert!(result.review_cnt_per_day.sum() > 0);
+    }
+}
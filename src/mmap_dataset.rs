@@ -0,0 +1,221 @@
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind, Result, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use burn::data::dataset::Dataset;
+use memmap2::Mmap;
+
+use crate::dataset::{FSRSItem, FSRSReview, ItemMetadata};
+
+const MAGIC: &[u8; 8] = b"FSRSMMAP";
+/// Bumped whenever the layout below changes. Files with a different version are rejected.
+const VERSION: u32 = 2;
+
+const HAS_CARD_ID: u32 = 1;
+const HAS_DECK_ID: u32 = 2;
+
+const HEADER_LEN: usize = 32;
+const REVIEW_LEN: usize = 16;
+const ENTRY_LEN: usize = 32;
+
+// Layout, all integers little endian:
+// - header: magic, version (u32), 4 reserved bytes, item count (u64), offset of the item
+//   table (u64)
+// - the reviews of all items, back to back: (rating: u32, delta_t: u32, elapsed_seconds + 1
+//   or 0 if unknown: u64)
+// - the item table: (index of the first review: u64, review count: u32, flags: u32,
+//   card id: i64, deck id: i64) for each item
+// Every record has a fixed width, so any item can be located without reading the others.
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// Write items to a file that can be opened with [MmapFSRSDataset::open]. The items are
+/// streamed to disk as they are yielded; only a fixed-size entry per item is kept in
+/// memory until the end. Returns the number of items written.
+pub fn write_mmap_items(
+    items: impl IntoIterator<Item = FSRSItem>,
+    path: impl AsRef<Path>,
+) -> Result<usize> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&[0; HEADER_LEN])?;
+    let mut table = vec![];
+    let mut review_count: u64 = 0;
+    for item in items {
+        for review in &item.reviews {
+            writer.write_all(&review.rating.to_le_bytes())?;
+            writer.write_all(&review.delta_t.to_le_bytes())?;
+            let elapsed_seconds = review
+                .elapsed_seconds
+                .map_or(0, |seconds| seconds as u64 + 1);
+            writer.write_all(&elapsed_seconds.to_le_bytes())?;
+        }
+        let len = u32::try_from(item.reviews.len())
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "item has too many reviews"))?;
+        let metadata = item.metadata.unwrap_or_default();
+        let mut flags = 0;
+        if metadata.card_id.is_some() {
+            flags |= HAS_CARD_ID;
+        }
+        if metadata.deck_id.is_some() {
+            flags |= HAS_DECK_ID;
+        }
+        table.extend_from_slice(&review_count.to_le_bytes());
+        table.extend_from_slice(&len.to_le_bytes());
+        table.extend_from_slice(&flags.to_le_bytes());
+        table.extend_from_slice(&metadata.card_id.unwrap_or_default().to_le_bytes());
+        table.extend_from_slice(&metadata.deck_id.unwrap_or_default().to_le_bytes());
+        review_count += len as u64;
+    }
+    writer.write_all(&table)?;
+    let item_count = table.len() / ENTRY_LEN;
+    let table_offset = HEADER_LEN as u64 + review_count * REVIEW_LEN as u64;
+    writer.seek(SeekFrom::Start(0))?;
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&[0; 4])?;
+    writer.write_all(&(item_count as u64).to_le_bytes())?;
+    writer.write_all(&table_offset.to_le_bytes())?;
+    writer.flush()?;
+    Ok(item_count)
+}
+
+/// A [Dataset] backed by a memory-mapped file written by [write_mmap_items]. Items are
+/// decoded on access, so the operating system only needs to keep the pages being read in
+/// memory, and datasets larger than RAM can be used for training.
+pub struct MmapFSRSDataset {
+    mmap: Mmap,
+    len: usize,
+    table_offset: usize,
+}
+
+impl MmapFSRSDataset {
+    /// Open a file written by [write_mmap_items]. Returns an [ErrorKind::InvalidData] error
+    /// if the file is not in the expected format, or was written by an incompatible
+    /// version. The file must not be modified while the dataset is open.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        // Safety: the file is only read, and callers must not modify it while it is mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+        if mmap.len() < HEADER_LEN || &mmap[..8] != MAGIC {
+            return Err(invalid("not an mmap item file"));
+        }
+        let version = u32_at(&mmap, 8);
+        if version != VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported mmap item file version {version}"),
+            ));
+        }
+        let len = usize::try_from(u64_at(&mmap, 16)).map_err(|_| invalid("too many items"))?;
+        let table_offset =
+            usize::try_from(u64_at(&mmap, 24)).map_err(|_| invalid("file is too large"))?;
+        let expected_len = len
+            .checked_mul(ENTRY_LEN)
+            .and_then(|table_len| table_len.checked_add(table_offset));
+        if table_offset < HEADER_LEN || expected_len != Some(mmap.len()) {
+            return Err(invalid("file is truncated"));
+        }
+        Ok(Self {
+            mmap,
+            len,
+            table_offset,
+        })
+    }
+}
+
+impl Dataset<FSRSItem> for MmapFSRSDataset {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Option<FSRSItem> {
+        if index >= self.len {
+            return None;
+        }
+        let entry = &self.mmap[self.table_offset + index * ENTRY_LEN..][..ENTRY_LEN];
+        let first = usize::try_from(u64_at(entry, 0)).ok()?;
+        let count = u32_at(entry, 8) as usize;
+        let flags = u32_at(entry, 12);
+        let start = HEADER_LEN.checked_add(first.checked_mul(REVIEW_LEN)?)?;
+        let end = start.checked_add(count * REVIEW_LEN)?;
+        if end > self.table_offset {
+            return None;
+        }
+        let reviews = self.mmap[start..end]
+            .chunks_exact(REVIEW_LEN)
+            .map(|review| FSRSReview {
+                rating: u32_at(review, 0),
+                delta_t: u32_at(review, 4),
+                elapsed_seconds: u64_at(review, 8)
+                    .checked_sub(1)
+                    .map(|seconds| seconds as u32),
+            })
+            .collect();
+        let metadata = (flags != 0).then(|| ItemMetadata {
+            card_id: (flags & HAS_CARD_ID != 0).then(|| u64_at(entry, 16) as i64),
+            deck_id: (flags & HAS_DECK_ID != 0).then(|| u64_at(entry, 24) as i64),
+        });
+        Some(FSRSItem { reviews, metadata })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::item_from;
+    use burn::backend::NdArray;
+    use burn::data::dataloader::batcher::Batcher;
+    use burn::data::dataloader::DataLoaderBuilder;
+
+    use crate::dataset::FSRSBatcher;
+
+    #[test]
+    fn mmap_dataset() {
+        let item = |reviews: &[(u32, u32)], card_id: Option<i64>| FSRSItem {
+            metadata: card_id.map(|card_id| ItemMetadata {
+                card_id: Some(card_id),
+                deck_id: None,
+            }),
+            ..item_from(reviews)
+        };
+        let mut items = vec![
+            item(&[(3, 0), (3, 1)], Some(-5)),
+            item(&[(1, 0), (3, 1), (4, 70_000)], None),
+            item(&[(4, 0), (2, 3)], Some(1_700_000_000_000)),
+        ];
+        items[1].reviews[1].elapsed_seconds = Some(43_200);
+        items[2].reviews[1].elapsed_seconds = Some(u32::MAX);
+        let path = std::env::temp_dir().join("fsrs_mmap_dataset.bin");
+        assert_eq!(write_mmap_items(items.clone(), &path).unwrap(), 3);
+
+        let dataset = MmapFSRSDataset::open(&path).unwrap();
+        assert_eq!(dataset.len(), 3);
+        assert_eq!(dataset.iter().collect::<Vec<_>>(), items);
+        assert_eq!(dataset.get(3), None);
+
+        let device = Default::default();
+        let batcher = FSRSBatcher::<NdArray>::new(device);
+        let dataloader = DataLoaderBuilder::new(batcher.clone())
+            .batch_size(3)
+            .build(dataset);
+        let batch = dataloader.iter().next().unwrap();
+        assert_eq!(
+            batch.t_historys.to_data(),
+            batcher.batch(items).t_historys.to_data()
+        );
+
+        std::fs::write(&path, b"FSRSMMAP").unwrap();
+        assert_eq!(
+            MmapFSRSDataset::open(&path).err().unwrap().kind(),
+            ErrorKind::InvalidData
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags, Result};
+
+use crate::convertor::{revlogs_to_fsrs_items, ConvertorConfig};
+use crate::dataset::FSRSItem;
+use crate::optimal_retention::{RevlogEntry, RevlogReviewKind};
+
+/// The event type Mnemosyne logs for a repetition.
+const REPETITION: i64 = 9;
+
+/// Mnemosyne grades cards 0-5, and considers grades of 2 or above as remembered.
+/// - 0-1 are mapped to again
+/// - 2 is mapped to hard
+/// - 3-4 are mapped to good
+/// - 5 is mapped to easy
+fn grade_to_rating(grade: i64) -> u8 {
+    match grade {
+        ..=1 => 1,
+        2 => 2,
+        3 | 4 => 3,
+        _ => 4,
+    }
+}
+
+/// Read the repetitions logged in a Mnemosyne 2.x database (default.db), sorted by
+/// card. Each card is given a numeric id, and its first repetition is treated as learning
+/// it. Grades are mapped to ratings as follows: 0-1 are again, 2 is hard, 3-4 are good
+/// and 5 is easy.
+pub fn mnemosyne_revlogs_from_db(path: impl AsRef<Path>) -> Result<Vec<RevlogEntry>> {
+    let db = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    let mut card_ids = HashMap::new();
+    let mut revlogs = db
+        .prepare(
+            "SELECT object_id, timestamp, grade, thinking_time
+            FROM log
+            WHERE event_type = ?1
+            AND grade >= 0
+            ORDER BY timestamp",
+        )?
+        .query_and_then((REPETITION,), |row| -> Result<_> {
+            let object_id: String = row.get(0)?;
+            let timestamp: i64 = row.get(1)?;
+            let grade: i64 = row.get(2)?;
+            let thinking_time: Option<i64> = row.get(3)?;
+            let next_id = card_ids.len() as i64 + 1;
+            let (cid, review_kind) = match card_ids.get(&object_id) {
+                Some(&cid) => (cid, RevlogReviewKind::Review),
+                None => {
+                    card_ids.insert(object_id, next_id);
+                    (next_id, RevlogReviewKind::Learning)
+                }
+            };
+            Ok(RevlogEntry {
+                id: timestamp * 1000,
+                cid,
+                button_chosen: grade_to_rating(grade),
+                taken_millis: thinking_time.unwrap_or_default().max(0) as u32 * 1000,
+                review_kind,
+                ..Default::default()
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    revlogs.sort_by_key(|r| (r.cid, r.id));
+    Ok(revlogs)
+}
+
+/// Read the repetitions logged in a Mnemosyne database and convert them into items that can
+/// be passed to [FSRS::compute_parameters](crate::FSRS::compute_parameters). See
+/// [mnemosyne_revlogs_from_db] for how grades are mapped.
+pub fn items_from_mnemosyne_db(
+    path: impl AsRef<Path>,
+    config: &ConvertorConfig,
+) -> Result<Vec<FSRSItem>> {
+    let revlogs = mnemosyne_revlogs_from_db(path)?;
+    Ok(revlogs_to_fsrs_items(revlogs, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::FSRSReview;
+
+    const DAY_S: i64 = 86400;
+
+    #[test]
+    fn read_mnemosyne_db() -> Result<()> {
+        let path = std::env::temp_dir().join("fsrs_read_mnemosyne.db");
+        let _ = std::fs::remove_file(&path);
+        let db = Connection::open(&path)?;
+        db.execute_batch(
+            "CREATE TABLE log (
+                _id integer primary key, event_type integer, timestamp integer,
+                object_id text, grade integer, thinking_time integer
+            );",
+        )?;
+        let start = 1_600_000_000;
+        for (day, card, grade, event_type) in [
+            (0, "a", 0, REPETITION),
+            (0, "a", 5, REPETITION),
+            (1, "b", 2, REPETITION),
+            // card edited
+            (2, "a", -1, 7),
+            (4, "a", 3, REPETITION),
+            (5, "b", 1, REPETITION),
+            (10, "a", 2, REPETITION),
+        ] {
+            db.execute(
+                "INSERT INTO log (event_type, timestamp, object_id, grade, thinking_time)
+                VALUES (?1, ?2, ?3, ?4, 3)",
+                (event_type, start + day * DAY_S, card, grade),
+            )?;
+        }
+
+        let revlogs = mnemosyne_revlogs_from_db(&path)?;
+        assert_eq!(revlogs.len(), 6);
+        assert_eq!(
+            revlogs
+                .iter()
+                .map(|r| (r.cid, r.button_chosen, r.review_kind))
+                .collect::<Vec<_>>(),
+            [
+                (1, 1, RevlogReviewKind::Learning),
+                (1, 4, RevlogReviewKind::Review),
+                (1, 3, RevlogReviewKind::Review),
+                (1, 2, RevlogReviewKind::Review),
+                (2, 2, RevlogReviewKind::Learning),
+                (2, 1, RevlogReviewKind::Review),
+            ]
+        );
+
+        let items = items_from_mnemosyne_db(&path, &ConvertorConfig::default())?;
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            items[2].reviews,
+            [(1, 0), (4, 0), (3, 4), (2, 6)]
+                .into_iter()
+                .map(|(rating, delta_t)| FSRSReview {
+                    rating,
+                    delta_t,
+                    elapsed_seconds: None
+                })
+                .collect::<Vec<_>>()
+        );
+
+        drop(db);
+        std::fs::remove_file(path).unwrap();
+        Ok(())
+    }
+}
@@ -1,17 +1,25 @@
+use crate::backend::{default_device, DefaultBackend};
+use crate::builder::FSRSBuilder;
 use crate::error::{FSRSError, Result};
-use crate::inference::{Parameters, DECAY, FACTOR, S_MAX, S_MIN};
+use crate::events::{EventHook, FSRSEvent};
+use crate::forgetting_curve::{Curve, CurveKind};
+use crate::inference::{ItemProgress, Parameters, DECAY, FACTOR, S_MAX, S_MIN};
 use crate::parameter_clipper::clip_parameters;
+use crate::progress::{CancellationToken, ProgressHandle, ProgressStage};
+use crate::training::TrainingConfig;
 use crate::DEFAULT_PARAMETERS;
-use burn::backend::ndarray::NdArrayDevice;
-use burn::backend::NdArray;
+use burn::backend::{ndarray::NdArrayDevice, NdArray};
 use burn::{
     config::Config,
     module::{Module, Param},
-    tensor::{backend::Backend, Data, Shape, Tensor},
+    tensor::{backend::Backend, Data, ElementConversion, Shape, Tensor},
 };
+use rayon::ThreadPool;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 #[derive(Module, Debug)]
-pub struct Model<B: Backend> {
+pub(crate) struct Model<B: Backend> {
     pub w: Param<Tensor<B, 1>>,
     pub config: ModelConfig,
 }
@@ -41,28 +49,70 @@ impl<B: Backend, const N: usize> Pow<B, N> for Tensor<B, N> {
 impl<B: Backend> Model<B> {
     #[allow(clippy::new_without_default)]
     pub fn new(config: ModelConfig) -> Self {
-        let mut initial_params: Vec<f32> = config
-            .initial_stability
-            .unwrap_or_else(|| DEFAULT_PARAMETERS[0..4].try_into().unwrap())
-            .into_iter()
-            .chain(DEFAULT_PARAMETERS[4..].iter().copied())
+        let start = config
+            .initial_parameters
+            .as_deref()
+            .unwrap_or(&DEFAULT_PARAMETERS);
+        let mut initial_params: Vec<f32> = start
+            .iter()
+            .chain(&DEFAULT_PARAMETERS[start.len().min(19)..])
+            .take(19)
+            .copied()
             .collect();
+        if let Some(initial_stability) = config.initial_stability {
+            initial_params[0..4].copy_from_slice(&initial_stability);
+        }
         if config.freeze_short_term_stability {
             initial_params[17] = 0.0;
             initial_params[18] = 0.0;
         }
+        if config.trainable_decay {
+            initial_params.push(start.get(19).copied().unwrap_or(-DECAY as f32));
+        }
+        let count = initial_params.len();
 
         Self {
             w: Param::from_tensor(Tensor::from_floats(
-                Data::new(initial_params, Shape { dims: [19] }),
+                Data::new(initial_params, Shape { dims: [count] }),
                 &B::Device::default(),
             )),
             config,
         }
     }
 
+    /// The retrievability after `t` days for stability `s`, on the curve of the config.
+    pub fn forgetting_curve(&self, t: Tensor<B, 1>, s: Tensor<B, 1>) -> Tensor<B, 1> {
+        match self.config.forgetting_curve {
+            CurveKind::Power => self.power_forgetting_curve(t, s),
+            CurveKind::Exponential => (t / s * 0.9f32.ln()).exp(),
+        }
+    }
+
     pub fn power_forgetting_curve(&self, t: Tensor<B, 1>, s: Tensor<B, 1>) -> Tensor<B, 1> {
-        (t / s * FACTOR + 1).powf_scalar(DECAY as f32)
+        if !self.has_trained_decay() {
+            return (t / s * FACTOR + 1).powf_scalar(DECAY as f32);
+        }
+        // the same curve with a trained decay: retention is still 90% after `s` days
+        let decay = -self.w.get(19);
+        let factor = (decay.clone().powf_scalar(-1.0) * 0.9f32.ln()).exp() - 1;
+        (t / s * factor + 1).pow(decay)
+    }
+
+    fn has_trained_decay(&self) -> bool {
+        self.w.dims()[0] > 19
+    }
+
+    /// The decay of the forgetting curve, which is [DECAY] unless it was trained.
+    pub(crate) fn decay(&self) -> f32 {
+        if self.has_trained_decay() {
+            -self.w.get(19).into_scalar().elem::<f32>()
+        } else {
+            DECAY as f32
+        }
+    }
+
+    pub(crate) fn curve(&self) -> Curve {
+        self.config.forgetting_curve.curve(self.decay())
     }
 
     fn stability_after_success(
@@ -118,7 +168,11 @@ impl<B: Backend> Model<B> {
     }
 
     fn init_difficulty(&self, rating: Tensor<B, 1>) -> Tensor<B, 1> {
-        self.w.get(4) - (self.w.get(5) * (rating - 1)).exp() + 1
+        if self.config.exponential_initial_difficulty {
+            self.w.get(4) - (self.w.get(5) * (rating - 1)).exp() + 1
+        } else {
+            self.w.get(4) - self.w.get(5) * (rating - 3)
+        }
     }
 
     fn linear_damping(&self, delta_d: Tensor<B, 1>, old_d: Tensor<B, 1>) -> Tensor<B, 1> {
@@ -127,7 +181,11 @@ impl<B: Backend> Model<B> {
 
     fn next_difficulty(&self, difficulty: Tensor<B, 1>, rating: Tensor<B, 1>) -> Tensor<B, 1> {
         let delta_d = -self.w.get(6) * (rating - 3);
-        difficulty.clone() + self.linear_damping(delta_d, difficulty)
+        if self.config.linear_damping {
+            difficulty.clone() + self.linear_damping(delta_d, difficulty)
+        } else {
+            difficulty + delta_d
+        }
     }
 
     pub(crate) fn step(
@@ -137,7 +195,7 @@ impl<B: Backend> Model<B> {
         state: Option<MemoryStateTensors<B>>,
     ) -> MemoryStateTensors<B> {
         let (new_s, new_d) = if let Some(state) = state {
-            let retention = self.power_forgetting_curve(delta_t.clone(), state.stability.clone());
+            let retention = self.forgetting_curve(delta_t.clone(), state.stability.clone());
             let stability_after_success = self.stability_after_success(
                 state.stability.clone(),
                 state.difficulty.clone(),
@@ -200,91 +258,400 @@ pub(crate) struct MemoryStateTensors<B: Backend> {
     pub difficulty: Tensor<B, 1>,
 }
 
-#[derive(Config, Module, Debug, Default)]
+#[derive(Config, Module, Debug)]
 pub struct ModelConfig {
     #[config(default = false)]
     pub freeze_initial_stability: bool,
     pub initial_stability: Option<[f32; 4]>,
+    /// Start training from these parameters (19, or 20 with a trained decay), eg those
+    /// trained previously, instead of the defaults. Training sets
+    /// [ModelConfig::initial_stability] to their first four, rather than pretraining it.
+    pub initial_parameters: Option<Vec<f32>>,
     #[config(default = false)]
     pub freeze_short_term_stability: bool,
+    /// Train the decay of the forgetting curve as a 20th parameter, instead of using the
+    /// fixed decay of FSRS-5.
+    #[config(default = false)]
+    pub trainable_decay: bool,
+    /// The parameters to keep at their initial values during training, by index, eg `true`
+    /// at 4..=7 to train everything but difficulty. Parameters past the end of the mask are
+    /// trained. The parameters frozen by the flags above are frozen as well.
+    pub freeze_mask: Option<Vec<bool>>,
+    #[config(default = "CurveKind::Power")]
+    pub forgetting_curve: CurveKind,
+    /// Scale the change in difficulty after a review by `(10 - D) / 9`, so that it shrinks
+    /// as the difficulty approaches its maximum of 10, as in FSRS-5. Without it, the change
+    /// is the same at any difficulty, as in FSRS-4.5.
+    #[config(default = true)]
+    pub linear_damping: bool,
+    /// The initial difficulty after the first rating G is `w4 - exp(w5 * (G - 1)) + 1`, as
+    /// in FSRS-5, so that w4 is the difficulty after again. Without it, it is
+    /// `w4 - w5 * (G - 3)`, as in FSRS-4.5, and w4 is the difficulty after good.
+    #[config(default = true)]
+    pub exponential_initial_difficulty: bool,
+}
+
+impl Default for ModelConfig {
+    /// The config of FSRS-5, with the defaults of each field.
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ModelConfig {
-    pub fn init<B: Backend>(&self) -> Model<B> {
+    pub(crate) fn init<B: Backend>(&self) -> Model<B> {
         Model::new(self.clone())
     }
+
+    /// Whether each of `count` parameters is frozen, combining [ModelConfig::freeze_mask]
+    /// and the flags. Returns [FSRSError::InvalidInput] if the mask is longer than `count`.
+    pub(crate) fn frozen_parameters(&self, count: usize) -> Result<Vec<bool>> {
+        let mask = self.freeze_mask.as_deref().unwrap_or_default();
+        if mask.len() > count {
+            return Err(FSRSError::InvalidInput);
+        }
+        let mut frozen = mask.to_vec();
+        frozen.resize(count, false);
+        if self.freeze_initial_stability {
+            frozen[0..4].fill(true);
+        }
+        if self.freeze_short_term_stability {
+            frozen[17..19].fill(true);
+        }
+        Ok(frozen)
+    }
 }
 
 /// This is the main structure provided by this crate. It can be used
 /// for both parameter training, and for reviews.
-#[derive(Debug, Clone)]
-pub struct FSRS<B: Backend = NdArray> {
-    model: Option<Model<B>>,
-    device: B::Device,
+///
+/// `FSRS` is `Send + Sync`, so one instance can be shared between threads (eg in an
+/// `Arc`), and its parameters replaced with [FSRS::set_parameters] while it is in use.
+/// Cloning is cheap, as the tensors holding the parameters are reference counted; a clone
+/// keeps its parameters when the original is given new ones.
+pub struct FSRS<B: Backend = DefaultBackend> {
+    /// Each command works on its own (cheap) copy of the model, as burn's parameters can't
+    /// be shared between threads.
+    pub(crate) model: Mutex<Option<Model<B>>>,
+    pub(crate) device: B::Device,
+    pub(crate) thread_pool: Option<Arc<ThreadPool>>,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+    pub(crate) progress_handle: Option<ProgressHandle>,
+    pub(crate) event_hook: Option<EventHook>,
+    pub(crate) training_config: Option<TrainingConfig>,
+    pub(crate) forgetting_curve: CurveKind,
+    pub(crate) linear_damping: bool,
+    pub(crate) exponential_initial_difficulty: bool,
 }
 
-impl FSRS<NdArray> {
+impl FSRS<DefaultBackend> {
+    /// An instance on the [DefaultBackend] selected with the `backend-*` cargo features,
+    /// and its [default_device].
     /// - Parameters must be provided before running commands that need them.
     /// - Parameters may be an empty slice to use the default values instead.
     pub fn new(parameters: Option<&Parameters>) -> Result<Self> {
+        Self::new_with_backend(parameters, default_device())
+    }
+}
+
+impl FSRS<NdArray<f64>> {
+    /// Like [FSRS::new], computing in double precision on the CPU. Training and evaluation
+    /// are slower, but comparing with the Python optimizer run in `torch.float64` then
+    /// tells algorithmic differences apart from rounding ones. Parameters are still
+    /// returned as f32.
+    pub fn new_f64(parameters: Option<&Parameters>) -> Result<Self> {
         Self::new_with_backend(parameters, NdArrayDevice::Cpu)
     }
 }
 
 impl<B: Backend> FSRS<B> {
+    /// Like [FSRS::new], on `device` of another backend. See also [FSRSBuilder].
     pub fn new_with_backend<B2: Backend>(
         parameters: Option<&Parameters>,
         device: B2::Device,
     ) -> Result<FSRS<B2>> {
-        let model = match parameters {
-            Some(params) => {
-                let parameters = check_and_fill_parameters(params)?;
-                let model = parameters_to_model::<B2>(&parameters);
-                Some(model)
-            }
-            None => None,
-        };
+        let builder = FSRSBuilder::<B2>::on_device(device);
+        match parameters {
+            Some(parameters) => builder.parameters(parameters),
+            None => builder,
+        }
+        .build()
+    }
+
+    /// Change some options of this instance, keeping its parameters unless new ones are set.
+    pub fn reconfigure(
+        self,
+        configure: impl FnOnce(FSRSBuilder<B>) -> FSRSBuilder<B>,
+    ) -> Result<Self> {
+        configure(self.into()).build()
+    }
+
+    /// Like [FSRS::reconfigure], for options that can't fail to apply.
+    fn reconfigure_infallible(
+        self,
+        configure: impl FnOnce(FSRSBuilder<B>) -> FSRSBuilder<B>,
+    ) -> Self {
+        self.reconfigure(configure)
+            .expect("the parameters and thread pool are kept")
+    }
+
+    /// See [FSRSBuilder::thread_pool].
+    pub fn with_thread_pool(self, thread_pool: Arc<ThreadPool>) -> Self {
+        self.reconfigure_infallible(|builder| builder.thread_pool(thread_pool))
+    }
 
-        Ok(FSRS { model, device })
+    /// See [FSRSBuilder::max_threads].
+    pub fn with_max_threads(self, max_threads: usize) -> Result<Self> {
+        self.reconfigure(|builder| builder.max_threads(max_threads))
     }
 
-    pub(crate) fn model(&self) -> &Model<B> {
-        self.model
+    /// Run `op` in the thread pool provided with [FSRS::with_thread_pool], if any.
+    pub(crate) fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.thread_pool {
+            Some(thread_pool) => thread_pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// See [FSRSBuilder::cancellation_token].
+    pub fn with_cancellation_token(self, token: CancellationToken) -> Self {
+        self.reconfigure_infallible(|builder| builder.cancellation_token(token))
+    }
+
+    pub(crate) fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+
+    /// Returns [FSRSError::Interrupted] if the token provided with
+    /// [FSRS::with_cancellation_token] has been cancelled.
+    pub(crate) fn check_cancelled(&self, stage: ProgressStage) -> Result<()> {
+        match &self.cancellation_token {
+            Some(token) if token.is_cancelled() => Err(FSRSError::Interrupted { stage }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn progress_handle(&self) -> Option<&ProgressHandle> {
+        self.progress_handle.as_ref()
+    }
+
+    /// Report `progress` to the [ProgressHandle] of this instance, if any, and to the
+    /// `callback` of the command. Returns [FSRSError::Interrupted] if the command was
+    /// cancelled, or either of them asks to stop.
+    pub(crate) fn report_progress(
+        &self,
+        stage: ProgressStage,
+        progress: ItemProgress,
+        callback: &mut impl FnMut(ItemProgress) -> bool,
+    ) -> Result<()> {
+        self.check_cancelled(stage)?;
+        let carry_on = self
+            .progress_handle
             .as_ref()
+            .map_or(true, |handle| handle.update(stage, progress));
+        if callback(progress) && carry_on {
+            Ok(())
+        } else {
+            Err(FSRSError::Interrupted { stage })
+        }
+    }
+
+    /// See [FSRSBuilder::event_hook].
+    pub fn with_event_hook(self, hook: impl Fn(&FSRSEvent) + Send + Sync + 'static) -> Self {
+        self.reconfigure_infallible(|builder| builder.event_hook(hook))
+    }
+
+    /// See [FSRSBuilder::training_config].
+    pub fn with_training_config(self, config: TrainingConfig) -> Self {
+        self.reconfigure_infallible(|builder| builder.training_config(config))
+    }
+
+    /// See [FSRSBuilder::forgetting_curve].
+    pub fn with_forgetting_curve(self, curve: CurveKind) -> Self {
+        self.reconfigure_infallible(|builder| builder.forgetting_curve(curve))
+    }
+
+    pub(crate) fn forgetting_curve(&self) -> CurveKind {
+        self.forgetting_curve
+    }
+
+    /// See [FSRSBuilder::linear_damping].
+    pub fn with_linear_damping(self, linear_damping: bool) -> Self {
+        self.reconfigure_infallible(|builder| builder.linear_damping(linear_damping))
+    }
+
+    pub(crate) fn linear_damping(&self) -> bool {
+        self.linear_damping
+    }
+
+    /// See [FSRSBuilder::exponential_initial_difficulty].
+    pub fn with_exponential_initial_difficulty(self, exponential: bool) -> Self {
+        self.reconfigure_infallible(|builder| builder.exponential_initial_difficulty(exponential))
+    }
+
+    pub(crate) fn exponential_initial_difficulty(&self) -> bool {
+        self.exponential_initial_difficulty
+    }
+
+    pub(crate) fn training_config(&self) -> Option<&TrainingConfig> {
+        self.training_config.as_ref()
+    }
+
+    pub(crate) fn emit(&self, event: FSRSEvent) {
+        if let Some(hook) = &self.event_hook {
+            hook.emit(event);
+        }
+    }
+
+    /// Replace the parameters, eg with newly trained ones. Commands already running keep
+    /// using the previous parameters; commands started afterwards use the new ones.
+    pub fn set_parameters(&self, parameters: &Parameters) -> Result<()> {
+        let parameters =
+            check_and_fill_parameters(parameters, self.exponential_initial_difficulty)?;
+        let mut model = parameters_to_model::<B>(&parameters, self.exponential_initial_difficulty);
+        model.config.forgetting_curve = self.forgetting_curve;
+        model.config.linear_damping = self.linear_damping;
+        *self.model_slot() = Some(model);
+        self.emit(FSRSEvent::ParametersUpdated { parameters });
+        Ok(())
+    }
+
+    /// The model slot. A panic while it was held can't leave the model half-replaced, so
+    /// poisoning is ignored rather than making every later command panic too.
+    fn model_slot(&self) -> MutexGuard<'_, Option<Model<B>>> {
+        self.model.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn model(&self) -> Model<B> {
+        self.model_slot()
+            .clone()
             .expect("command requires parameters to be set on creation")
     }
 
+    /// The forgetting curve of the parameters, with the fixed decay if there are none.
+    pub(crate) fn curve(&self) -> Curve {
+        let decay = self
+            .model_slot()
+            .as_ref()
+            .map_or(DECAY as f32, Model::decay);
+        self.forgetting_curve.curve(decay)
+    }
+
     pub(crate) fn device(&self) -> B::Device {
         self.device.clone()
     }
 }
 
-pub(crate) fn parameters_to_model<B: Backend>(parameters: &Parameters) -> Model<B> {
-    let config = ModelConfig::default();
+impl<B: Backend> fmt::Debug for FSRS<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FSRS")
+            .field("model", &self.model)
+            .field("device", &self.device)
+            .field("thread_pool", &self.thread_pool)
+            .field("cancellation_token", &self.cancellation_token)
+            .field("progress_handle", &self.progress_handle)
+            .field("event_hook", &self.event_hook)
+            // burn configs only implement Display, as JSON
+            .field(
+                "training_config",
+                &self.training_config.as_ref().map(ToString::to_string),
+            )
+            .field("forgetting_curve", &self.forgetting_curve)
+            .field("linear_damping", &self.linear_damping)
+            .field(
+                "exponential_initial_difficulty",
+                &self.exponential_initial_difficulty,
+            )
+            .finish()
+    }
+}
+
+impl<B: Backend> Clone for FSRS<B> {
+    fn clone(&self) -> Self {
+        Self {
+            model: Mutex::new(self.model_slot().clone()),
+            device: self.device.clone(),
+            thread_pool: self.thread_pool.clone(),
+            cancellation_token: self.cancellation_token.clone(),
+            progress_handle: self.progress_handle.clone(),
+            event_hook: self.event_hook.clone(),
+            training_config: self.training_config.clone(),
+            forgetting_curve: self.forgetting_curve,
+            linear_damping: self.linear_damping,
+            exponential_initial_difficulty: self.exponential_initial_difficulty,
+        }
+    }
+}
+
+pub(crate) fn parameters_to_model<B: Backend>(
+    parameters: &Parameters,
+    exponential_initial_difficulty: bool,
+) -> Model<B> {
+    let parameters = clip_parameters(parameters, exponential_initial_difficulty);
+    let config = ModelConfig {
+        trainable_decay: parameters.len() > 19,
+        exponential_initial_difficulty,
+        ..Default::default()
+    };
     let mut model = Model::new(config);
+    let count = parameters.len();
     model.w = Param::from_tensor(Tensor::from_floats(
-        Data::new(clip_parameters(parameters), Shape { dims: [19] }),
+        Data::new(parameters, Shape { dims: [count] }),
         &B::Device::default(),
     ));
     model
 }
 
-pub(crate) fn check_and_fill_parameters(parameters: &Parameters) -> Result<Vec<f32>, FSRSError> {
+/// Why a set of parameters was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidParametersReason {
+    /// Only 17 (FSRS-4.5), 19, or 20 (with a trained decay) parameters are supported.
+    WrongCount { count: usize },
+    /// The parameter at `index` is NaN or infinite.
+    NotFinite { index: usize },
+}
+
+impl std::fmt::Display for InvalidParametersReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongCount { count } => {
+                write!(f, "expected 17, 19 or 20 parameters, got {count}")
+            }
+            Self::NotFinite { index } => write!(f, "parameter {index} is not finite"),
+        }
+    }
+}
+
+/// Fill in defaults for empty parameters, and convert those of FSRS-4.5. Their initial
+/// difficulty is converted to the exponential formula, unless
+/// [ModelConfig::exponential_initial_difficulty] is off.
+pub(crate) fn check_and_fill_parameters(
+    parameters: &Parameters,
+    exponential_initial_difficulty: bool,
+) -> Result<Vec<f32>, FSRSError> {
     let parameters = match parameters.len() {
         0 => DEFAULT_PARAMETERS.to_vec(),
         17 => {
             let mut parameters = parameters.to_vec();
-            parameters[4] = parameters[5].mul_add(2.0, parameters[4]);
-            parameters[5] = parameters[5].mul_add(3.0, 1.0).ln() / 3.0;
+            if exponential_initial_difficulty {
+                parameters[4] = parameters[5].mul_add(2.0, parameters[4]);
+                parameters[5] = parameters[5].mul_add(3.0, 1.0).ln() / 3.0;
+            }
             parameters[6] += 0.5;
             parameters.extend_from_slice(&[0.0, 0.0]);
             parameters
         }
-        19 => parameters.to_vec(),
-        _ => return Err(FSRSError::InvalidParameters),
+        19 | 20 => parameters.to_vec(),
+        count => {
+            return Err(FSRSError::InvalidParameters {
+                reason: InvalidParametersReason::WrongCount { count },
+            })
+        }
     };
-    if parameters.iter().any(|&w| !w.is_finite()) {
-        return Err(FSRSError::InvalidParameters);
+    if let Some(index) = parameters.iter().position(|w| !w.is_finite()) {
+        return Err(FSRSError::InvalidParameters {
+            reason: InvalidParametersReason::NotFinite { index },
+        });
     }
     Ok(parameters)
 }
@@ -292,7 +659,7 @@ pub(crate) fn check_and_fill_parameters(parameters: &Parameters) -> Result<Vec<f
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_helpers::{assert_approx_eq, Model, Tensor};
+    use crate::test_helpers::{assert_approx_eq, item_from, Model, Tensor};
     use burn::tensor::Data;
 
     #[test]
@@ -307,14 +674,18 @@ mod tests {
             0.4, 0.6, 2.4, 5.8, 4.93, 0.94, 0.86, 0.01, 1.49, 0.14, 0.94, 2.18, 0.05, 0.34, 1.26,
             0.29, 2.61,
         ];
-        let fsrs5_param = check_and_fill_parameters(&fsrs4dot5_param).unwrap();
+        let fsrs5_param = check_and_fill_parameters(&fsrs4dot5_param, true).unwrap();
         assert_eq!(
             fsrs5_param,
             vec![
                 0.4, 0.6, 2.4, 5.8, 6.81, 0.44675013, 1.36, 0.01, 1.49, 0.14, 0.94, 2.18, 0.05,
                 0.34, 1.26, 0.29, 2.61, 0.0, 0.0,
             ]
-        )
+        );
+        // kept as they are for the linear initial difficulty of FSRS-4.5
+        let linear_param = check_and_fill_parameters(&fsrs4dot5_param, false).unwrap();
+        assert_eq!(linear_param[4..6], [4.93, 0.94]);
+        assert_eq!(linear_param[6], 1.36);
     }
 
     #[test]
@@ -330,6 +701,84 @@ mod tests {
         )
     }
 
+    #[test]
+    fn trainable_decay() {
+        let device = NdArrayDevice::Cpu;
+        let model = Model::new(ModelConfig::default().with_trainable_decay(true));
+        assert_eq!(model.w.dims(), [20]);
+        assert_eq!(model.decay(), DECAY as f32);
+        let delta_t = Tensor::from_floats([0.0, 1.0, 4.0, 5.0], &device);
+        let stability = Tensor::from_floats([1.0, 2.0, 4.0, 2.0], &device);
+        let retention = model.power_forgetting_curve(delta_t.clone(), stability.clone());
+        retention
+            .to_data()
+            .assert_approx_eq(&Data::from([1.0, 0.946059, 0.9, 0.79394597]), 5);
+
+        // a smaller decay still gives 90% retention after `stability` days, with a flatter tail
+        let mut parameters = DEFAULT_PARAMETERS.to_vec();
+        parameters.push(0.2);
+        let model: Model = parameters_to_model(&parameters, true);
+        assert_eq!(model.decay(), -0.2);
+        let retention: Vec<f32> = model
+            .power_forgetting_curve(delta_t, stability)
+            .to_data()
+            .value;
+        assert_approx_eq([retention[2], 0.0], [0.9, 0.0]);
+        assert!(retention[1] < 0.946059 && retention[3] > 0.79394597);
+    }
+
+    #[test]
+    fn exponential_forgetting_curve() {
+        let device = NdArrayDevice::Cpu;
+        let config = ModelConfig::default().with_forgetting_curve(CurveKind::Exponential);
+        let model = Model::new(config);
+        let delta_t = Tensor::from_floats([0.0, 2.0, 8.0], &device);
+        let stability = Tensor::from_floats([1.0, 2.0, 4.0], &device);
+        let retention = model.forgetting_curve(delta_t, stability);
+        retention
+            .to_data()
+            .assert_approx_eq(&Data::from([1.0, 0.9, 0.81]), 5);
+    }
+
+    #[test]
+    fn frozen_parameters() {
+        let config = ModelConfig::default();
+        assert_eq!(config.frozen_parameters(19), Ok(vec![false; 19]));
+        let config = ModelConfig {
+            freeze_mask: Some(vec![false, false, false, false, true, true, true, true]),
+            freeze_short_term_stability: true,
+            ..Default::default()
+        };
+        let frozen = config.frozen_parameters(20).unwrap();
+        let indices: Vec<_> = (0..20).filter(|&i| frozen[i]).collect();
+        assert_eq!(indices, [4, 5, 6, 7, 17, 18]);
+        assert_eq!(config.frozen_parameters(7), Err(FSRSError::InvalidInput));
+    }
+
+    #[test]
+    fn initial_parameters() {
+        let mut parameters = DEFAULT_PARAMETERS.to_vec();
+        parameters[0] = 1.0;
+        parameters[10] = 2.0;
+        parameters.push(0.3);
+        let config = ModelConfig {
+            initial_parameters: Some(parameters.clone()),
+            trainable_decay: true,
+            ..Default::default()
+        };
+        let w = |config: ModelConfig| Model::new(config).w.val().to_data().value;
+        assert_eq!(w(config.clone()), parameters);
+        let config = ModelConfig {
+            initial_stability: Some([0.5, 1.0, 2.0, 3.0]),
+            trainable_decay: false,
+            ..config
+        };
+        assert_eq!(
+            w(config)[..11],
+            [[0.5, 1.0, 2.0, 3.0].as_slice(), &parameters[4..11]].concat()
+        );
+    }
+
     #[test]
     fn init_stability() {
         let device = NdArrayDevice::Cpu;
@@ -368,6 +817,32 @@ mod tests {
         )
     }
 
+    #[test]
+    fn linear_initial_difficulty() -> Result<()> {
+        let device = NdArrayDevice::Cpu;
+        let config = ModelConfig::default().with_exponential_initial_difficulty(false);
+        let model = Model::new(config);
+        let rating = Tensor::from_floats([1.0, 2.0, 3.0, 4.0], &device);
+        let difficulty: Vec<f32> = model.init_difficulty(rating).to_data().value;
+        let (w4, w5) = (DEFAULT_PARAMETERS[4], DEFAULT_PARAMETERS[5]);
+        assert_approx_eq(
+            [difficulty[0], difficulty[1], difficulty[2], difficulty[3]],
+            [w4 + 2.0 * w5, w4 + w5, w4, w4 - w5],
+        );
+
+        // FSRS-4.5 parameters give the same initial difficulty as FSRS-4.5 did
+        let fsrs4dot5_param = [
+            0.4, 0.6, 2.4, 5.8, 4.93, 0.94, 0.86, 0.01, 1.49, 0.14, 0.94, 2.18, 0.05, 0.34, 1.26,
+            0.29, 2.61,
+        ];
+        let fsrs = FSRS::new(None)?.with_exponential_initial_difficulty(false);
+        fsrs.set_parameters(&fsrs4dot5_param)?;
+        let item = item_from(&[(1, 0)]);
+        let state = fsrs.memory_state(item, None)?;
+        assert_approx_eq([state.difficulty, 0.0], [4.93 + 2.0 * 0.94, 0.0]);
+        Ok(())
+    }
+
     #[test]
     fn forward() {
         let device = NdArrayDevice::Cpu;
@@ -419,6 +894,21 @@ mod tests {
         )
     }
 
+    #[test]
+    fn next_difficulty_without_linear_damping() {
+        let device = NdArrayDevice::Cpu;
+        let model = Model::new(ModelConfig::default().with_linear_damping(false));
+        let difficulty = Tensor::from_floats([5.0, 9.5, 9.5, 9.5], &device);
+        let rating = Tensor::from_floats([1.0, 1.0, 3.0, 4.0], &device);
+        let next_difficulty: Vec<f32> = model.next_difficulty(difficulty, rating).to_data().value;
+        let w6 = DEFAULT_PARAMETERS[6];
+        assert_approx_eq(
+            [next_difficulty[0], next_difficulty[1]],
+            [5.0 + 2.0 * w6, 9.5 + 2.0 * w6],
+        );
+        assert_approx_eq([next_difficulty[2], next_difficulty[3]], [9.5, 9.5 - w6]);
+    }
+
     #[test]
     fn next_stability() {
         let device = NdArrayDevice::Cpu;
@@ -460,8 +950,71 @@ mod tests {
     #[test]
     fn fsrs() {
         assert!(FSRS::new(Some(&[])).is_ok());
-        assert!(FSRS::new(Some(&[1.])).is_err());
+        assert_eq!(
+            FSRS::new(Some(&[1.])).unwrap_err(),
+            FSRSError::InvalidParameters {
+                reason: InvalidParametersReason::WrongCount { count: 1 }
+            }
+        );
+        let mut parameters = DEFAULT_PARAMETERS;
+        parameters[7] = f32::NAN;
+        assert_eq!(
+            FSRS::new(Some(&parameters)).unwrap_err().to_string(),
+            "invalid parameters: parameter 7 is not finite"
+        );
         assert!(FSRS::new(Some(DEFAULT_PARAMETERS.as_slice())).is_ok());
         assert!(FSRS::new(Some(&DEFAULT_PARAMETERS[..17])).is_ok());
+        let with_decay = [&DEFAULT_PARAMETERS[..], &[0.3]].concat();
+        assert!(FSRS::new(Some(&with_decay)).is_ok());
+    }
+
+    #[test]
+    fn thread_pool() -> Result<()> {
+        let fsrs = FSRS::new(Some(&[]))?;
+        assert_eq!(
+            fsrs.install(rayon::current_num_threads),
+            rayon::current_num_threads()
+        );
+        let fsrs = fsrs.with_max_threads(3)?;
+        assert_eq!(fsrs.install(rayon::current_num_threads), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn shared() -> Result<()> {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        let fsrs = Arc::new(FSRS::new(Some(&[]))?);
+        assert_send_sync(&fsrs);
+        let clone = (*fsrs).clone();
+        let mut parameters = DEFAULT_PARAMETERS;
+        parameters[0] = 1.0;
+        std::thread::scope(|s| s.spawn(|| fsrs.set_parameters(&parameters)).join().unwrap())?;
+        let initial_stability = |fsrs: &FSRS| -> f32 { fsrs.model().w.val().to_data().value[0] };
+        assert_eq!(initial_stability(&fsrs), 1.0);
+        // clones keep their own parameters
+        assert_eq!(initial_stability(&clone), DEFAULT_PARAMETERS[0]);
+        assert!(fsrs.set_parameters(&[1.0]).is_err());
+        assert_eq!(initial_stability(&fsrs), 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn survives_poisoning() -> Result<()> {
+        let fsrs = FSRS::new(Some(&[]))?;
+        let _ = std::thread::scope(|s| {
+            s.spawn(|| {
+                let _guard = fsrs.model_slot();
+                panic!("training thread panicked");
+            })
+            .join()
+        });
+        assert!(fsrs.model.is_poisoned());
+        fsrs.set_parameters(&DEFAULT_PARAMETERS)?;
+        assert_eq!(
+            fsrs.model().w.val().to_data().value[0],
+            DEFAULT_PARAMETERS[0]
+        );
+        let _ = fsrs.clone();
+        Ok(())
     }
 }
@@ -1,17 +1,22 @@
+use crate::dataset::{FSRSItem, FSRSReview, ItemMetadata};
 use crate::error::{FSRSError, Result};
-use crate::inference::{next_interval, ItemProgress, Parameters, DECAY, FACTOR, S_MAX, S_MIN};
+use crate::events::FSRSEvent;
+use crate::forgetting_curve::{CurveKind, ForgettingCurve};
+use crate::inference::{decay_of, ItemProgress, Parameters, S_MAX, S_MIN};
 use crate::model::check_and_fill_parameters;
 use crate::parameter_clipper::clip_parameters;
+use crate::progress::{CancellationToken, ProgressStage};
 use crate::FSRS;
 use burn::tensor::backend::Backend;
 use itertools::{izip, Itertools};
 use ndarray::Array1;
-use ndarray_rand::rand_distr::Distribution;
+use ndarray_rand::rand_distr::{Distribution, StandardNormal};
 use priority_queue::PriorityQueue;
 use rand::Rng;
 use rand::{distributions::WeightedIndex, rngs::StdRng, SeedableRng};
 use rayon::iter::IntoParallelIterator;
 use rayon::iter::ParallelIterator;
+use serde::{Deserialize, Serialize};
 use std::cmp::Reverse;
 use std::collections::HashMap;
 
@@ -21,6 +26,9 @@ pub struct SimulationResult {
     pub review_cnt_per_day: Array1<usize>,
     pub learn_cnt_per_day: Array1<usize>,
     pub cost_per_day: Array1<f32>,
+    /// The state of each card at the end of the simulation: the existing cards that were
+    /// simulated, in the order they were passed in, followed by the new cards.
+    pub cards: Vec<SimulatedCard>,
 }
 
 trait Round {
@@ -36,7 +44,8 @@ impl Round for f32 {
 const R_MIN: f32 = 0.70;
 const R_MAX: f32 = 0.95;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimulatorConfig {
     pub deck_size: usize,
     pub learn_span: usize,
@@ -44,16 +53,70 @@ pub struct SimulatorConfig {
     pub max_ivl: f32,
     pub learn_costs: [f32; 4],
     pub review_costs: [f32; 4],
+    /// The standard deviations of `learn_costs` for each rating, to sample the cost of each
+    /// review from a log-normal distribution with the cost as its mean, as answer times are
+    /// heavy-tailed. None to use the costs as they are.
+    pub learn_cost_stddevs: Option<[f32; 4]>,
+    /// The standard deviations of `review_costs`, like `learn_cost_stddevs`.
+    pub review_cost_stddevs: Option<[f32; 4]>,
+    /// Multiply the cost of a review by the card's difficulty, one multiplier for each of ten
+    /// equal bands of difficulty from 1 to 10, as difficult cards take longer to answer. See
+    /// [extract_difficulty_cost_multipliers].
+    pub difficulty_cost_multipliers: Option<[f32; 10]>,
     pub first_rating_prob: [f32; 4],
     pub review_rating_prob: [f32; 3],
+    /// The probability of recalling an existing card on its first review in the
+    /// simulation, instead of its retrievability, eg for cards migrated from another
+    /// scheduler, whose memory states are only estimates. Later reviews use the
+    /// retrievability as usual.
+    pub existing_card_recall: Option<f32>,
+    /// The probabilities of hard, good and easy when an existing card is recalled on its
+    /// first review in the simulation, instead of `review_rating_prob`.
+    pub existing_review_rating_prob: Option<[f32; 3]>,
     pub first_rating_offsets: [f32; 4],
     pub first_session_lens: [f32; 4],
     pub forget_rating_offset: f32,
     pub forget_session_len: f32,
     pub loss_aversion: f32,
+    /// Time spent on the relearning steps on the day of a lapse, on top of the cost of the
+    /// failed review, which `loss_aversion` applies to.
+    pub relearning_cost: f32,
+    /// The interval after a lapse, eg 1.0 for a relearning step on the next day, as with
+    /// Anki's default presets. By default, FSRS schedules the card like after any other
+    /// review.
+    pub lapse_interval: Option<f32>,
+    /// Suspend a card once it has been forgotten this many times during the simulation, as
+    /// Anki does with leeches. Suspended cards are not reviewed again, and no longer count
+    /// as memorized.
+    pub suspend_after_lapses: Option<u32>,
+    /// The probability of a review being done 0, 1, 2... days after the card is due, for
+    /// users who don't review every card on time. The retrievability is computed from the
+    /// actual elapsed time. Empty to review every card when due.
+    pub review_delay_probs: Vec<f32>,
+    /// The probability of no cards being learnt or reviewed on a day.
+    pub skip_day_prob: f32,
     pub learn_limit: usize,
     pub review_limit: usize,
     pub new_cards_ignore_review_limit: bool,
+    /// Override `learn_limit` for each day of the week, starting from Monday, eg to learn
+    /// no new cards at weekends.
+    pub learn_limits_per_weekday: Option<[usize; 7]>,
+    /// Override `review_limit` for each day of the week, starting from Monday.
+    pub review_limits_per_weekday: Option<[usize; 7]>,
+    /// Override `max_cost_perday` for each day of the week, starting from Monday, eg to
+    /// review less on easy days.
+    pub max_costs_per_weekday: Option<[f32; 7]>,
+    /// The day of the week of the first simulated day, 0 for Monday.
+    pub first_weekday: usize,
+    /// The curve the cards are forgotten on, which should be the one the parameters were
+    /// trained on.
+    pub forgetting_curve: CurveKind,
+    /// Whether the parameters were trained with
+    /// [ModelConfig::linear_damping](crate::ModelConfig::linear_damping).
+    pub linear_damping: bool,
+    /// Whether the parameters were trained with
+    /// [ModelConfig::exponential_initial_difficulty](crate::ModelConfig::exponential_initial_difficulty).
+    pub exponential_initial_difficulty: bool,
 }
 
 impl Default for SimulatorConfig {
@@ -65,21 +128,38 @@ impl Default for SimulatorConfig {
             max_ivl: 36500.0,
             learn_costs: [33.79, 24.3, 13.68, 6.5],
             review_costs: [23.0, 11.68, 7.33, 5.6],
+            learn_cost_stddevs: None,
+            review_cost_stddevs: None,
+            difficulty_cost_multipliers: None,
             first_rating_prob: [0.24, 0.094, 0.495, 0.171],
             review_rating_prob: [0.224, 0.631, 0.145],
+            existing_card_recall: None,
+            existing_review_rating_prob: None,
             first_rating_offsets: [-0.72, -0.15, -0.01, 0.0],
             first_session_lens: [2.02, 1.28, 0.81, 0.0],
             forget_rating_offset: -0.28,
             forget_session_len: 1.05,
             loss_aversion: 2.5,
+            relearning_cost: 0.0,
+            lapse_interval: None,
+            suspend_after_lapses: None,
+            review_delay_probs: vec![],
+            skip_day_prob: 0.0,
             learn_limit: usize::MAX,
             review_limit: usize::MAX,
             new_cards_ignore_review_limit: true,
+            learn_limits_per_weekday: None,
+            review_limits_per_weekday: None,
+            max_costs_per_weekday: None,
+            first_weekday: 0,
+            forgetting_curve: CurveKind::Power,
+            linear_damping: true,
+            exponential_initial_difficulty: true,
         }
     }
 }
 
-fn stability_after_success(w: &[f32], s: f32, r: f32, d: f32, rating: usize) -> f32 {
+pub(crate) fn stability_after_success(w: &[f32], s: f32, r: f32, d: f32, rating: usize) -> f32 {
     let hard_penalty = if rating == 2 { w[15] } else { 1.0 };
     let easy_bonus = if rating == 4 { w[16] } else { 1.0 };
     (s * (f32::exp(w[8])
@@ -91,7 +171,7 @@ fn stability_after_success(w: &[f32], s: f32, r: f32, d: f32, rating: usize) ->
     .clamp(S_MIN, S_MAX)
 }
 
-fn stability_after_failure(w: &[f32], s: f32, r: f32, d: f32) -> f32 {
+pub(crate) fn stability_after_failure(w: &[f32], s: f32, r: f32, d: f32) -> f32 {
     let new_s_min = s / (w[17] * w[18]).exp();
     let new_s =
         (w[11] * d.powf(-w[12]) * ((s + 1.0).powf(w[13]) - 1.0) * f32::exp((1.0 - r) * w[14]))
@@ -103,12 +183,17 @@ fn stability_short_term(w: &[f32], s: f32, rating_offset: f32, session_len: f32)
     (s * (w[17] * (rating_offset + session_len * w[18])).exp()).clamp(S_MIN, S_MAX)
 }
 
-fn init_d(w: &[f32], rating: usize) -> f32 {
-    w[4] - (w[5] * (rating - 1) as f32).exp() + 1.0
+/// See [ModelConfig::exponential_initial_difficulty](crate::ModelConfig::exponential_initial_difficulty).
+pub(crate) fn init_d(w: &[f32], rating: usize, exponential: bool) -> f32 {
+    if exponential {
+        w[4] - (w[5] * (rating - 1) as f32).exp() + 1.0
+    } else {
+        w[4] - w[5] * (rating as f32 - 3.0)
+    }
 }
 
-fn init_d_with_short_term(w: &[f32], rating: usize, rating_offset: f32) -> f32 {
-    let new_d = init_d(w, rating) - w[6] * rating_offset;
+fn init_d_with_short_term(w: &[f32], rating: usize, rating_offset: f32, exponential: bool) -> f32 {
+    let new_d = init_d(w, rating, exponential) - w[6] * rating_offset;
     new_d.clamp(1.0, 10.0)
 }
 
@@ -116,21 +201,31 @@ fn linear_damping(delta_d: f32, old_d: f32) -> f32 {
     (10.0 - old_d) / 9.0 * delta_d
 }
 
-fn next_d(w: &[f32], d: f32, rating: usize) -> f32 {
+/// See [ModelConfig::linear_damping](crate::ModelConfig::linear_damping).
+pub(crate) fn next_d(w: &[f32], d: f32, rating: usize, damped: bool, exponential: bool) -> f32 {
     let delta_d = -w[6] * (rating as f32 - 3.0);
-    let new_d = d + linear_damping(delta_d, d);
-    mean_reversion(w, init_d(w, 4), new_d).clamp(1.0, 10.0)
+    let new_d = if damped {
+        d + linear_damping(delta_d, d)
+    } else {
+        d + delta_d
+    };
+    mean_reversion(w, init_d(w, 4, exponential), new_d).clamp(1.0, 10.0)
 }
 
 fn mean_reversion(w: &[f32], init: f32, current: f32) -> f32 {
     w[7] * init + (1.0 - w[7]) * current
 }
 
-fn power_forgetting_curve(t: f32, s: f32) -> f32 {
-    (t / s).mul_add(FACTOR as f32, 1.0).powf(DECAY as f32)
+/// The band of `difficulty` among ten equal bands from 1 to 10.
+fn difficulty_band(difficulty: f32) -> usize {
+    (((difficulty - 1.0) / 0.9) as usize).min(9)
 }
 
-#[derive(Debug, Clone)]
+/// A card to seed a simulation with. Dates are in days since the start of the simulation,
+/// and may be fractional, eg `-(elapsed_seconds as f32) / 86400.0` for a card last reviewed
+/// at a specific time: the card is reviewed on the day `due` falls in, with
+/// `due - last_date` as the time elapsed since its last review.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Card {
     pub difficulty: f32,
     pub stability: f32,
@@ -138,6 +233,42 @@ pub struct Card {
     pub due: f32,
 }
 
+/// A card at the end of a simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedCard {
+    /// The memory state and dates of the card, in days since the start of the simulation.
+    /// The stability and dates are negative infinity if the card was never learnt.
+    pub card: Card,
+    /// The interval the card was last scheduled with, or 0 if it was never learnt.
+    pub interval: f32,
+    /// The number of times the card was learnt or reviewed during the simulation.
+    pub review_cnt: usize,
+    /// The number of reviews during the simulation that were forgotten.
+    pub lapse_cnt: usize,
+    /// Whether the card was suspended after `suspend_after_lapses` lapses.
+    pub suspended: bool,
+}
+
+impl SimulatedCard {
+    /// The card as one of the `existing_cards` of a simulation that starts `days` after the
+    /// start of this one, eg its `learn_span`. None if the card was never learnt, or was
+    /// suspended.
+    pub fn to_existing_card(&self, days: usize) -> Option<Card> {
+        let Card {
+            difficulty,
+            stability,
+            last_date,
+            due,
+        } = self.card;
+        (last_date != f32::NEG_INFINITY && !self.suspended).then_some(Card {
+            difficulty,
+            stability,
+            last_date: last_date - days as f32,
+            due: due - days as f32,
+        })
+    }
+}
+
 pub fn simulate(
     config: &SimulatorConfig,
     w: &Parameters,
@@ -145,8 +276,76 @@ pub fn simulate(
     seed: Option<u64>,
     existing_cards: Option<Vec<Card>>,
 ) -> Result<SimulationResult, FSRSError> {
-    let w = &check_and_fill_parameters(w)?;
-    let w = &clip_parameters(w);
+    simulate_and_log(
+        config,
+        w,
+        desired_retention,
+        seed,
+        existing_cards,
+        None,
+        |_| Ok(()),
+    )
+}
+
+/// The cost of each rating, sampled from log-normal distributions with the given means and
+/// standard deviations, or constant without them.
+struct Costs {
+    means: [f32; 4],
+    /// The standard deviations of the logs of the costs.
+    sigmas: Option<[f32; 4]>,
+}
+
+impl Costs {
+    fn new(means: [f32; 4], stddevs: Option<[f32; 4]>) -> Result<Self, FSRSError> {
+        let sigmas = stddevs
+            .map(|stddevs| {
+                let mut sigmas = [0.0; 4];
+                for (sigma, mean, stddev) in izip!(&mut sigmas, means, stddevs) {
+                    if mean < 0.0 || stddev < 0.0 || !stddev.is_finite() {
+                        return Err(FSRSError::InvalidInput);
+                    }
+                    if mean > 0.0 {
+                        *sigma = (stddev / mean).powi(2).ln_1p().sqrt();
+                    }
+                }
+                Ok(sigmas)
+            })
+            .transpose()?;
+        Ok(Self { means, sigmas })
+    }
+
+    fn sample(&self, rating: usize, rng: &mut StdRng) -> f32 {
+        let mean = self.means[rating - 1];
+        match self.sigmas {
+            Some(sigmas) => {
+                let sigma = sigmas[rating - 1];
+                let z: f32 = StandardNormal.sample(rng);
+                mean * (sigma * z - sigma * sigma / 2.0).exp()
+            }
+            None => mean,
+        }
+    }
+}
+
+/// A review done during the simulation.
+struct SimulatedReview {
+    card_index: usize,
+    day: usize,
+    rating: usize,
+}
+
+fn simulate_and_log(
+    config: &SimulatorConfig,
+    w: &Parameters,
+    desired_retention: f32,
+    seed: Option<u64>,
+    existing_cards: Option<Vec<Card>>,
+    mut review_log: Option<&mut Vec<SimulatedReview>>,
+    mut on_day: impl FnMut(usize) -> Result<(), FSRSError>,
+) -> Result<SimulationResult, FSRSError> {
+    let w = &check_and_fill_parameters(w, config.exponential_initial_difficulty)?;
+    let w = &clip_parameters(w, config.exponential_initial_difficulty);
+    let curve = config.forgetting_curve.curve(decay_of(w));
     let SimulatorConfig {
         deck_size,
         learn_span,
@@ -154,20 +353,62 @@ pub fn simulate(
         max_ivl,
         learn_costs,
         review_costs,
+        learn_cost_stddevs,
+        review_cost_stddevs,
+        difficulty_cost_multipliers,
         first_rating_prob,
         review_rating_prob,
+        existing_card_recall,
+        existing_review_rating_prob,
         first_rating_offsets,
         first_session_lens,
         forget_rating_offset,
         forget_session_len,
         loss_aversion,
+        relearning_cost,
+        lapse_interval,
+        suspend_after_lapses,
+        review_delay_probs,
+        skip_day_prob,
         learn_limit,
         review_limit,
         new_cards_ignore_review_limit,
+        learn_limits_per_weekday,
+        review_limits_per_weekday,
+        max_costs_per_weekday,
+        first_weekday,
+        forgetting_curve: _,
+        linear_damping,
+        exponential_initial_difficulty,
     } = config.clone();
+    let weekday = |day: usize| (first_weekday + day) % 7;
+    let learn_limit_on =
+        |day: usize| learn_limits_per_weekday.map_or(learn_limit, |limits| limits[weekday(day)]);
+    let review_limit_on =
+        |day: usize| review_limits_per_weekday.map_or(review_limit, |limits| limits[weekday(day)]);
+    let max_cost_on =
+        |day: usize| max_costs_per_weekday.map_or(max_cost_perday, |costs| costs[weekday(day)]);
     if deck_size == 0 {
         return Err(FSRSError::InvalidDeckSize);
     }
+    if !(0.0..=1.0).contains(&skip_day_prob)
+        || suspend_after_lapses == Some(0)
+        || existing_card_recall.is_some_and(|recall| !(0.0..=1.0).contains(&recall))
+        || difficulty_cost_multipliers.is_some_and(|multipliers| {
+            multipliers
+                .iter()
+                .any(|multiplier| *multiplier < 0.0 || !multiplier.is_finite())
+        })
+    {
+        return Err(FSRSError::InvalidInput);
+    }
+    let sampled_learn_costs = Costs::new(learn_costs, learn_cost_stddevs)?;
+    let sampled_review_costs = Costs::new(review_costs, review_cost_stddevs)?;
+    let review_delay_dist = if review_delay_probs.is_empty() {
+        None
+    } else {
+        Some(WeightedIndex::new(&review_delay_probs).map_err(|_| FSRSError::InvalidInput)?)
+    };
 
     let mut review_cnt_per_day = Array1::<usize>::zeros(learn_span);
     let mut learn_cnt_per_day = Array1::<usize>::zeros(learn_span);
@@ -179,12 +420,24 @@ pub fn simulate(
 
     let review_rating_choices = [2, 3, 4];
     let review_rating_dist = WeightedIndex::new(review_rating_prob).unwrap();
+    let existing_review_rating_dist = existing_review_rating_prob
+        .map(WeightedIndex::new)
+        .transpose()
+        .map_err(|_| FSRSError::InvalidInput)?;
 
     let mut rng = StdRng::seed_from_u64(seed.unwrap_or(42));
 
+    let skipped_days: Vec<bool> = if skip_day_prob > 0.0 {
+        (0..learn_span)
+            .map(|_| rng.gen_bool(skip_day_prob as f64))
+            .collect()
+    } else {
+        vec![false; learn_span]
+    };
+
     let mut cards = Vec::with_capacity(deck_size);
 
-    let fail_cost = review_costs[0] * loss_aversion;
+    let fail_cost = review_costs[0] * loss_aversion + relearning_cost;
 
     let existing_count = if let Some(existing_cards) = &existing_cards {
         existing_cards.len()
@@ -204,17 +457,42 @@ pub fn simulate(
         );
     }
 
-    if learn_limit > 0 {
-        let init_ratings = (0..(deck_size - cards.len())).map(|i| Card {
-            difficulty: f32::NEG_INFINITY,
-            stability: f32::NEG_INFINITY,
-            last_date: f32::NEG_INFINITY,
-            due: (i / learn_limit) as f32,
-        });
+    // the existing cards come first
+    let existing_card_count = cards.len();
 
-        cards.extend(init_ratings);
+    let new_card = |due: usize| Card {
+        difficulty: f32::NEG_INFINITY,
+        stability: f32::NEG_INFINITY,
+        last_date: f32::NEG_INFINITY,
+        due: due as f32,
+    };
+    let new_card_count = deck_size - cards.len();
+    if learn_limits_per_weekday.is_some() {
+        // introduce the cards on the days they can be learnt; the rest are never learnt
+        let mut learn_days = (0..learn_span)
+            .flat_map(|day| std::iter::repeat(day).take(learn_limit_on(day).min(new_card_count)));
+        cards
+            .extend((0..new_card_count).map(|_| new_card(learn_days.next().unwrap_or(learn_span))));
+    } else if learn_limit > 0 {
+        cards.extend((0..new_card_count).map(|i| new_card(i / learn_limit)));
     }
 
+    let mut intervals: Vec<f32> = cards
+        .iter()
+        .map(|card| {
+            if card.last_date == f32::NEG_INFINITY {
+                0.0
+            } else {
+                card.due - card.last_date
+            }
+        })
+        .collect();
+    let mut review_cnts = vec![0; cards.len()];
+    let mut lapse_cnts = vec![0; cards.len()];
+    let mut suspended = vec![false; cards.len()];
+    // whether the delay of the card's next review has been sampled
+    let mut delayed = vec![false; cards.len()];
+
     let mut card_priorities = PriorityQueue::new();
 
     fn card_priority(card: &Card, learn: bool) -> Reverse<(i32, bool, i32)> {
@@ -227,11 +505,18 @@ pub fn simulate(
     }
 
     // Main simulation loop
+    let mut current_day = None;
     while let Some((&card_index, _)) = card_priorities.peek() {
         let card = &mut cards[card_index];
 
         let day_index = card.due as usize;
 
+        // cards are processed in due order, so this runs once per simulated day
+        if current_day != Some(day_index) {
+            current_day = Some(day_index);
+            on_day(day_index)?;
+        }
+
         let is_learn = card.last_date == f32::NEG_INFINITY;
 
         let last_date_index = card.last_date as usize;
@@ -240,26 +525,49 @@ pub fn simulate(
         if card.due >= learn_span as f32 {
             if !is_learn {
                 let delta_t = learn_span.max(last_date_index) - last_date_index;
-                let pre_sim_days = (-card.last_date) as usize;
                 for i in 0..delta_t {
+                    let elapsed_days = (last_date_index + i) as f32 - card.last_date;
                     memorized_cnt_per_day[last_date_index + i] +=
-                        power_forgetting_curve((pre_sim_days + i) as f32, card.stability);
+                        curve.retrievability(elapsed_days, card.stability);
                 }
             }
             card_priorities.pop();
             continue;
         }
 
+        if skipped_days[day_index] {
+            card.due = day_index as f32 + 1.0;
+            card_priorities.change_priority(&card_index, card_priority(card, is_learn));
+            continue;
+        }
+
+        if let (Some(review_delay_dist), false, false) =
+            (&review_delay_dist, is_learn, delayed[card_index])
+        {
+            delayed[card_index] = true;
+            let delay = review_delay_dist.sample(&mut rng);
+            if delay > 0 {
+                card.due = (day_index + delay) as f32;
+                card_priorities.change_priority(&card_index, card_priority(card, is_learn));
+                continue;
+            }
+        }
+
         let todays_learn = learn_cnt_per_day[day_index];
         let todays_review = review_cnt_per_day[day_index];
+        let cost_multiplier = match difficulty_cost_multipliers {
+            Some(multipliers) if !is_learn => multipliers[difficulty_band(card.difficulty)],
+            _ => 1.0,
+        };
 
+        let (learn_limit, review_limit) = (learn_limit_on(day_index), review_limit_on(day_index));
         if match (new_cards_ignore_review_limit, is_learn) {
             (true, true) => todays_learn + 1 > learn_limit,
             (false, true) => {
                 todays_learn + todays_review + 1 > review_limit || todays_learn + 1 > learn_limit
             }
             (_, false) => todays_review + 1 > review_limit,
-        } || (cost_per_day[day_index] + fail_cost > max_cost_perday)
+        } || (cost_per_day[day_index] + fail_cost * cost_multiplier > max_cost_on(day_index))
         {
             card.due = day_index as f32 + 1.0;
             card_priorities.change_priority(&card_index, card_priority(card, is_learn));
@@ -275,17 +583,27 @@ pub fn simulate(
             let rating = first_rating_choices[first_rating_dist.sample(&mut rng)];
             let offset = first_rating_offsets[rating - 1];
 
-            card.difficulty = init_d_with_short_term(w, rating, offset);
+            card.difficulty =
+                init_d_with_short_term(w, rating, offset, exponential_initial_difficulty);
             card.stability =
                 stability_short_term(w, w[rating - 1], offset, first_session_lens[rating - 1]);
 
-            ivl = next_interval(card.stability, desired_retention)
+            ivl = curve
+                .interval(card.stability, desired_retention)
                 .round()
                 .clamp(1.0, max_ivl);
 
+            if let Some(log) = review_log.as_mut() {
+                log.push(SimulatedReview {
+                    card_index,
+                    day: day_index,
+                    rating,
+                });
+            }
+
             // Update days statistics
             learn_cnt_per_day[day_index] += 1;
-            cost_per_day[day_index] += learn_costs[rating - 1];
+            cost_per_day[day_index] += sampled_learn_costs.sample(rating, &mut rng);
         } else {
             // For review cards
             // Updating delta_t for 'has_learned' cards
@@ -293,16 +611,27 @@ pub fn simulate(
             let last_stability = card.stability;
 
             // Calculate retrievability for entries where has_learned is true
-            let retrievability = power_forgetting_curve(elapsed_days, card.stability);
+            let retrievability = curve.retrievability(elapsed_days, card.stability);
+
+            let first_existing_review =
+                card_index < existing_card_count && review_cnts[card_index] == 0;
+            let recall = match existing_card_recall {
+                Some(recall) if first_existing_review => recall,
+                _ => retrievability,
+            };
 
             // Create 'forget' mask
-            let forget = !rng.gen_bool(retrievability as f64);
+            let forget = !rng.gen_bool(recall as f64);
 
             // Sample 'rating' for 'need_review' entries
             let rating = if forget {
                 1
             } else {
-                review_rating_choices[review_rating_dist.sample(&mut rng)]
+                let dist = match &existing_review_rating_dist {
+                    Some(dist) if first_existing_review => dist,
+                    _ => &review_rating_dist,
+                };
+                review_rating_choices[dist.sample(&mut rng)]
             };
 
             //dbg!(&card, &rating);
@@ -317,35 +646,65 @@ pub fn simulate(
             };
 
             // Update difficulty for review cards
-            card.difficulty = next_d(w, card.difficulty, rating);
+            card.difficulty = next_d(
+                w,
+                card.difficulty,
+                rating,
+                linear_damping,
+                exponential_initial_difficulty,
+            );
             if rating == 1 {
                 card.difficulty -= (w[6] * forget_rating_offset).clamp(1.0, 10.0);
             }
 
-            let cost = if forget {
-                fail_cost
-            } else {
-                review_costs[rating - 1]
-            };
+            let cost = cost_multiplier
+                * if forget {
+                    sampled_review_costs.sample(1, &mut rng) * loss_aversion + relearning_cost
+                } else {
+                    sampled_review_costs.sample(rating, &mut rng)
+                };
 
-            ivl = next_interval(card.stability, desired_retention)
-                .round()
-                .clamp(1.0, max_ivl);
+            ivl = match lapse_interval {
+                Some(lapse_interval) if forget => lapse_interval,
+                _ => curve.interval(card.stability, desired_retention),
+            }
+            .round()
+            .clamp(1.0, max_ivl);
+
+            if let Some(log) = review_log.as_mut() {
+                log.push(SimulatedReview {
+                    card_index,
+                    day: day_index,
+                    rating,
+                });
+            }
 
             // Update days statistics
             review_cnt_per_day[day_index] += 1;
             cost_per_day[day_index] += cost;
+            if forget {
+                lapse_cnts[card_index] += 1;
+            }
 
             let delta_t = day_index - last_date_index;
-            let pre_sim_days = (-card.last_date) as usize;
             for i in 0..delta_t {
+                let elapsed_days = (last_date_index + i) as f32 - card.last_date;
                 memorized_cnt_per_day[last_date_index + i] +=
-                    power_forgetting_curve((pre_sim_days + i) as f32, last_stability);
+                    curve.retrievability(elapsed_days, last_stability);
             }
         }
 
         card.last_date = day_index as f32;
         card.due = day_index as f32 + ivl;
+        intervals[card_index] = ivl;
+        review_cnts[card_index] += 1;
+        delayed[card_index] = false;
+
+        if suspend_after_lapses.is_some_and(|lapses| lapse_cnts[card_index] >= lapses as usize) {
+            suspended[card_index] = true;
+            card_priorities.remove(&card_index);
+            continue;
+        }
 
         card_priorities.change_priority(&card_index, card_priority(card, false));
     }
@@ -357,108 +716,518 @@ pub fn simulate(
         &cost_per_day[learn_span - 1],
     ));*/
 
+    let cards = izip!(cards, intervals, review_cnts, lapse_cnts, suspended)
+        .map(
+            |(card, interval, review_cnt, lapse_cnt, suspended)| SimulatedCard {
+                card,
+                interval,
+                review_cnt,
+                lapse_cnt,
+                suspended,
+            },
+        )
+        .collect();
+
     Ok(SimulationResult {
         memorized_cnt_per_day,
         review_cnt_per_day,
         learn_cnt_per_day,
         cost_per_day,
+        cards,
     })
 }
 
+/// Run the simulator, and return the reviews done on the new cards as items, in the same
+/// shape as [revlogs_to_fsrs_items](crate::convertor::revlogs_to_fsrs_items): one item per review
+/// after the first, sorted by review day. Training on these items should recover
+/// parameters close to `w`, though the short-term effects the simulator models for the
+/// learning and relearning steps are not part of the items.
+pub fn simulate_fsrs_items(
+    config: &SimulatorConfig,
+    w: &Parameters,
+    desired_retention: f32,
+    seed: Option<u64>,
+) -> Result<Vec<FSRSItem>, FSRSError> {
+    let mut review_log = vec![];
+    simulate_and_log(
+        config,
+        w,
+        desired_retention,
+        seed,
+        None,
+        Some(&mut review_log),
+        |_| Ok(()),
+    )?;
+    let mut histories: HashMap<usize, (usize, Vec<FSRSReview>)> = HashMap::new();
+    let mut items = vec![];
+    for review in review_log {
+        let (last_day, reviews) = histories
+            .entry(review.card_index)
+            .or_insert((review.day, vec![]));
+        reviews.push(FSRSReview {
+            rating: review.rating as u32,
+            delta_t: (review.day - *last_day) as u32,
+            elapsed_seconds: None,
+        });
+        *last_day = review.day;
+        if reviews.len() > 1 {
+            items.push((
+                review.day,
+                FSRSItem {
+                    reviews: reviews.clone(),
+                    metadata: Some(ItemMetadata {
+                        card_id: Some(review.card_index as i64),
+                        deck_id: None,
+                    }),
+                },
+            ));
+        }
+    }
+    items.sort_by_key(|(day, _)| *day);
+    Ok(items.into_iter().map(|(_, item)| item).collect())
+}
+
+/// The daily cost of the last days of a simulation is taken as its long-run workload.
+const RECENT_DAYS: usize = 30;
+
+/// Costs averaged over several simulations.
+#[derive(Debug, Clone, Copy, Default)]
+struct SampledCosts {
+    cost_per_memorized: f32,
+    /// The number of cards memorized at the end.
+    memorized: f32,
+    daily_cost: f32,
+    /// The average daily cost over the last [RECENT_DAYS] days.
+    recent_daily_cost: f32,
+}
+
 fn sample<F>(
     config: &SimulatorConfig,
     parameters: &Parameters,
     desired_retention: f32,
     n: usize,
+    cancellation_token: Option<&CancellationToken>,
     progress: &mut F,
-) -> Result<f32, FSRSError>
+) -> Result<SampledCosts, FSRSError>
 where
     F: FnMut() -> bool,
 {
     if !progress() {
-        return Err(FSRSError::Interrupted);
+        return Err(FSRSError::Interrupted {
+            stage: ProgressStage::OptimalRetention,
+        });
     }
-    let results: Result<Vec<f32>, FSRSError> = (0..n)
+    let results: Result<Vec<SampledCosts>, FSRSError> = (0..n)
         .into_par_iter()
         .map(|i| {
             let SimulationResult {
                 memorized_cnt_per_day,
                 cost_per_day,
                 ..
-            } = simulate(
+            } = simulate_and_log(
                 config,
                 parameters,
                 desired_retention,
                 Some((i + 42).try_into().unwrap()),
                 None,
+                None,
+                |_| match cancellation_token {
+                    Some(token) if token.is_cancelled() => Err(FSRSError::Interrupted {
+                        stage: ProgressStage::OptimalRetention,
+                    }),
+                    _ => Ok(()),
+                },
             )?;
             let total_memorized = memorized_cnt_per_day[memorized_cnt_per_day.len() - 1];
             let total_cost = cost_per_day.sum();
-            Ok(total_cost / total_memorized)
+            let recent_days = RECENT_DAYS.min(cost_per_day.len());
+            let recent_cost: f32 = cost_per_day.iter().rev().take(recent_days).sum();
+            Ok(SampledCosts {
+                cost_per_memorized: total_cost / total_memorized,
+                memorized: total_memorized,
+                daily_cost: total_cost / cost_per_day.len() as f32,
+                recent_daily_cost: recent_cost / recent_days as f32,
+            })
         })
         .collect();
-    results.map(|v| v.iter().sum::<f32>() / n as f32)
+    results.map(|v| {
+        let sum = v
+            .iter()
+            .fold(SampledCosts::default(), |sum, costs| SampledCosts {
+                cost_per_memorized: sum.cost_per_memorized + costs.cost_per_memorized,
+                memorized: sum.memorized + costs.memorized,
+                daily_cost: sum.daily_cost + costs.daily_cost,
+                recent_daily_cost: sum.recent_daily_cost + costs.recent_daily_cost,
+            });
+        SampledCosts {
+            cost_per_memorized: sum.cost_per_memorized / n as f32,
+            memorized: sum.memorized / n as f32,
+            daily_cost: sum.daily_cost / n as f32,
+            recent_daily_cost: sum.recent_daily_cost / n as f32,
+        }
+    })
+}
+
+/// The number of simulations to average, fewer for longer simulations.
+fn sample_size(config: &SimulatorConfig) -> usize {
+    let default_sample_size = 16.0;
+    match config.learn_span {
+        ..=30 => 180,
+        31..365 => {
+            let (a1, a2, a3) = (8.20e-7, 2.41e-3, 1.30e-2);
+            let factor = (config.learn_span as f32)
+                .powf(2.0)
+                .mul_add(a1, config.learn_span as f32 * a2 + a3);
+            (default_sample_size / factor).round() as usize
+        }
+        365.. => default_sample_size as usize,
+    }
+}
+
+/// A point on the curve returned by [FSRS::retention_workload_curve].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RetentionWorkload {
+    pub desired_retention: f32,
+    /// The total cost of the simulation, in seconds.
+    pub total_cost: f32,
+    /// The number of cards memorized at the end of the simulation.
+    pub memorized: f32,
+}
+
+/// Bounds on the desired retention suggested by
+/// [FSRS::optimal_retention_with_constraints].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConstraints {
+    pub min_retention: f32,
+    pub max_retention: f32,
+    /// The highest acceptable average cost per day over the simulation, in seconds. If
+    /// the cheapest retention per memorized card costs more, the highest retention within
+    /// the budget is suggested instead.
+    pub max_daily_cost: Option<f32>,
+}
+
+impl Default for RetentionConstraints {
+    fn default() -> Self {
+        Self {
+            min_retention: R_MIN,
+            max_retention: R_MAX,
+            max_daily_cost: None,
+        }
+    }
 }
 
 impl<B: Backend> FSRS<B> {
+    /// Like [simulate], with the parameters of this instance, which must have been provided.
+    /// The simulated day is reported to the [ProgressHandle](crate::ProgressHandle) of the
+    /// instance, if any, and the simulation stops with [FSRSError::Interrupted] once it is
+    /// cancelled.
+    pub fn simulate(
+        &self,
+        config: &SimulatorConfig,
+        desired_retention: f32,
+        seed: Option<u64>,
+        existing_cards: Option<Vec<Card>>,
+    ) -> Result<SimulationResult> {
+        let parameters: Vec<f32> = self.model().w.val().to_data().convert().value;
+        let total = config.learn_span;
+        let mut report = |day: usize| {
+            let progress = ItemProgress {
+                // the simulation ends with the first card due after the last day
+                current: day.min(total),
+                total,
+            };
+            self.report_progress(ProgressStage::Simulation, progress, &mut |_| true)
+        };
+        let result = simulate_and_log(
+            config,
+            &parameters,
+            desired_retention,
+            seed,
+            existing_cards,
+            None,
+            &mut report,
+        )?;
+        report(total)?;
+        Ok(result)
+    }
+
     /// For the given simulator parameters and parameters, determine the suggested `desired_retention`
     /// value.
     pub fn optimal_retention<F>(
         &self,
         config: &SimulatorConfig,
         parameters: &Parameters,
+        progress: F,
+    ) -> Result<f32>
+    where
+        F: FnMut(ItemProgress) -> bool + Send,
+    {
+        self.optimal_retention_with_constraints(
+            config,
+            parameters,
+            &RetentionConstraints::default(),
+            progress,
+        )
+    }
+
+    /// Like [FSRS::optimal_retention], searching between the bounds of `constraints`
+    /// instead of the defaults, and lowering the result to keep within its daily cost.
+    /// Returns [FSRSError::InvalidInput] if the bounds are not within 0..1 or are reversed,
+    /// and [FSRSError::OptimalNotFound] if even the minimum retention is over budget.
+    pub fn optimal_retention_with_constraints<F>(
+        &self,
+        config: &SimulatorConfig,
+        parameters: &Parameters,
+        constraints: &RetentionConstraints,
         mut progress: F,
     ) -> Result<f32>
     where
         F: FnMut(ItemProgress) -> bool + Send,
     {
+        let RetentionConstraints {
+            min_retention,
+            max_retention,
+            max_daily_cost,
+        } = *constraints;
+        if !(min_retention > 0.0 && min_retention < max_retention && max_retention < 1.0)
+            || max_daily_cost.is_some_and(|cost| cost.is_nan() || cost < 0.0)
+        {
+            return Err(FSRSError::InvalidInput);
+        }
         let mut progress_info = ItemProgress {
             current: 0,
             // not provided for this method
             total: 0,
         };
-        let inc_progress = move || {
+        let mut inc_progress = move || {
             progress_info.current += 1;
-            progress(progress_info)
+            self.report_progress(
+                ProgressStage::OptimalRetention,
+                progress_info,
+                &mut progress,
+            )
+            .is_ok()
         };
 
-        Self::brent(config, parameters, inc_progress)
+        let sample_size = sample_size(config);
+        let cancellation_token = self.cancellation_token();
+        let mut sample_costs = |desired_retention| {
+            sample(
+                config,
+                parameters,
+                desired_retention,
+                sample_size,
+                cancellation_token,
+                &mut inc_progress,
+            )
+        };
+        let desired_retention = self.install(|| {
+            let optimal = Self::brent((min_retention, max_retention), |desired_retention| {
+                let cost = sample_costs(desired_retention)?.cost_per_memorized;
+                self.emit(FSRSEvent::RetentionSimulated {
+                    desired_retention,
+                    cost,
+                });
+                Ok(cost)
+            })?;
+            let Some(max_daily_cost) = max_daily_cost else {
+                return Ok(optimal);
+            };
+            let mut daily_cost =
+                |desired_retention| sample_costs(desired_retention).map(|costs| costs.daily_cost);
+            if daily_cost(optimal)? <= max_daily_cost {
+                return Ok(optimal);
+            }
+            // the daily cost rises again at low retention, as more cards are forgotten, so
+            // search down from the optimum only as far as the cheapest retention per day
+            let cheapest = if optimal - min_retention > 0.01 {
+                Self::brent((min_retention, optimal), &mut daily_cost)?
+            } else {
+                min_retention
+            };
+            if daily_cost(cheapest)? > max_daily_cost {
+                return Err(FSRSError::OptimalNotFound);
+            }
+            let (mut low, mut high) = (cheapest, optimal);
+            while high - low > 0.005 {
+                let mid = 0.5 * (low + high);
+                if daily_cost(mid)? <= max_daily_cost {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            Ok(low)
+        })?;
+        self.emit(FSRSEvent::OptimalRetentionFound { desired_retention });
+        Ok(desired_retention)
     }
-    /// https://argmin-rs.github.io/argmin/argmin/solver/brent/index.html
-    /// https://github.com/scipy/scipy/blob/5e4a5e3785f79dd4e8930eed883da89958860db2/scipy/optimize/_optimize.py#L2446
-    fn brent<F>(
+
+    /// Simulate `points` desired retentions evenly spaced from 0.70 to 0.95, the range
+    /// [FSRS::optimal_retention] searches, to plot the tradeoff between workload and
+    /// memorized cards. Each point is averaged over the same number of runs as
+    /// [FSRS::optimal_retention], and `progress` is told about each one. Returns
+    /// [FSRSError::InvalidInput] for fewer than 2 points.
+    pub fn retention_workload_curve<F>(
+        &self,
+        config: &SimulatorConfig,
+        parameters: &Parameters,
+        points: usize,
+        mut progress: F,
+    ) -> Result<Vec<RetentionWorkload>>
+    where
+        F: FnMut(ItemProgress) -> bool + Send,
+    {
+        if points < 2 {
+            return Err(FSRSError::InvalidInput);
+        }
+        let mut progress_info = ItemProgress {
+            current: 0,
+            total: points,
+        };
+        let mut inc_progress = move || {
+            let carry_on = self
+                .report_progress(
+                    ProgressStage::OptimalRetention,
+                    progress_info,
+                    &mut progress,
+                )
+                .is_ok();
+            progress_info.current += 1;
+            carry_on
+        };
+        let sample_size = sample_size(config);
+        let cancellation_token = self.cancellation_token();
+        self.install(|| {
+            (0..points)
+                .map(|i| {
+                    let desired_retention =
+                        R_MIN + (R_MAX - R_MIN) * i as f32 / (points - 1) as f32;
+                    let costs = sample(
+                        config,
+                        parameters,
+                        desired_retention,
+                        sample_size,
+                        cancellation_token,
+                        &mut inc_progress,
+                    )?;
+                    self.emit(FSRSEvent::RetentionSimulated {
+                        desired_retention,
+                        cost: costs.cost_per_memorized,
+                    });
+                    Ok(RetentionWorkload {
+                        desired_retention,
+                        total_cost: costs.daily_cost * config.learn_span as f32,
+                        memorized: costs.memorized,
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// The largest `learn_limit` whose long-run workload at `desired_retention` stays within
+    /// `max_daily_cost` seconds per day, eg to answer how many new cards a user can learn
+    /// each day in 30 minutes. The workload is the average daily cost over the last 30 days
+    /// of simulations that learn new cards at that rate for the whole `learn_span`, so the
+    /// deck size and the daily cost and learn limits of `config` are ignored. The search
+    /// stops at 10,000 cards per day.
+    pub fn max_learn_limit<F>(
+        &self,
         config: &SimulatorConfig,
         parameters: &Parameters,
+        desired_retention: f32,
+        max_daily_cost: f32,
         mut progress: F,
-    ) -> Result<f32, FSRSError>
+    ) -> Result<usize>
+    where
+        F: FnMut(ItemProgress) -> bool + Send,
+    {
+        const MAX_LEARN_LIMIT: usize = 10_000;
+        if config.learn_span == 0
+            || !(0.0..1.0).contains(&desired_retention)
+            || max_daily_cost.is_nan()
+            || max_daily_cost < 0.0
+        {
+            return Err(FSRSError::InvalidInput);
+        }
+        let mut progress_info = ItemProgress {
+            current: 0,
+            // not provided for this method
+            total: 0,
+        };
+        let mut inc_progress = move || {
+            progress_info.current += 1;
+            self.report_progress(
+                ProgressStage::OptimalRetention,
+                progress_info,
+                &mut progress,
+            )
+            .is_ok()
+        };
+        let sample_size = sample_size(config);
+        let cancellation_token = self.cancellation_token();
+        let mut affordable = |learn_limit: usize| {
+            if learn_limit == 0 {
+                return Ok(true);
+            }
+            let config = SimulatorConfig {
+                deck_size: learn_limit * config.learn_span,
+                max_cost_perday: f32::INFINITY,
+                learn_limit,
+                learn_limits_per_weekday: None,
+                max_costs_per_weekday: None,
+                ..config.clone()
+            };
+            let costs = sample(
+                &config,
+                parameters,
+                desired_retention,
+                sample_size,
+                cancellation_token,
+                &mut inc_progress,
+            )?;
+            Ok::<_, FSRSError>(costs.recent_daily_cost <= max_daily_cost)
+        };
+        self.install(|| {
+            // double the limit until it is over budget, then bisect
+            let mut low = 0;
+            let mut high = 1;
+            while affordable(high)? {
+                low = high;
+                if high == MAX_LEARN_LIMIT {
+                    return Ok(high);
+                }
+                high = (high * 2).min(MAX_LEARN_LIMIT);
+            }
+            while high - low > 1 {
+                let mid = (low + high) / 2;
+                if affordable(mid)? {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            Ok(low)
+        })
+    }
+
+    /// The retention in `(r_min, r_max)` that minimizes `cost`.
+    /// https://argmin-rs.github.io/argmin/argmin/solver/brent/index.html
+    /// https://github.com/scipy/scipy/blob/5e4a5e3785f79dd4e8930eed883da89958860db2/scipy/optimize/_optimize.py#L2446
+    fn brent<C>((r_min, r_max): (f32, f32), mut cost: C) -> Result<f32, FSRSError>
     where
-        F: FnMut() -> bool,
+        C: FnMut(f32) -> Result<f32, FSRSError>,
     {
         let mintol = 1e-10;
         let cg = 0.381_966;
         let maxiter = 64;
         let tol = 0.01f32;
 
-        let default_sample_size = 16.0;
-        let sample_size = match config.learn_span {
-            ..=30 => 180,
-            31..365 => {
-                let (a1, a2, a3) = (8.20e-7, 2.41e-3, 1.30e-2);
-                let factor = (config.learn_span as f32)
-                    .powf(2.0)
-                    .mul_add(a1, config.learn_span as f32 * a2 + a3);
-                (default_sample_size / factor).round() as usize
-            }
-            365.. => default_sample_size as usize,
-        };
-
-        let (xb, fb) = (
-            R_MIN,
-            sample(config, parameters, R_MIN, sample_size, &mut progress)?,
-        );
+        let (xb, fb) = (r_min, cost(r_min)?);
         let (mut x, mut v, mut w) = (xb, xb, xb);
         let (mut fx, mut fv, mut fw) = (fb, fb, fb);
-        let (mut a, mut b) = (R_MIN, R_MAX);
+        let (mut a, mut b) = (r_min, r_max);
         let mut deltax: f32 = 0.0;
         let mut iter = 0;
         let mut rat = 0.0;
@@ -512,7 +1281,7 @@ impl<B: Backend> FSRS<B> {
                 rat
             };
             // calculate new output value
-            let fu = sample(config, parameters, u, sample_size, &mut progress)?;
+            let fu = cost(u)?;
 
             // if it's bigger than current
             if fu > fx {
@@ -541,7 +1310,7 @@ impl<B: Backend> FSRS<B> {
             iter += 1;
         }
         let xmin = x;
-        let success = iter < maxiter && (R_MIN..=R_MAX).contains(&xmin);
+        let success = iter < maxiter && (r_min..=r_max).contains(&xmin);
         dbg!(iter);
 
         if success {
@@ -552,7 +1321,7 @@ impl<B: Backend> FSRS<B> {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum RevlogReviewKind {
     #[default]
     Learning = 0,
@@ -565,7 +1334,7 @@ pub enum RevlogReviewKind {
     Manual = 4,
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct RevlogEntry {
     pub id: i64,
     pub cid: i64,
@@ -919,10 +1688,100 @@ pub fn extract_simulator_config(
     }
 }
 
+/// Estimate [SimulatorConfig::difficulty_cost_multipliers] from the answer times of the
+/// reviews in `revlogs`: for each band of difficulty, the median ratio of the answer time to
+/// the median answer time of all reviews with the same rating. The difficulty of each card
+/// is replayed with `parameters` from its first learning step, updating on the first review
+/// of each day. Bands without reviews get 1.0.
+pub fn extract_difficulty_cost_multipliers(
+    revlogs: &[RevlogEntry],
+    parameters: &Parameters,
+    day_cutoff: i64,
+) -> Result<[f32; 10]> {
+    let w = &check_and_fill_parameters(parameters, true)?;
+    fn median(mut x: Vec<f32>) -> Option<f32> {
+        x.sort_unstable_by(f32::total_cmp);
+        let n = x.len();
+        match n {
+            0 => None,
+            _ if n % 2 == 0 => Some((x[n / 2 - 1] + x[n / 2]) / 2.0),
+            _ => Some(x[n / 2]),
+        }
+    }
+
+    // the difficulty band, rating and answer time of each review
+    let mut reviews = vec![];
+    for (_, mut entries) in revlogs
+        .iter()
+        .map(|entry| (entry.cid, entry))
+        .into_group_map()
+    {
+        entries.sort_by_key(|entry| entry.id);
+        let mut difficulty = None;
+        let mut last_day = None;
+        for entry in entries {
+            let rating = entry.button_chosen as usize;
+            if !(1..=4).contains(&rating) {
+                continue;
+            }
+            let day = (entry.id / 1000 - day_cutoff) / 86400;
+            match (entry.review_kind, difficulty) {
+                (RevlogReviewKind::Learning, None) => {
+                    difficulty = Some(init_d(w, rating, true).clamp(1.0, 10.0));
+                }
+                (RevlogReviewKind::Review, Some(d)) if last_day != Some(day) => {
+                    if entry.taken_millis > 0 && entry.taken_millis < 1200000 {
+                        reviews.push((difficulty_band(d), rating, entry.taken_millis as f32));
+                    }
+                    difficulty = Some(next_d(w, d, rating, true, true));
+                }
+                _ => {}
+            }
+            last_day = Some(day);
+        }
+    }
+
+    let rating_medians = reviews
+        .iter()
+        .map(|&(_, rating, time)| (rating, time))
+        .into_group_map()
+        .into_iter()
+        .filter_map(|(rating, times)| Some((rating, median(times)?)))
+        .collect::<HashMap<_, _>>();
+    let mut multipliers = [1.0; 10];
+    for (band, ratios) in reviews
+        .iter()
+        .map(|&(band, rating, time)| (band, time / rating_medians[&rating]))
+        .into_group_map()
+    {
+        multipliers[band] = median(ratios).unwrap_or(1.0);
+    }
+    Ok(multipliers)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{convertor_tests::read_collection, DEFAULT_PARAMETERS};
+    use crate::test_helpers::assert_approx_eq;
+    use crate::{convertor_tests::read_collection, MemoryState, DEFAULT_PARAMETERS};
+
+    #[test]
+    fn simulator_config_serde() {
+        let config = SimulatorConfig {
+            deck_size: 1000,
+            forgetting_curve: CurveKind::Exponential,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SimulatorConfig>(&json).unwrap(),
+            config
+        );
+        // missing fields take their defaults
+        let partial: SimulatorConfig = serde_json::from_str(r#"{"deck_size": 1000}"#).unwrap();
+        assert_eq!(partial.deck_size, 1000);
+        assert_eq!(partial.learn_limit, SimulatorConfig::default().learn_limit);
+    }
 
     #[test]
     fn simulator() -> Result<()> {
@@ -939,30 +1798,144 @@ mod tests {
     }
 
     #[test]
-    fn changing_learn_span_should_get_same_review_cnt_per_day() -> Result<()> {
-        const LOWER: usize = 365;
-        const DECK_SIZE: usize = 1000;
-        const LEARN_LIMIT: usize = 10;
-        let config = SimulatorConfig {
-            learn_span: LOWER,
-            learn_limit: LEARN_LIMIT,
-            deck_size: DECK_SIZE,
-            ..Default::default()
+    fn simulator_with_trained_decay() -> Result<()> {
+        let config = SimulatorConfig::default();
+        let memorized = |parameters: &[f32]| -> Result<f32> {
+            let result = simulate(&config, parameters, 0.9, None, None)?;
+            Ok(result.memorized_cnt_per_day[config.learn_span - 1])
         };
-        let SimulationResult {
-            review_cnt_per_day: review_cnt_per_day_lower,
-            ..
-        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        // the default decay gives the same result as no decay parameter
+        let default_decay = [&DEFAULT_PARAMETERS[..], &[0.5]].concat();
+        assert_eq!(memorized(&default_decay)?, 6781.493);
+        let other_decay = [&DEFAULT_PARAMETERS[..], &[0.2]].concat();
+        assert_ne!(memorized(&other_decay)?, 6781.493);
+        Ok(())
+    }
+
+    #[test]
+    fn simulator_with_exponential_curve() -> Result<()> {
         let config = SimulatorConfig {
-            learn_span: LOWER + 10,
-            learn_limit: LEARN_LIMIT,
-            deck_size: DECK_SIZE,
+            forgetting_curve: CurveKind::Exponential,
             ..Default::default()
         };
-        let SimulationResult {
-            review_cnt_per_day: review_cnt_per_day_higher,
-            ..
-        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        let result = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        let memorized = result.memorized_cnt_per_day[config.learn_span - 1];
+        // before cards are due, the exponential curve forgets less than the power curve
+        assert!(memorized > 6781.493, "{memorized}");
+        Ok(())
+    }
+
+    #[test]
+    fn simulator_without_linear_damping() -> Result<()> {
+        let w = &DEFAULT_PARAMETERS;
+        // the same difficulty as the model's, before mean reversion
+        let undamped = next_d(&[&w[..7], &[0.0], &w[8..]].concat(), 9.5, 1, false, true);
+        assert_eq!(undamped, (9.5 + 2.0 * w[6]).min(10.0));
+        assert!(next_d(w, 5.0, 1, false, true) > next_d(w, 5.0, 1, true, true));
+        assert_eq!(
+            next_d(w, 5.0, 3, false, true),
+            next_d(w, 5.0, 3, true, true)
+        );
+
+        let config = SimulatorConfig {
+            deck_size: 1000,
+            learn_span: 100,
+            linear_damping: false,
+            ..Default::default()
+        };
+        let undamped = simulate(&config, w, 0.9, Some(42), None)?;
+        let damped = simulate(
+            &SimulatorConfig {
+                linear_damping: true,
+                ..config
+            },
+            w,
+            0.9,
+            Some(42),
+            None,
+        )?;
+        assert_ne!(undamped.review_cnt_per_day, damped.review_cnt_per_day);
+        Ok(())
+    }
+
+    #[test]
+    fn simulator_with_linear_initial_difficulty() -> Result<()> {
+        let w = &DEFAULT_PARAMETERS;
+        assert_eq!(init_d(w, 3, false), w[4]);
+        assert_eq!(init_d(w, 1, false), w[4] + 2.0 * w[5]);
+        assert_eq!(
+            init_d_with_short_term(w, 4, 0.0, false),
+            (w[4] - w[5]).clamp(1.0, 10.0)
+        );
+        let config = SimulatorConfig {
+            deck_size: 1000,
+            learn_span: 100,
+            exponential_initial_difficulty: false,
+            ..Default::default()
+        };
+        let linear = simulate(&config, w, 0.9, Some(42), None)?;
+        let exponential = simulate(
+            &SimulatorConfig {
+                exponential_initial_difficulty: true,
+                ..config
+            },
+            w,
+            0.9,
+            Some(42),
+            None,
+        )?;
+        assert_ne!(linear.review_cnt_per_day, exponential.review_cnt_per_day);
+        Ok(())
+    }
+
+    #[test]
+    fn simulated_items() -> Result<()> {
+        let config = SimulatorConfig {
+            deck_size: 500,
+            learn_span: 200,
+            learn_limit: 10,
+            ..Default::default()
+        };
+        let items = simulate_fsrs_items(&config, &DEFAULT_PARAMETERS, 0.9, Some(1))?;
+        let SimulationResult {
+            review_cnt_per_day, ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, Some(1), None)?;
+        assert_eq!(items.len(), review_cnt_per_day.sum());
+        assert_eq!(crate::dataset::validate_items(&items), Ok(()));
+        assert!(items
+            .iter()
+            .all(|item| item.reviews.iter().map(|r| r.delta_t).sum::<u32>() < 200));
+        let recall =
+            items.iter().filter(|i| i.current().rating > 1).count() as f32 / items.len() as f32;
+        assert!((0.85..0.95).contains(&recall), "{recall}");
+        Ok(())
+    }
+
+    #[test]
+    fn changing_learn_span_should_get_same_review_cnt_per_day() -> Result<()> {
+        const LOWER: usize = 365;
+        const DECK_SIZE: usize = 1000;
+        const LEARN_LIMIT: usize = 10;
+        let config = SimulatorConfig {
+            learn_span: LOWER,
+            learn_limit: LEARN_LIMIT,
+            deck_size: DECK_SIZE,
+            ..Default::default()
+        };
+        let SimulationResult {
+            review_cnt_per_day: review_cnt_per_day_lower,
+            ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        let config = SimulatorConfig {
+            learn_span: LOWER + 10,
+            learn_limit: LEARN_LIMIT,
+            deck_size: DECK_SIZE,
+            ..Default::default()
+        };
+        let SimulationResult {
+            review_cnt_per_day: review_cnt_per_day_higher,
+            ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
         // Compare first LOWER items of review_cnt_per_day arrays
         for i in 0..LOWER {
             assert_eq!(
@@ -1021,6 +1994,446 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn simulate_fractional_dates() -> Result<()> {
+        let config = SimulatorConfig {
+            deck_size: 3,
+            learn_span: 1,
+            existing_card_recall: Some(1.0),
+            existing_review_rating_prob: Some([0.0, 1.0, 0.0]),
+            ..Default::default()
+        };
+        let cards = |due| {
+            [-3.0, -3.5, -4.0]
+                .map(|last_date| Card {
+                    difficulty: 5.0,
+                    stability: 10.0,
+                    last_date,
+                    due,
+                })
+                .to_vec()
+        };
+        let SimulationResult {
+            cards: simulated, ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, Some(cards(0.0)))?;
+        // the stability gained grows with the time elapsed since the last review
+        let stabilities = simulated.iter().map(|c| c.card.stability).collect_vec();
+        assert!(stabilities[0] < stabilities[1] && stabilities[1] < stabilities[2]);
+
+        // cards that are not due count towards the memorized cards with their exact age
+        let SimulationResult {
+            memorized_cnt_per_day,
+            ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, Some(cards(5.0)))?;
+        let fsrs = FSRS::new(Some(&[]))?;
+        let state = MemoryState {
+            stability: 10.0,
+            difficulty: 5.0,
+        };
+        assert_approx_eq(
+            [memorized_cnt_per_day[0]],
+            [[259_200, 302_400, 345_600]
+                .map(|seconds| fsrs.current_retrievability_seconds(state, seconds))
+                .iter()
+                .sum()],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn simulate_first_review_of_existing_cards() -> Result<()> {
+        let config = SimulatorConfig {
+            deck_size: 10,
+            learn_span: 1,
+            existing_card_recall: Some(0.0),
+            ..Default::default()
+        };
+        let cards = vec![
+            Card {
+                difficulty: 5.0,
+                stability: 100.0,
+                last_date: -1.0,
+                due: 0.0,
+            };
+            10
+        ];
+        let SimulationResult {
+            cards: simulated, ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, Some(cards.clone()))?;
+        assert!(simulated.iter().all(|card| card.lapse_cnt == 1));
+
+        let config = SimulatorConfig {
+            existing_card_recall: Some(1.0),
+            existing_review_rating_prob: Some([0.0, 0.0, 1.0]),
+            ..config
+        };
+        let SimulationResult {
+            cost_per_day,
+            cards: simulated,
+            ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, Some(cards.clone()))?;
+        assert!(simulated.iter().all(|card| card.lapse_cnt == 0));
+        assert_approx_eq([cost_per_day[0]], [10.0 * config.review_costs[3]]);
+
+        let config = SimulatorConfig {
+            existing_card_recall: Some(1.5),
+            ..config
+        };
+        assert!(matches!(
+            simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, Some(cards)),
+            Err(FSRSError::InvalidInput)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn simulate_with_leech_suspension() -> Result<()> {
+        let config = SimulatorConfig {
+            learn_span: 200,
+            learn_limit: 10,
+            max_cost_perday: f32::INFINITY,
+            ..Default::default()
+        };
+        let without = simulate(&config, &DEFAULT_PARAMETERS, 0.8, None, None)?;
+        let config = SimulatorConfig {
+            suspend_after_lapses: Some(2),
+            ..config
+        };
+        let with = simulate(&config, &DEFAULT_PARAMETERS, 0.8, None, None)?;
+        let suspended = with
+            .cards
+            .iter()
+            .filter(|card| card.suspended)
+            .collect_vec();
+        assert!(!suspended.is_empty());
+        assert!(suspended.iter().all(|card| card.lapse_cnt == 2));
+        assert!(suspended
+            .iter()
+            .all(|card| card.to_existing_card(config.learn_span).is_none()));
+        assert!(with.cards.iter().all(|card| card.lapse_cnt <= 2));
+        assert!(with.review_cnt_per_day.sum() < without.review_cnt_per_day.sum());
+        assert!(
+            with.memorized_cnt_per_day[config.learn_span - 1]
+                < without.memorized_cnt_per_day[config.learn_span - 1]
+        );
+
+        let config = SimulatorConfig {
+            suspend_after_lapses: Some(0),
+            ..config
+        };
+        assert!(matches!(
+            simulate(&config, &DEFAULT_PARAMETERS, 0.8, None, None),
+            Err(FSRSError::InvalidInput)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn simulate_with_sampled_costs() -> Result<()> {
+        let config = SimulatorConfig {
+            deck_size: 10000,
+            learn_span: 1,
+            max_cost_perday: f32::INFINITY,
+            first_rating_prob: [0.0, 0.0, 1.0, 0.0],
+            learn_cost_stddevs: Some([0.0, 0.0, 20.0, 0.0]),
+            ..Default::default()
+        };
+        let SimulationResult { cost_per_day, .. } =
+            simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        let mean_cost = cost_per_day[0] / 10000.0;
+        assert_ne!(mean_cost, config.learn_costs[2]);
+        assert!((mean_cost - config.learn_costs[2]).abs() < 0.5);
+
+        let config = SimulatorConfig {
+            review_cost_stddevs: Some([-1.0; 4]),
+            ..config
+        };
+        assert!(matches!(
+            simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None),
+            Err(FSRSError::InvalidInput)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn simulate_with_difficulty_cost_multipliers() -> Result<()> {
+        let config = SimulatorConfig {
+            learn_span: 30,
+            learn_limit: 10,
+            max_cost_perday: f32::INFINITY,
+            ..Default::default()
+        };
+        let cost = |config: &SimulatorConfig| -> Result<(f32, usize)> {
+            let SimulationResult {
+                cost_per_day,
+                review_cnt_per_day,
+                ..
+            } = simulate(config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+            Ok((cost_per_day.sum(), review_cnt_per_day.sum()))
+        };
+        let (base_cost, review_cnt) = cost(&config)?;
+        let doubled = SimulatorConfig {
+            difficulty_cost_multipliers: Some([2.0; 10]),
+            ..config.clone()
+        };
+        let (doubled_cost, doubled_review_cnt) = cost(&doubled)?;
+        // the same reviews are done, and only their cost changes
+        assert_eq!(doubled_review_cnt, review_cnt);
+        assert!(doubled_cost > base_cost);
+        Ok(())
+    }
+
+    #[test]
+    fn extract_difficulty_cost_multipliers_from_revlog() -> Result<()> {
+        // easy cards are answered in 2s, and cards rated hard or again at first in 8s
+        let revlogs = (0..40)
+            .flat_map(|cid| {
+                let first_rating = [1, 2, 3, 4][cid as usize % 4];
+                let taken_millis = if first_rating <= 2 { 8000 } else { 2000 };
+                let learn = RevlogEntry {
+                    id: cid * 1000,
+                    cid,
+                    button_chosen: first_rating,
+                    review_kind: RevlogReviewKind::Learning,
+                    ..Default::default()
+                };
+                let review = RevlogEntry {
+                    id: cid * 1000 + 86_400_000,
+                    cid,
+                    button_chosen: 3,
+                    taken_millis,
+                    review_kind: RevlogReviewKind::Review,
+                    ..Default::default()
+                };
+                [learn, review]
+            })
+            .collect_vec();
+        let multipliers = extract_difficulty_cost_multipliers(&revlogs, &DEFAULT_PARAMETERS, 0)?;
+        let w = DEFAULT_PARAMETERS;
+        let band = |rating| difficulty_band(init_d(&w, rating, true).clamp(1.0, 10.0));
+        assert!(multipliers[band(1)] > 1.0);
+        assert!(multipliers[band(4)] < 1.0);
+        assert_eq!(multipliers[band(1)], 4.0 * multipliers[band(4)]);
+        assert_eq!(
+            extract_difficulty_cost_multipliers(&[], &DEFAULT_PARAMETERS, 0)?,
+            [1.0; 10]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn simulated_cards() -> Result<()> {
+        let config = SimulatorConfig {
+            deck_size: 1000,
+            learn_span: 30,
+            learn_limit: 10,
+            ..Default::default()
+        };
+        let existing = Card {
+            difficulty: 5.0,
+            stability: 5.0,
+            last_date: -5.0,
+            due: 0.0,
+        };
+        let SimulationResult {
+            review_cnt_per_day,
+            learn_cnt_per_day,
+            cards,
+            ..
+        } = simulate(
+            &config,
+            &DEFAULT_PARAMETERS,
+            0.9,
+            None,
+            Some(vec![existing.clone()]),
+        )?;
+        assert_eq!(cards.len(), config.deck_size);
+        assert!(cards[0].review_cnt > 0);
+        assert_ne!(cards[0].card, existing);
+        assert_eq!(
+            cards.iter().map(|card| card.review_cnt).sum::<usize>(),
+            review_cnt_per_day.sum() + learn_cnt_per_day.sum()
+        );
+        assert!(cards.iter().all(|card| card.lapse_cnt <= card.review_cnt));
+        for card in &cards {
+            if card.review_cnt == 0 && card.card.last_date == f32::NEG_INFINITY {
+                assert_eq!(card.interval, 0.0);
+                assert_eq!(card.to_existing_card(config.learn_span), None);
+            } else {
+                let existing = card.to_existing_card(config.learn_span).unwrap();
+                assert!(existing.last_date < 0.0);
+                assert_eq!(existing.due - existing.last_date, card.interval);
+            }
+        }
+
+        // continue the simulation with the learnt cards
+        let existing_cards: Vec<Card> = cards
+            .iter()
+            .filter_map(|card| card.to_existing_card(config.learn_span))
+            .collect();
+        assert_eq!(existing_cards.len(), learn_cnt_per_day.sum() + 1);
+        let SimulationResult { cards, .. } = simulate(
+            &config,
+            &DEFAULT_PARAMETERS,
+            0.9,
+            None,
+            Some(existing_cards),
+        )?;
+        assert_eq!(cards.len(), config.deck_size);
+        Ok(())
+    }
+
+    #[test]
+    fn simulate_relearning() -> Result<()> {
+        let config = SimulatorConfig {
+            deck_size: 200,
+            learn_span: 100,
+            max_cost_perday: f32::INFINITY,
+            ..Default::default()
+        };
+        let SimulationResult {
+            cost_per_day,
+            cards,
+            ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        let lapses = cards.iter().map(|card| card.lapse_cnt).sum::<usize>();
+        assert!(lapses > 0);
+        let config = SimulatorConfig {
+            relearning_cost: 10.0,
+            ..config
+        };
+        let SimulationResult {
+            cost_per_day: cost_per_day_with_relearning,
+            ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        assert_eq!(
+            cost_per_day_with_relearning.sum().round(),
+            (cost_per_day.sum() + 10.0 * lapses as f32).round()
+        );
+
+        let config = SimulatorConfig {
+            lapse_interval: Some(1.0),
+            ..config
+        };
+        let items = simulate_fsrs_items(&config, &DEFAULT_PARAMETERS, 0.9, None)?;
+        let mut relearnt = 0;
+        for item in &items {
+            for (previous, review) in item.reviews.iter().skip(1).tuple_windows() {
+                if previous.rating == 1 {
+                    assert_eq!(review.delta_t, 1);
+                    relearnt += 1;
+                }
+            }
+        }
+        assert!(relearnt > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn simulate_with_review_delays() -> Result<()> {
+        let config = SimulatorConfig {
+            deck_size: 200,
+            learn_span: 100,
+            max_cost_perday: f32::INFINITY,
+            // always a day late
+            review_delay_probs: vec![0.0, 1.0],
+            ..Default::default()
+        };
+        let items = simulate_fsrs_items(&config, &DEFAULT_PARAMETERS, 0.9, None)?;
+        assert!(!items.is_empty());
+        assert!(items.iter().all(|item| item.current().delta_t >= 2));
+
+        let config = SimulatorConfig {
+            review_delay_probs: vec![-1.0],
+            ..config
+        };
+        assert!(simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn simulate_with_skipped_days() -> Result<()> {
+        let config = SimulatorConfig {
+            deck_size: 1000,
+            learn_span: 100,
+            learn_limit: 10,
+            skip_day_prob: 0.5,
+            ..Default::default()
+        };
+        let SimulationResult {
+            learn_cnt_per_day,
+            review_cnt_per_day,
+            ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        let days_off = izip!(&learn_cnt_per_day, &review_cnt_per_day)
+            .filter(|(&learn, &review)| learn + review == 0)
+            .count();
+        assert!((30..70).contains(&days_off));
+
+        let config = SimulatorConfig {
+            skip_day_prob: 1.0,
+            ..config
+        };
+        let SimulationResult {
+            learn_cnt_per_day, ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        assert_eq!(learn_cnt_per_day.sum(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn simulate_with_weekday_limits() -> Result<()> {
+        // weekends off
+        let config = SimulatorConfig {
+            deck_size: 1000,
+            learn_span: 28,
+            learn_limit: 10,
+            learn_limits_per_weekday: Some([10, 10, 10, 10, 10, 0, 0]),
+            review_limits_per_weekday: Some([usize::MAX, usize::MAX, 100, 100, 100, 0, 0]),
+            ..Default::default()
+        };
+        let SimulationResult {
+            learn_cnt_per_day,
+            review_cnt_per_day,
+            ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        for day in 0..config.learn_span {
+            let weekend = day % 7 >= 5;
+            assert_eq!(learn_cnt_per_day[day], if weekend { 0 } else { 10 });
+            if weekend {
+                assert_eq!(review_cnt_per_day[day], 0);
+            } else if day % 7 >= 2 {
+                assert!(review_cnt_per_day[day] <= 100);
+            }
+        }
+        // reviews pile up over the weekend
+        assert!(review_cnt_per_day[7] > review_cnt_per_day[4]);
+
+        // starting on a Saturday
+        let config = SimulatorConfig {
+            first_weekday: 5,
+            ..config
+        };
+        let SimulationResult {
+            learn_cnt_per_day, ..
+        } = simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        assert_eq!(learn_cnt_per_day.to_vec()[..3], [0, 0, 10]);
+
+        // an easy Sunday, with only the time for a few reviews
+        let config = SimulatorConfig {
+            learn_limits_per_weekday: None,
+            review_limits_per_weekday: None,
+            max_costs_per_weekday: Some([1800.0, 1800.0, 1800.0, 1800.0, 1800.0, 1800.0, 100.0]),
+            first_weekday: 0,
+            ..config
+        };
+        let SimulationResult { cost_per_day, .. } =
+            simulate(&config, &DEFAULT_PARAMETERS, 0.9, None, None)?;
+        assert!(cost_per_day[6] <= 100.0);
+        assert!(cost_per_day[5] > 100.0);
+        Ok(())
+    }
+
     #[test]
     fn simulate_with_learn_limit() -> Result<()> {
         let config = SimulatorConfig {
@@ -1231,6 +2644,138 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn optimal_retention_with_constraints() -> Result<()> {
+        let learn_span = 1000;
+        let learn_limit = 10;
+        let fsrs = FSRS::new(None)?;
+        let config = SimulatorConfig {
+            deck_size: learn_span * learn_limit,
+            learn_span,
+            max_cost_perday: f32::INFINITY,
+            learn_limit,
+            ..Default::default()
+        };
+        let constraints = RetentionConstraints {
+            min_retention: 0.9,
+            ..Default::default()
+        };
+        let retention =
+            fsrs.optimal_retention_with_constraints(&config, &[], &constraints, |_| true)?;
+        assert!((0.9..0.91).contains(&retention));
+
+        // the daily cost of the unconstrained optimum is over budget
+        let daily_cost =
+            sample(&config, &DEFAULT_PARAMETERS, 0.8, 16, None, &mut || true)?.daily_cost;
+        let constraints = RetentionConstraints {
+            max_daily_cost: Some(daily_cost),
+            ..Default::default()
+        };
+        let retention =
+            fsrs.optimal_retention_with_constraints(&config, &[], &constraints, |_| true)?;
+        assert!(retention < 0.85);
+        let cost = sample(
+            &config,
+            &DEFAULT_PARAMETERS,
+            retention,
+            16,
+            None,
+            &mut || true,
+        )?;
+        assert!(cost.daily_cost <= daily_cost);
+
+        let constraints = RetentionConstraints {
+            max_daily_cost: Some(0.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            fsrs.optimal_retention_with_constraints(&config, &[], &constraints, |_| true),
+            Err(FSRSError::OptimalNotFound)
+        );
+        let constraints = RetentionConstraints {
+            min_retention: 0.95,
+            max_retention: 0.9,
+            ..Default::default()
+        };
+        assert_eq!(
+            fsrs.optimal_retention_with_constraints(&config, &[], &constraints, |_| true),
+            Err(FSRSError::InvalidInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn retention_workload_curve() -> Result<()> {
+        let fsrs = FSRS::new(None)?;
+        let config = SimulatorConfig {
+            learn_span: 180,
+            learn_limit: 10,
+            ..Default::default()
+        };
+        let mut progress = vec![];
+        let curve = fsrs.retention_workload_curve(&config, &[], 6, |p| {
+            progress.push(p.current);
+            true
+        })?;
+        assert_eq!(progress, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            curve
+                .iter()
+                .map(|point| point.desired_retention.to_2_decimal())
+                .collect_vec(),
+            [0.7, 0.75, 0.8, 0.85, 0.9, 0.95]
+        );
+        // more cards are memorized at a higher retention, at a higher cost near the top
+        assert!(curve
+            .iter()
+            .tuple_windows()
+            .all(|(a, b)| a.memorized < b.memorized));
+        assert!(curve[5].total_cost > curve[4].total_cost);
+        assert_eq!(
+            fsrs.retention_workload_curve(&config, &[], 1, |_| true),
+            Err(FSRSError::InvalidInput)
+        );
+        assert_eq!(
+            fsrs.retention_workload_curve(&config, &[], 6, |_| false),
+            Err(FSRSError::Interrupted {
+                stage: ProgressStage::OptimalRetention
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn max_learn_limit() -> Result<()> {
+        let fsrs = FSRS::new(None)?;
+        let config = SimulatorConfig {
+            learn_span: 180,
+            ..Default::default()
+        };
+        let learn_limit = fsrs.max_learn_limit(&config, &[], 0.9, 1800.0, |_| true)?;
+        assert!(learn_limit > 0);
+        let recent_daily_cost = |learn_limit| -> Result<f32> {
+            let config = SimulatorConfig {
+                deck_size: learn_limit * config.learn_span,
+                max_cost_perday: f32::INFINITY,
+                learn_limit,
+                ..config.clone()
+            };
+            let n = sample_size(&config);
+            let costs = sample(&config, &DEFAULT_PARAMETERS, 0.9, n, None, &mut || true)?;
+            Ok(costs.recent_daily_cost)
+        };
+        assert!(recent_daily_cost(learn_limit)? <= 1800.0);
+        assert!(recent_daily_cost(learn_limit + 1)? > 1800.0);
+        // a higher retention costs more, so fewer cards can be learnt
+        assert!(fsrs.max_learn_limit(&config, &[], 0.95, 1800.0, |_| true)? < learn_limit);
+        assert_eq!(fsrs.max_learn_limit(&config, &[], 0.9, 0.0, |_| true)?, 0);
+        assert_eq!(
+            fsrs.max_learn_limit(&config, &[], 1.5, 1800.0, |_| true),
+            Err(FSRSError::InvalidInput)
+        );
+        Ok(())
+    }
+
     #[test]
     fn extract_simulator_config_from_revlog() {
         let mut revlogs = read_collection().unwrap();
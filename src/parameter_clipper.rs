@@ -9,9 +9,13 @@ use burn::{
 
 pub(crate) fn parameter_clipper<B: Backend>(
     parameters: Param<Tensor<B, 1>>,
+    exponential_initial_difficulty: bool,
 ) -> Param<Tensor<B, 1>> {
     let (id, val) = parameters.consume();
-    let clipped = clip_parameters(&val.to_data().convert().value);
+    let clipped = clip_parameters(
+        &val.to_data().convert().value,
+        exponential_initial_difficulty,
+    );
     Param::initialized(
         id,
         Tensor::from_data(
@@ -22,9 +26,15 @@ pub(crate) fn parameter_clipper<B: Backend>(
     )
 }
 
-pub(crate) fn clip_parameters(parameters: &Parameters) -> Vec<f32> {
+/// Clamp each parameter to its range. w5 is the exponent of the initial difficulty, or its
+/// slope, as in FSRS-4.5, without
+/// [ModelConfig::exponential_initial_difficulty](crate::ModelConfig::exponential_initial_difficulty).
+pub(crate) fn clip_parameters(
+    parameters: &Parameters,
+    exponential_initial_difficulty: bool,
+) -> Vec<f32> {
     // https://regex101.com/r/21mXNI/1
-    const CLAMPS: [(f32, f32); 19] = [
+    const CLAMPS: [(f32, f32); 20] = [
         (S_MIN, INIT_S_MAX),
         (S_MIN, INIT_S_MAX),
         (S_MIN, INIT_S_MAX),
@@ -44,12 +54,19 @@ pub(crate) fn clip_parameters(parameters: &Parameters) -> Vec<f32> {
         (1.0, 6.0),
         (0.0, 2.0),
         (0.0, 2.0),
+        // decay
+        (0.1, 0.8),
     ];
 
+    let mut clamps = CLAMPS;
+    if !exponential_initial_difficulty {
+        clamps[5] = (0.1, 5.0);
+    }
+
     let mut parameters = parameters.to_vec();
     parameters
         .iter_mut()
-        .zip(CLAMPS)
+        .zip(clamps)
         .for_each(|(w, (low, high))| *w = w.clamp(low, high));
     parameters
 }
@@ -68,7 +85,7 @@ mod tests {
             &device,
         );
 
-        let param = parameter_clipper(Param::from_tensor(tensor));
+        let param = parameter_clipper(Param::from_tensor(tensor), true);
         let values = &param.to_data().value;
 
         assert_eq!(
@@ -76,4 +93,24 @@ mod tests {
             &[0.01, 0.01, 100.0, 0.01, 10.0, 0.001, 1.0, 0.25, 0.0]
         );
     }
+
+    #[test]
+    fn decay_is_clipped() {
+        let mut parameters = crate::DEFAULT_PARAMETERS.to_vec();
+        parameters.push(2.0);
+        assert_eq!(clip_parameters(&parameters, true)[19], 0.8);
+        parameters[19] = 0.0;
+        assert_eq!(clip_parameters(&parameters, true)[19], 0.1);
+    }
+
+    #[test]
+    fn linear_initial_difficulty_is_clipped() {
+        let mut parameters = crate::DEFAULT_PARAMETERS.to_vec();
+        parameters[5] = 4.5;
+        assert_eq!(clip_parameters(&parameters, true)[5], 4.0);
+        assert_eq!(clip_parameters(&parameters, false)[5], 4.5);
+        parameters[5] = 0.01;
+        assert_eq!(clip_parameters(&parameters, true)[5], 0.01);
+        assert_eq!(clip_parameters(&parameters, false)[5], 0.1);
+    }
 }
@@ -0,0 +1,292 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::inference::ModelEvaluation;
+use crate::model::check_and_fill_parameters;
+use crate::training::TrainingReport;
+
+/// Bumped whenever a field is added or changed. Files written by older versions are
+/// migrated when loaded; files written by newer versions are rejected.
+pub const PARAMETER_FILE_VERSION: u32 = 2;
+
+/// Trained parameters, together with how they were produced, saved as JSON.
+///
+/// Files written before the format was introduced, which contain only an array of
+/// parameters, can also be loaded; the other fields are then left empty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterFile {
+    pub version: u32,
+    pub parameters: Vec<f32>,
+    /// The version of this crate the parameters were trained with.
+    pub fsrs_version: String,
+    /// When the parameters were trained, in seconds since the epoch.
+    #[serde(default)]
+    pub trained_at: Option<i64>,
+    /// The number of items the parameters were trained on.
+    #[serde(default)]
+    pub item_count: Option<usize>,
+    /// The seed used for training.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// How well the parameters fit the items they were trained on.
+    #[serde(default)]
+    pub evaluation: Option<ModelEvaluation>,
+    /// The validation loss at the end of training. See [TrainingReport::final_loss].
+    #[serde(default)]
+    pub final_loss: Option<f64>,
+}
+
+impl ParameterFile {
+    /// Parameters trained with the current version of this crate, without any other
+    /// metadata.
+    pub fn new(parameters: Vec<f32>) -> Self {
+        Self {
+            version: PARAMETER_FILE_VERSION,
+            parameters,
+            fsrs_version: env!("CARGO_PKG_VERSION").to_string(),
+            trained_at: None,
+            item_count: None,
+            seed: None,
+            evaluation: None,
+            final_loss: None,
+        }
+    }
+
+    /// The parameters of `report`, with its item count and loss, trained now with `seed`.
+    pub fn from_report(report: &TrainingReport, seed: u64) -> Self {
+        Self {
+            trained_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|elapsed| elapsed.as_secs() as i64),
+            item_count: Some(report.item_count),
+            seed: Some(seed),
+            final_loss: report.final_loss,
+            ..Self::new(report.parameters.clone())
+        }
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+
+    /// Read a parameter file, migrating it from older versions. Returns an
+    /// [ErrorKind::InvalidData] error if it is not valid JSON, was written by a newer
+    /// version, or the parameters are invalid.
+    pub fn read(reader: impl Read) -> Result<Self> {
+        Self::from_value(serde_json::from_reader(reader)?)
+    }
+
+    /// Write the file in MessagePack, which is several times smaller than JSON. Fields are
+    /// written with their names, so files stay readable as fields are added.
+    #[cfg(any(test, feature = "msgpack"))]
+    pub fn write_compact(&self, mut writer: impl Write) -> Result<()> {
+        rmp_serde::encode::write_named(&mut writer, self)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        writer.flush()
+    }
+
+    /// Read a file written by [ParameterFile::write_compact], migrating it like
+    /// [ParameterFile::read].
+    #[cfg(any(test, feature = "msgpack"))]
+    pub fn read_compact(reader: impl Read) -> Result<Self> {
+        let value: Value =
+            rmp_serde::from_read(reader).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        Self::from_value(value)
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+        let mut file = match value {
+            // written before this format existed
+            Value::Array(_) => Self {
+                fsrs_version: String::new(),
+                ..Self::new(serde_json::from_value(value)?)
+            },
+            _ => {
+                let version = value.get("version").and_then(Value::as_u64);
+                match version {
+                    // version 2 added final_loss
+                    Some(1 | 2) => serde_json::from_value(value)?,
+                    Some(version) => {
+                        return Err(invalid(format!(
+                            "unsupported parameter file version {version}"
+                        )))
+                    }
+                    None => return Err(invalid("parameter file has no version".into())),
+                }
+            }
+        };
+        file.parameters = check_parameters(&file.parameters)?;
+        file.version = PARAMETER_FILE_VERSION;
+        Ok(file)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+fn check_parameters(parameters: &[f32]) -> Result<Vec<f32>> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+    if parameters.is_empty() {
+        return Err(invalid("parameter file has no parameters"));
+    }
+    // parameters from older versions of FSRS are converted to the current model
+    check_and_fill_parameters(parameters, true).map_err(|_| invalid("invalid parameters"))
+}
+
+/// The version byte at the start of [parameters_to_bytes]'s output.
+const PARAMETER_BYTES_VERSION: u8 = 1;
+
+/// Encode bare parameters in a fixed binary layout: a version byte, the parameter count
+/// (u8), and each parameter as a little endian f32. The smallest and fastest way to sync
+/// parameters between devices, when the metadata of a [ParameterFile] is not needed.
+pub fn parameters_to_bytes(parameters: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(2 + parameters.len() * 4);
+    bytes.push(PARAMETER_BYTES_VERSION);
+    bytes.push(parameters.len() as u8);
+    for parameter in parameters {
+        bytes.extend_from_slice(&parameter.to_le_bytes());
+    }
+    bytes
+}
+
+/// Decode the output of [parameters_to_bytes], migrating parameters from older versions of
+/// FSRS. Returns an [ErrorKind::InvalidData] error if the bytes are not in that layout, or
+/// the parameters are invalid.
+pub fn parameters_from_bytes(bytes: &[u8]) -> Result<Vec<f32>> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+    match bytes {
+        [PARAMETER_BYTES_VERSION, count, rest @ ..] if rest.len() == *count as usize * 4 => {
+            let parameters: Vec<f32> = rest
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+                .collect();
+            check_parameters(&parameters)
+        }
+        [PARAMETER_BYTES_VERSION, ..] => Err(invalid("parameter bytes are truncated")),
+        [version, ..] => Err(invalid(&format!(
+            "unsupported parameter bytes version {version}"
+        ))),
+        [] => Err(invalid("no parameter bytes")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_PARAMETERS;
+
+    #[test]
+    fn round_trip() {
+        let file = ParameterFile {
+            trained_at: Some(1_700_000_000),
+            item_count: Some(1000),
+            seed: Some(2023),
+            evaluation: Some(ModelEvaluation {
+                log_loss: 0.3,
+                rmse_bins: 0.05,
+                auc: Some(0.7),
+            }),
+            final_loss: Some(0.31),
+            ..ParameterFile::new(DEFAULT_PARAMETERS.to_vec())
+        };
+        let mut json = vec![];
+        file.write(&mut json).unwrap();
+        assert_eq!(ParameterFile::read(json.as_slice()).unwrap(), file);
+
+        let path = std::env::temp_dir().join("fsrs_parameter_file.json");
+        file.save(&path).unwrap();
+        assert_eq!(ParameterFile::load(&path).unwrap(), file);
+        std::fs::remove_file(path).unwrap();
+
+        let report = TrainingReport {
+            parameters: DEFAULT_PARAMETERS.to_vec(),
+            final_loss: Some(0.3),
+            epoch_losses: vec![0.4, 0.3],
+            evaluated_rmse: Some(0.05),
+            item_count: 100,
+        };
+        let file = ParameterFile::from_report(&report, 2023);
+        assert!(file.trained_at.unwrap() > 1_700_000_000);
+        assert_eq!(file.item_count, Some(100));
+        assert_eq!(file.final_loss, Some(0.3));
+        assert_eq!(file.seed, Some(2023));
+    }
+
+    #[test]
+    fn migration_and_validation() {
+        // a bare array of FSRS-4.5 parameters
+        let legacy = serde_json::to_string(&[0.4f32; 17]).unwrap();
+        let file = ParameterFile::read(legacy.as_bytes()).unwrap();
+        assert_eq!(file.version, PARAMETER_FILE_VERSION);
+        assert_eq!(file.parameters.len(), 19);
+        assert_eq!(file.item_count, None);
+
+        let read = |json: &str| ParameterFile::read(json.as_bytes()).unwrap_err().kind();
+        assert_eq!(
+            read(r#"{"version": 3, "parameters": []}"#),
+            ErrorKind::InvalidData
+        );
+        assert_eq!(read(r#"{"parameters": [1.0]}"#), ErrorKind::InvalidData);
+        assert_eq!(
+            read(r#"{"version": 1, "parameters": [1.0, 2.0], "fsrs_version": "2.0.2"}"#),
+            ErrorKind::InvalidData
+        );
+        assert_eq!(read("[]"), ErrorKind::InvalidData);
+
+        let v1 = r#"{"version": 1, "parameters": [0.4, 0.6, 2.4, 5.8, 4.93, 0.94, 0.86, 0.01, 1.49, 0.14, 0.94, 2.18, 0.05, 0.34, 1.26, 0.29, 2.61, 0.0, 0.0], "fsrs_version": "2.0.2", "item_count": 10}"#;
+        let file = ParameterFile::read(v1.as_bytes()).unwrap();
+        assert_eq!(file.version, PARAMETER_FILE_VERSION);
+        assert_eq!(file.item_count, Some(10));
+        assert_eq!(file.final_loss, None);
+        assert_eq!(read("not json"), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn compact_formats() {
+        let file = ParameterFile {
+            item_count: Some(1000),
+            ..ParameterFile::new(DEFAULT_PARAMETERS.to_vec())
+        };
+        let mut compact = vec![];
+        file.write_compact(&mut compact).unwrap();
+        let mut json = vec![];
+        file.write(&mut json).unwrap();
+        assert!(compact.len() < json.len() / 2);
+        assert_eq!(
+            ParameterFile::read_compact(compact.as_slice()).unwrap(),
+            file
+        );
+        assert_eq!(
+            ParameterFile::read_compact(&b"not msgpack"[..])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidData
+        );
+
+        let bytes = parameters_to_bytes(&DEFAULT_PARAMETERS);
+        assert_eq!(bytes.len(), 2 + 19 * 4);
+        assert_eq!(parameters_from_bytes(&bytes).unwrap(), DEFAULT_PARAMETERS);
+        // FSRS-4.5 parameters are migrated
+        let legacy = parameters_to_bytes(&[0.4; 17]);
+        assert_eq!(parameters_from_bytes(&legacy).unwrap().len(), 19);
+        for invalid in [&[][..], &[2, 0], &bytes[..10], &[1, 0]] {
+            assert_eq!(
+                parameters_from_bytes(invalid).unwrap_err().kind(),
+                ErrorKind::InvalidData
+            );
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::{FSRSError, Result};
+use crate::forgetting_curve::CurveKind;
 use crate::inference::{DECAY, FACTOR, S_MIN};
 use crate::FSRSItem;
 use crate::DEFAULT_PARAMETERS;
@@ -12,13 +13,20 @@ static R_S0_DEFAULT_ARRAY: &[(u32, f32); 4] = &[
     (4, DEFAULT_PARAMETERS[3]),
 ];
 
-pub fn pretrain(
+/// Fit the initial stability of each first rating on `curve`, the curve the rest of the
+/// parameters are trained on.
+pub(crate) fn pretrain(
     fsrs_items: Vec<FSRSItem>,
     average_recall: f32,
+    curve: CurveKind,
 ) -> Result<([f32; 4], HashMap<u32, u32>)> {
     let pretrainset = create_pretrain_data(fsrs_items);
     let rating_count = total_rating_count(&pretrainset);
-    let mut rating_stability = search_parameters(pretrainset, average_recall);
+    let found = rating_count.values().sum::<u32>() as usize;
+    if found == 0 {
+        return Err(FSRSError::NotEnoughData { found, required: 1 });
+    }
+    let mut rating_stability = search_parameters(pretrainset, average_recall, curve);
     Ok((
         smooth_and_fill(&mut rating_stability, &rating_count)?,
         rating_count,
@@ -28,19 +36,41 @@ pub fn pretrain(
 type FirstRating = u32;
 type Count = u32;
 
-fn create_pretrain_data(fsrs_items: Vec<FSRSItem>) -> HashMap<FirstRating, Vec<AverageRecall>> {
-    // filter FSRSItem instances with exactly 1 long term review.
-    let items: Vec<_> = fsrs_items
+/// The data used to fit the initial stability of each first rating, split from the items
+/// that are only used to train the other parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PretrainPartition {
+    /// Items with exactly one long-term review, keyed by the rating of the first review.
+    /// Within each rating, items are grouped by the `delta_t` of the long-term review,
+    /// sorted by `delta_t`.
+    pub groups: HashMap<FirstRating, Vec<AverageRecall>>,
+    /// All other items.
+    pub remaining: Vec<FSRSItem>,
+}
+
+/// Split items into the groups used to pretrain the initial stability, and the rest.
+pub fn partition_pretrain_data(fsrs_items: Vec<FSRSItem>) -> PretrainPartition {
+    let groups = first_review_recall(&fsrs_items);
+    let remaining = fsrs_items
         .into_iter()
-        .filter(|item| item.long_term_review_cnt() == 1)
+        .filter(|item| item.long_term_review_cnt() != 1)
         .collect();
+    PretrainPartition { groups, remaining }
+}
 
+/// The empirical forgetting curve after the first review: for each rating of the first
+/// review, the average recall and count of the first long-term review at each `delta_t`,
+/// sorted by `delta_t`. Only items with exactly one long-term review are counted.
+pub fn first_review_recall(fsrs_items: &[FSRSItem]) -> HashMap<FirstRating, Vec<AverageRecall>> {
     // use a nested HashMap (groups) to group items first by the rating in the first FSRSReview
     // and then by the delta_t in the second FSRSReview.
     // (first_rating -> first_long_term_delta_t -> vec![0/1 for fail/pass])
     let mut groups = HashMap::new();
 
-    for item in items {
+    for item in fsrs_items
+        .iter()
+        .filter(|item| item.long_term_review_cnt() == 1)
+    {
         let first_rating = item.reviews[0].rating;
         let first_long_term_review = item.first_long_term_review();
         let first_long_term_delta_t = first_long_term_review.delta_t;
@@ -76,11 +106,16 @@ fn create_pretrain_data(fsrs_items: Vec<FSRSItem>) -> HashMap<FirstRating, Vec<A
     results
 }
 
+fn create_pretrain_data(fsrs_items: Vec<FSRSItem>) -> HashMap<FirstRating, Vec<AverageRecall>> {
+    first_review_recall(&fsrs_items)
+}
+
 /// The average pass rate & count for a single delta_t for a given first rating.
-struct AverageRecall {
-    delta_t: f64,
-    recall: f64,
-    count: f64,
+#[derive(Debug, Clone, PartialEq)]
+pub struct AverageRecall {
+    pub delta_t: f64,
+    pub recall: f64,
+    pub count: f64,
 }
 
 fn total_rating_count(
@@ -99,14 +134,22 @@ fn power_forgetting_curve(t: &Array1<f64>, s: f64) -> Array1<f64> {
     (t / s * FACTOR + 1.0).mapv(|v| v.powf(DECAY))
 }
 
+fn forgetting_curve(curve: CurveKind, t: &Array1<f64>, s: f64) -> Array1<f64> {
+    match curve {
+        CurveKind::Power => power_forgetting_curve(t, s),
+        CurveKind::Exponential => (t / s).mapv(|v| 0.9f64.powf(v)),
+    }
+}
+
 fn loss(
+    curve: CurveKind,
     delta_t: &Array1<f64>,
     recall: &Array1<f64>,
     count: &Array1<f64>,
     init_s0: f64,
     default_s0: f64,
 ) -> f64 {
-    let y_pred = power_forgetting_curve(delta_t, init_s0);
+    let y_pred = forgetting_curve(curve, delta_t, init_s0);
     let logloss = (-(recall * y_pred.clone().mapv_into(|v| v.ln())
         + (1.0 - recall) * (1.0 - &y_pred).mapv_into(|v| v.ln()))
         * count)
@@ -120,6 +163,7 @@ pub(crate) const INIT_S_MAX: f32 = 100.0;
 fn search_parameters(
     mut pretrainset: HashMap<FirstRating, Vec<AverageRecall>>,
     average_recall: f32,
+    curve: CurveKind,
 ) -> HashMap<u32, f32> {
     let mut optimal_stabilities = HashMap::new();
     let epsilon = f64::EPSILON;
@@ -146,8 +190,8 @@ fn search_parameters(
             let mid1 = low + (high - low) / 3.0;
             let mid2 = high - (high - low) / 3.0;
 
-            let loss1 = loss(&delta_t, &recall, &count, mid1, default_s0);
-            let loss2 = loss(&delta_t, &recall, &count, mid2, default_s0);
+            let loss1 = loss(curve, &delta_t, &recall, &count, mid1, default_s0);
+            let loss2 = loss(curve, &delta_t, &recall, &count, mid2, default_s0);
 
             if loss1 < loss2 {
                 high = mid2;
@@ -201,7 +245,12 @@ pub(crate) fn smooth_and_fill(
         rating_stability.get(&4).cloned(),
     ];
     match rating_stability.len() {
-        0 => return Err(FSRSError::NotEnoughData),
+        0 => {
+            return Err(FSRSError::NotEnoughData {
+                found: 0,
+                required: 1,
+            })
+        }
         1 => {
             let rating = rating_stability.keys().next().unwrap();
             let factor = rating_stability[rating] / r_s0_default[rating];
@@ -280,10 +329,63 @@ pub(crate) fn smooth_and_fill(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::dataset::filter_outlier;
-    use crate::test_helpers::assert_approx_eq;
+    use crate::dataset::{filter_outlier, OutlierFilterConfig};
+    use crate::test_helpers::{assert_approx_eq, item_from};
     use crate::training::calculate_average_recall;
 
+    #[test]
+    fn test_partition_pretrain_data() {
+        let items = vec![
+            item_from(&[(3, 0), (3, 2)]),
+            item_from(&[(3, 0), (1, 2)]),
+            item_from(&[(3, 0), (3, 1)]),
+            item_from(&[(1, 0), (3, 0), (3, 1)]),
+            item_from(&[(3, 0), (3, 2), (3, 5)]),
+            item_from(&[(3, 0), (3, 0)]),
+        ];
+        assert_eq!(first_review_recall(&items).len(), 2);
+        let PretrainPartition { groups, remaining } = partition_pretrain_data(items.clone());
+        assert_eq!(remaining, [items[4].clone(), items[5].clone()]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[&3],
+            [
+                AverageRecall {
+                    delta_t: 1.0,
+                    recall: 1.0,
+                    count: 1.0
+                },
+                AverageRecall {
+                    delta_t: 2.0,
+                    recall: 0.5,
+                    count: 2.0
+                }
+            ]
+        );
+        assert_eq!(
+            groups[&1],
+            [AverageRecall {
+                delta_t: 1.0,
+                recall: 1.0,
+                count: 1.0
+            }]
+        );
+
+        // none of the remaining items can be used for pretraining
+        let err = pretrain(remaining, 0.9, CurveKind::Power).unwrap_err();
+        assert_eq!(
+            err,
+            FSRSError::NotEnoughData {
+                found: 0,
+                required: 1
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "not enough data: found 0 items, at least 1 required"
+        );
+    }
+
     #[test]
     fn test_power_forgetting_curve() {
         let t = Array1::from(vec![0.0, 1.0, 2.0, 3.0]);
@@ -301,9 +403,23 @@ mod tests {
         ]);
         let count = Array1::from(vec![435.0, 97.0, 63.0, 38.0, 28.0]);
         let default_s0 = DEFAULT_PARAMETERS[0] as f64;
-        let actual = loss(&delta_t, &recall, &count, 1.017056, default_s0);
+        let actual = loss(
+            CurveKind::Power,
+            &delta_t,
+            &recall,
+            &count,
+            1.017056,
+            default_s0,
+        );
         assert_eq!(actual, 280.75007086903867);
-        let actual = loss(&delta_t, &recall, &count, 1.017011, default_s0);
+        let actual = loss(
+            CurveKind::Power,
+            &delta_t,
+            &recall,
+            &count,
+            1.017011,
+            default_s0,
+        );
         assert_eq!(actual, 280.74973684868695);
     }
 
@@ -340,8 +456,12 @@ mod tests {
                 },
             ],
         )]);
-        let actual = search_parameters(pretrainset, 0.943_028_57);
+        let actual = search_parameters(pretrainset.clone(), 0.943_028_57, CurveKind::Power);
         assert_approx_eq([*actual.get(&first_rating).unwrap()], [0.908_688]);
+        // the exponential curve falls faster after the first day, so it needs a higher
+        // stability to fit the later reviews
+        let exponential = search_parameters(pretrainset, 0.943_028_57, CurveKind::Exponential);
+        assert!(exponential[&first_rating] > actual[&first_rating] + 0.01);
     }
 
     #[test]
@@ -351,12 +471,15 @@ mod tests {
         let (mut pretrainset, mut trainset) = items
             .into_iter()
             .partition(|item| item.long_term_review_cnt() == 1);
-        (pretrainset, trainset) = filter_outlier(pretrainset, trainset);
+        (pretrainset, trainset) =
+            filter_outlier(pretrainset, trainset, &OutlierFilterConfig::new());
         let items = [pretrainset.clone(), trainset].concat();
         let average_recall = calculate_average_recall(&items);
 
         assert_approx_eq(
-            pretrain(pretrainset, average_recall).unwrap().0,
+            pretrain(pretrainset, average_recall, CurveKind::Power)
+                .unwrap()
+                .0,
             [0.908_688, 2.247_462, 4.216_837, 9.615_904],
         );
     }
@@ -0,0 +1,27 @@
+//! The types most applications need, in one import:
+//!
+//! ```
+//! use fsrs::prelude::*;
+//! ```
+//!
+//! Everything here is also exported from the crate root, which exports little else: the
+//! rest of the API is used through its module, eg `fsrs::dataset::FSRSBatcher` or
+//! `fsrs::legacy::migrate_fsrs_v4_weights`. Items are only removed from the prelude in a
+//! semver-breaking release.
+
+pub use crate::builder::FSRSBuilder;
+pub use crate::convertor::ConvertorConfig;
+pub use crate::dataset::{FSRSItem, FSRSReview};
+pub use crate::error::{FSRSError, Result};
+pub use crate::inference::{
+    ItemProgress, ItemState, MemoryState, ModelEvaluation, NextStates, Parameters,
+    DEFAULT_PARAMETERS,
+};
+pub use crate::model::{ModelConfig, FSRS};
+pub use crate::optimal_retention::{
+    RevlogEntry, RevlogReviewKind, SimulationResult, SimulatorConfig,
+};
+pub use crate::parameter_file::ParameterFile;
+pub use crate::progress::{CancellationToken, ProgressHandle, ProgressStage};
+pub use crate::scheduler::{Scheduler, SchedulerConfig};
+pub use crate::training::{CombinedProgressState, TrainingConfig};
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use burn::tensor::backend::Backend;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::builder::FSRSBuilder;
+use crate::dataset::FSRSItem;
+use crate::error::Result;
+use crate::inference::{DefaultComparison, ParametersRecommendation};
+use crate::progress::ProgressHandle;
+use crate::training::{CombinedProgressState, ProgressState};
+use crate::FSRS;
+
+/// The result of [FSRS::optimize_presets] for a single preset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresetOptimization {
+    pub parameters: Vec<f32>,
+    /// The parameters evaluated against the defaults on the items of the preset. None if the
+    /// preset has no items.
+    pub comparison: Option<DefaultComparison>,
+}
+
+impl PresetOptimization {
+    /// Whether the parameters should be saved to the preset, rather than keeping the
+    /// defaults.
+    pub fn should_use_parameters(&self) -> bool {
+        matches!(
+            self.comparison.map(|comparison| comparison.recommendation),
+            Some(ParametersRecommendation::UseParameters)
+        )
+    }
+}
+
+impl<B: Backend> FSRS<B> {
+    /// Train parameters for each preset (eg an Anki deck preset) on its items, and evaluate
+    /// them against the defaults, as [FSRS::compute_parameters] and
+    /// [FSRS::benchmark_against_default] would.
+    ///
+    /// Presets are trained in parallel, on the thread pool of this instance. Training of
+    /// each preset is reported in its own entry of `progress.splits`, in the order of the
+    /// keys, so [CombinedProgressState::current] and [CombinedProgressState::total] cover
+    /// all of them; the state is marked finished once every preset is done. As with
+    /// [FSRS::compute_parameters], parameters are evaluated on the items they were trained
+    /// on, so the comparison favours them slightly.
+    ///
+    /// Training of a preset with an entry in `warm_starts` (eg its current parameters)
+    /// starts from those parameters, via
+    /// [ModelConfig::initial_parameters](crate::ModelConfig::initial_parameters), instead of
+    /// the defaults and pretrained initial stabilities. This usually needs fewer epochs to
+    /// converge when re-optimizing after a few more weeks of reviews.
+    ///
+    /// Stops at the first error, eg when the items of a preset are invalid or the
+    /// operation is cancelled.
+    pub fn optimize_presets<K: Ord + Send + Sync>(
+        &self,
+        presets: BTreeMap<K, Vec<FSRSItem>>,
+        warm_starts: &BTreeMap<K, Vec<f32>>,
+        progress: Option<Arc<Mutex<CombinedProgressState>>>,
+        enable_short_term: bool,
+    ) -> Result<BTreeMap<K, PresetOptimization>> {
+        let config = self.default_training_config(enable_short_term);
+        let progress =
+            progress.or_else(|| self.progress_handle().map(ProgressHandle::training_state));
+        if let Some(progress) = &progress {
+            // estimates, replaced once training of a preset starts
+            progress.lock().unwrap().splits = presets
+                .values()
+                .flat_map(|items| {
+                    let state = ProgressState {
+                        epoch: 0,
+                        epoch_total: config.num_epochs,
+                        items_processed: 0,
+                        items_total: items.len(),
+                    };
+                    vec![state; config.n_splits]
+                })
+                .collect();
+        }
+        let results = self.install(|| {
+            presets
+                .into_iter()
+                .enumerate()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|(split, (key, items))| {
+                    let mut config = config.clone();
+                    config.model.initial_parameters = warm_starts.get(&key).cloned();
+                    let parameters = self
+                        .compute_split(
+                            items.clone(),
+                            None,
+                            progress.as_ref(),
+                            split,
+                            &config,
+                            None,
+                        )?
+                        .parameters;
+                    if let Some(progress) = &progress {
+                        // training may stop early, or not be needed
+                        let splits = &mut progress.lock().unwrap().splits;
+                        let folds = split * config.n_splits..(split + 1) * config.n_splits;
+                        for state in &mut splits[folds] {
+                            state.epoch = state.epoch_total;
+                            state.items_processed = state.items_total;
+                        }
+                    }
+                    let comparison = if items.is_empty() {
+                        None
+                    } else {
+                        let trained = FSRSBuilder::<B>::on_device(self.device())
+                            .parameters(&parameters)
+                            .forgetting_curve(self.forgetting_curve())
+                            .linear_damping(self.linear_damping())
+                            .exponential_initial_difficulty(self.exponential_initial_difficulty())
+                            .build()?;
+                        let trained = match self.cancellation_token() {
+                            Some(token) => trained.with_cancellation_token(token.clone()),
+                            None => trained,
+                        };
+                        Some(trained.benchmark_against_default(items, |_| true)?)
+                    };
+                    Ok((
+                        key,
+                        PresetOptimization {
+                            parameters,
+                            comparison,
+                        },
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()
+        });
+        if let Some(progress) = &progress {
+            progress.lock().unwrap().finished = true;
+        }
+        Ok(results?.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FSRSError;
+    use crate::model::ModelConfig;
+    use crate::progress::CancellationToken;
+    use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
+    use crate::test_helpers::item_from;
+    use crate::training::TrainingConfig;
+    use crate::DEFAULT_PARAMETERS;
+
+    #[test]
+    fn optimize_presets() -> Result<()> {
+        let item = item_from(&[(3, 0), (3, 2)]);
+        // "empty" and "small" have too few items to train on
+        let presets = BTreeMap::from([
+            ("empty", vec![]),
+            ("small", vec![item.clone(); 5]),
+            ("larger", vec![item.clone(); 100]),
+        ]);
+        let fsrs = FSRS::new(None)?.with_max_threads(2)?;
+        let progress = CombinedProgressState::new_shared();
+        let results = fsrs.optimize_presets(
+            presets.clone(),
+            &BTreeMap::new(),
+            Some(progress.clone()),
+            false,
+        )?;
+        assert_eq!(
+            results.keys().copied().collect::<Vec<_>>(),
+            ["empty", "larger", "small"]
+        );
+        let empty = &results["empty"];
+        assert_eq!(empty.parameters, DEFAULT_PARAMETERS);
+        assert_eq!(empty.comparison, None);
+        assert!(!empty.should_use_parameters());
+        let small = results["small"].comparison.unwrap();
+        assert_eq!(small.recommendation, ParametersRecommendation::NeedMoreData);
+        assert!(results["larger"].comparison.is_some());
+
+        let progress = progress.lock().unwrap();
+        assert!(progress.finished());
+        assert_eq!(progress.splits.len(), 3);
+        assert_eq!(progress.current(), progress.total());
+        drop(progress);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let fsrs = FSRS::new(None)?.with_cancellation_token(token);
+        // whichever preset is stopped first
+        assert!(matches!(
+            fsrs.optimize_presets(presets, &BTreeMap::new(), None, false),
+            Err(FSRSError::Interrupted { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn warm_starts() -> Result<()> {
+        let items = generate_sample_items(&SampleDatasetConfig {
+            reviews_per_card: 5,
+            ..Default::default()
+        })?;
+        let presets = BTreeMap::from([("cold", items.clone()), ("warm", items)]);
+        let mut warm_start = DEFAULT_PARAMETERS.to_vec();
+        warm_start[0] = 3.0;
+        let fsrs = FSRS::new(None)?
+            .with_training_config(TrainingConfig::new(ModelConfig::default()).with_num_epochs(1));
+        let results = fsrs.optimize_presets(
+            presets.clone(),
+            &BTreeMap::from([("warm", warm_start)]),
+            None,
+            false,
+        )?;
+        assert_ne!(results["warm"].parameters, results["cold"].parameters);
+        // the initial stability isn't trained without short-term scheduling
+        assert_eq!(results["warm"].parameters[0], 3.0);
+
+        assert!(matches!(
+            fsrs.optimize_presets(presets, &BTreeMap::from([("warm", vec![1.0])]), None, false),
+            Err(FSRSError::InvalidParameters { .. })
+        ));
+        Ok(())
+    }
+}
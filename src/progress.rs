@@ -0,0 +1,334 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use crate::inference::ItemProgress;
+use crate::training::CombinedProgressState;
+
+/// The long-running operation a [ProgressHandle] is tracking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStage {
+    Training,
+    /// [FSRS::evaluate](crate::FSRS::evaluate), and the other methods comparing predictions
+    /// with the items.
+    Evaluation,
+    /// [FSRS::optimal_retention](crate::FSRS::optimal_retention), and the other methods
+    /// running many simulations.
+    OptimalRetention,
+    /// A single run of [FSRS::simulate](crate::FSRS::simulate).
+    Simulation,
+}
+
+/// Aborts training, evaluation or [FSRS::optimal_retention](crate::FSRS::optimal_retention)
+/// from another thread, without relying on the progress callback being called.
+///
+/// Attach a token with [FSRS::with_cancellation_token](crate::FSRS::with_cancellation_token).
+/// It is checked after every batch, or every simulated day, and once cancelled, every
+/// operation started with it returns [FSRSError::Interrupted](crate::FSRSError::Interrupted).
+/// Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Default)]
+struct HandleState {
+    stage: Option<ProgressStage>,
+    current: usize,
+    total: usize,
+    cancelled: CancellationToken,
+    training: Option<Arc<Mutex<CombinedProgressState>>>,
+}
+
+/// A single place to follow the progress of, and cancel, any of the long-running
+/// operations: training ([FSRS::compute_parameters](crate::FSRS::compute_parameters)),
+/// evaluation, simulation and [FSRS::optimal_retention](crate::FSRS::optimal_retention).
+///
+/// The handle is cheap to clone and can be shared with another thread, eg a UI thread that
+/// polls [ProgressHandle::fraction] and calls [ProgressHandle::cancel]. Attach it to an
+/// instance with [FSRSBuilder::progress_handle](crate::FSRSBuilder::progress_handle), and
+/// every operation of the instance reports to it, with the stage it is in (training only
+/// when it isn't given a progress state of its own). Alternatively, pass [ProgressHandle::reporter] or [ProgressHandle::training_state]
+/// to a single operation. Once cancelled, the operation returns
+/// [FSRSError::Interrupted](crate::FSRSError::Interrupted).
+#[derive(Clone, Default)]
+pub struct ProgressHandle {
+    state: Arc<Mutex<HandleState>>,
+}
+
+impl ProgressHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A callback that records progress under `stage`, and stops the operation once the
+    /// handle is cancelled.
+    pub fn reporter(&self, stage: ProgressStage) -> impl FnMut(ItemProgress) -> bool + Send {
+        self.start(stage, None);
+        let handle = self.clone();
+        move |progress| handle.update(stage, progress)
+    }
+
+    /// Record `progress` under `stage`, returning false once the handle is cancelled.
+    pub(crate) fn update(&self, stage: ProgressStage, progress: ItemProgress) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.stage != Some(stage) {
+            state.stage = Some(stage);
+            state.training = None;
+        }
+        state.current = progress.current;
+        state.total = progress.total;
+        !state.cancelled.is_cancelled()
+    }
+
+    /// The progress state to pass to training.
+    pub fn training_state(&self) -> Arc<Mutex<CombinedProgressState>> {
+        let training = CombinedProgressState::new_shared();
+        self.start(ProgressStage::Training, Some(training.clone()));
+        training
+    }
+
+    fn start(&self, stage: ProgressStage, training: Option<Arc<Mutex<CombinedProgressState>>>) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(training) = &training {
+            training.lock().unwrap().want_abort = state.cancelled.is_cancelled();
+        }
+        state.stage = Some(stage);
+        state.current = 0;
+        state.total = 0;
+        state.training = training;
+    }
+
+    /// The operation currently tracked, if any.
+    pub fn stage(&self) -> Option<ProgressStage> {
+        self.state.lock().unwrap().stage
+    }
+
+    /// The number of steps done and the total, if known (0 otherwise).
+    pub fn progress(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap();
+        match &state.training {
+            Some(training) => {
+                let training = training.lock().unwrap();
+                (training.current(), training.total())
+            }
+            None => (state.current, state.total),
+        }
+    }
+
+    /// The fraction of the operation that has been done, or None if the total is unknown.
+    pub fn fraction(&self) -> Option<f32> {
+        let (current, total) = self.progress();
+        (total > 0).then(|| (current as f32 / total as f32).min(1.0))
+    }
+
+    /// Ask the operation to stop at the next opportunity.
+    pub fn cancel(&self) {
+        let state = self.state.lock().unwrap();
+        state.cancelled.cancel();
+        if let Some(training) = &state.training {
+            training.lock().unwrap().want_abort = true;
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().cancelled.is_cancelled()
+    }
+
+    /// A token that is cancelled together with this handle, to also stop operations between
+    /// calls to the progress callback.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.state.lock().unwrap().cancelled.clone()
+    }
+}
+
+impl fmt::Debug for ProgressHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressHandle")
+            .field("stage", &self.stage())
+            .field("progress", &self.progress())
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// An update sent by a [ProgressSender].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressMessage {
+    pub stage: ProgressStage,
+    pub current: usize,
+    /// 0 if not known yet.
+    pub total: usize,
+}
+
+/// Reports progress as [ProgressMessage]s on a channel, so another thread can poll it with
+/// [Receiver::try_recv], or wait on it, instead of sharing a callback. Create one with
+/// [progress_channel].
+///
+/// Sending never blocks, and the operation is not stopped when the receiver is dropped; use
+/// a [CancellationToken] for that.
+#[derive(Debug, Clone)]
+pub struct ProgressSender {
+    sender: Sender<ProgressMessage>,
+}
+
+/// A [ProgressSender], and the receiving end of its messages. The channel is closed once
+/// the sender, and everything created from it, has been dropped.
+pub fn progress_channel() -> (ProgressSender, Receiver<ProgressMessage>) {
+    let (sender, receiver) = mpsc::channel();
+    (ProgressSender { sender }, receiver)
+}
+
+impl ProgressSender {
+    /// A callback for operations taking an [ItemProgress] callback, that sends each update
+    /// under `stage`.
+    pub fn reporter(&self, stage: ProgressStage) -> impl FnMut(ItemProgress) -> bool + Send {
+        let sender = self.sender.clone();
+        move |progress| {
+            let _ = sender.send(ProgressMessage {
+                stage,
+                current: progress.current,
+                total: progress.total,
+            });
+            true
+        }
+    }
+
+    /// The progress state to pass to training, which sends an update after every batch.
+    pub fn training_state(&self) -> Arc<Mutex<CombinedProgressState>> {
+        let training = CombinedProgressState::new_shared();
+        training.lock().unwrap().listener = Some(self.sender.clone());
+        training
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FSRSError;
+    use crate::model::FSRS;
+    use crate::optimal_retention::SimulatorConfig;
+    use crate::test_helpers::item_from;
+    use crate::DEFAULT_PARAMETERS;
+
+    #[test]
+    fn progress_handle() -> crate::Result<()> {
+        let items = vec![item_from(&[(3, 0), (3, 2)]); 1000];
+        let fsrs = FSRS::new(Some(&[]))?;
+        let handle = ProgressHandle::new();
+        assert_eq!(handle.stage(), None);
+        assert_eq!(handle.fraction(), None);
+        fsrs.evaluate(items.clone(), handle.reporter(ProgressStage::Evaluation))?;
+        assert_eq!(handle.stage(), Some(ProgressStage::Evaluation));
+        assert_eq!(handle.progress(), (1000, 1000));
+        assert_eq!(handle.fraction(), Some(1.0));
+
+        let token = handle.cancellation_token();
+        assert!(!token.is_cancelled());
+        handle.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(
+            fsrs.evaluate(items, handle.reporter(ProgressStage::Evaluation))
+                .unwrap_err(),
+            FSRSError::Interrupted {
+                stage: ProgressStage::Evaluation
+            }
+        );
+        let training = handle.training_state();
+        assert!(training.lock().unwrap().want_abort);
+        assert_eq!(handle.stage(), Some(ProgressStage::Training));
+        Ok(())
+    }
+
+    #[test]
+    fn attached_progress_handle() -> crate::Result<()> {
+        let items = vec![item_from(&[(3, 0), (3, 2)]); 100];
+        let handle = ProgressHandle::new();
+        let fsrs = FSRS::builder()
+            .parameters(&[])
+            .progress_handle(&handle)
+            .build()?;
+        fsrs.evaluate(items.clone(), |_| true)?;
+        assert_eq!(handle.stage(), Some(ProgressStage::Evaluation));
+        assert_eq!(handle.progress(), (100, 100));
+
+        let config = SimulatorConfig {
+            deck_size: 100,
+            learn_span: 30,
+            ..Default::default()
+        };
+        fsrs.simulate(&config, 0.9, Some(42), None)?;
+        assert_eq!(handle.stage(), Some(ProgressStage::Simulation));
+        assert_eq!(handle.progress(), (30, 30));
+
+        fsrs.retention_workload_curve(&config, &DEFAULT_PARAMETERS, 2, |_| true)?;
+        assert_eq!(handle.stage(), Some(ProgressStage::OptimalRetention));
+        assert_eq!(handle.progress(), (1, 2));
+
+        // the callback of the command can still stop it
+        assert_eq!(
+            fsrs.evaluate(items.clone(), |_| false).unwrap_err(),
+            FSRSError::Interrupted {
+                stage: ProgressStage::Evaluation
+            }
+        );
+        handle.cancel();
+        assert_eq!(
+            fsrs.simulate(&config, 0.9, Some(42), None).unwrap_err(),
+            FSRSError::Interrupted {
+                stage: ProgressStage::Simulation
+            }
+        );
+        assert_eq!(
+            fsrs.optimal_retention(&config, &DEFAULT_PARAMETERS, |_| true)
+                .unwrap_err(),
+            FSRSError::Interrupted {
+                stage: ProgressStage::OptimalRetention
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn progress_channel() -> crate::Result<()> {
+        let items = vec![item_from(&[(3, 0), (3, 2)]); 1000];
+        let (sender, receiver) = super::progress_channel();
+        let worker = std::thread::spawn(move || {
+            let fsrs = FSRS::new(Some(&[]))?;
+            fsrs.evaluate(items, sender.reporter(ProgressStage::Evaluation))
+        });
+        let messages: Vec<_> = receiver.iter().collect();
+        worker.join().unwrap()?;
+        assert!(!messages.is_empty());
+        assert!(messages
+            .iter()
+            .all(|message| message.stage == ProgressStage::Evaluation && message.total == 1000));
+        assert!(messages
+            .windows(2)
+            .all(|pair| pair[0].current <= pair[1].current));
+        assert_eq!(messages.last().unwrap().current, 1000);
+
+        let (sender, receiver) = super::progress_channel();
+        let training = sender.training_state();
+        drop(sender);
+        assert!(training.lock().unwrap().listener.is_some());
+        drop(training);
+        assert!(receiver.recv().is_err());
+        Ok(())
+    }
+}
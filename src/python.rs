@@ -0,0 +1,364 @@
+//! Python bindings, enabled with the `python` feature. They mirror the interface of the
+//! Python optimizer, so the `fsrs` module can be built as an extension with eg
+//! `cargo rustc --release --lib --features python,pyo3/extension-module --crate-type cdylib`,
+//! and the resulting library renamed to `fsrs.so` (`fsrs.pyd` on Windows).
+//!
+//! ```python
+//! from fsrs import FSRS, FSRSItem, FSRSReview
+//!
+//! fsrs = FSRS()
+//! parameters = fsrs.compute_parameters(items)
+//! fsrs = FSRS(parameters)
+//! print(fsrs.evaluate(items).log_loss)
+//! print(fsrs.next_states(None, 0.9, 0).good.interval)
+//! ```
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::dataset::{FSRSItem, FSRSReview};
+use crate::error::FSRSError;
+use crate::inference::{ItemState, MemoryState, ModelEvaluation, NextStates};
+use crate::model::FSRS;
+use crate::optimal_retention::{SimulationResult, SimulatorConfig};
+
+impl From<FSRSError> for PyErr {
+    fn from(err: FSRSError) -> Self {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+#[pyclass(name = "FSRS")]
+pub struct PyFSRS(FSRS);
+
+#[pymethods]
+impl PyFSRS {
+    /// The default parameters are used if `parameters` is not given or empty.
+    #[new]
+    #[pyo3(signature = (parameters = None))]
+    fn new(parameters: Option<Vec<f32>>) -> PyResult<Self> {
+        let parameters = parameters.unwrap_or_default();
+        Ok(Self(FSRS::new(Some(&parameters))?))
+    }
+
+    #[pyo3(signature = (train_set, enable_short_term = true))]
+    fn compute_parameters(
+        &self,
+        py: Python<'_>,
+        train_set: Vec<PyFSRSItem>,
+        enable_short_term: bool,
+    ) -> PyResult<Vec<f32>> {
+        let train_set = train_set.into_iter().map(|item| item.0).collect();
+        let parameters = py.allow_threads(|| {
+            self.0
+                .compute_parameters(train_set, None, enable_short_term)
+        })?;
+        Ok(parameters)
+    }
+
+    fn evaluate(&self, py: Python<'_>, items: Vec<PyFSRSItem>) -> PyResult<PyModelEvaluation> {
+        let items = items.into_iter().map(|item| item.0).collect();
+        let evaluation = py.allow_threads(|| self.0.evaluate(items, |_| true))?;
+        Ok(PyModelEvaluation(evaluation))
+    }
+
+    #[pyo3(signature = (item, starting_state = None))]
+    fn memory_state(
+        &self,
+        item: PyFSRSItem,
+        starting_state: Option<PyMemoryState>,
+    ) -> PyResult<PyMemoryState> {
+        let state = self.0.memory_state(item.0, starting_state.map(|s| s.0))?;
+        Ok(PyMemoryState(state))
+    }
+
+    #[pyo3(signature = (current_memory_state, desired_retention, days_elapsed))]
+    fn next_states(
+        &self,
+        current_memory_state: Option<PyMemoryState>,
+        desired_retention: f32,
+        days_elapsed: u32,
+    ) -> PyResult<PyNextStates> {
+        let states = self.0.next_states(
+            current_memory_state.map(|s| s.0),
+            desired_retention,
+            days_elapsed,
+        )?;
+        Ok(PyNextStates(states))
+    }
+
+    /// The options of the simulation are passed as keyword arguments named like the fields
+    /// of [SimulatorConfig], eg `deck_size=1000`; the others keep their defaults.
+    #[pyo3(signature = (desired_retention, seed = None, **config))]
+    fn simulate(
+        &self,
+        py: Python<'_>,
+        desired_retention: f32,
+        seed: Option<u64>,
+        config: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<PySimulationResult> {
+        let config = simulator_config(py, config)?;
+        let result =
+            py.allow_threads(|| self.0.simulate(&config, desired_retention, seed, None))?;
+        Ok(PySimulationResult(result))
+    }
+}
+
+/// Converts keyword arguments through JSON, which gives the same errors for invalid values as
+/// a config file.
+fn simulator_config(
+    py: Python<'_>,
+    config: Option<&Bound<'_, PyDict>>,
+) -> PyResult<SimulatorConfig> {
+    let Some(config) = config else {
+        return Ok(SimulatorConfig::default());
+    };
+    let json: String = py
+        .import_bound("json")?
+        .call_method1("dumps", (config,))?
+        .extract()?;
+    serde_json::from_str(&json).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+#[pyclass(name = "FSRSReview")]
+#[derive(Clone)]
+pub struct PyFSRSReview(FSRSReview);
+
+#[pymethods]
+impl PyFSRSReview {
+    #[new]
+    #[pyo3(signature = (rating, delta_t, elapsed_seconds = None))]
+    fn new(rating: u32, delta_t: u32, elapsed_seconds: Option<u32>) -> Self {
+        Self(FSRSReview {
+            rating,
+            delta_t,
+            elapsed_seconds,
+        })
+    }
+
+    #[getter]
+    fn rating(&self) -> u32 {
+        self.0.rating
+    }
+
+    #[getter]
+    fn delta_t(&self) -> u32 {
+        self.0.delta_t
+    }
+
+    #[getter]
+    fn elapsed_seconds(&self) -> Option<u32> {
+        self.0.elapsed_seconds
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+#[pyclass(name = "FSRSItem")]
+#[derive(Clone)]
+pub struct PyFSRSItem(FSRSItem);
+
+#[pymethods]
+impl PyFSRSItem {
+    #[new]
+    fn new(reviews: Vec<PyFSRSReview>) -> Self {
+        Self(FSRSItem::new(
+            reviews.into_iter().map(|review| review.0).collect(),
+        ))
+    }
+
+    #[getter]
+    fn reviews(&self) -> Vec<PyFSRSReview> {
+        self.0.reviews.iter().cloned().map(PyFSRSReview).collect()
+    }
+
+    fn long_term_review_cnt(&self) -> usize {
+        self.0.long_term_review_cnt()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+#[pyclass(name = "MemoryState")]
+#[derive(Clone)]
+pub struct PyMemoryState(MemoryState);
+
+#[pymethods]
+impl PyMemoryState {
+    #[new]
+    fn new(stability: f32, difficulty: f32) -> Self {
+        Self(MemoryState {
+            stability,
+            difficulty,
+        })
+    }
+
+    #[getter]
+    fn stability(&self) -> f32 {
+        self.0.stability
+    }
+
+    #[getter]
+    fn difficulty(&self) -> f32 {
+        self.0.difficulty
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+#[pyclass(name = "ItemState")]
+pub struct PyItemState(ItemState);
+
+#[pymethods]
+impl PyItemState {
+    #[getter]
+    fn memory(&self) -> PyMemoryState {
+        PyMemoryState(self.0.memory)
+    }
+
+    #[getter]
+    fn interval(&self) -> f32 {
+        self.0.interval
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+#[pyclass(name = "NextStates")]
+pub struct PyNextStates(NextStates);
+
+#[pymethods]
+impl PyNextStates {
+    #[getter]
+    fn again(&self) -> PyItemState {
+        PyItemState(self.0.again.clone())
+    }
+
+    #[getter]
+    fn hard(&self) -> PyItemState {
+        PyItemState(self.0.hard.clone())
+    }
+
+    #[getter]
+    fn good(&self) -> PyItemState {
+        PyItemState(self.0.good.clone())
+    }
+
+    #[getter]
+    fn easy(&self) -> PyItemState {
+        PyItemState(self.0.easy.clone())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+#[pyclass(name = "ModelEvaluation")]
+pub struct PyModelEvaluation(ModelEvaluation);
+
+#[pymethods]
+impl PyModelEvaluation {
+    #[getter]
+    fn log_loss(&self) -> f32 {
+        self.0.log_loss
+    }
+
+    #[getter]
+    fn rmse_bins(&self) -> f32 {
+        self.0.rmse_bins
+    }
+
+    #[getter]
+    fn auc(&self) -> Option<f32> {
+        self.0.auc
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+/// The totals of each simulated day, as lists.
+#[pyclass(name = "SimulationResult")]
+pub struct PySimulationResult(SimulationResult);
+
+#[pymethods]
+impl PySimulationResult {
+    #[getter]
+    fn memorized_cnt_per_day(&self) -> Vec<f32> {
+        self.0.memorized_cnt_per_day.to_vec()
+    }
+
+    #[getter]
+    fn review_cnt_per_day(&self) -> Vec<usize> {
+        self.0.review_cnt_per_day.to_vec()
+    }
+
+    #[getter]
+    fn learn_cnt_per_day(&self) -> Vec<usize> {
+        self.0.learn_cnt_per_day.to_vec()
+    }
+
+    #[getter]
+    fn cost_per_day(&self) -> Vec<f32> {
+        self.0.cost_per_day.to_vec()
+    }
+}
+
+#[pymodule]
+fn fsrs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyFSRS>()?;
+    m.add_class::<PyFSRSReview>()?;
+    m.add_class::<PyFSRSItem>()?;
+    m.add_class::<PyMemoryState>()?;
+    m.add_class::<PyItemState>()?;
+    m.add_class::<PyNextStates>()?;
+    m.add_class::<PyModelEvaluation>()?;
+    m.add_class::<PySimulationResult>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::IntoPyDict;
+
+    #[test]
+    fn wrappers() -> PyResult<()> {
+        let fsrs = PyFSRS::new(None)?;
+        let item = PyFSRSItem::new(vec![
+            PyFSRSReview::new(3, 0, None),
+            PyFSRSReview::new(3, 3, None),
+        ]);
+        let state = fsrs.memory_state(item.clone(), None)?;
+        let next = fsrs.next_states(Some(state.clone()), 0.9, 3)?;
+        assert!(next.good().interval() > 3.0);
+        assert_eq!(state.0, FSRS::new(Some(&[]))?.memory_state(item.0, None)?);
+        assert!(PyFSRS::new(Some(vec![1.0])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn simulate() -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let fsrs = PyFSRS::new(None)?;
+            let config = [("deck_size", 100), ("learn_span", 10)].into_py_dict_bound(py);
+            let result = fsrs.simulate(py, 0.9, Some(42), Some(&config))?;
+            assert_eq!(result.memorized_cnt_per_day().len(), 10);
+            let config = [("deck_size", "many")].into_py_dict_bound(py);
+            assert!(fsrs.simulate(py, 0.9, Some(42), Some(&config)).is_err());
+            Ok(())
+        })
+    }
+}
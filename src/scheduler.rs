@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use burn::tensor::backend::Backend;
+use chrono::{Duration, NaiveDate};
+
+use crate::backend::DefaultBackend;
+use crate::error::{FSRSError, Result};
+use crate::forgetting_curve::ForgettingCurve;
+use crate::fuzz::{fuzz_factor, fuzz_range, fuzz_with_seed};
+use crate::inference::MemoryState;
+use crate::model::FSRS;
+
+/// Controls how [Scheduler] turns memory states into intervals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerConfig {
+    /// The probability of recall the card should have when it is next due.
+    pub desired_retention: f32,
+    /// Intervals are capped at this many days.
+    pub maximum_interval: u32,
+    /// Randomly spread intervals of 3 days or more over a few days, so that cards learnt
+    /// together don't stay due together.
+    pub enable_fuzz: bool,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            desired_retention: 0.9,
+            maximum_interval: 36500,
+            enable_fuzz: true,
+        }
+    }
+}
+
+/// The result of answering a card.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledReview {
+    pub memory: MemoryState,
+    /// The number of days until the card is due, after fuzzing and capping.
+    pub interval: u32,
+    pub due: NaiveDate,
+}
+
+/// Schedules cards with a set of parameters: given a card's memory state, the days elapsed
+/// since it was last reviewed and the user's answer, it returns the card's new memory state
+/// and when it should be reviewed next.
+pub struct Scheduler<B: Backend = DefaultBackend> {
+    fsrs: FSRS<B>,
+    config: SchedulerConfig,
+}
+
+impl<B: Backend> Scheduler<B> {
+    /// Parameters must have been provided when calling FSRS::new().
+    pub fn new(fsrs: FSRS<B>, config: SchedulerConfig) -> Self {
+        Self { fsrs, config }
+    }
+
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    /// Answer a card on `today` with `rating` (1-4). `memory` is the card's state after its
+    /// previous review, or None for a new card. `fuzz_seed` determines the fuzz applied to
+    /// the interval; using eg the card id plus its review count keeps it stable when the
+    /// same answer is previewed and then given.
+    pub fn review(
+        &self,
+        memory: Option<MemoryState>,
+        elapsed_days: u32,
+        rating: u32,
+        today: NaiveDate,
+        fuzz_seed: u64,
+    ) -> Result<ScheduledReview> {
+        self.review_inner(memory, elapsed_days, rating, today, fuzz_seed, None)
+    }
+
+    /// Like [Scheduler::review], but when the interval is fuzzed, days with fewer cards due
+    /// in `load_balancer` are preferred. The card's new due date is added to it.
+    pub fn review_balanced(
+        &self,
+        memory: Option<MemoryState>,
+        elapsed_days: u32,
+        rating: u32,
+        today: NaiveDate,
+        fuzz_seed: u64,
+        load_balancer: &mut LoadBalancer,
+    ) -> Result<ScheduledReview> {
+        let review = self.review_inner(
+            memory,
+            elapsed_days,
+            rating,
+            today,
+            fuzz_seed,
+            Some(load_balancer),
+        )?;
+        load_balancer.add_due(review.due);
+        Ok(review)
+    }
+
+    fn review_inner(
+        &self,
+        memory: Option<MemoryState>,
+        elapsed_days: u32,
+        rating: u32,
+        today: NaiveDate,
+        fuzz_seed: u64,
+        load_balancer: Option<&LoadBalancer>,
+    ) -> Result<ScheduledReview> {
+        let states = self
+            .fsrs
+            .next_states(memory, self.config.desired_retention, elapsed_days)?;
+        let state = match rating {
+            1 => states.again,
+            2 => states.hard,
+            3 => states.good,
+            4 => states.easy,
+            _ => return Err(FSRSError::InvalidInput),
+        };
+        let maximum_interval = self.config.maximum_interval.max(1);
+        let interval = match load_balancer {
+            Some(load_balancer) if self.config.enable_fuzz => load_balancer.pick_interval(
+                state.interval,
+                elapsed_days,
+                maximum_interval,
+                today,
+                fuzz_factor(fuzz_seed),
+            ),
+            _ => self.constrain_interval(state.interval, elapsed_days, fuzz_seed),
+        };
+        Ok(ScheduledReview {
+            memory: state.memory,
+            interval,
+            due: today + Duration::days(interval as i64),
+        })
+    }
+
+    /// The interval for a memory state with the given stability, in whole days.
+    pub fn interval(&self, stability: f32, elapsed_days: u32, fuzz_seed: u64) -> u32 {
+        self.constrain_interval(
+            self.fsrs
+                .curve()
+                .interval(stability, self.config.desired_retention),
+            elapsed_days,
+            fuzz_seed,
+        )
+    }
+
+    fn constrain_interval(&self, interval: f32, elapsed_days: u32, fuzz_seed: u64) -> u32 {
+        let maximum_interval = self.config.maximum_interval.max(1);
+        if self.config.enable_fuzz {
+            fuzz_with_seed(interval, elapsed_days, maximum_interval, fuzz_seed)
+        } else {
+            (interval.round() as u32).clamp(1, maximum_interval)
+        }
+    }
+}
+
+/// Spreads reviews evenly over the days: instead of picking a day in the fuzz range
+/// uniformly at random, days with fewer cards due are more likely to be picked. Shorter
+/// intervals are also slightly preferred, as reviewing a little early costs less retention
+/// than reviewing late.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadBalancer {
+    due_counts: HashMap<NaiveDate, usize>,
+}
+
+impl LoadBalancer {
+    /// `due_counts` holds the number of cards already due on each day.
+    pub fn new(due_counts: HashMap<NaiveDate, usize>) -> Self {
+        Self { due_counts }
+    }
+
+    pub fn due_count(&self, day: NaiveDate) -> usize {
+        self.due_counts.get(&day).copied().unwrap_or_default()
+    }
+
+    /// Record a card that has become due on `day`.
+    pub fn add_due(&mut self, day: NaiveDate) {
+        *self.due_counts.entry(day).or_default() += 1;
+    }
+
+    /// Forget a card that was due on `day`, eg because it was reviewed or rescheduled.
+    pub fn remove_due(&mut self, day: NaiveDate) {
+        if let Some(count) = self.due_counts.get_mut(&day) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// Pick an interval in [fuzz_range] for a card reviewed on `today`, with `fuzz_factor`,
+    /// a number in `[0, 1)`. Each day is weighted by `1 / (interval * (due + 1)^2)`, where
+    /// `due` is the number of cards due that day. Intervals too short to be fuzzed are only
+    /// rounded and capped.
+    pub fn pick_interval(
+        &self,
+        interval: f32,
+        elapsed_days: u32,
+        maximum_interval: u32,
+        today: NaiveDate,
+        fuzz_factor: f32,
+    ) -> u32 {
+        if interval < 2.5 {
+            return (interval.round() as u32).clamp(1, maximum_interval.max(1));
+        }
+        let (min_interval, max_interval) = fuzz_range(interval, elapsed_days, maximum_interval);
+        let weights: Vec<(u32, f32)> = (min_interval..=max_interval)
+            .map(|days| {
+                let due = self.due_count(today + Duration::days(days as i64)) as f32;
+                (days, 1.0 / (days as f32 * (due + 1.0).powi(2)))
+            })
+            .collect();
+        let mut target = fuzz_factor * weights.iter().map(|(_, w)| w).sum::<f32>();
+        for &(days, weight) in &weights {
+            if target < weight {
+                return days;
+            }
+            target -= weight;
+        }
+        max_interval
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_PARAMETERS;
+    use itertools::Itertools;
+
+    #[test]
+    fn review() -> Result<()> {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let scheduler = Scheduler::new(
+            FSRS::new(Some(&DEFAULT_PARAMETERS))?,
+            SchedulerConfig {
+                enable_fuzz: false,
+                ..Default::default()
+            },
+        );
+        let first = scheduler.review(None, 0, 3, today, 0)?;
+        assert_eq!(first.interval, 3);
+        assert_eq!(first.due, NaiveDate::from_ymd_opt(2024, 1, 4).unwrap());
+        let second = scheduler.review(Some(first.memory), 3, 3, first.due, 0)?;
+        assert!(second.interval > first.interval);
+        assert_eq!(
+            second.interval,
+            scheduler.interval(second.memory.stability, 3, 0)
+        );
+        assert_eq!(
+            scheduler.review(None, 0, 5, today, 0),
+            Err(FSRSError::InvalidInput)
+        );
+
+        let capped = Scheduler::new(
+            FSRS::new(Some(&DEFAULT_PARAMETERS))?,
+            SchedulerConfig {
+                maximum_interval: 10,
+                ..Default::default()
+            },
+        );
+        let review = capped.review(None, 0, 4, today, 42)?;
+        assert_eq!(review.interval, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn load_balancing() -> Result<()> {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let day = |days| today + Duration::days(days);
+        // every day in the fuzz range of a 100 day interval is busy, except day 104
+        let mut balancer = LoadBalancer::new((93..=107).map(|days| (day(days), 50)).collect());
+        for _ in 0..50 {
+            balancer.remove_due(day(104));
+        }
+        for factor in [0.1, 0.5, 0.9] {
+            assert_eq!(balancer.pick_interval(100.0, 0, 36500, today, factor), 104);
+        }
+        // without any cards due, the range is covered with a slight bias to shorter intervals
+        let empty = LoadBalancer::default();
+        assert_eq!(empty.pick_interval(100.0, 0, 36500, today, 0.0), 93);
+        assert_eq!(empty.pick_interval(100.0, 0, 36500, today, 0.999), 107);
+        assert!(empty.pick_interval(100.0, 0, 36500, today, 0.5) <= 100);
+        assert_eq!(empty.pick_interval(1.2, 0, 36500, today, 0.5), 1);
+
+        let scheduler = Scheduler::new(
+            FSRS::new(Some(&DEFAULT_PARAMETERS))?,
+            SchedulerConfig::default(),
+        );
+        let mut balancer = LoadBalancer::default();
+        let mut dues = vec![];
+        for card in 0..30 {
+            let review = scheduler.review_balanced(None, 0, 4, today, card, &mut balancer)?;
+            dues.push(review.due);
+        }
+        let counts = dues.iter().counts();
+        assert_eq!(counts.values().sum::<usize>(), 30);
+        assert!(counts
+            .iter()
+            .all(|(&&due, &count)| balancer.due_count(due) == count));
+        // the cards are spread over the whole fuzz range of the easy interval
+        assert_eq!(counts.len(), 6);
+        assert!(counts.values().max().unwrap() - counts.values().min().unwrap() <= 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+//! `proptest` strategies for the inputs of this crate, enabled with the `proptest` feature.
+//! Every value they generate is valid, eg items pass
+//! [validate_items](crate::dataset::validate_items) and parameters are within the range
+//! training keeps them in, so properties of integrations can be checked without filtering
+//! out invalid cases.
+//!
+//! [FSRSReview], [FSRSItem], [MemoryState] and [SimulatorConfig] implement [Arbitrary];
+//! as [Parameters](crate::Parameters) is a slice, its strategy is [parameters].
+
+use proptest::array::uniform;
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+
+use crate::dataset::{FSRSItem, FSRSReview};
+use crate::inference::{MemoryState, DEFAULT_PARAMETERS, S_MAX, S_MIN};
+use crate::optimal_retention::SimulatorConfig;
+use crate::parameter_clipper::clip_parameters;
+
+const DAY_SECONDS: u32 = 86400;
+
+/// A review after `delta_t` days, with an exact elapsed time consistent with it when set.
+fn review_after(delta_t: u32) -> impl Strategy<Value = FSRSReview> {
+    // reviews on consecutive days can be less than a day apart, but not more than two
+    let seconds = match delta_t {
+        0 => 0..DAY_SECONDS,
+        _ => (delta_t - 1) * DAY_SECONDS + 1..(delta_t + 1) * DAY_SECONDS,
+    };
+    (1..=4u32, option::of(seconds)).prop_map(move |(rating, elapsed_seconds)| FSRSReview {
+        rating,
+        delta_t,
+        elapsed_seconds,
+    })
+}
+
+impl Arbitrary for FSRSReview {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// A rating of 1-4, after up to 10 years.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (0..3650u32).prop_flat_map(review_after).boxed()
+    }
+}
+
+impl Arbitrary for FSRSItem {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// A first review, up to 20 reviews of history, and a current review at least a day
+    /// after the last one, as produced by
+    /// [revlogs_to_fsrs_items](crate::convertor::revlogs_to_fsrs_items).
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (review_after(0), vec(0..365u32, 0..20), 1..365u32)
+            .prop_flat_map(|(first, history, current)| {
+                let later = history
+                    .into_iter()
+                    .chain([current])
+                    .map(review_after)
+                    .collect::<Vec<_>>();
+                (Just(first), later)
+            })
+            .prop_map(|(first, later)| {
+                let mut reviews = vec![FSRSReview {
+                    elapsed_seconds: None,
+                    ..first
+                }];
+                reviews.extend(later);
+                FSRSItem {
+                    reviews,
+                    ..Default::default()
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for MemoryState {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (S_MIN..S_MAX, 1.0f32..10.0)
+            .prop_map(|(stability, difficulty)| MemoryState {
+                stability,
+                difficulty,
+            })
+            .boxed()
+    }
+}
+
+/// Probabilities that sum to 1, none of which are 0.
+fn probabilities<const N: usize>() -> impl Strategy<Value = [f32; N]> {
+    uniform(0.05f32..1.0).prop_map(|weights: [f32; N]| {
+        let total: f32 = weights.iter().sum();
+        weights.map(|weight| weight / total)
+    })
+}
+
+impl Arbitrary for SimulatorConfig {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// A small deck and a short span, so that simulations are quick, with random costs,
+    /// rating probabilities and limits. The other options keep their defaults.
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            (1..200usize, 1..60usize, 1..50usize, 1..500usize),
+            (60.0f32..3600.0, 1.0f32..36500.0, 1.0f32..5.0),
+            (uniform(1.0f32..60.0), uniform(1.0f32..60.0)),
+            (probabilities::<4>(), probabilities::<3>()),
+        )
+            .prop_map(
+                |(
+                    (deck_size, learn_span, learn_limit, review_limit),
+                    (max_cost_perday, max_ivl, loss_aversion),
+                    (learn_costs, review_costs),
+                    (first_rating_prob, review_rating_prob),
+                )| SimulatorConfig {
+                    deck_size,
+                    learn_span,
+                    learn_limit,
+                    review_limit,
+                    max_cost_perday,
+                    max_ivl,
+                    loss_aversion,
+                    learn_costs,
+                    review_costs,
+                    first_rating_prob,
+                    review_rating_prob,
+                    ..Default::default()
+                },
+            )
+            .boxed()
+    }
+}
+
+/// Parameters between half and twice the defaults, clipped to the range training keeps
+/// them in.
+pub fn parameters() -> impl Strategy<Value = Vec<f32>> {
+    uniform(0.5f32..2.0).prop_map(|factors: [f32; 19]| {
+        let parameters: Vec<f32> = DEFAULT_PARAMETERS
+            .iter()
+            .zip(factors)
+            .map(|(w, factor)| w * factor)
+            .collect();
+        clip_parameters(&parameters, true)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::validate_items;
+    use crate::optimal_retention::simulate;
+    use crate::FSRS;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn generated_values_are_valid(
+            item in any::<FSRSItem>(),
+            parameters in parameters(),
+        ) {
+            prop_assert!(validate_items(&[item]).is_ok());
+            prop_assert!(FSRS::new(Some(&parameters)).is_ok());
+        }
+
+        #[test]
+        fn retrievability_decreases_with_time(
+            parameters in parameters(),
+            state in any::<MemoryState>(),
+            days_elapsed in 0..36500u32,
+            later in 1..3650u32,
+        ) {
+            let fsrs = FSRS::new(Some(&parameters)).unwrap();
+            let retrievability = fsrs.current_retrievability(state, days_elapsed);
+            prop_assert!((0.0..=1.0).contains(&retrievability));
+            prop_assert!(
+                fsrs.current_retrievability(state, days_elapsed + later) <= retrievability
+            );
+        }
+
+        #[test]
+        fn stability_grows_on_success(
+            parameters in parameters(),
+            state in any::<MemoryState>(),
+            days_elapsed in 1..3650u32,
+        ) {
+            let fsrs = FSRS::new(Some(&parameters)).unwrap();
+            let next = fsrs.next_states(Some(state), 0.9, days_elapsed).unwrap();
+            // better ratings give higher stability; up to rounding at the clamps
+            prop_assert!(next.hard.memory.stability >= state.stability * 0.999);
+            prop_assert!(next.good.memory.stability >= next.hard.memory.stability * 0.999);
+            prop_assert!(next.easy.memory.stability >= next.good.memory.stability * 0.999);
+            prop_assert!(next.again.memory.stability <= next.good.memory.stability);
+        }
+
+        #[test]
+        fn memory_states_are_clamped(
+            parameters in parameters(),
+            item in any::<FSRSItem>(),
+            state in any::<MemoryState>(),
+            days_elapsed in 0..36500u32,
+        ) {
+            let fsrs = FSRS::new(Some(&parameters)).unwrap();
+            let next = fsrs.next_states(Some(state), 0.9, days_elapsed).unwrap();
+            let states = [next.again, next.hard, next.good, next.easy].map(|s| s.memory);
+            for memory in states.into_iter().chain([fsrs.memory_state(item, None).unwrap()]) {
+                prop_assert!((S_MIN..=S_MAX).contains(&memory.stability), "{memory:?}");
+                prop_assert!((1.0..=10.0).contains(&memory.difficulty), "{memory:?}");
+            }
+        }
+
+        #[test]
+        fn simulation_is_bounded(
+            config in any::<SimulatorConfig>(),
+            parameters in parameters(),
+            desired_retention in 0.7f32..0.97,
+        ) {
+            let result = simulate(&config, &parameters, desired_retention, Some(42), None)
+                .unwrap();
+            prop_assert_eq!(result.memorized_cnt_per_day.len(), config.learn_span);
+            prop_assert!(result.learn_cnt_per_day.sum() <= config.deck_size);
+            for day in 0..config.learn_span {
+                prop_assert!(result.memorized_cnt_per_day[day] <= config.deck_size as f32 + 1e-3);
+                prop_assert!(result.learn_cnt_per_day[day] <= config.learn_limit);
+            }
+        }
+    }
+}
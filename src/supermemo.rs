@@ -0,0 +1,152 @@
+use chrono::{NaiveDate, TimeZone};
+
+use crate::convertor::{revlogs_to_fsrs_items, ConvertorConfig};
+use crate::dataset::FSRSItem;
+use crate::error::{FSRSError, Result};
+use crate::optimal_retention::{RevlogEntry, RevlogReviewKind};
+
+/// SuperMemo grades cards 0-5, and considers grades of 3 or above as remembered.
+/// - 0-2 are mapped to again
+/// - 3 is mapped to hard
+/// - 4 is mapped to good
+/// - 5 is mapped to easy
+fn grade_to_rating(grade: u32) -> u8 {
+    match grade {
+        0..=2 => 1,
+        3 => 2,
+        4 => 3,
+        _ => 4,
+    }
+}
+
+fn field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    line.split_whitespace()
+        .find_map(|part| part.strip_prefix(name)?.strip_prefix('='))
+}
+
+/// Parse a repetition history exported from SuperMemo, where each element starts with an
+/// `Item #<number>: <title>` line and is followed by one line per repetition, eg
+/// `1) Rep=1 Laps=0 Date=21.03.2020 Hour=9.512 Int=0 Grade=4 ...`.
+///
+/// Only `Date` and `Grade` are used. Grades are mapped to ratings as follows: 0-2 are
+/// again, 3 is hard, 4 is good and 5 is easy. As SuperMemo records the date of each
+/// repetition but not its exact time, repetitions are placed at noon of that day in the
+/// timezone of `config`, so they are assigned to the same day when converted. Returns
+/// [FSRSError::InvalidInput] if a repetition line can't be parsed.
+pub fn supermemo_revlogs_from_history(
+    history: &str,
+    config: &ConvertorConfig,
+) -> Result<Vec<RevlogEntry>> {
+    let mut revlogs: Vec<RevlogEntry> = vec![];
+    let mut cid = 0;
+    for line in history.lines().map(str::trim) {
+        if line.starts_with("Item #") {
+            cid += 1;
+            continue;
+        }
+        let (Some(date), Some(grade)) = (field(line, "Date"), field(line, "Grade")) else {
+            continue;
+        };
+        if cid == 0 {
+            return Err(FSRSError::InvalidInput);
+        }
+        let date =
+            NaiveDate::parse_from_str(date, "%d.%m.%Y").map_err(|_| FSRSError::InvalidInput)?;
+        let grade: u32 = grade.parse().map_err(|_| FSRSError::InvalidInput)?;
+        let noon = config
+            .timezone
+            .from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap())
+            .earliest()
+            .ok_or(FSRSError::InvalidInput)?;
+        let first = revlogs.last().map_or(true, |r| r.cid != cid);
+        // keep repetitions done on the same day in order
+        let order = revlogs.iter().rev().take_while(|r| r.cid == cid).count() as i64;
+        revlogs.push(RevlogEntry {
+            id: noon.timestamp_millis() + config.next_day_starts_at * 3600 * 1000 + order,
+            cid,
+            button_chosen: grade_to_rating(grade),
+            review_kind: if first {
+                RevlogReviewKind::Learning
+            } else {
+                RevlogReviewKind::Review
+            },
+            ..Default::default()
+        });
+    }
+    Ok(revlogs)
+}
+
+/// Parse a SuperMemo repetition history and convert it into items that can be passed to
+/// [FSRS::compute_parameters](crate::FSRS::compute_parameters). See
+/// [supermemo_revlogs_from_history] for the expected format.
+pub fn items_from_supermemo_history(
+    history: &str,
+    config: &ConvertorConfig,
+) -> Result<Vec<FSRSItem>> {
+    let revlogs = supermemo_revlogs_from_history(history, config)?;
+    Ok(revlogs_to_fsrs_items(revlogs, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::item_from;
+    use chrono_tz::Tz;
+
+    const HISTORY: &str = "
+Item #1: What is the capital of France?
+1) Rep=1 Laps=0 Date=01.03.2020 Hour=23.900 Int=0 Grade=5 expFI=0 UF=0.000 Priority=0.12%
+2) Rep=2 Laps=0 Date=05.03.2020 Hour=9.100 Int=4 Grade=2 expFI=90 UF=0.000 Priority=0.12%
+3) Rep=3 Laps=1 Date=05.03.2020 Hour=9.300 Int=0 Grade=4 expFI=0 UF=0.000 Priority=0.12%
+4) Rep=4 Laps=1 Date=15.03.2020 Hour=9.100 Int=10 Grade=3 expFI=90 UF=0.000 Priority=0.12%
+
+Item #2: What is the capital of Italy?
+1) Rep=1 Laps=0 Date=02.03.2020 Hour=8.000 Int=0 Grade=4 expFI=0 UF=0.000 Priority=0.20%
+2) Rep=2 Laps=0 Date=04.03.2020 Hour=8.000 Int=2 Grade=5 expFI=90 UF=0.000 Priority=0.20%
+";
+
+    #[test]
+    fn parse_history() {
+        for timezone in [Tz::UTC, Tz::Pacific__Auckland, Tz::America__Los_Angeles] {
+            let config = ConvertorConfig {
+                timezone,
+                ..Default::default()
+            };
+            let revlogs = supermemo_revlogs_from_history(HISTORY, &config).unwrap();
+            assert_eq!(revlogs.len(), 6);
+            assert_eq!(
+                revlogs.iter().map(|r| r.button_chosen).collect::<Vec<_>>(),
+                [4, 1, 3, 2, 3, 4]
+            );
+            let items = items_from_supermemo_history(HISTORY, &config).unwrap();
+            assert_eq!(
+                items
+                    .into_iter()
+                    .map(|item| item.reviews)
+                    .collect::<Vec<_>>(),
+                [
+                    item_from(&[(3, 0), (4, 2)]).reviews,
+                    item_from(&[(4, 0), (1, 4)]).reviews,
+                    item_from(&[(4, 0), (1, 4), (3, 0), (2, 10)]).reviews,
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_history() {
+        let config = ConvertorConfig::default();
+        assert_eq!(
+            supermemo_revlogs_from_history("1) Rep=1 Date=01.03.2020 Grade=5", &config),
+            Err(FSRSError::InvalidInput)
+        );
+        assert_eq!(
+            supermemo_revlogs_from_history("Item #1: a\n1) Date=31.02.2020 Grade=5", &config),
+            Err(FSRSError::InvalidInput)
+        );
+        assert_eq!(
+            supermemo_revlogs_from_history("Item #1: a\n1) Date=01.03.2020 Grade=A", &config),
+            Err(FSRSError::InvalidInput)
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::dataset::{FSRSItem, FSRSReview, ItemMetadata};
+use crate::error::{FSRSError, Result};
+use crate::forgetting_curve::{ForgettingCurve, PowerCurve};
+use crate::inference::{decay_of, DEFAULT_PARAMETERS};
+use crate::model::check_and_fill_parameters;
+use crate::optimal_retention::{init_d, next_d, stability_after_failure, stability_after_success};
+
+/// Controls the synthetic collection produced by [generate_sample_items].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleDatasetConfig {
+    /// The parameters used to simulate the memory of the (virtual) user.
+    pub parameters: Vec<f32>,
+    /// The number of cards. One card is introduced per day.
+    pub card_count: usize,
+    /// The number of reviews done on each card after it was first learnt.
+    pub reviews_per_card: usize,
+    /// The retention the reviews are scheduled for.
+    pub desired_retention: f32,
+    /// The probability of each rating (again, hard, good, easy) when a card is first learnt.
+    pub first_rating_prob: [f32; 4],
+    /// The probability of each rating (hard, good, easy) when a card is recalled.
+    pub review_rating_prob: [f32; 3],
+    pub seed: u64,
+}
+
+impl Default for SampleDatasetConfig {
+    fn default() -> Self {
+        Self {
+            parameters: DEFAULT_PARAMETERS.to_vec(),
+            card_count: 100,
+            reviews_per_card: 10,
+            desired_retention: 0.9,
+            first_rating_prob: [0.24, 0.094, 0.495, 0.171],
+            review_rating_prob: [0.224, 0.631, 0.145],
+            seed: 42,
+        }
+    }
+}
+
+/// Generate a synthetic review history, for use as a test fixture when a real collection is
+/// not available. Each card is scheduled at the desired retention, and whether it is
+/// remembered is drawn from the retrievability predicted by the parameters. The output has
+/// the same shape as [revlogs_to_fsrs_items](crate::convertor::revlogs_to_fsrs_items): one item per
+/// review after the first, sorted by review day. The same config always produces the same
+/// items.
+pub fn generate_sample_items(config: &SampleDatasetConfig) -> Result<Vec<FSRSItem>> {
+    let w = &check_and_fill_parameters(&config.parameters, true)?;
+    let curve = PowerCurve { decay: decay_of(w) };
+    let first_rating_dist =
+        WeightedIndex::new(config.first_rating_prob).map_err(|_| FSRSError::InvalidInput)?;
+    let review_rating_dist =
+        WeightedIndex::new(config.review_rating_prob).map_err(|_| FSRSError::InvalidInput)?;
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    let mut items = vec![];
+    for card in 0..config.card_count {
+        let rating = first_rating_dist.sample(&mut rng) + 1;
+        let mut stability = w[rating - 1];
+        let mut difficulty = init_d(w, rating, true).clamp(1.0, 10.0);
+        let mut day = card as u32;
+        let mut reviews = vec![FSRSReview {
+            rating: rating as u32,
+            delta_t: 0,
+            elapsed_seconds: None,
+        }];
+        for _ in 0..config.reviews_per_card {
+            let delta_t = curve
+                .interval(stability, config.desired_retention)
+                .round()
+                .max(1.0);
+            let retrievability = curve.retrievability(delta_t, stability);
+            let rating = if rng.gen::<f32>() < retrievability {
+                review_rating_dist.sample(&mut rng) + 2
+            } else {
+                1
+            };
+            stability = if rating == 1 {
+                stability_after_failure(w, stability, retrievability, difficulty)
+            } else {
+                stability_after_success(w, stability, retrievability, difficulty, rating)
+            };
+            difficulty = next_d(w, difficulty, rating, true, true);
+            day += delta_t as u32;
+            reviews.push(FSRSReview {
+                rating: rating as u32,
+                delta_t: delta_t as u32,
+                elapsed_seconds: None,
+            });
+            items.push((
+                day,
+                FSRSItem {
+                    reviews: reviews.clone(),
+                    metadata: Some(ItemMetadata {
+                        card_id: Some(card as i64),
+                        deck_id: None,
+                    }),
+                },
+            ));
+        }
+    }
+    items.sort_by_key(|(day, _)| *day);
+    Ok(items.into_iter().map(|(_, item)| item).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::validate_items;
+
+    #[test]
+    fn sample_items() {
+        let config = SampleDatasetConfig::default();
+        let items = generate_sample_items(&config).unwrap();
+        assert_eq!(items.len(), 1000);
+        assert_eq!(validate_items(&items), Ok(()));
+        assert_eq!(generate_sample_items(&config).unwrap(), items);
+        let recall = items.iter().filter(|i| i.current().rating > 1).count() as f32 / 1000.0;
+        assert!((0.85..0.95).contains(&recall), "{recall}");
+
+        let other_seed = SampleDatasetConfig {
+            seed: 1,
+            ..config.clone()
+        };
+        assert_ne!(generate_sample_items(&other_seed).unwrap(), items);
+
+        let invalid = SampleDatasetConfig {
+            first_rating_prob: [0.0; 4],
+            ..config
+        };
+        assert_eq!(
+            generate_sample_items(&invalid),
+            Err(FSRSError::InvalidInput)
+        );
+    }
+}
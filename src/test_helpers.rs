@@ -2,6 +2,8 @@ use burn::backend::autodiff::Autodiff;
 pub type NdArrayAutodiff = Autodiff<burn::backend::NdArray>;
 use burn::tensor::{Data, Float};
 
+use crate::dataset::{FSRSItem, FSRSReview};
+
 pub type Model = crate::model::Model<NdArrayAutodiff>;
 pub type Tensor<const D: usize, K = Float> = burn::tensor::Tensor<NdArrayAutodiff, D, K>;
 
@@ -12,3 +14,13 @@ where
 {
     Data::from(a).assert_approx_eq(&Data::from(b), 4);
 }
+
+/// An item without metadata, from the `(rating, delta_t)` of each review.
+pub(crate) fn item_from(reviews: &[(u32, u32)]) -> FSRSItem {
+    FSRSItem::new(
+        reviews
+            .iter()
+            .map(|&(rating, delta_t)| FSRSReview::new(rating, delta_t))
+            .collect(),
+    )
+}
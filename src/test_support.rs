@@ -0,0 +1,426 @@
+//! Helpers for testing code that uses this crate, without training or bundling a
+//! collection. Enabled with the `test-support` feature, which is meant for
+//! `[dev-dependencies]`.
+//!
+//! [MockFSRS] has the same methods as [FSRS](crate::FSRS) for scheduling, training,
+//! evaluation and optimal retention, and returns canned results instantly. Both implement
+//! [MemoryModel], so code that is generic over it can be given either. The fixtures are
+//! small synthetic datasets, which are the same on every run.
+
+use std::sync::{Arc, Mutex};
+
+use itertools::Itertools;
+
+use crate::dataset::{FSRSItem, ItemMetadata};
+use crate::error::{FSRSError, Result};
+use crate::inference::{
+    ItemProgress, ItemState, MemoryModel, MemoryState, ModelEvaluation, NextStates, Parameters,
+    DEFAULT_PARAMETERS,
+};
+use crate::optimal_retention::{
+    RetentionConstraints, RevlogEntry, RevlogReviewKind, SimulatorConfig,
+};
+use crate::progress::ProgressStage;
+use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
+use crate::training::CombinedProgressState;
+
+/// A stand-in for [FSRS](crate::FSRS), whose results are set in its fields. The methods
+/// check their input like the real ones (eg [MockFSRS::evaluate] fails on no items), and
+/// record their names, so tests can check what was called with [MockFSRS::calls].
+#[derive(Debug)]
+pub struct MockFSRS {
+    /// Returned by the memory state methods, and for every rating by
+    /// [MockFSRS::next_states].
+    pub memory_state: MemoryState,
+    /// The interval after each rating (again, hard, good, easy), regardless of the memory
+    /// state.
+    pub intervals: [f32; 4],
+    /// Returned by [MockFSRS::compute_parameters].
+    pub parameters: Vec<f32>,
+    pub evaluation: ModelEvaluation,
+    pub optimal_retention: f32,
+    calls: Mutex<Vec<&'static str>>,
+}
+
+impl Default for MockFSRS {
+    fn default() -> Self {
+        Self {
+            memory_state: MemoryState {
+                stability: 10.0,
+                difficulty: 5.0,
+            },
+            intervals: [1.0, 3.0, 10.0, 30.0],
+            parameters: DEFAULT_PARAMETERS.to_vec(),
+            evaluation: ModelEvaluation {
+                log_loss: 0.3,
+                rmse_bins: 0.05,
+                auc: Some(0.7),
+            },
+            optimal_retention: 0.85,
+            calls: Default::default(),
+        }
+    }
+}
+
+impl MockFSRS {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The names of the methods called so far, in order.
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, call: &'static str) {
+        self.calls.lock().unwrap().push(call);
+    }
+
+    fn check_rating(rating: u32) -> Result<usize> {
+        match rating {
+            1..=4 => Ok(rating as usize - 1),
+            _ => Err(FSRSError::InvalidInput),
+        }
+    }
+
+    pub fn next_states(
+        &self,
+        _current_memory_state: Option<MemoryState>,
+        desired_retention: f32,
+        _days_elapsed: u32,
+    ) -> Result<NextStates> {
+        self.record("next_states");
+        if !(0.0..=1.0).contains(&desired_retention) {
+            return Err(FSRSError::InvalidInput);
+        }
+        let state = |index: usize| ItemState {
+            memory: self.memory_state,
+            interval: self.intervals[index],
+        };
+        Ok(NextStates {
+            again: state(0),
+            hard: state(1),
+            good: state(2),
+            easy: state(3),
+        })
+    }
+
+    pub fn next_interval(
+        &self,
+        _stability: Option<f32>,
+        _desired_retention: f32,
+        rating: u32,
+    ) -> f32 {
+        self.record("next_interval");
+        Self::check_rating(rating).map_or(f32::NAN, |index| self.intervals[index])
+    }
+
+    pub fn memory_state(
+        &self,
+        item: FSRSItem,
+        _starting_state: Option<MemoryState>,
+    ) -> Result<MemoryState> {
+        self.record("memory_state");
+        for review in &item.reviews {
+            Self::check_rating(review.rating)?;
+        }
+        if item.reviews.is_empty() {
+            return Err(FSRSError::InvalidInput);
+        }
+        Ok(self.memory_state)
+    }
+
+    pub fn memory_state_from_sm2(
+        &self,
+        _ease_factor: f32,
+        _interval: f32,
+        _sm2_retention: f32,
+    ) -> Result<MemoryState> {
+        self.record("memory_state_from_sm2");
+        Ok(self.memory_state)
+    }
+
+    /// Returns [MockFSRS::parameters] at once, marking `progress` as finished.
+    pub fn compute_parameters(
+        &self,
+        _train_set: Vec<FSRSItem>,
+        progress: Option<Arc<Mutex<CombinedProgressState>>>,
+        _enable_short_term: bool,
+    ) -> Result<Vec<f32>> {
+        self.record("compute_parameters");
+        if let Some(progress) = progress {
+            progress.lock().unwrap().finished = true;
+        }
+        Ok(self.parameters.clone())
+    }
+
+    /// Reports all items as done to `progress`, and returns [MockFSRS::evaluation].
+    pub fn evaluate<F>(&self, items: Vec<FSRSItem>, mut progress: F) -> Result<ModelEvaluation>
+    where
+        F: FnMut(ItemProgress) -> bool,
+    {
+        self.record("evaluate");
+        if items.is_empty() {
+            return Err(FSRSError::NotEnoughData {
+                found: 0,
+                required: 1,
+            });
+        }
+        let done = ItemProgress {
+            current: items.len(),
+            total: items.len(),
+        };
+        if !progress(done) {
+            return Err(FSRSError::Interrupted {
+                stage: ProgressStage::Evaluation,
+            });
+        }
+        Ok(self.evaluation)
+    }
+
+    pub fn optimal_retention<F>(
+        &self,
+        _config: &SimulatorConfig,
+        _parameters: &Parameters,
+        _progress: F,
+    ) -> Result<f32>
+    where
+        F: FnMut(ItemProgress) -> bool + Send,
+    {
+        self.record("optimal_retention");
+        Ok(self.optimal_retention)
+    }
+
+    /// Returns [MockFSRS::optimal_retention], clamped to the bounds of `constraints`. The
+    /// daily cost is not checked.
+    pub fn optimal_retention_with_constraints<F>(
+        &self,
+        _config: &SimulatorConfig,
+        _parameters: &Parameters,
+        constraints: &RetentionConstraints,
+        _progress: F,
+    ) -> Result<f32>
+    where
+        F: FnMut(ItemProgress) -> bool + Send,
+    {
+        self.record("optimal_retention_with_constraints");
+        let (min, max) = (constraints.min_retention, constraints.max_retention);
+        if !(min > 0.0 && min < max && max < 1.0) {
+            return Err(FSRSError::InvalidInput);
+        }
+        Ok(self.optimal_retention.clamp(min, max))
+    }
+}
+
+impl MemoryModel for MockFSRS {
+    fn memory_state(
+        &self,
+        item: FSRSItem,
+        starting_state: Option<MemoryState>,
+    ) -> Result<MemoryState> {
+        MockFSRS::memory_state(self, item, starting_state)
+    }
+
+    fn next_states(
+        &self,
+        current_memory_state: Option<MemoryState>,
+        desired_retention: f32,
+        days_elapsed: u32,
+    ) -> Result<NextStates> {
+        MockFSRS::next_states(self, current_memory_state, desired_retention, days_elapsed)
+    }
+
+    fn evaluate<F>(&self, items: Vec<FSRSItem>, progress: F) -> Result<ModelEvaluation>
+    where
+        F: FnMut(ItemProgress) -> bool,
+    {
+        MockFSRS::evaluate(self, items, progress)
+    }
+}
+
+fn fixture_config() -> SampleDatasetConfig {
+    SampleDatasetConfig {
+        card_count: 20,
+        reviews_per_card: 5,
+        seed: 1,
+        ..Default::default()
+    }
+}
+
+/// 100 items of 20 cards, in the order [revlogs_to_fsrs_items](crate::convertor::revlogs_to_fsrs_items)
+/// returns them, enough to exercise training and evaluation.
+pub fn fixture_items() -> Vec<FSRSItem> {
+    generate_sample_items(&fixture_config()).unwrap()
+}
+
+/// The review logs of the cards in [fixture_items], starting on 2023-11-15 (UTC), which
+/// convert back to the same items with the default
+/// [ConvertorConfig](crate::ConvertorConfig).
+pub fn fixture_revlogs() -> Vec<RevlogEntry> {
+    // noon, so reviews are far from the start of the day
+    const START: i64 = 1_700_049_600_000;
+    const DAY_MS: i64 = 86_400_000;
+    let items = fixture_items();
+    // the longest item of each card has its whole history
+    let histories = items
+        .iter()
+        .filter_map(|item| match item.metadata {
+            Some(ItemMetadata {
+                card_id: Some(card_id),
+                ..
+            }) => Some((card_id, item)),
+            _ => None,
+        })
+        .into_group_map()
+        .into_iter()
+        .map(|(card_id, items)| {
+            let longest = items.into_iter().max_by_key(|item| item.reviews.len());
+            (card_id, longest.unwrap())
+        })
+        .sorted_by_key(|(card_id, _)| *card_id);
+    let mut revlogs = vec![];
+    for (card_id, item) in histories {
+        // one card was introduced per day
+        let mut day = card_id;
+        let mut last_interval = 0;
+        for (index, review) in item.reviews.iter().enumerate() {
+            day += review.delta_t as i64;
+            let interval = item
+                .reviews
+                .get(index + 1)
+                .map_or(0, |next| next.delta_t as i32);
+            revlogs.push(RevlogEntry {
+                id: START + day * DAY_MS,
+                cid: card_id,
+                button_chosen: review.rating as u8,
+                interval,
+                last_interval,
+                ease_factor: 2500,
+                taken_millis: 8000,
+                review_kind: match (index, review.rating) {
+                    (0, _) => RevlogReviewKind::Learning,
+                    (_, 1) => RevlogReviewKind::Relearning,
+                    _ => RevlogReviewKind::Review,
+                },
+                ..Default::default()
+            });
+            last_interval = interval;
+        }
+    }
+    revlogs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convertor::{revlogs_to_fsrs_items, ConvertorConfig};
+    use crate::dataset::validate_items;
+    use crate::test_helpers::{assert_approx_eq, item_from};
+
+    #[test]
+    fn mock() {
+        let fsrs = MockFSRS {
+            intervals: [1.0, 2.0, 3.0, 4.0],
+            ..MockFSRS::new()
+        };
+        let next = fsrs.next_states(None, 0.9, 0).unwrap();
+        assert_eq!(next.hard.interval, 2.0);
+        assert_eq!(next.easy.memory, fsrs.memory_state);
+        assert_eq!(fsrs.next_interval(None, 0.9, 4), 4.0);
+        assert!(fsrs.next_interval(None, 0.9, 5).is_nan());
+        let item = item_from(&[(3, 0)]);
+        assert_eq!(fsrs.memory_state(item.clone(), None), Ok(fsrs.memory_state));
+
+        let progress = CombinedProgressState::new_shared();
+        let parameters = fsrs
+            .compute_parameters(vec![], Some(progress.clone()), true)
+            .unwrap();
+        assert_eq!(parameters, DEFAULT_PARAMETERS);
+        assert!(progress.lock().unwrap().finished());
+        assert_eq!(
+            fsrs.evaluate(vec![], |_| true),
+            Err(FSRSError::NotEnoughData {
+                found: 0,
+                required: 1
+            })
+        );
+        assert_eq!(
+            fsrs.evaluate(vec![item], |_| false),
+            Err(FSRSError::Interrupted {
+                stage: ProgressStage::Evaluation
+            })
+        );
+        assert_eq!(
+            fsrs.optimal_retention(&SimulatorConfig::default(), &[], |_| true),
+            Ok(0.85)
+        );
+        let constraints = RetentionConstraints {
+            min_retention: 0.9,
+            ..Default::default()
+        };
+        assert_eq!(
+            fsrs.optimal_retention_with_constraints(
+                &SimulatorConfig::default(),
+                &[],
+                &constraints,
+                |_| true
+            ),
+            Ok(0.9)
+        );
+        assert_eq!(
+            fsrs.calls(),
+            [
+                "next_states",
+                "next_interval",
+                "next_interval",
+                "memory_state",
+                "compute_parameters",
+                "evaluate",
+                "evaluate",
+                "optimal_retention",
+                "optimal_retention_with_constraints"
+            ]
+        );
+    }
+
+    /// The interval after good, and the log loss, as an app generic over the model would.
+    fn schedule_and_evaluate(model: &impl MemoryModel, items: Vec<FSRSItem>) -> Result<(f32, f32)> {
+        let state = model.memory_state(items[0].clone(), None)?;
+        let next = model.next_states(Some(state), 0.9, 1)?;
+        let evaluation = model.evaluate(items, |_| true)?;
+        Ok((next.good.interval, evaluation.log_loss))
+    }
+
+    #[test]
+    fn mock_is_a_memory_model() -> Result<()> {
+        let items = fixture_items();
+        let mock = MockFSRS::new();
+        assert_eq!(schedule_and_evaluate(&mock, items.clone())?, (10.0, 0.3));
+        assert_eq!(mock.calls(), ["memory_state", "next_states", "evaluate"]);
+
+        let fsrs = crate::FSRS::new(Some(&[]))?;
+        let (interval, log_loss) = schedule_and_evaluate(&fsrs, items.clone())?;
+        let state = fsrs.memory_state(items[0].clone(), None)?;
+        assert_eq!(
+            interval,
+            fsrs.next_states(Some(state), 0.9, 1)?.good.interval
+        );
+        assert_approx_eq([log_loss], [fsrs.evaluate(items, |_| true)?.log_loss]);
+        Ok(())
+    }
+
+    #[test]
+    fn fixtures() {
+        let items = fixture_items();
+        assert_eq!(items.len(), 100);
+        assert_eq!(validate_items(&items), Ok(()));
+        assert_eq!(fixture_items(), items);
+
+        let revlogs = fixture_revlogs();
+        assert_eq!(revlogs.len(), 120);
+        assert_eq!(
+            revlogs_to_fsrs_items(revlogs, &ConvertorConfig::default()),
+            items
+        );
+    }
+}
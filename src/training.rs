@@ -1,36 +1,53 @@
 use crate::batch_shuffle::{BatchTensorDataset, ShuffleDataLoader};
-use crate::cosine_annealing::CosineAnnealingLR;
+use crate::checkpoint::{
+    record_from_value, record_to_value, Checkpointer, SplitCheckpoint, TrainingCheckpoint,
+};
 use crate::dataset::{
-    prepare_training_data, recency_weighted_fsrs_items, FSRSDataset, FSRSItem, WeightedFSRSItem,
+    apply_recency_weights, constant_weighted_fsrs_items, prepare_training_data,
+    recency_weighted_fsrs_items, resample, sample_weighted_fsrs_items, validate_items, FSRSBatch,
+    FSRSDataset, FSRSItem, OutlierFilterConfig, ResampleConfig, WeightedFSRSItem,
 };
 use crate::error::Result;
-use crate::model::{Model, ModelConfig};
+use crate::events::FSRSEvent;
+use crate::inference::{ItemProgress, DECAY};
+use crate::lr_schedule::{LrSchedule, ScheduledLr};
+use crate::model::{check_and_fill_parameters, Model, ModelConfig};
 use crate::parameter_clipper::parameter_clipper;
 use crate::pre_training::{pretrain, smooth_and_fill};
+use crate::progress::{CancellationToken, ProgressHandle, ProgressMessage, ProgressStage};
 use crate::{FSRSError, DEFAULT_PARAMETERS, FSRS};
 use burn::backend::Autodiff;
 
 use burn::lr_scheduler::LrScheduler;
-use burn::module::AutodiffModule;
+use burn::module::{AutodiffModule, Module};
 use burn::nn::loss::Reduction;
 use burn::optim::Optimizer;
-use burn::optim::{AdamConfig, GradientsParams};
+use burn::optim::{AdamConfig, AdamWConfig, GradientsParams, RmsPropConfig, SgdConfig};
 use burn::tensor::backend::Backend;
-use burn::tensor::{Int, Tensor};
+use burn::tensor::{Data, Shape, Tensor};
 use burn::train::renderer::{MetricState, MetricsRenderer, TrainingProgress};
 use burn::train::TrainingInterrupter;
 use burn::{config::Config, tensor::backend::AutodiffBackend};
 use core::marker::PhantomData;
 use log::info;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
 
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
-static PARAMS_STDDEV: [f32; 19] = [
+// the last one is for the decay, which is only used when it is trained
+static PARAMS_STDDEV: [f32; 20] = [
     6.61, 9.52, 17.69, 27.74, 0.55, 0.28, 0.67, 0.12, 0.4, 0.18, 0.34, 0.27, 0.08, 0.14, 0.57,
-    0.25, 1.03, 0.27, 0.39,
+    0.25, 1.03, 0.27, 0.39, 0.1,
 ];
 
-pub struct BCELoss<B: Backend> {
+pub(crate) struct BCELoss<B: Backend> {
     backend: PhantomData<B>,
 }
 
@@ -60,20 +77,13 @@ impl<B: Backend> BCELoss<B> {
 }
 
 impl<B: Backend> Model<B> {
-    pub fn forward_classification(
-        &self,
-        t_historys: Tensor<B, 2>,
-        r_historys: Tensor<B, 2>,
-        delta_ts: Tensor<B, 1>,
-        labels: Tensor<B, 1, Int>,
-        weights: Tensor<B, 1>,
-        reduce: Reduction,
-    ) -> Tensor<B, 1> {
-        // info!("t_historys: {}", &t_historys);
-        // info!("r_historys: {}", &r_historys);
-        let state = self.forward(t_historys, r_historys, None);
-        let retention = self.power_forgetting_curve(delta_ts, state.stability);
-        BCELoss::new().forward(retention, labels.float(), weights, reduce)
+    pub fn forward_classification(&self, batch: FSRSBatch<B>, reduce: Reduction) -> Tensor<B, 1> {
+        batch.assert_right_padded();
+        // info!("t_historys: {}", &batch.t_historys);
+        // info!("r_historys: {}", &batch.r_historys);
+        let state = self.forward(batch.t_historys, batch.r_historys, None);
+        let retention = self.forgetting_curve(batch.delta_ts, state.stability);
+        BCELoss::new().forward(retention, batch.labels.float(), batch.weights, reduce)
     }
 
     pub(crate) fn l2_regularization(
@@ -93,20 +103,23 @@ impl<B: Backend> Model<B> {
 }
 
 impl<B: AutodiffBackend> Model<B> {
-    fn freeze_initial_stability(&self, mut grad: B::Gradients) -> B::Gradients {
+    /// Zero the gradients of the parameters that are `frozen`.
+    fn freeze_parameters(&self, mut grad: B::Gradients, frozen: &[bool]) -> B::Gradients {
         let grad_tensor = self.w.grad(&grad).unwrap();
-        let updated_grad_tensor =
-            grad_tensor.slice_assign([0..4], Tensor::zeros([4], &B::Device::default()));
-
-        self.w.grad_remove(&mut grad);
-        self.w.grad_replace(&mut grad, updated_grad_tensor);
-        grad
-    }
-
-    fn free_short_term_stability(&self, mut grad: B::Gradients) -> B::Gradients {
-        let grad_tensor = self.w.grad(&grad).unwrap();
-        let updated_grad_tensor =
-            grad_tensor.slice_assign([17..19], Tensor::zeros([2], &B::Device::default()));
+        let trained = frozen
+            .iter()
+            .map(|&frozen| if frozen { 0.0 } else { 1.0 })
+            .collect();
+        let trained = Tensor::from_floats(
+            Data::new(
+                trained,
+                Shape {
+                    dims: [frozen.len()],
+                },
+            ),
+            &B::Device::default(),
+        );
+        let updated_grad_tensor = grad_tensor * trained;
 
         self.w.grad_remove(&mut grad);
         self.w.grad_replace(&mut grad, updated_grad_tensor);
@@ -126,7 +139,9 @@ pub struct ProgressState {
 pub struct CombinedProgressState {
     pub want_abort: bool,
     pub splits: Vec<ProgressState>,
-    finished: bool,
+    pub(crate) finished: bool,
+    /// Set by [ProgressSender::training_state](crate::ProgressSender::training_state).
+    pub(crate) listener: Option<Sender<ProgressMessage>>,
 }
 
 impl CombinedProgressState {
@@ -148,7 +163,7 @@ impl CombinedProgressState {
 }
 
 #[derive(Clone)]
-pub struct ProgressCollector {
+pub(crate) struct ProgressCollector {
     pub state: Arc<Mutex<CombinedProgressState>>,
     pub interrupter: TrainingInterrupter,
     /// The index of the split we should update.
@@ -187,6 +202,14 @@ impl MetricsRenderer for ProgressCollector {
         split.epoch_total = item.epoch_total;
         split.items_processed = item.progress.items_processed;
         split.items_total = item.progress.items_total;
+        if let Some(listener) = &info.listener {
+            // the receiver may have been dropped, which doesn't affect training
+            let _ = listener.send(ProgressMessage {
+                stage: ProgressStage::Training,
+                current: info.current(),
+                total: info.total(),
+            });
+        }
         if info.want_abort {
             self.interrupter.stop();
         }
@@ -195,25 +218,139 @@ impl MetricsRenderer for ProgressCollector {
     fn render_valid(&mut self, _item: TrainingProgress) {}
 }
 
+/// Controls how parameters are trained by [FSRS::compute_parameters_with_config]. The
+/// initial stability of `model` is replaced by the pretrained one.
 #[derive(Config)]
-pub(crate) struct TrainingConfig {
+pub struct TrainingConfig {
     pub model: ModelConfig,
-    pub optimizer: AdamConfig,
+    #[config(default = "OptimizerChoice::default()")]
+    pub optimizer: OptimizerChoice,
     #[config(default = 5)]
     pub num_epochs: usize,
     #[config(default = 512)]
     pub batch_size: usize,
+    /// Seeds the order of the batches, resampling and the splits. Batches are built and
+    /// shuffled on the training thread, so the same items, config and seed give the same
+    /// parameters whatever the number of threads.
     #[config(default = 2023)]
     pub seed: u64,
     #[config(default = 4e-2)]
     pub learning_rate: f64,
+    #[config(default = "LrSchedule::default()")]
+    pub lr_schedule: LrSchedule,
+    /// Items with more reviews than this are left out of training, as in the Python
+    /// optimizer, unless `truncate_long_histories` is set.
     #[config(default = 64)]
     pub max_seq_len: usize,
+    /// Instead of leaving out items with more than `max_seq_len` reviews, train on their
+    /// most recent `max_seq_len` reviews. Cards with hundreds of reviews are kept, without
+    /// widening the padded batches they are in. As with
+    /// [TruncationPolicy::KeepRecent](crate::dataset::TruncationPolicy::KeepRecent), the truncated
+    /// cards are trained on as if their first kept review was their first one.
+    #[config(default = false)]
+    pub truncate_long_histories: bool,
     #[config(default = 1.0)]
     pub gamma: f64,
+    /// Remove outliers before pretraining and training. None keeps all items.
+    #[config(default = "Some(OutlierFilterConfig::new())")]
+    pub outlier_filter: Option<OutlierFilterConfig>,
+    /// Rebalance successes and failures in the training set. The seed above is used.
+    pub resample: Option<ResampleConfig>,
+    /// Train on this many splits of the items in parallel, and average the parameters, to
+    /// reduce the variance caused by the order of the batches. Each split holds out a
+    /// different part of the shuffled items to pick its best epoch with. With one split,
+    /// all items are used for both.
+    #[config(default = 1)]
+    pub n_splits: usize,
+}
+
+/// How training went, returned by [FSRS::compute_parameters_with_report].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainingReport {
+    pub parameters: Vec<f32>,
+    /// The validation loss of the parameters, averaged over the splits. None if there
+    /// were too few items to train on, and defaults or pretrained parameters were returned.
+    pub final_loss: Option<f64>,
+    /// The validation loss after each epoch, averaged over the splits. A loss that is
+    /// still falling at the last epoch suggests training more epochs. Empty if training
+    /// was skipped.
+    pub epoch_losses: Vec<f64>,
+    /// [ModelEvaluation::rmse_bins](crate::ModelEvaluation::rmse_bins) of the parameters
+    /// on the items they were trained on. None if there were no items.
+    pub evaluated_rmse: Option<f32>,
+    /// The number of items passed in, before outliers were filtered out.
+    pub item_count: usize,
+}
+
+/// The optimizer parameters are trained with. Betas, epsilon and weight decay are set on
+/// the burn config of each optimizer, eg with [AdamConfig::with_beta_1] and
+/// [AdamConfig::with_weight_decay]. The learning rate comes from [TrainingConfig].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptimizerChoice {
+    Adam(AdamConfig),
+    AdamW(AdamWConfig),
+    Sgd(SgdConfig),
+    RmsProp(RmsPropConfig),
+}
+
+impl Default for OptimizerChoice {
+    /// Adam with the epsilon of the Python optimizer.
+    fn default() -> Self {
+        Self::Adam(AdamConfig::new().with_epsilon(1e-8))
+    }
+}
+
+impl From<AdamConfig> for OptimizerChoice {
+    fn from(config: AdamConfig) -> Self {
+        Self::Adam(config)
+    }
+}
+
+impl From<AdamWConfig> for OptimizerChoice {
+    fn from(config: AdamWConfig) -> Self {
+        Self::AdamW(config)
+    }
 }
 
-pub fn calculate_average_recall(items: &[FSRSItem]) -> f32 {
+impl From<SgdConfig> for OptimizerChoice {
+    fn from(config: SgdConfig) -> Self {
+        Self::Sgd(config)
+    }
+}
+
+impl From<RmsPropConfig> for OptimizerChoice {
+    fn from(config: RmsPropConfig) -> Self {
+        Self::RmsProp(config)
+    }
+}
+
+/// Split `items` into `n_splits` pairs of training and validation items, each validating
+/// on a different part of the shuffled items and training on the rest. The order of the
+/// items is kept in each part.
+fn split_folds<T: Clone>(items: Vec<T>, n_splits: usize, seed: u64) -> Vec<(Vec<T>, Vec<T>)> {
+    if n_splits == 1 {
+        return vec![(items.clone(), items)];
+    }
+    let mut folds: Vec<usize> = (0..items.len()).map(|i| i % n_splits).collect();
+    folds.shuffle(&mut StdRng::seed_from_u64(seed));
+    (0..n_splits)
+        .map(|split| {
+            let mut train_set = vec![];
+            let mut test_set = vec![];
+            for (item, &fold) in items.iter().zip(&folds) {
+                if fold == split {
+                    test_set.push(item.clone());
+                } else {
+                    train_set.push(item.clone());
+                }
+            }
+            (train_set, test_set)
+        })
+        .collect()
+}
+
+pub(crate) fn calculate_average_recall(items: &[FSRSItem]) -> f32 {
     let (total_recall, total_reviews) = items
         .iter()
         .map(|item| item.current())
@@ -235,76 +372,328 @@ impl<B: Backend> FSRS<B> {
         progress: Option<Arc<Mutex<CombinedProgressState>>>,
         enable_short_term: bool,
     ) -> Result<Vec<f32>> {
-        let finish_progress = || {
-            if let Some(progress) = &progress {
-                // The progress state at completion time may not indicate completion, because:
-                // - If there were fewer than 512 entries, render_train() will have never been called
-                // - One or more of the splits may have ignored later epochs, if accuracy went backwards
-                // Because of this, we need a separate finished flag.
-                progress.lock().unwrap().finished = true;
+        let config = self.default_training_config(enable_short_term);
+        self.compute_parameters_with_config(train_set, progress, &config)
+    }
+
+    /// Like [FSRS::compute_parameters], calling `progress` with the number of items trained
+    /// on so far after every batch, like the callbacks of [FSRS::evaluate]. Training stops
+    /// with [FSRSError::Interrupted] once `progress` returns false. `progress` is called on
+    /// the calling thread, while training runs on another.
+    pub fn compute_parameters_with_progress<F>(
+        &self,
+        train_set: Vec<FSRSItem>,
+        enable_short_term: bool,
+        mut progress: F,
+    ) -> Result<Vec<f32>>
+    where
+        F: FnMut(ItemProgress) -> bool,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let state = CombinedProgressState::new_shared();
+        state.lock().unwrap().listener = Some(sender);
+        thread::scope(|scope| {
+            let training = scope.spawn(|| {
+                let parameters =
+                    self.compute_parameters(train_set, Some(state.clone()), enable_short_term);
+                // closes the channel
+                state.lock().unwrap().listener = None;
+                parameters
+            });
+            loop {
+                match receiver.recv_timeout(Duration::from_millis(100)) {
+                    Ok(message) => {
+                        let info = ItemProgress {
+                            current: message.current,
+                            total: message.total,
+                        };
+                        if !progress(info) {
+                            state.lock().unwrap().want_abort = true;
+                        }
+                    }
+                    // if training panicked, the channel is never closed
+                    Err(RecvTimeoutError::Timeout) if !training.is_finished() => {}
+                    Err(_) => break,
+                }
             }
-        };
+            training.join().unwrap()
+        })
+    }
+
+    /// The config used by [FSRS::compute_parameters].
+    pub(crate) fn default_training_config(&self, enable_short_term: bool) -> TrainingConfig {
+        let mut config = self.training_config().cloned().unwrap_or_else(|| {
+            TrainingConfig::new(
+                ModelConfig::default()
+                    .with_forgetting_curve(self.forgetting_curve())
+                    .with_linear_damping(self.linear_damping())
+                    .with_exponential_initial_difficulty(self.exponential_initial_difficulty()),
+            )
+        });
+        config.model.freeze_initial_stability = !enable_short_term;
+        config.model.freeze_short_term_stability = !enable_short_term;
+        config
+    }
+
+    /// Like [FSRS::compute_parameters], with control over how training is done.
+    pub fn compute_parameters_with_config(
+        &self,
+        train_set: Vec<FSRSItem>,
+        progress: Option<Arc<Mutex<CombinedProgressState>>>,
+        config: &TrainingConfig,
+    ) -> Result<Vec<f32>> {
+        self.compute_parameters_inner(train_set, None, progress, config, None)
+            .map(|report| report.parameters)
+    }
+
+    /// Like [FSRS::compute_parameters_with_config], passing the state of training to
+    /// `on_checkpoint` after every epoch, so that it can be resumed from that point by
+    /// passing the last checkpoint as `resume_from`. With several splits, the checkpoint
+    /// holds the last completed epoch of each. Resuming with other items or another config
+    /// fails with [FSRSError::InvalidInput].
+    pub fn compute_parameters_with_checkpoints<F>(
+        &self,
+        train_set: Vec<FSRSItem>,
+        progress: Option<Arc<Mutex<CombinedProgressState>>>,
+        config: &TrainingConfig,
+        resume_from: Option<TrainingCheckpoint>,
+        on_checkpoint: F,
+    ) -> Result<Vec<f32>>
+    where
+        F: Fn(&TrainingCheckpoint) + Sync,
+    {
+        let checkpointer = Checkpointer::new(resume_from, &on_checkpoint);
+        self.compute_parameters_inner(train_set, None, progress, config, Some(&checkpointer))
+            .map(|report| report.parameters)
+    }
+
+    /// Like [FSRS::compute_parameters], also reporting how training went. The parameters
+    /// are evaluated on `train_set`, as with [FSRS::evaluate], to fill
+    /// [TrainingReport::evaluated_rmse].
+    pub fn compute_parameters_with_report(
+        &self,
+        train_set: Vec<FSRSItem>,
+        progress: Option<Arc<Mutex<CombinedProgressState>>>,
+        enable_short_term: bool,
+    ) -> Result<TrainingReport> {
+        let config = self.default_training_config(enable_short_term);
+        let mut report =
+            self.compute_parameters_inner(train_set.clone(), None, progress, &config, None)?;
+        if !train_set.is_empty() {
+            let trained = Self::new_with_backend::<B>(Some(&report.parameters), self.device())?
+                .with_forgetting_curve(self.forgetting_curve())
+                .with_linear_damping(self.linear_damping())
+                .with_exponential_initial_difficulty(self.exponential_initial_difficulty());
+            report.evaluated_rmse = Some(trained.evaluate(train_set, |_| true)?.rmse_bins);
+        }
+        Ok(report)
+    }
+
+    /// Like [FSRS::compute_parameters], with a weight for each item of `train_set`, in the
+    /// same order. The loss of each item is scaled by its weight on top of the weighting
+    /// of recent reviews, so that eg re-imported or suspect reviews count less without
+    /// being filtered out. Weights must be positive and finite; pretraining of the
+    /// initial stability does not use them.
+    pub fn compute_parameters_with_sample_weights(
+        &self,
+        train_set: Vec<FSRSItem>,
+        sample_weights: &[f32],
+        progress: Option<Arc<Mutex<CombinedProgressState>>>,
+        enable_short_term: bool,
+    ) -> Result<Vec<f32>> {
+        let config = self.default_training_config(enable_short_term);
+        self.compute_parameters_inner(train_set, Some(sample_weights), progress, &config, None)
+            .map(|report| report.parameters)
+    }
 
+    fn compute_parameters_inner(
+        &self,
+        train_set: Vec<FSRSItem>,
+        sample_weights: Option<&[f32]>,
+        progress: Option<Arc<Mutex<CombinedProgressState>>>,
+        config: &TrainingConfig,
+        checkpointer: Option<&Checkpointer>,
+    ) -> Result<TrainingReport> {
+        let progress =
+            progress.or_else(|| self.progress_handle().map(ProgressHandle::training_state));
+        let report = self.compute_split(
+            train_set,
+            sample_weights,
+            progress.as_ref(),
+            0,
+            config,
+            checkpointer,
+        );
+        if let Some(progress) = &progress {
+            // The progress state at completion time may not indicate completion, because:
+            // - If there were fewer than 512 entries, render_train() will have never been called
+            // - One or more of the splits may have ignored later epochs, if accuracy went backwards
+            // Because of this, we need a separate finished flag.
+            progress.lock().unwrap().finished = true;
+        }
+        report
+    }
+
+    /// Train on `train_set`, reporting progress in `splits[split]` of `progress`. The caller
+    /// marks the progress as finished.
+    pub(crate) fn compute_split(
+        &self,
+        train_set: Vec<FSRSItem>,
+        sample_weights: Option<&[f32]>,
+        progress: Option<&Arc<Mutex<CombinedProgressState>>>,
+        split: usize,
+        config: &TrainingConfig,
+        checkpointer: Option<&Checkpointer>,
+    ) -> Result<TrainingReport> {
+        self.emit(FSRSEvent::TrainingStarted {
+            item_count: train_set.len(),
+        });
+        let report = self.train_parameters(
+            train_set,
+            sample_weights,
+            progress,
+            split,
+            config,
+            checkpointer,
+        )?;
+        self.emit(FSRSEvent::TrainingFinished {
+            parameters: report.parameters.clone(),
+        });
+        Ok(report)
+    }
+
+    fn train_parameters(
+        &self,
+        train_set: Vec<FSRSItem>,
+        sample_weights: Option<&[f32]>,
+        progress: Option<&Arc<Mutex<CombinedProgressState>>>,
+        split: usize,
+        config: &TrainingConfig,
+        checkpointer: Option<&Checkpointer>,
+    ) -> Result<TrainingReport> {
+        validate_items(&train_set)?;
+        let initial_parameters = config
+            .model
+            .initial_parameters
+            .as_deref()
+            .map(|parameters| {
+                check_and_fill_parameters(parameters, config.model.exponential_initial_difficulty)
+            })
+            .transpose()?;
+        let item_count = train_set.len();
         let average_recall = calculate_average_recall(&train_set);
-        let (pre_train_set, train_set) = prepare_training_data(train_set);
-        if train_set.len() < 8 {
-            finish_progress();
-            return Ok(DEFAULT_PARAMETERS.to_vec());
+        let train_set = match sample_weights {
+            Some(sample_weights) => sample_weighted_fsrs_items(train_set, sample_weights)?,
+            None => constant_weighted_fsrs_items(train_set),
+        };
+        let (pre_train_set, mut weighted_train_set) =
+            prepare_training_data(train_set, config.outlier_filter.as_ref());
+        let pre_train_set: Vec<FSRSItem> =
+            pre_train_set.into_iter().map(|item| item.item).collect();
+        // training is skipped when there are too few items
+        let untrained = |mut parameters: Vec<f32>| {
+            if config.model.trainable_decay {
+                parameters.push(-DECAY as f32);
+            }
+            TrainingReport {
+                parameters,
+                final_loss: None,
+                epoch_losses: vec![],
+                evaluated_rmse: None,
+                item_count,
+            }
+        };
+        if weighted_train_set.len() < 8 {
+            return Ok(untrained(DEFAULT_PARAMETERS.to_vec()));
         }
 
-        let (initial_stability, initial_rating_count) =
-            pretrain(pre_train_set.clone(), average_recall).map_err(|e| {
-                finish_progress();
-                e
-            })?;
+        let (initial_stability, initial_rating_count) = pretrain(
+            pre_train_set.clone(),
+            average_recall,
+            config.model.forgetting_curve,
+        )?;
         let pretrained_parameters: Vec<f32> = initial_stability
             .into_iter()
             .chain(DEFAULT_PARAMETERS[4..].iter().copied())
             .collect();
-        if train_set.len() == pre_train_set.len() || train_set.len() < 64 {
-            finish_progress();
-            return Ok(pretrained_parameters);
+        if weighted_train_set.len() == pre_train_set.len() || weighted_train_set.len() < 64 {
+            return Ok(untrained(pretrained_parameters));
+        }
+        let mut config = config.clone();
+        config.model.initial_stability = Some(match &initial_parameters {
+            Some(parameters) => parameters[0..4].try_into().unwrap(),
+            None => initial_stability,
+        });
+        config.model.initial_parameters = initial_parameters;
+        apply_recency_weights(&mut weighted_train_set);
+        if !config.truncate_long_histories {
+            weighted_train_set.retain(|item| item.item.reviews.len() <= config.max_seq_len);
+        }
+        if let Some(resample_config) = &config.resample {
+            weighted_train_set = resample(
+                weighted_train_set,
+                |item| item.item.current().rating == 1,
+                resample_config,
+                config.seed,
+            );
         }
-        let config = TrainingConfig::new(
-            ModelConfig {
-                freeze_initial_stability: !enable_short_term,
-                initial_stability: Some(initial_stability),
-                freeze_short_term_stability: !enable_short_term,
-            },
-            AdamConfig::new().with_epsilon(1e-8),
-        );
-        let mut weighted_train_set = recency_weighted_fsrs_items(train_set);
-        weighted_train_set.retain(|item| item.item.reviews.len() <= config.max_seq_len);
 
-        if let Some(progress) = &progress {
-            let progress_state = ProgressState {
-                epoch_total: config.num_epochs,
-                items_total: weighted_train_set.len(),
-                epoch: 0,
-                items_processed: 0,
-            };
-            progress.lock().unwrap().splits = vec![progress_state];
+        let n_splits = config.n_splits;
+        if n_splits == 0 || n_splits > weighted_train_set.len() {
+            return Err(FSRSError::InvalidInput);
         }
-        let model = train::<Autodiff<B>>(
-            weighted_train_set.clone(),
-            weighted_train_set,
-            &config,
-            self.device(),
-            progress.clone().map(|p| ProgressCollector::new(p, 0)),
+        if let Some(checkpointer) = checkpointer {
+            let fingerprint = format!("{} items; {}", weighted_train_set.len(), config);
+            checkpointer.start(fingerprint, n_splits)?;
+        }
+        let folds = split_folds(weighted_train_set, n_splits, config.seed);
+        let fold_parameters = self.install(|| {
+            folds
+                .into_par_iter()
+                .enumerate()
+                .map(|(fold, (train_set, test_set))| {
+                    let index = split * n_splits + fold;
+                    if let Some(progress) = &progress {
+                        let progress_state = ProgressState {
+                            epoch_total: config.num_epochs,
+                            items_total: train_set.len(),
+                            epoch: 0,
+                            items_processed: 0,
+                        };
+                        let splits = &mut progress.lock().unwrap().splits;
+                        if splits.len() <= index {
+                            splits.resize(index + 1, ProgressState::default());
+                        }
+                        splits[index] = progress_state;
+                    }
+                    let hooks = TrainingHooks {
+                        progress: progress.map(|p| ProgressCollector::new(p.clone(), index)),
+                        cancellation_token: self.cancellation_token(),
+                        checkpoint: checkpointer.map(|checkpointer| (checkpointer, fold)),
+                    };
+                    let (model, epoch_losses) =
+                        train::<Autodiff<B>>(train_set, test_set, &config, self.device(), hooks)?;
+                    Ok((model.w.val().to_data().convert().value, epoch_losses))
+                })
+                .collect::<Result<Vec<(Vec<f32>, Vec<f64>)>>>()
+        })?;
+        let (fold_parameters, fold_losses): (Vec<Vec<f32>>, Vec<Vec<f64>>) =
+            fold_parameters.into_iter().unzip();
+        let mean = |values: Vec<f64>| values.iter().sum::<f64>() / values.len() as f64;
+        // each split keeps the parameters of its epoch with the lowest loss
+        let final_loss = mean(
+            fold_losses
+                .iter()
+                .map(|losses| losses.iter().copied().fold(f64::INFINITY, f64::min))
+                .collect(),
         );
-
-        let optimized_parameters = model
-            .map_err(|e| {
-                finish_progress();
-                e
-            })?
-            .w
-            .val()
-            .to_data()
-            .convert()
-            .value;
-
-        finish_progress();
+        let epoch_losses = (0..config.num_epochs)
+            .map(|epoch| mean(fold_losses.iter().map(|losses| losses[epoch]).collect()))
+            .collect();
+        // the mean of the parameters trained on each split
+        let optimized_parameters: Vec<f32> = (0..fold_parameters[0].len())
+            .map(|i| fold_parameters.iter().map(|w| w[i]).sum::<f32>() / n_splits as f32)
+            .collect();
 
         if optimized_parameters
             .iter()
@@ -325,7 +714,13 @@ impl<B: Backend> FSRS<B> {
             .chain(optimized_parameters[4..].iter().copied())
             .collect();
 
-        Ok(optimized_parameters)
+        Ok(TrainingReport {
+            parameters: optimized_parameters,
+            final_loss: Some(final_loss),
+            epoch_losses,
+            evaluated_rmse: None,
+            item_count,
+        })
     }
 
     pub fn benchmark(&self, train_set: Vec<FSRSItem>, enable_short_term: bool) -> Vec<f32> {
@@ -334,15 +729,20 @@ impl<B: Backend> FSRS<B> {
             .clone()
             .into_iter()
             .partition(|item| item.long_term_review_cnt() == 1);
-        let initial_stability = pretrain(pre_train_set, average_recall).unwrap().0;
-        let config = TrainingConfig::new(
-            ModelConfig {
-                freeze_initial_stability: !enable_short_term,
-                initial_stability: Some(initial_stability),
-                freeze_short_term_stability: !enable_short_term,
-            },
-            AdamConfig::new().with_epsilon(1e-8),
-        );
+        let initial_stability = pretrain(pre_train_set, average_recall, self.forgetting_curve())
+            .unwrap()
+            .0;
+        let config = TrainingConfig::new(ModelConfig {
+            freeze_initial_stability: !enable_short_term,
+            initial_stability: Some(initial_stability),
+            freeze_short_term_stability: !enable_short_term,
+            trainable_decay: false,
+            initial_parameters: None,
+            freeze_mask: None,
+            forgetting_curve: self.forgetting_curve(),
+            linear_damping: self.linear_damping(),
+            exponential_initial_difficulty: self.exponential_initial_difficulty(),
+        });
         let mut weighted_train_set = recency_weighted_fsrs_items(train_set);
         weighted_train_set.retain(|item| item.item.reviews.len() <= config.max_seq_len);
         let model = train::<Autodiff<B>>(
@@ -350,9 +750,12 @@ impl<B: Backend> FSRS<B> {
             weighted_train_set,
             &config,
             self.device(),
-            None,
+            TrainingHooks {
+                cancellation_token: self.cancellation_token(),
+                ..Default::default()
+            },
         );
-        let parameters: Vec<f32> = model.unwrap().w.val().to_data().convert().value;
+        let parameters: Vec<f32> = model.unwrap().0.w.val().to_data().convert().value;
         parameters
     }
 }
@@ -362,16 +765,51 @@ fn train<B: AutodiffBackend>(
     test_set: Vec<WeightedFSRSItem>,
     config: &TrainingConfig,
     device: B::Device,
-    progress: Option<ProgressCollector>,
-) -> Result<Model<B>> {
+    hooks: TrainingHooks,
+) -> Result<(Model<B>, Vec<f64>)> {
+    macro_rules! train_with {
+        ($optimizer:expr) => {
+            train_with_optimizer(
+                train_set,
+                test_set,
+                config,
+                device,
+                hooks,
+                $optimizer.init(),
+            )
+        };
+    }
+    match &config.optimizer {
+        OptimizerChoice::Adam(optimizer) => train_with!(optimizer),
+        OptimizerChoice::AdamW(optimizer) => train_with!(optimizer),
+        OptimizerChoice::Sgd(optimizer) => train_with!(optimizer),
+        OptimizerChoice::RmsProp(optimizer) => train_with!(optimizer),
+    }
+}
+
+fn train_with_optimizer<B: AutodiffBackend, O: Optimizer<Model<B>, B>>(
+    train_set: Vec<WeightedFSRSItem>,
+    test_set: Vec<WeightedFSRSItem>,
+    config: &TrainingConfig,
+    device: B::Device,
+    hooks: TrainingHooks,
+    mut optim: O,
+) -> Result<(Model<B>, Vec<f64>)> {
     B::seed(config.seed);
+    let TrainingHooks {
+        progress,
+        cancellation_token,
+        checkpoint,
+    } = hooks;
 
     // Training data
     let total_size = train_set.len();
     let iterations = (total_size / config.batch_size + 1) * config.num_epochs;
+    let max_seq_len = config.truncate_long_histories.then_some(config.max_seq_len);
     let batch_dataset = BatchTensorDataset::<B>::new(
         FSRSDataset::from(train_set),
         config.batch_size,
+        max_seq_len,
         device.clone(),
     );
     let dataloader_train = ShuffleDataLoader::new(batch_dataset, config.seed);
@@ -379,11 +817,12 @@ fn train<B: AutodiffBackend>(
     let batch_dataset = BatchTensorDataset::<B::InnerBackend>::new(
         FSRSDataset::from(test_set.clone()),
         config.batch_size,
+        max_seq_len,
         device.clone(),
     );
     let dataloader_valid = ShuffleDataLoader::new(batch_dataset, config.seed);
 
-    let mut lr_scheduler = CosineAnnealingLR::init(iterations as f64, config.learning_rate);
+    let mut lr_scheduler = ScheduledLr::init(config.lr_schedule, config.learning_rate, iterations)?;
     let interrupter = TrainingInterrupter::new();
     let mut renderer: Box<dyn MetricsRenderer> = match progress {
         Some(mut progress) => {
@@ -392,15 +831,33 @@ fn train<B: AutodiffBackend>(
         }
         None => Box::new(NoProgress {}),
     };
+    let should_stop = || {
+        interrupter.should_stop() || cancellation_token.is_some_and(|token| token.is_cancelled())
+    };
 
     let mut model: Model<B> = config.model.init();
     let init_w = model.w.val();
-    let params_stddev = Tensor::from_floats(PARAMS_STDDEV, &device);
-    let mut optim = config.optimizer.init::<B, Model<B>>();
+    let params_stddev = Tensor::from_floats(&PARAMS_STDDEV[..init_w.dims()[0]], &device);
+    let frozen = config.model.frozen_parameters(init_w.dims()[0])?;
 
     let mut best_loss = f64::INFINITY;
     let mut best_model = model.clone();
-    for epoch in 1..=config.num_epochs {
+    let mut epoch_losses = vec![];
+    let mut first_epoch = 1;
+    if let Some(resumed) = checkpoint.and_then(|(checkpointer, split)| checkpointer.resume(split)) {
+        model = model.load_record(record_from_value::<B, _>(&resumed.model, &device)?);
+        best_model =
+            best_model.load_record(record_from_value::<B, _>(&resumed.best_model, &device)?);
+        optim = optim.load_record(record_from_value::<B, _>(&resumed.optimizer, &device)?);
+        lr_scheduler = LrScheduler::<B>::load_record(lr_scheduler, resumed.lr_step);
+        best_loss = resumed.best_loss.unwrap_or(f64::INFINITY);
+        epoch_losses = resumed.epoch_losses;
+        // the batches are shuffled again every epoch
+        dataloader_train.skip_epochs(resumed.epoch);
+        dataloader_valid.skip_epochs(resumed.epoch);
+        first_epoch = resumed.epoch + 1;
+    }
+    for epoch in first_epoch..=config.num_epochs {
         let mut iterator = dataloader_train.iter();
         let mut iteration = 0;
         while let Some(item) = iterator.next() {
@@ -415,24 +872,14 @@ fn train<B: AutodiffBackend>(
                 total_size,
                 config.gamma,
             );
-            let loss = model.forward_classification(
-                item.t_historys,
-                item.r_historys,
-                item.delta_ts,
-                item.labels,
-                item.weights,
-                Reduction::Sum,
-            );
+            let loss = model.forward_classification(item, Reduction::Sum);
             let mut gradients = (loss + penalty).backward();
-            if model.config.freeze_initial_stability {
-                gradients = model.freeze_initial_stability(gradients);
-            }
-            if model.config.freeze_short_term_stability {
-                gradients = model.free_short_term_stability(gradients);
+            if frozen.contains(&true) {
+                gradients = model.freeze_parameters(gradients, &frozen);
             }
             let grads = GradientsParams::from_grads(gradients, &model);
             model = optim.step(lr, model, grads);
-            model.w = parameter_clipper(model.w);
+            model.w = parameter_clipper(model.w, model.config.exponential_initial_difficulty);
             // info!("epoch: {:?} iteration: {:?} lr: {:?}", epoch, iteration, lr);
             renderer.render_train(TrainingProgress {
                 progress,
@@ -441,12 +888,12 @@ fn train<B: AutodiffBackend>(
                 iteration,
             });
 
-            if interrupter.should_stop() {
+            if should_stop() {
                 break;
             }
         }
 
-        if interrupter.should_stop() {
+        if should_stop() {
             break;
         }
 
@@ -461,37 +908,57 @@ fn train<B: AutodiffBackend>(
                 total_size,
                 config.gamma,
             );
-            let loss = model_valid.forward_classification(
-                batch.t_historys,
-                batch.r_historys,
-                batch.delta_ts,
-                batch.labels,
-                batch.weights,
-                Reduction::Sum,
-            );
+            let loss = model_valid.forward_classification(batch, Reduction::Sum);
             let loss = loss.into_data().convert::<f64>().value[0];
             let penalty = penalty.into_data().convert::<f64>().value[0];
             loss_valid += loss + penalty;
 
-            if interrupter.should_stop() {
+            if should_stop() {
                 break;
             }
         }
         loss_valid /= test_set.len() as f64;
         info!("epoch: {:?} loss: {:?}", epoch, loss_valid);
+        epoch_losses.push(loss_valid);
         if loss_valid < best_loss {
             best_loss = loss_valid;
             best_model = model.clone();
         }
+
+        if let Some((checkpointer, split)) = checkpoint.filter(|_| !should_stop()) {
+            checkpointer.save(
+                split,
+                SplitCheckpoint {
+                    epoch,
+                    model: record_to_value::<B, _>(model.clone().into_record()),
+                    best_model: record_to_value::<B, _>(best_model.clone().into_record()),
+                    best_loss: Some(best_loss).filter(|loss| loss.is_finite()),
+                    epoch_losses: epoch_losses.clone(),
+                    optimizer: record_to_value::<B, _>(optim.to_record()),
+                    lr_step: LrScheduler::<B>::to_record(&lr_scheduler),
+                },
+            );
+        }
     }
 
     info!("best_loss: {:?}", best_loss);
 
-    if interrupter.should_stop() {
-        return Err(FSRSError::Interrupted);
+    if should_stop() {
+        return Err(FSRSError::Interrupted {
+            stage: ProgressStage::Training,
+        });
     }
 
-    Ok(best_model)
+    Ok((best_model, epoch_losses))
+}
+
+/// What [train] reports to and is controlled by, besides its config.
+#[derive(Default)]
+struct TrainingHooks<'a> {
+    progress: Option<ProgressCollector>,
+    cancellation_token: Option<&'a CancellationToken>,
+    /// Where to save the state after each epoch, and the index of the split.
+    checkpoint: Option<(&'a Checkpointer<'a>, usize)>,
 }
 
 struct NoProgress {}
@@ -516,11 +983,352 @@ mod tests {
     use super::*;
     use crate::convertor_tests::anki21_sample_file_converted_to_fsrs;
     use crate::convertor_tests::data_from_csv;
-    use crate::dataset::FSRSBatch;
+    use crate::dataset::{FSRSBatcher, PaddingSide};
+    use crate::forgetting_curve::CurveKind;
+    use crate::synthetic::{generate_sample_items, SampleDatasetConfig};
     use crate::test_helpers::assert_approx_eq;
     use burn::backend::NdArray;
+    use burn::data::dataloader::batcher::Batcher;
+    use burn::optim::decay::WeightDecayConfig;
     use log::LevelFilter;
 
+    #[test]
+    fn folds() {
+        let items: Vec<usize> = (0..10).collect();
+        assert_eq!(
+            split_folds(items.clone(), 1, 1),
+            [(items.clone(), items.clone())]
+        );
+        let folds = split_folds(items.clone(), 3, 1);
+        assert_eq!(folds.len(), 3);
+        let mut held_out: Vec<usize> = vec![];
+        for (train_set, test_set) in &folds {
+            assert_eq!(train_set.len() + test_set.len(), 10);
+            assert!((3..=4).contains(&test_set.len()));
+            assert!(train_set.windows(2).all(|pair| pair[0] < pair[1]));
+            held_out.extend(test_set.iter().copied());
+        }
+        held_out.sort();
+        assert_eq!(held_out, items);
+        assert_eq!(split_folds(items, 3, 1), folds);
+    }
+
+    /// Enough cards for a few batches, with short histories to keep training fast.
+    fn sample_items() -> Result<Vec<FSRSItem>> {
+        generate_sample_items(&SampleDatasetConfig {
+            reviews_per_card: 5,
+            ..Default::default()
+        })
+    }
+
+    fn quick_config(num_epochs: usize) -> TrainingConfig {
+        TrainingConfig::new(ModelConfig::default()).with_num_epochs(num_epochs)
+    }
+
+    #[test]
+    fn averaged_splits() -> Result<()> {
+        let fsrs = FSRS::new(None)?;
+        let items = sample_items()?;
+        let config = quick_config(1).with_n_splits(2);
+        let progress = CombinedProgressState::new_shared();
+        let parameters =
+            fsrs.compute_parameters_with_config(items.clone(), Some(progress.clone()), &config)?;
+        assert_eq!(parameters.len(), 19);
+        assert_eq!(progress.lock().unwrap().splits.len(), 2);
+        let single = fsrs.compute_parameters_with_config(
+            items.clone(),
+            None,
+            &config.clone().with_n_splits(1),
+        )?;
+        assert_ne!(parameters, single);
+        assert_eq!(
+            fsrs.compute_parameters_with_config(items, None, &config.with_n_splits(0)),
+            Err(FSRSError::InvalidInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sample_weights() -> Result<()> {
+        let items = sample_items()?;
+        let config = quick_config(1);
+        let fsrs = FSRS::new(None)?.with_training_config(config);
+        let unweighted = fsrs.compute_parameters(items.clone(), None, false)?;
+        // the loss is normalized by the total weight of each batch
+        let doubled = vec![2.0; items.len()];
+        let parameters =
+            fsrs.compute_parameters_with_sample_weights(items.clone(), &doubled, None, false)?;
+        assert!(parameters
+            .iter()
+            .zip(&unweighted)
+            .all(|(a, b)| (a - b).abs() < 1e-3));
+        let weights: Vec<f32> = (0..items.len())
+            .map(|i| if i % 2 == 0 { 1.0 } else { 0.1 })
+            .collect();
+        let weighted =
+            fsrs.compute_parameters_with_sample_weights(items.clone(), &weights, None, false)?;
+        assert_ne!(weighted, unweighted);
+
+        for weights in [vec![1.0; items.len() - 1], vec![0.0; items.len()]] {
+            assert_eq!(
+                fsrs.compute_parameters_with_sample_weights(items.clone(), &weights, None, false),
+                Err(FSRSError::InvalidInput)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn optimizers() -> Result<()> {
+        let items = sample_items()?;
+        let config = quick_config(1);
+        let fsrs = FSRS::new(None)?;
+        let adam = fsrs.compute_parameters_with_config(items.clone(), None, &config)?;
+        let choices: [OptimizerChoice; 4] = [
+            AdamConfig::new()
+                .with_beta_2(0.99)
+                .with_weight_decay(Some(WeightDecayConfig::new(0.01)))
+                .into(),
+            AdamWConfig::new().into(),
+            SgdConfig::new().into(),
+            RmsPropConfig::new().into(),
+        ];
+        for choice in choices {
+            let config = config.clone().with_optimizer(choice);
+            let json = config.to_string();
+            let config = TrainingConfig::load_binary(json.as_bytes()).unwrap();
+            assert_eq!(config.to_string(), json);
+            let parameters = fsrs.compute_parameters_with_config(items.clone(), None, &config)?;
+            assert_eq!(parameters.len(), adam.len());
+            assert_ne!(parameters, adam);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn lr_schedules() -> Result<()> {
+        let items = sample_items()?;
+        let config = quick_config(1);
+        let fsrs = FSRS::new(None)?;
+        let cosine = fsrs.compute_parameters_with_config(items.clone(), None, &config)?;
+        let warmup = config.clone().with_lr_schedule(LrSchedule::WarmupCosine {
+            warmup_steps: 2,
+            eta_min: 0.0,
+        });
+        let parameters = fsrs.compute_parameters_with_config(items.clone(), None, &warmup)?;
+        assert_ne!(parameters, cosine);
+        let invalid = config.with_lr_schedule(LrSchedule::CosineWithPeriod { t_max: 0 });
+        assert_eq!(
+            fsrs.compute_parameters_with_config(items, None, &invalid),
+            Err(FSRSError::InvalidInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn deterministic_across_threads() -> Result<()> {
+        let items = sample_items()?;
+        let config = quick_config(2)
+            .with_batch_size(64)
+            .with_n_splits(3)
+            .with_resample(Some(ResampleConfig::new().with_success_keep_ratio(0.8)));
+        let train = |threads| {
+            FSRS::new(None)?
+                .with_max_threads(threads)?
+                .compute_parameters_with_config(items.clone(), None, &config)
+        };
+        let single = train(1)?;
+        assert_eq!(train(4)?, single);
+        assert_eq!(train(1)?, single);
+        Ok(())
+    }
+
+    #[test]
+    fn trains_on_the_forgetting_curve() -> Result<()> {
+        let items = sample_items()?;
+        let train = |curve| {
+            let config = TrainingConfig::new(ModelConfig::default().with_forgetting_curve(curve))
+                .with_num_epochs(1);
+            FSRS::new(None)?
+                .with_training_config(config)
+                .compute_parameters_with_report(items.clone(), None, false)
+        };
+        let power = train(CurveKind::Power)?;
+        let exponential = train(CurveKind::Exponential)?;
+        assert_ne!(exponential.epoch_losses, power.epoch_losses);
+        assert_ne!(exponential.parameters, power.parameters);
+        Ok(())
+    }
+
+    #[test]
+    fn training_report() -> Result<()> {
+        let items = sample_items()?;
+        let config = quick_config(2);
+        let fsrs = FSRS::new(None)?.with_training_config(config);
+        let report = fsrs.compute_parameters_with_report(items.clone(), None, false)?;
+        assert_eq!(
+            report.parameters,
+            fsrs.compute_parameters(items.clone(), None, false)?
+        );
+        assert_eq!(report.item_count, items.len());
+        assert_eq!(report.epoch_losses.len(), 2);
+        assert_eq!(
+            report.final_loss,
+            Some(report.epoch_losses[0].min(report.epoch_losses[1]))
+        );
+        let rmse = report.evaluated_rmse.unwrap();
+        assert!(rmse > 0.0 && rmse < 0.2);
+
+        let report = fsrs.compute_parameters_with_report(items[..5].to_vec(), None, false)?;
+        assert_eq!(report.parameters, DEFAULT_PARAMETERS);
+        assert_eq!(report.final_loss, None);
+        assert!(report.epoch_losses.is_empty());
+        assert!(report.evaluated_rmse.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn resume_from_checkpoint() -> Result<()> {
+        let items = sample_items()?;
+        let config = quick_config(3).with_batch_size(64).with_n_splits(2);
+        let fsrs = FSRS::new(None)?;
+        let checkpoints = Mutex::new(vec![]);
+        let parameters = fsrs.compute_parameters_with_checkpoints(
+            items.clone(),
+            None,
+            &config,
+            None,
+            |checkpoint| checkpoints.lock().unwrap().push(checkpoint.clone()),
+        )?;
+        assert_eq!(
+            parameters,
+            fsrs.compute_parameters_with_config(items.clone(), None, &config)?
+        );
+        let checkpoints = checkpoints.into_inner().unwrap();
+        // an epoch for each split
+        assert_eq!(checkpoints.len(), 6);
+        assert_eq!(checkpoints[5].epochs_completed(), 3);
+
+        let after_first_epoch = checkpoints
+            .iter()
+            .rev()
+            .find(|checkpoint| checkpoint.epochs_completed() == 1)
+            .unwrap();
+        let saved = serde_json::to_string(after_first_epoch).unwrap();
+        let resume = |checkpoint: &str, config: &TrainingConfig| {
+            fsrs.compute_parameters_with_checkpoints(
+                items.clone(),
+                None,
+                config,
+                Some(serde_json::from_str(checkpoint).unwrap()),
+                |_| {},
+            )
+        };
+        assert_eq!(resume(&saved, &config)?, parameters);
+        let finished = serde_json::to_string(&checkpoints[5]).unwrap();
+        assert_eq!(resume(&finished, &config)?, parameters);
+        assert_eq!(
+            resume(&saved, &config.clone().with_num_epochs(4)),
+            Err(FSRSError::InvalidInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn config_serde() {
+        let model = ModelConfig::default()
+            .with_trainable_decay(true)
+            .with_freeze_mask(Some(vec![false, true]))
+            .with_forgetting_curve(CurveKind::Exponential);
+        let config = TrainingConfig::new(model)
+            .with_optimizer(SgdConfig::new().into())
+            .with_lr_schedule(LrSchedule::Constant)
+            .with_resample(Some(ResampleConfig::new().with_failure_copies(2)));
+        let json = serde_json::to_string(&config).unwrap();
+        let read: TrainingConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.to_string(), config.to_string());
+        assert_eq!(read.model.freeze_mask, Some(vec![false, true]));
+        assert_eq!(read.model.forgetting_curve, CurveKind::Exponential);
+        assert_eq!(read.lr_schedule, LrSchedule::Constant);
+        assert!(matches!(read.optimizer, OptimizerChoice::Sgd(_)));
+        assert_eq!(read.outlier_filter, Some(OutlierFilterConfig::new()));
+
+        let config = config.with_outlier_filter(None);
+        let json = serde_json::to_string(&config).unwrap();
+        let read: TrainingConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.outlier_filter, None);
+    }
+
+    #[test]
+    fn truncate_long_histories() -> Result<()> {
+        let items = generate_sample_items(&SampleDatasetConfig {
+            reviews_per_card: 8,
+            ..Default::default()
+        })?;
+        let fsrs = FSRS::new(None)?;
+        let config = quick_config(1).with_max_seq_len(4);
+        let dropped = fsrs.compute_parameters_with_config(items.clone(), None, &config)?;
+        let config = config.with_truncate_long_histories(true);
+        let truncated = fsrs.compute_parameters_with_config(items, None, &config)?;
+        assert_ne!(truncated, dropped);
+        assert!(truncated.iter().all(|p| p.is_finite()));
+        Ok(())
+    }
+
+    #[test]
+    fn double_precision() -> Result<()> {
+        let items = sample_items()?;
+        let config = quick_config(2);
+        let single =
+            FSRS::new(None)?.compute_parameters_with_config(items.clone(), None, &config)?;
+        let fsrs = FSRS::new_f64(None)?;
+        let double = fsrs.compute_parameters_with_config(items.clone(), None, &config)?;
+        assert_ne!(double, single);
+        assert!(double
+            .iter()
+            .zip(&single)
+            .all(|(a, b)| (a - b).abs() < 1e-2));
+        let report = fsrs.compute_parameters_with_report(items, None, false)?;
+        assert!(report.evaluated_rmse.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn progress_callback() -> Result<()> {
+        let items = sample_items()?;
+        let config = quick_config(2).with_batch_size(64);
+        let fsrs = FSRS::new(None)?.with_training_config(config);
+        let mut updates = vec![];
+        fsrs.compute_parameters_with_progress(items.clone(), true, |progress| {
+            updates.push(progress);
+            true
+        })?;
+        assert!(updates.len() > 2);
+        assert!(updates.windows(2).all(|w| w[0].current <= w[1].current));
+        let last = updates.last().unwrap();
+        assert_eq!(last.current, last.total);
+
+        assert_eq!(
+            fsrs.compute_parameters_with_progress(items, true, |_| false),
+            Err(FSRSError::Interrupted {
+                stage: ProgressStage::Training
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "right-padded")]
+    fn left_padded_batch() {
+        type B = NdArray<f32>;
+        let batcher =
+            FSRSBatcher::<B>::new(Default::default()).with_padding_side(PaddingSide::Left);
+        let items = sample_items().unwrap();
+        let batch = batcher.batch(items[..4].to_vec());
+        let model = Model::<B>::new(ModelConfig::default());
+        model.forward_classification(batch, Reduction::Sum);
+    }
+
     #[test]
     fn test_calculate_average_recall() {
         let items = anki21_sample_file_converted_to_fsrs();
@@ -528,6 +1336,21 @@ mod tests {
         assert_eq!(average_recall, 0.9435269);
     }
 
+    #[test]
+    fn freeze_parameters() {
+        type B = Autodiff<NdArray<f32>>;
+        let model: Model<B> = ModelConfig::default().init();
+        let gradients = model.w.val().powf_scalar(2.0).sum().backward();
+        let mut frozen = vec![false; 19];
+        frozen[5] = true;
+        frozen[18] = true;
+        let gradients = model.freeze_parameters(gradients, &frozen);
+        let w_grad = model.w.grad(&gradients).unwrap().into_data().value;
+        for (index, grad) in w_grad.into_iter().enumerate() {
+            assert_eq!(grad == 0.0, frozen[index], "{index}");
+        }
+    }
+
     #[test]
     fn test_loss_and_grad() {
         use burn::backend::ndarray::NdArrayDevice;
@@ -538,7 +1361,7 @@ mod tests {
         type B = Autodiff<NdArray<f32>>;
         let mut model: Model<B> = config.init();
         let init_w = model.w.val();
-        let params_stddev = Tensor::from_floats(PARAMS_STDDEV, &device);
+        let params_stddev = Tensor::from_floats(&PARAMS_STDDEV[..19], &device);
 
         let item = FSRSBatch {
             t_historys: Tensor::from_floats(
@@ -563,19 +1386,14 @@ mod tests {
                 ]),
                 &device,
             ),
+            mask: Tensor::<B, 2>::ones([6, 4], &device).bool(),
             delta_ts: Tensor::from_floats(Data::from([4.0, 11.0, 12.0, 23.0]), &device),
             labels: Tensor::from_ints(Data::from([1, 1, 1, 0]), &device),
             weights: Tensor::from_floats(Data::from([1.0, 1.0, 1.0, 1.0]), &device),
+            padding_side: PaddingSide::Right,
         };
 
-        let loss = model.forward_classification(
-            item.t_historys,
-            item.r_historys,
-            item.delta_ts,
-            item.labels,
-            item.weights,
-            Reduction::Sum,
-        );
+        let loss = model.forward_classification(item, Reduction::Sum);
 
         assert_eq!(
             loss.clone().into_data().convert::<f32>().value[0],
@@ -594,13 +1412,11 @@ mod tests {
             w_grad.clone().into_data().value.try_into().unwrap(),
         );
 
-        let config =
-            TrainingConfig::new(ModelConfig::default(), AdamConfig::new().with_epsilon(1e-8));
-        let mut optim = config.optimizer.init::<B, Model<B>>();
+        let mut optim = AdamConfig::new().with_epsilon(1e-8).init::<B, Model<B>>();
         let lr = 0.04;
         let grads = GradientsParams::from_grads(gradients, &model);
         model = optim.step(lr, model, grads);
-        model.w = parameter_clipper(model.w);
+        model.w = parameter_clipper(model.w, true);
         assert_eq!(
             model.w.val().to_data(),
             Data::from([
@@ -667,19 +1483,14 @@ mod tests {
                 ]),
                 &device,
             ),
+            mask: Tensor::<B, 2>::ones([6, 4], &device).bool(),
             delta_ts: Tensor::from_floats(Data::from([4.0, 11.0, 12.0, 23.0]), &device),
             labels: Tensor::from_ints(Data::from([1, 1, 1, 0]), &device),
             weights: Tensor::from_floats(Data::from([1.0, 1.0, 1.0, 1.0]), &device),
+            padding_side: PaddingSide::Right,
         };
 
-        let loss = model.forward_classification(
-            item.t_historys,
-            item.r_historys,
-            item.delta_ts,
-            item.labels,
-            item.weights,
-            Reduction::Sum,
-        );
+        let loss = model.forward_classification(item, Reduction::Sum);
         assert_eq!(loss.clone().into_data().convert::<f32>().value[0], 4.176347);
         let gradients = loss.backward();
         let w_grad = model.w.grad(&gradients).unwrap();
@@ -709,7 +1520,7 @@ mod tests {
         );
         let grads = GradientsParams::from_grads(gradients, &model);
         model = optim.step(lr, model, grads);
-        model.w = parameter_clipper(model.w);
+        model.w = parameter_clipper(model.w, true);
         assert_eq!(
             model.w.val().to_data(),
             Data::from([